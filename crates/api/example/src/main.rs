@@ -1,6 +1,6 @@
 use ggcat_api::{
-    ColoredQueryOutputFormat, ExtraElaboration, GGCATConfig, GGCATInstance,
-    GeneralSequenceBlockData,
+    AbundanceOutputMode, ColoredQueryOutputFormat, ColormapEncoding, ExtraElaboration, GGCATConfig,
+    GGCATInstance, GeneralSequenceBlockData, MinMultiplicity, QueryResultsFilter,
 };
 use itertools::Itertools;
 use std::{path::PathBuf, sync::Mutex};
@@ -24,10 +24,12 @@ fn main() {
                 println!("Error: {}", msg);
             }
             ggcat_api::MessageLevel::UnrecoverableError => {
-                panic!("Unrecoverable error: {}", msg);
+                println!("Unrecoverable error: {}", msg);
             }
         }),
         disk_optimization_level: 5,
+        progress_callback: None,
+        cancellation_token: None,
     })
     .unwrap();
 
@@ -60,14 +62,24 @@ fn main() {
             false,
             None,
             true,
-            1,
+            ColormapEncoding::RunLength,
+            MinMultiplicity::Fixed(1),
+            usize::MAX,
+            false,
+            None,
+            None,
+            AbundanceOutputMode::None,
+            None,
             ExtraElaboration::UnitigLinks,
             None,
             5,
         )
         .unwrap();
 
-    let input_query = PathBuf::from("../../../example-inputs/query.fa");
+    let input_query = vec![GeneralSequenceBlockData::FASTA((
+        PathBuf::from("../../../example-inputs/query.fa"),
+        None,
+    ))];
 
     let output_query = instance
         .query_graph(
@@ -80,6 +92,9 @@ fn main() {
             None,
             true,
             ColoredQueryOutputFormat::JsonLinesWithNames,
+            false,
+            false,
+            QueryResultsFilter::default(),
         )
         .unwrap();
 
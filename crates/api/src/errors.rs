@@ -0,0 +1,190 @@
+use assembler::BuildResumeError;
+use colors::graph_stream::IncompatibleKmerLength;
+use colors::storage::deserializer::CorruptedColormapError;
+use colors::storage::versions::UnsupportedColormapVersion;
+use config::progress::OperationCancelled;
+use config::MemoryBudgetExceeded;
+use io::sequences_reader::SequencesFormatError;
+use io::FileIoError;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// Errors returned by the GGCATInstance methods
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GGCATError {
+    /// An input file has an unknown or invalid format
+    InputFormat { file: PathBuf, message: String },
+    /// A file cannot be read or written, the path is not available for errors not tied to a specific file
+    Io {
+        path: Option<PathBuf>,
+        error: std::io::Error,
+    },
+    /// A colormap file cannot be decoded
+    ColormapCorrupted { file: PathBuf, message: String },
+    /// A colormap file has a storage version not supported by this release
+    ColormapVersion {
+        file: PathBuf,
+        version: u64,
+        supported_versions: Vec<u64>,
+    },
+    /// The kmer length is not the one used to build the input graph
+    IncompatibleK {
+        graph_file: PathBuf,
+        k: usize,
+        graph_k: Option<usize>,
+    },
+    /// The temporary files needed more than the available memory when running without a temporary directory
+    MemoryBudgetExceeded(MemoryBudgetExceeded),
+    /// The given parameters are not valid or not compatible with each other
+    InvalidArguments(String),
+    /// The operation was cancelled before its completion
+    Cancelled,
+    /// Any other error
+    Other(anyhow::Error),
+}
+
+impl Display for GGCATError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GGCATError::InputFormat { file, message } => {
+                write!(f, "Invalid input file '{}': {}", file.display(), message)
+            }
+            GGCATError::Io {
+                path: Some(path),
+                error,
+            } => write!(f, "I/O error on '{}': {}", path.display(), error),
+            GGCATError::Io { path: None, error } => write!(f, "I/O error: {}", error),
+            GGCATError::ColormapCorrupted { file, message } => {
+                write!(
+                    f,
+                    "Colormap file '{}' is corrupted: {}",
+                    file.display(),
+                    message
+                )
+            }
+            GGCATError::ColormapVersion {
+                file,
+                version,
+                supported_versions,
+            } => UnsupportedColormapVersion {
+                file: file.clone(),
+                version: *version,
+                supported_versions: supported_versions.clone(),
+            }
+            .fmt(f),
+            GGCATError::IncompatibleK {
+                graph_file,
+                k,
+                graph_k,
+            } => {
+                write!(
+                    f,
+                    "Kmer length {} is not compatible with graph '{}'",
+                    k,
+                    graph_file.display()
+                )?;
+                match graph_k {
+                    Some(graph_k) => write!(f, ", built with k = {}", graph_k),
+                    None => write!(f, ", its sequences are shorter than k"),
+                }
+            }
+            GGCATError::MemoryBudgetExceeded(err) => err.fmt(f),
+            GGCATError::InvalidArguments(message) => write!(f, "Invalid arguments: {}", message),
+            GGCATError::Cancelled => write!(f, "Operation cancelled"),
+            GGCATError::Other(err) => write!(f, "{:#}", err),
+        }
+    }
+}
+
+impl std::error::Error for GGCATError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GGCATError::Io { error, .. } => Some(error),
+            GGCATError::Other(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Maps the typed errors raised by the inner crates, the remaining ones are returned as `Other`
+impl From<anyhow::Error> for GGCATError {
+    fn from(err: anyhow::Error) -> Self {
+        if err.is::<OperationCancelled>() {
+            return GGCATError::Cancelled;
+        }
+
+        let err = match err.downcast::<MemoryBudgetExceeded>() {
+            Ok(err) => return GGCATError::MemoryBudgetExceeded(err),
+            Err(err) => err,
+        };
+
+        let err = match err.downcast::<SequencesFormatError>() {
+            Ok(err) => {
+                return GGCATError::InputFormat {
+                    file: err.file,
+                    message: err.message,
+                }
+            }
+            Err(err) => err,
+        };
+
+        let err = match err.downcast::<CorruptedColormapError>() {
+            Ok(err) => {
+                return GGCATError::ColormapCorrupted {
+                    file: err.file,
+                    message: err.message,
+                }
+            }
+            Err(err) => err,
+        };
+
+        let err = match err.downcast::<UnsupportedColormapVersion>() {
+            Ok(err) => {
+                return GGCATError::ColormapVersion {
+                    file: err.file,
+                    version: err.version,
+                    supported_versions: err.supported_versions,
+                }
+            }
+            Err(err) => err,
+        };
+
+        let err = match err.downcast::<IncompatibleKmerLength>() {
+            Ok(err) => {
+                return GGCATError::IncompatibleK {
+                    graph_file: err.graph_file,
+                    k: err.k,
+                    graph_k: err.graph_k,
+                }
+            }
+            Err(err) => err,
+        };
+
+        let err = match err.downcast::<BuildResumeError>() {
+            Ok(err) => return GGCATError::InvalidArguments(err.to_string()),
+            Err(err) => err,
+        };
+
+        let err = match err.downcast::<FileIoError>() {
+            Ok(err) => {
+                return GGCATError::Io {
+                    path: Some(err.path),
+                    error: err.error,
+                }
+            }
+            Err(err) => err,
+        };
+
+        match err.downcast::<std::io::Error>() {
+            Ok(error) => GGCATError::Io { path: None, error },
+            Err(err) => GGCATError::Other(err),
+        }
+    }
+}
+
+impl From<MemoryBudgetExceeded> for GGCATError {
+    fn from(err: MemoryBudgetExceeded) -> Self {
+        GGCATError::MemoryBudgetExceeded(err)
+    }
+}
@@ -1,3 +1,4 @@
+mod errors;
 mod utils;
 
 use colors::bundles::graph_querying::ColorBundleGraphQuerying;
@@ -5,58 +6,82 @@ use colors::colors_manager::ColorsManager;
 use colors::{
     bundles::multifile_building::ColorBundleMultifileBuilding, non_colored::NonColoredManager,
 };
+use config::operation::{OperationContext, OperationSettings};
+use config::progress::OperationCancelled;
 use config::{MAX_BUCKET_CHUNK_SIZE, MIN_BUCKET_CHUNK_SIZE};
 pub use ggcat_logging::MessageLevel;
 use ggcat_logging::UnrecoverableErrorLogging;
 use io::concurrent::structured_sequences::fasta::FastaWriterWrapper;
 use io::concurrent::structured_sequences::gfa::{GFAWriterWrapperV1, GFAWriterWrapperV2};
 use io::concurrent::structured_sequences::StructuredSequenceBackendWrapper;
-use io::sequences_stream::fasta::FastaFileSequencesStream;
+use io::sequences_reader::SequencesReader;
+use io::sequences_stream::general::GeneralSequencesStream;
 use io::sequences_stream::GenericSequencesStream;
 use parallel_processor::enable_counters_logging;
 use parallel_processor::memory_data_size::MemoryDataSize;
 use parallel_processor::memory_fs::MemoryFs;
 use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
-use parallel_processor::scheduler::PriorityScheduler;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
 use std::cmp::max;
 use std::fs::create_dir_all;
-use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::Ordering;
+use std::sync::{Once, OnceLock};
 use std::time::Duration;
 
+pub use crate::errors::GGCATError;
 pub use crate::utils::HashType;
+pub use assembler::{GraphCleaningConfig, KmersCountsFormat, MinMultiplicity};
+pub use colors::storage::inspector::ColormapInfo;
+pub use colors::storage::ColormapEncoding;
+pub use config::progress::{
+    CancellationToken, ProgressCallback, ProgressInfo, ProgressPhase, ProgressUnit,
+};
 pub use config::ColorIndexType;
+pub use dumper::{ColorsStats, GraphStats, HistogramBin, LinksStats};
+pub use io::concurrent::structured_sequences::AbundanceOutputMode;
 pub use io::sequences_reader::{DnaSequence, DnaSequencesFileType};
 pub use io::sequences_stream::{
     general::{DynamicSequencesStream, GeneralSequenceBlockData},
     SequenceInfo,
 };
 pub use querier::ColoredQueryOutputFormat;
+use querier::QueryOutput;
+pub use querier::{QueryKmerHit, QueryKmersRun, QueryResultsFilter};
 
 pub mod debug {
     use crate::utils::HashType;
     use assembler::AssemblerStartingStep;
-    pub use config::KEEP_FILES as DEBUG_KEEP_FILES;
-    use parking_lot::Mutex;
     use querier::QuerierStartingStep;
-    use std::sync::atomic::{AtomicBool, AtomicUsize};
-
-    pub static DEBUG_ASSEMBLER_FIRST_STEP: Mutex<AssemblerStartingStep> =
-        Mutex::new(AssemblerStartingStep::MinimizerBucketing);
-    pub static DEBUG_ASSEMBLER_LAST_STEP: Mutex<AssemblerStartingStep> =
-        Mutex::new(AssemblerStartingStep::MaximalUnitigsLinks);
-
-    pub static DEBUG_QUERIER_FIRST_STEP: Mutex<QuerierStartingStep> =
-        Mutex::new(QuerierStartingStep::MinimizerBucketing);
-
-    pub static DEBUG_HASH_TYPE: Mutex<HashType> = Mutex::new(HashType::Auto);
 
-    pub static DEBUG_LINK_PHASE_ITERATION_START_STEP: AtomicUsize = AtomicUsize::new(0);
-    pub static DEBUG_ONLY_BSTATS: AtomicBool = AtomicBool::new(false);
+    /// Debug settings of a GGCATInstance, applied to all its successive operations
+    #[derive(Clone)]
+    pub struct DebugConfig {
+        pub assembler_first_step: AssemblerStartingStep,
+        pub assembler_last_step: AssemblerStartingStep,
+        pub querier_first_step: QuerierStartingStep,
+        pub hash_type: HashType,
+        pub link_phase_iteration_start_step: usize,
+        pub only_bstats: bool,
+        /// Forces the number of buckets (log2) instead of computing it from the inputs
+        pub buckets_count_log_force: Option<usize>,
+        /// Keeps the intermediate files in the temporary directory
+        pub keep_files: bool,
+    }
 
-    pub static BUCKETS_COUNT_LOG_FORCE: Mutex<Option<usize>> = Mutex::new(None);
+    impl Default for DebugConfig {
+        fn default() -> Self {
+            Self {
+                assembler_first_step: AssemblerStartingStep::MinimizerBucketing,
+                assembler_last_step: AssemblerStartingStep::MaximalUnitigsLinks,
+                querier_first_step: QuerierStartingStep::MinimizerBucketing,
+                hash_type: HashType::Auto,
+                link_phase_iteration_start_step: 0,
+                only_bstats: false,
+                buckets_count_log_force: None,
+                keep_files: false,
+            }
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -71,34 +96,48 @@ pub enum GfaVersion {
     V2,
 }
 
-/// Main config of GGCAT. This config is global and should be passed to GGCATInstance::create
+/// Main config of GGCAT, passed to GGCATInstance::create. Each instance keeps its own config
 pub struct GGCATConfig {
-    /// Directory for temporary files
+    /// Directory for temporary files. If None, all the temporary files are kept in memory
+    /// and the operations fail if they need more than the given memory
     pub temp_dir: Option<PathBuf>,
 
     /// Maximum suggested memory usage (GB)
     /// The tool will try use only up to this GB of memory to store temporary files
     /// without writing to disk. This usage does not include the needed memory for the processing steps.
-    /// GGCAT can allocate extra memory for files if the current memory is not enough to complete the current operation
+    /// GGCAT can allocate extra memory for files if the current memory is not enough to complete the current operation.
+    /// The memory pool is shared by all the instances of the process and initially sized by the first one.
+    /// Without a temporary directory, it is the memory budget of each operation of this instance
     pub memory: f64,
 
     /// Use all the given memory before writing to disk
     pub prefer_memory: bool,
 
-    /// The total threads to be used
+    /// The total threads to be used, by the thread pool of the instance
     pub total_threads_count: usize,
 
     /// The default lz4 compression level for the intermediate files
     pub intermediate_compression_level: Option<u32>,
 
-    /// The path to an optional json-formatted real time stats file
+    /// The path to an optional json-formatted real time stats file.
+    /// The stats are collected process-wide, so only the first instance that sets it enables them
     pub stats_file: Option<PathBuf>,
 
-    /// The messages callback, if present, no output will be automatically written to stdout
+    /// The messages callback, if present, no output will be automatically written to stdout.
+    /// It receives the messages emitted while running the operations of this instance
     pub messages_callback: Option<fn(MessageLevel, &str)>,
 
     /// Sets the level of disk usage reduction optimization
     pub disk_optimization_level: u32,
+
+    /// Called with the progress of the current phase of the operations of this instance
+    pub progress_callback: Option<ProgressCallback>,
+
+    /// Token to cancel the running operation of this instance, that returns GGCATError::Cancelled
+    /// and removes its temporary files. The token is reset when the cancelled operation returns,
+    /// so it can be reused for the following operations. A cancellation requested while no operation
+    /// is running cancels the next one
+    pub cancellation_token: Option<CancellationToken>,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -118,92 +157,214 @@ pub enum ExtraElaboration {
     FastEulertigs,
 }
 
-static INSTANCE: Mutex<Option<&'static GGCATInstance>> = Mutex::new(None);
+/// An already built graph used as input, with its optional colormap
+pub struct GraphInputFile {
+    pub graph_file: PathBuf,
+    pub colormap_file: Option<PathBuf>,
+}
 
-pub struct GGCATInstance(GGCATConfig);
+/// The stats file of the process, set by the first instance that requests it
+static STATS_FILE: OnceLock<PathBuf> = OnceLock::new();
 
-fn create_tempdir(base_path: Option<PathBuf>) -> Option<PathBuf> {
-    base_path.map(|t| {
-        let temp_dir = t.join(&format!("build_graph_{}", uuid::Uuid::new_v4()));
-        let _ = create_dir_all(&temp_dir);
-        temp_dir
-    })
+pub struct GGCATInstance {
+    config: GGCATConfig,
+    thread_pool: rayon::ThreadPool,
+    debug_config: Mutex<debug::DebugConfig>,
+    /// The operations of an instance share its thread pool, so they are run one at a time
+    operation_lock: Mutex<()>,
 }
 
-fn remove_tempdir(temp_dir: Option<PathBuf>) {
-    if let Some(temp_dir) = temp_dir {
+/// Base path of the in-memory temporary files, used when no temporary directory is given
+const MEMORY_ONLY_TEMP_DIR: &str = "ggcat-memory-only";
+
+fn create_tempdir(base_path: Option<PathBuf>) -> PathBuf {
+    let temp_dir_name = format!("build_graph_{}", uuid::Uuid::new_v4());
+    match base_path {
+        Some(base_path) => {
+            let temp_dir = base_path.join(&temp_dir_name);
+            let _ = create_dir_all(&temp_dir);
+            temp_dir
+        }
+        None => {
+            // Memory-only mode, the directory is used only to give unique names to the in-memory files
+            std::env::temp_dir()
+                .join(MEMORY_ONLY_TEMP_DIR)
+                .join(temp_dir_name)
+        }
+    }
+}
+
+/// Removes the temporary files, keeping them on disk if the operation failed.
+/// In memory-only mode or if the operation was cancelled they are always removed
+fn remove_tempdir<T>(temp_dir: PathBuf, result: &anyhow::Result<T>) {
+    let remove = match result {
+        Ok(_) => true,
+        Err(err) => err.is::<OperationCancelled>() || config::is_memory_only(),
+    };
+
+    if remove {
         MemoryFs::remove_directory(&temp_dir, true);
         let _ = std::fs::remove_dir_all(temp_dir);
     }
 }
 
-/// Main GGCAT struct. It's a singleton and can be created by passing a GGCATConfig.
-/// Successive calls to create will return the same instance, ignoring the new configuration.
-impl GGCATInstance {
-    /// Creates a new GGCATInstance. If an instance already exists, it will be returned, ignoring the new config.
-    pub fn create(config: GGCATConfig) -> anyhow::Result<&'static Self> {
-        let mut instance = INSTANCE.lock();
-
-        if let Some(instance) = instance.deref() {
-            return Ok(instance);
+/// Checks that all the input files exist and have a supported format, before starting any processing
+fn check_input_streams(input_streams: &[GeneralSequenceBlockData]) -> Result<(), GGCATError> {
+    for block in input_streams {
+        match block {
+            GeneralSequenceBlockData::FASTA((file, _))
+            | GeneralSequenceBlockData::GFA((file, _)) => {
+                SequencesReader::check_file(file)?;
+            }
+            GeneralSequenceBlockData::Dynamic(_) => {}
         }
+    }
+    Ok(())
+}
 
-        parallel_processor::set_logger_function(|level, message| {
-            ggcat_logging::log(
-                match level {
-                    parallel_processor::LogLevel::Info => MessageLevel::Info,
-                    parallel_processor::LogLevel::Warning => MessageLevel::Warning,
-                    parallel_processor::LogLevel::Error => MessageLevel::Error,
-                },
-                &message,
-            );
-        });
-
-        // Increase the maximum allowed number of open files
-        if let Err(err) = fdlimit::raise_fd_limit() {
-            ggcat_logging::warn!(
-                "WARNING: Failed to increase the maximum number of open files: {}",
-                err
-            );
+/// Checks that the directory of an output file exists, to avoid failing only at the end of the processing
+fn check_output_file(output_file: &Path) -> Result<(), GGCATError> {
+    match output_file.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() && !directory.is_dir() => {
+            Err(GGCATError::Io {
+                path: Some(directory.to_path_buf()),
+                error: std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "Output directory does not exist",
+                ),
+            })
         }
+        _ => Ok(()),
+    }
+}
 
-        config::PREFER_MEMORY.store(config.prefer_memory, Ordering::Relaxed);
+/// Main GGCAT struct, created by passing a GGCATConfig.
+/// Multiple instances with different configurations can coexist in the same process,
+/// each one with its own thread pool, temporary files settings and debug settings.
+/// The operations of different instances can run at the same time, while the memory pool
+/// of the temporary files and the stats file are shared by the whole process
+impl GGCATInstance {
+    /// Creates a new GGCATInstance with the given config
+    pub fn create(config: GGCATConfig) -> Result<Self, GGCATError> {
+        static PROCESS_INIT: Once = Once::new();
+        PROCESS_INIT.call_once(|| {
+            parallel_processor::set_logger_function(|level, message| {
+                ggcat_logging::log(
+                    match level {
+                        parallel_processor::LogLevel::Info => MessageLevel::Info,
+                        parallel_processor::LogLevel::Warning => MessageLevel::Warning,
+                        parallel_processor::LogLevel::Error => MessageLevel::Error,
+                    },
+                    &message,
+                );
+            });
 
-        if let Some(callback) = config.messages_callback {
-            ggcat_logging::setup_logging_callback(callback);
-        }
+            // Increase the maximum allowed number of open files
+            if let Err(err) = fdlimit::raise_fd_limit() {
+                ggcat_logging::warn!(
+                    "WARNING: Failed to increase the maximum number of open files: {}",
+                    err
+                );
+            }
 
-        rayon::ThreadPoolBuilder::new()
+            MemoryFs::init(
+                MemoryDataSize::from_bytes(
+                    (config.memory * (MemoryDataSize::OCTET_GIBIOCTET_FACTOR as f64)) as usize,
+                ),
+                config::FLUSH_QUEUE_FACTOR * config.total_threads_count,
+                max(1, config.total_threads_count / 4),
+                8192,
+            );
+        });
+
+        let thread_pool = rayon::ThreadPoolBuilder::new()
             .num_threads(config.total_threads_count)
             .thread_name(|i| format!("rayon-thread-{}", i))
-            .build_global()
-            .log_unrecoverable_error("Cannot initialize rayon thread pool")?;
+            .build()
+            .log_unrecoverable_error("Cannot initialize rayon thread pool")
+            .map_err(anyhow::Error::from)?;
 
         if let Some(temp_dir) = &config.temp_dir {
-            create_dir_all(temp_dir).log_unrecoverable_error_with_data(
-                "Cannot create temporary directory",
-                temp_dir.display(),
-            )?;
-        } else {
-            todo!("Force memory-only usage")
+            create_dir_all(temp_dir)
+                .log_unrecoverable_error_with_data(
+                    "Cannot create temporary directory",
+                    temp_dir.display(),
+                )
+                .map_err(|error| GGCATError::Io {
+                    path: Some(temp_dir.clone()),
+                    error,
+                })?;
         }
 
         if let Some(stats_file) = &config.stats_file {
-            enable_counters_logging(stats_file, Duration::from_millis(1000), |val| {
-                val["phase"] = PHASES_TIMES_MONITOR.read().get_phase_desc().into();
-            });
+            if STATS_FILE.set(stats_file.clone()).is_ok() {
+                // The stats file is created by a background thread that cannot report errors
+                std::fs::File::create(stats_file).map_err(|error| GGCATError::Io {
+                    path: Some(stats_file.clone()),
+                    error,
+                })?;
+                enable_counters_logging(stats_file, Duration::from_millis(1000), |val| {
+                    val["phase"] = PHASES_TIMES_MONITOR.read().get_phase_desc().into();
+                });
+            } else if STATS_FILE.get() != Some(stats_file) {
+                ggcat_logging::warn!(
+                    "WARNING: The stats are already written to {}, ignoring the stats file {}",
+                    STATS_FILE.get().unwrap().display(),
+                    stats_file.display()
+                );
+            }
         }
 
-        MemoryFs::init(
-            MemoryDataSize::from_bytes(
-                (config.memory * (MemoryDataSize::OCTET_GIBIOCTET_FACTOR as f64)) as usize,
-            ),
-            config::FLUSH_QUEUE_FACTOR * config.total_threads_count,
-            max(1, config.total_threads_count / 4),
-            8192,
-        );
-        *instance = Some(Box::leak(Box::new(GGCATInstance(config))));
-        return Ok(instance.unwrap());
+        Ok(GGCATInstance {
+            config,
+            thread_pool,
+            debug_config: Mutex::new(debug::DebugConfig::default()),
+            operation_lock: Mutex::new(()),
+        })
+    }
+
+    /// Returns the debug settings of this instance, that can be changed before running an operation
+    pub fn debug_config(&self) -> MutexGuard<'_, debug::DebugConfig> {
+        self.debug_config.lock()
+    }
+
+    /// Runs an operation in the thread pool of this instance, with the settings
+    /// of the instance available to the pipelines of the operation
+    fn run_operation<T: Send>(&self, operation: impl FnOnce(&debug::DebugConfig) -> T + Send) -> T {
+        let _operation_guard = self.operation_lock.lock();
+        let debug_config = self.debug_config.lock().clone();
+
+        let context = OperationContext::new(OperationSettings {
+            keep_files: debug_config.keep_files,
+            prefer_memory: self.config.prefer_memory,
+            memory_only_budget: match self.config.temp_dir {
+                Some(_) => 0,
+                None => {
+                    (self.config.memory * (MemoryDataSize::OCTET_GIBIOCTET_FACTOR as f64)) as u64
+                }
+            },
+            intermediate_compression_level_slow: self
+                .config
+                .intermediate_compression_level
+                .unwrap_or(config::DEFAULT_INTERMEDIATE_COMPRESSION_LEVEL_SLOW),
+            intermediate_compression_level_fast: self
+                .config
+                .intermediate_compression_level
+                .unwrap_or(config::DEFAULT_INTERMEDIATE_COMPRESSION_LEVEL_FAST),
+            messages_callback: self.config.messages_callback,
+            progress_callback: self.config.progress_callback.clone(),
+            cancellation_token: self.config.cancellation_token.clone(),
+        });
+
+        self.thread_pool.broadcast(|_| {
+            OperationContext::set_current(Some(context.clone()));
+        });
+        let result = self.thread_pool.install(|| operation(&debug_config));
+        self.thread_pool.broadcast(|_| {
+            OperationContext::set_current(None);
+        });
+        context.finish();
+        result
     }
 
     /// Builds a new graph from the given input streams, with the specified parameters
@@ -229,96 +390,671 @@ impl GGCATInstance {
 
         // Enable colors
         colors: bool,
+        // The encoding of the color subsets in the colormap
+        colormap_encoding: ColormapEncoding,
 
-        // Minimum multiplicity required to keep a kmer
-        min_multiplicity: usize,
+        // Minimum multiplicity required to keep a kmer, or automatically chosen from the kmers abundance histogram
+        min_multiplicity: MinMultiplicity,
 
         max_multiplicity: usize,
 
+        // Applies the minimum multiplicity to the kmer count of each color, removing the colors below it
+        per_color_min_multiplicity: bool,
+
+        // Writes the kmers abundance histogram, with the number of distinct kmers for each multiplicity, as a TSV file
+        abundance_histogram_file: Option<PathBuf>,
+
+        // Bases of FASTQ reads with a lower phred quality are discarded, splitting the read
+        min_base_quality: Option<u8>,
+
+        // The kmer abundance tags written for each output sequence
+        abundance_mode: AbundanceOutputMode,
+
+        // Removes the tips and the low coverage bubbles from the unitigs graph
+        graph_cleaning: Option<GraphCleaningConfig>,
+
         extra_elab: ExtraElaboration,
 
         gfa_output_version: Option<GfaVersion>,
 
         disk_optimization_level: u32,
-    ) -> anyhow::Result<PathBuf> {
-        PriorityScheduler::set_max_threads_count(threads_count);
+    ) -> Result<PathBuf, GGCATError> {
+        self.run_build_graph(
+            None,
+            input_streams,
+            output_file,
+            color_names,
+            kmer_length,
+            threads_count,
+            forward_only,
+            minimizer_length,
+            colors,
+            colormap_encoding,
+            min_multiplicity,
+            max_multiplicity,
+            per_color_min_multiplicity,
+            abundance_histogram_file,
+            min_base_quality,
+            abundance_mode,
+            graph_cleaning,
+            extra_elab,
+            gfa_output_version,
+            None,
+            disk_optimization_level,
+        )
+    }
+
+    /// Resumes an interrupted build from its temporary directory, restarting from the last completed phase.
+    /// The parameters must be the same of the interrupted build, except for the threads count.
+    /// The resumable phases are the minimizer bucketing, the kmers merge, the hashes sorting and the links compaction,
+    /// that is restarted from its first iteration if interrupted
+    pub fn resume_build_graph(
+        &self,
+        // The temporary directory of the interrupted build
+        temp_dir: PathBuf,
 
-        let merging_hash_dispatch = utils::get_hash_static_id(
-            debug::DEBUG_HASH_TYPE.lock().clone(),
+        // The input streams
+        input_streams: Vec<GeneralSequenceBlockData>,
+
+        // The output file
+        output_file: PathBuf,
+
+        // The names of the colors, ordered by color index
+        color_names: Option<&[String]>,
+
+        // Specifies the k-mers length
+        kmer_length: usize,
+        // The threads to be used
+        threads_count: usize,
+        // Treats reverse complementary kmers as different
+        forward_only: bool,
+        // Overrides the default m-mers (minimizers) length
+        minimizer_length: Option<usize>,
+
+        // Enable colors
+        colors: bool,
+        // The encoding of the color subsets in the colormap
+        colormap_encoding: ColormapEncoding,
+
+        // Minimum multiplicity required to keep a kmer, or automatically chosen from the kmers abundance histogram
+        min_multiplicity: MinMultiplicity,
+
+        max_multiplicity: usize,
+
+        // Applies the minimum multiplicity to the kmer count of each color, removing the colors below it
+        per_color_min_multiplicity: bool,
+
+        // Writes the kmers abundance histogram, with the number of distinct kmers for each multiplicity, as a TSV file
+        abundance_histogram_file: Option<PathBuf>,
+
+        // Bases of FASTQ reads with a lower phred quality are discarded, splitting the read
+        min_base_quality: Option<u8>,
+
+        // The kmer abundance tags written for each output sequence
+        abundance_mode: AbundanceOutputMode,
+
+        // Removes the tips and the low coverage bubbles from the unitigs graph
+        graph_cleaning: Option<GraphCleaningConfig>,
+
+        extra_elab: ExtraElaboration,
+
+        gfa_output_version: Option<GfaVersion>,
+
+        disk_optimization_level: u32,
+    ) -> Result<PathBuf, GGCATError> {
+        self.run_build_graph(
+            Some(temp_dir),
+            input_streams,
+            output_file,
+            color_names,
             kmer_length,
+            threads_count,
             forward_only,
-        );
+            minimizer_length,
+            colors,
+            colormap_encoding,
+            min_multiplicity,
+            max_multiplicity,
+            per_color_min_multiplicity,
+            abundance_histogram_file,
+            min_base_quality,
+            abundance_mode,
+            graph_cleaning,
+            extra_elab,
+            gfa_output_version,
+            None,
+            disk_optimization_level,
+        )
+    }
 
-        let colors_hash = if colors {
-            ColorBundleMultifileBuilding::dynamic_dispatch_id()
-        } else {
-            NonColoredManager::dynamic_dispatch_id()
-        };
+    fn run_build_graph(
+        &self,
+        resume_temp_dir: Option<PathBuf>,
+
+        // The input streams
+        input_streams: Vec<GeneralSequenceBlockData>,
+
+        // The output file
+        output_file: PathBuf,
+
+        // The names of the colors, ordered by color index
+        color_names: Option<&[String]>,
+
+        // Specifies the k-mers length
+        kmer_length: usize,
+        // The threads to be used
+        threads_count: usize,
+        // Treats reverse complementary kmers as different
+        forward_only: bool,
+        // Overrides the default m-mers (minimizers) length
+        minimizer_length: Option<usize>,
+
+        // Enable colors
+        colors: bool,
+        // The encoding of the color subsets in the colormap
+        colormap_encoding: ColormapEncoding,
+
+        // Minimum multiplicity required to keep a kmer, or automatically chosen from the kmers abundance histogram
+        min_multiplicity: MinMultiplicity,
 
-        if gfa_output_version.is_some() && colors {
-            anyhow::bail!("GFA output is not supported with colors");
+        max_multiplicity: usize,
+
+        // Applies the minimum multiplicity to the kmer count of each color, removing the colors below it
+        per_color_min_multiplicity: bool,
+
+        // Writes the kmers abundance histogram, with the number of distinct kmers for each multiplicity, as a TSV file
+        abundance_histogram_file: Option<PathBuf>,
+
+        // Bases of FASTQ reads with a lower phred quality are discarded, splitting the read
+        min_base_quality: Option<u8>,
+
+        // The kmer abundance tags written for each output sequence
+        abundance_mode: AbundanceOutputMode,
+
+        // Removes the tips and the low coverage bubbles from the unitigs graph
+        graph_cleaning: Option<GraphCleaningConfig>,
+
+        extra_elab: ExtraElaboration,
+
+        gfa_output_version: Option<GfaVersion>,
+
+        // Writes the solid kmers with their counts instead of building the graph
+        kmers_counts: Option<assembler::KmersCountsOutput>,
+
+        disk_optimization_level: u32,
+    ) -> Result<PathBuf, GGCATError> {
+        if abundance_mode.needs_kmers_abundances()
+            && matches!(
+                extra_elab,
+                ExtraElaboration::GreedyMatchtigs
+                    | ExtraElaboration::Eulertigs
+                    | ExtraElaboration::Pathtigs
+                    | ExtraElaboration::FastEulertigs
+            )
+        {
+            return Err(GGCATError::InvalidArguments(
+                "Per-kmer abundances are supported only for unitigs and simplitigs".to_string(),
+            ));
         }
 
-        let output_mode = match gfa_output_version {
-            None => FastaWriterWrapper::dynamic_dispatch_id(),
-            Some(GfaVersion::V1) => GFAWriterWrapperV1::dynamic_dispatch_id(),
-            Some(GfaVersion::V2) => GFAWriterWrapperV2::dynamic_dispatch_id(),
-        };
+        if per_color_min_multiplicity && !colors {
+            return Err(GGCATError::InvalidArguments(
+                "The per-color minimum multiplicity requires colors to be enabled".to_string(),
+            ));
+        }
 
-        let temp_dir = create_tempdir(self.0.temp_dir.clone());
+        if let Some(graph_cleaning) = &graph_cleaning {
+            if !matches!(
+                extra_elab,
+                ExtraElaboration::None | ExtraElaboration::UnitigLinks
+            ) {
+                return Err(GGCATError::InvalidArguments(
+                    "Graph cleaning is supported only for unitigs".to_string(),
+                ));
+            }
+            if !(0.0..=1.0).contains(&graph_cleaning.bubble_coverage_ratio) {
+                return Err(GGCATError::InvalidArguments(format!(
+                    "The bubble coverage ratio must be between 0 and 1, found {}",
+                    graph_cleaning.bubble_coverage_ratio
+                )));
+            }
+        }
 
-        let bucket_chunk_size = if disk_optimization_level == 0 {
-            None
-        } else {
-            let estimated_bases_count: u64 = input_streams
-                .iter()
-                .map(|file| file.estimated_bases_count().unwrap())
-                .sum();
+        check_input_streams(&input_streams)?;
+        check_output_file(&output_file)?;
+
+        let output_file = self.run_operation(|debug_config| {
+            let merging_hash_dispatch =
+                utils::get_hash_static_id(debug_config.hash_type, kmer_length, forward_only);
+
+            let colors_hash = if colors {
+                ColorBundleMultifileBuilding::dynamic_dispatch_id()
+            } else {
+                NonColoredManager::dynamic_dispatch_id()
+            };
 
-            if colors {
+            let output_mode = match gfa_output_version {
+                None => FastaWriterWrapper::dynamic_dispatch_id(),
+                Some(GfaVersion::V1) => GFAWriterWrapperV1::dynamic_dispatch_id(),
+                Some(GfaVersion::V2) => GFAWriterWrapperV2::dynamic_dispatch_id(),
+            };
+
+            let resume = resume_temp_dir.is_some();
+            let temp_dir = resume_temp_dir
+                .clone()
+                .unwrap_or_else(|| create_tempdir(self.config.temp_dir.clone()));
+
+            let bucket_chunk_size = if disk_optimization_level == 0 {
                 None
             } else {
+                let estimated_bases_count: u64 = input_streams
+                    .iter()
+                    .map(|file| file.estimated_bases_count())
+                    .sum::<anyhow::Result<_>>()?;
+
                 // Heuristic for chunks used for maximum disk usage
                 Some(
                     ((estimated_bases_count as u64) / (disk_optimization_level as u64 + 1))
                         .min(MAX_BUCKET_CHUNK_SIZE)
                         .max(MIN_BUCKET_CHUNK_SIZE),
                 )
+            };
+
+            let output_file = assembler::dynamic_dispatch::run_assembler(
+                (merging_hash_dispatch, colors_hash, output_mode),
+                kmer_length,
+                minimizer_length.unwrap_or(::utils::compute_best_m(kmer_length)),
+                debug_config.assembler_first_step.clone(),
+                debug_config.assembler_last_step.clone(),
+                input_streams,
+                color_names.unwrap_or(&[]),
+                colormap_encoding,
+                output_file,
+                Some(temp_dir.clone()),
+                resume,
+                threads_count,
+                min_multiplicity,
+                max_multiplicity,
+                per_color_min_multiplicity,
+                abundance_histogram_file,
+                kmers_counts,
+                min_base_quality,
+                abundance_mode,
+                debug_config.buckets_count_log_force,
+                Some(debug_config.link_phase_iteration_start_step),
+                self.config.intermediate_compression_level,
+                extra_elab == ExtraElaboration::UnitigLinks,
+                match extra_elab {
+                    ExtraElaboration::GreedyMatchtigs => Some(assembler::MatchtigMode::GreedyTigs),
+                    ExtraElaboration::Eulertigs => Some(assembler::MatchtigMode::EulerTigs),
+                    ExtraElaboration::Pathtigs => Some(assembler::MatchtigMode::PathTigs),
+                    ExtraElaboration::FastSimplitigs => {
+                        Some(assembler::MatchtigMode::FastSimpliTigs)
+                    }
+                    ExtraElaboration::FastEulertigs => Some(assembler::MatchtigMode::FastEulerTigs),
+                    _ => None,
+                },
+                graph_cleaning,
+                debug_config.only_bstats,
+                bucket_chunk_size,
+            );
+
+            if matches!(&output_file, Err(err) if !err.is::<OperationCancelled>())
+                && assembler::is_resumable_build_dir(&temp_dir)
+            {
+                ggcat_logging::info!(
+                    "The build can be resumed from the temporary directory '{}'",
+                    temp_dir.display()
+                );
             }
-        };
 
-        let output_file = assembler::dynamic_dispatch::run_assembler(
-            (merging_hash_dispatch, colors_hash, output_mode),
-            kmer_length,
-            minimizer_length.unwrap_or(::utils::compute_best_m(kmer_length)),
-            debug::DEBUG_ASSEMBLER_FIRST_STEP.lock().clone(),
-            debug::DEBUG_ASSEMBLER_LAST_STEP.lock().clone(),
+            remove_tempdir(temp_dir, &output_file);
+
+            output_file
+        });
+
+        Ok(output_file?)
+    }
+
+    /// Counts the kmers of the given input streams, writing each solid kmer with its multiplicity
+    /// sorted in lexicographic order, without building the graph.
+    /// With colors enabled each kmer is written with its colors subset index in the colormap
+    pub fn count_kmers(
+        &self,
+        // The input streams
+        input_streams: Vec<GeneralSequenceBlockData>,
+
+        // The output file
+        output_file: PathBuf,
+
+        // The names of the colors, ordered by color index
+        color_names: Option<&[String]>,
+
+        // Specifies the k-mers length
+        kmer_length: usize,
+        // The threads to be used
+        threads_count: usize,
+        // Treats reverse complementary kmers as different
+        forward_only: bool,
+        // Overrides the default m-mers (minimizers) length
+        minimizer_length: Option<usize>,
+
+        // Enable colors
+        colors: bool,
+        // The encoding of the color subsets in the colormap
+        colormap_encoding: ColormapEncoding,
+
+        // Minimum multiplicity required to keep a kmer, or automatically chosen from the kmers abundance histogram
+        min_multiplicity: MinMultiplicity,
+
+        max_multiplicity: usize,
+
+        // Applies the minimum multiplicity to the kmer count of each color, removing the colors below it
+        per_color_min_multiplicity: bool,
+
+        // Writes the kmers abundance histogram, with the number of distinct kmers for each multiplicity, as a TSV file
+        abundance_histogram_file: Option<PathBuf>,
+
+        // Bases of FASTQ reads with a lower phred quality are discarded, splitting the read
+        min_base_quality: Option<u8>,
+
+        // The format of the kmers counts table
+        format: KmersCountsFormat,
+
+        disk_optimization_level: u32,
+    ) -> Result<PathBuf, GGCATError> {
+        self.run_build_graph(
+            None,
             input_streams,
-            color_names.unwrap_or(&[]),
             output_file,
-            temp_dir.clone(),
+            color_names,
+            kmer_length,
             threads_count,
+            forward_only,
+            minimizer_length,
+            colors,
+            colormap_encoding,
             min_multiplicity,
             max_multiplicity,
-            *debug::BUCKETS_COUNT_LOG_FORCE.lock(),
-            Some(debug::DEBUG_LINK_PHASE_ITERATION_START_STEP.load(Ordering::Relaxed)),
-            self.0.intermediate_compression_level,
-            extra_elab == ExtraElaboration::UnitigLinks,
-            match extra_elab {
-                ExtraElaboration::GreedyMatchtigs => Some(assembler::MatchtigMode::GreedyTigs),
-                ExtraElaboration::Eulertigs => Some(assembler::MatchtigMode::EulerTigs),
-                ExtraElaboration::Pathtigs => Some(assembler::MatchtigMode::PathTigs),
-                ExtraElaboration::FastSimplitigs => Some(assembler::MatchtigMode::FastSimpliTigs),
-                ExtraElaboration::FastEulertigs => Some(assembler::MatchtigMode::FastEulerTigs),
-                _ => None,
-            },
-            debug::DEBUG_ONLY_BSTATS.load(Ordering::Relaxed),
-            bucket_chunk_size,
+            per_color_min_multiplicity,
+            abundance_histogram_file,
+            min_base_quality,
+            AbundanceOutputMode::None,
+            None,
+            ExtraElaboration::None,
+            None,
+            Some(assembler::KmersCountsOutput {
+                format,
+                canonical: !forward_only,
+            }),
+            disk_optimization_level,
+        )
+    }
+
+    /// Adds new samples to an existing colored graph, without rebuilding it from the original inputs.
+    /// The kmers of the input graph keep their colors, while the new inputs are assigned to new colors
+    /// appended after the ones already present in the colormap.
+    /// The graph is read only once and its kmers are always kept, while a kmer gets the color of a new input
+    /// only if it's found at least `min_multiplicity` times in that input
+    pub fn update_graph(
+        &self,
+        // The input graph
+        input_graph: PathBuf,
+        // The colormap of the input graph, if not specified the standard colormap file path is used
+        input_colormap: Option<PathBuf>,
+
+        // The new input streams, their colors are relative to new_color_names
+        new_input_streams: Vec<GeneralSequenceBlockData>,
+        // The names of the new colors, ordered by color index
+        new_color_names: &[String],
+
+        // The output file
+        output_file: PathBuf,
+
+        // Specifies the k-mers length
+        kmer_length: usize,
+        // The threads to be used
+        threads_count: usize,
+        // Treats reverse complementary kmers as different
+        forward_only: bool,
+        // Overrides the default m-mers (minimizers) length
+        minimizer_length: Option<usize>,
+
+        // Minimum multiplicity of a kmer in each new input to assign it the color of the input,
+        // the kmers of the input graph are always kept
+        min_multiplicity: usize,
+
+        extra_elab: ExtraElaboration,
+
+        gfa_output_version: Option<GfaVersion>,
+
+        disk_optimization_level: u32,
+    ) -> Result<PathBuf, GGCATError> {
+        use colors::colors_manager::ColorMapReader;
+        use colors::graph_stream::{GraphColorsSource, GraphInput, GraphSequencesStream};
+        use colors::storage::deserializer::ColorsDeserializer;
+        use colors::DefaultColorsSerializer;
+        use io::sequences_stream::general::ColorsOffsetSequencesStream;
+
+        check_input_streams(&new_input_streams)?;
+
+        let input_colormap =
+            input_colormap.unwrap_or_else(|| Self::get_colormap_file(&input_graph));
+
+        // The updated colormap keeps the encoding of the input one
+        let (mut color_names, colormap_encoding): (Vec<_>, _) = {
+            let colors_deserializer =
+                ColorsDeserializer::<DefaultColorsSerializer>::new(&input_colormap, true)?;
+            (
+                (0..colors_deserializer.colors_count())
+                    .map(|i| {
+                        colors_deserializer
+                            .get_color_name(i as ColorIndexType, false)
+                            .to_string()
+                    })
+                    .collect(),
+                colors_deserializer.encoding().unwrap_or_default(),
+            )
+        };
+
+        let colors_offset = color_names.len() as ColorIndexType;
+
+        let graph_stream = GraphSequencesStream::new(
+            kmer_length,
+            vec![GraphInput {
+                graph_file: input_graph,
+                colors: GraphColorsSource::Colormap {
+                    colormap_file: input_colormap,
+                    colors_remap: (0..colors_offset).collect(),
+                },
+            }],
         )?;
 
-        remove_tempdir(temp_dir);
+        let mut input_streams = vec![GeneralSequenceBlockData::Dynamic((
+            std::sync::Arc::new(graph_stream),
+            0,
+        ))];
+
+        input_streams.extend(
+            new_input_streams
+                .into_iter()
+                .enumerate()
+                .map(|(index, block)| {
+                    let default_color = colors_offset + index as ColorIndexType;
+                    match block {
+                        GeneralSequenceBlockData::FASTA((file, color)) => {
+                            GeneralSequenceBlockData::FASTA((
+                                file,
+                                Some(color.map(|c| c + colors_offset).unwrap_or(default_color)),
+                            ))
+                        }
+                        GeneralSequenceBlockData::GFA((file, color)) => {
+                            GeneralSequenceBlockData::GFA((
+                                file,
+                                Some(color.map(|c| c + colors_offset).unwrap_or(default_color)),
+                            ))
+                        }
+                        GeneralSequenceBlockData::Dynamic((stream, block)) => {
+                            GeneralSequenceBlockData::Dynamic((
+                                std::sync::Arc::new(ColorsOffsetSequencesStream {
+                                    inner: stream,
+                                    colors_offset,
+                                    default_color,
+                                }),
+                                block,
+                            ))
+                        }
+                    }
+                }),
+        );
+
+        color_names.extend_from_slice(new_color_names);
 
-        Ok(output_file)
+        self.build_graph(
+            input_streams,
+            output_file,
+            Some(&color_names),
+            kmer_length,
+            threads_count,
+            forward_only,
+            minimizer_length,
+            true,
+            colormap_encoding,
+            // Only the colors of the new inputs are filtered, the color subsets of the graph are always kept
+            MinMultiplicity::Fixed(min_multiplicity.max(1)),
+            usize::MAX,
+            true,
+            None,
+            None,
+            AbundanceOutputMode::None,
+            None,
+            extra_elab,
+            gfa_output_version,
+            disk_optimization_level,
+        )
+    }
+
+    /// Merges multiple graphs into a single one.
+    /// If colors are enabled, the colors of each colored input graph are remapped into a unified
+    /// color table (graphs sharing a color name share the same color), while each uncolored input graph
+    /// is assigned to a single new color named after its file name
+    pub fn merge_graphs(
+        &self,
+        // The input graphs, with their optional colormaps
+        input_graphs: Vec<GraphInputFile>,
+
+        // The output file
+        output_file: PathBuf,
+
+        // Specifies the k-mers length
+        kmer_length: usize,
+        // The threads to be used
+        threads_count: usize,
+        // Treats reverse complementary kmers as different
+        forward_only: bool,
+        // Overrides the default m-mers (minimizers) length
+        minimizer_length: Option<usize>,
+
+        // Enable colors
+        colors: bool,
+
+        extra_elab: ExtraElaboration,
+
+        gfa_output_version: Option<GfaVersion>,
+
+        disk_optimization_level: u32,
+    ) -> Result<PathBuf, GGCATError> {
+        use colors::colors_manager::ColorMapReader;
+        use colors::graph_stream::{GraphColorsSource, GraphInput, GraphSequencesStream};
+        use colors::storage::deserializer::ColorsDeserializer;
+        use colors::DefaultColorsSerializer;
+        use std::collections::HashMap;
+
+        if input_graphs.is_empty() {
+            return Err(GGCATError::InvalidArguments(
+                "No input graphs specified".to_string(),
+            ));
+        }
+
+        let mut color_names: Vec<String> = vec![];
+        let mut colors_indices: HashMap<String, ColorIndexType> = HashMap::new();
+
+        let mut get_color_index = |name: &str| {
+            *colors_indices.entry(name.to_string()).or_insert_with(|| {
+                color_names.push(name.to_string());
+                (color_names.len() - 1) as ColorIndexType
+            })
+        };
+
+        let mut graph_inputs = Vec::with_capacity(input_graphs.len());
+        for input in input_graphs {
+            let colors_source = match (colors, input.colormap_file) {
+                (false, _) => GraphColorsSource::Static(0),
+                (true, Some(colormap_file)) => {
+                    let colors_deserializer =
+                        ColorsDeserializer::<DefaultColorsSerializer>::new(&colormap_file, true)?;
+
+                    let colors_remap = (0..colors_deserializer.colors_count())
+                        .map(|i| {
+                            get_color_index(
+                                colors_deserializer.get_color_name(i as ColorIndexType, false),
+                            )
+                        })
+                        .collect();
+
+                    GraphColorsSource::Colormap {
+                        colormap_file,
+                        colors_remap,
+                    }
+                }
+                (true, None) => GraphColorsSource::Static(get_color_index(
+                    &input
+                        .graph_file
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                )),
+            };
+
+            graph_inputs.push(GraphInput {
+                graph_file: input.graph_file,
+                colors: colors_source,
+            });
+        }
+
+        let graph_stream =
+            std::sync::Arc::new(GraphSequencesStream::new(kmer_length, graph_inputs)?);
+
+        let input_streams = (0..graph_stream.blocks_count())
+            .map(|block| {
+                GeneralSequenceBlockData::Dynamic((
+                    graph_stream.clone() as std::sync::Arc<dyn DynamicSequencesStream>,
+                    block,
+                ))
+            })
+            .collect();
+
+        self.build_graph(
+            input_streams,
+            output_file,
+            Some(&color_names),
+            kmer_length,
+            threads_count,
+            forward_only,
+            minimizer_length,
+            colors,
+            ColormapEncoding::default(),
+            MinMultiplicity::Fixed(1),
+            usize::MAX,
+            false,
+            None,
+            None,
+            AbundanceOutputMode::None,
+            None,
+            extra_elab,
+            gfa_output_version,
+            disk_optimization_level,
+        )
     }
 
     /// Queries a (optionally) colored graph with a specific set of sequences as queries
@@ -326,8 +1062,8 @@ impl GGCATInstance {
         &self,
         // The input graph
         input_graph: PathBuf,
-        // The input query as a .fasta file
-        input_query: PathBuf,
+        // The input queries, as files or dynamic streams
+        input_queries: Vec<GeneralSequenceBlockData>,
 
         // The output file
         output_file_prefix: PathBuf,
@@ -346,41 +1082,140 @@ impl GGCATInstance {
 
         // Query output format
         color_output_format: ColoredQueryOutputFormat,
-    ) -> anyhow::Result<PathBuf> {
-        PriorityScheduler::set_max_threads_count(threads_count);
 
-        let merging_hash_dispatch = utils::get_hash_static_id(
-            debug::DEBUG_HASH_TYPE.lock().clone(),
+        // Adds the run-length encoded hits of each query kmer to the output
+        emit_kmers_hits: bool,
+
+        // Adds the query names column to the uncolored csv output
+        emit_query_names: bool,
+
+        // Filters the reported queries (uncolored) or colors (colored)
+        results_filter: QueryResultsFilter,
+    ) -> Result<PathBuf, GGCATError> {
+        check_output_file(&output_file_prefix)?;
+
+        let output_file = self.run_query(
+            input_graph,
+            input_queries,
+            QueryOutput::File {
+                output_file_prefix,
+                colored_query_output_format: color_output_format,
+                emit_query_names,
+            },
             kmer_length,
+            threads_count,
             forward_only,
-        );
+            minimizer_length,
+            colors,
+            emit_kmers_hits,
+            results_filter,
+        )?;
 
-        let colors_hash = if colors {
-            ColorBundleGraphQuerying::dynamic_dispatch_id()
-        } else {
-            NonColoredManager::dynamic_dispatch_id()
-        };
+        Ok(output_file.unwrap())
+    }
 
-        let temp_dir = create_tempdir(self.0.temp_dir.clone());
+    /// Queries a (optionally) colored graph with a specific set of sequences as queries,
+    /// delivering the results to the output function instead of writing them to a file.
+    /// The output function receives `(query_index, query_name, matched_kmers, query_kmers, colors, kmers_hits)`
+    /// for each query sequence, in order and from a single thread at a time.
+    /// The colors are given as pairs of `(color, matched_kmers)` if the query is colored,
+    /// the kmers hits are the runs of consecutive query kmers sharing the same match status,
+    /// only if `emit_kmers_hits` is enabled
+    pub fn query_graph_with_callback(
+        &self,
+        // The input graph
+        input_graph: PathBuf,
+        // The input queries, as files or dynamic streams
+        input_queries: Vec<GeneralSequenceBlockData>,
 
-        let output_file = querier::dynamic_dispatch::run_query(
-            (merging_hash_dispatch, colors_hash),
-            kmer_length,
-            minimizer_length.unwrap_or(::utils::compute_best_m(kmer_length)),
-            debug::DEBUG_QUERIER_FIRST_STEP.lock().clone(),
+        // Specifies the k-mers length
+        kmer_length: usize,
+        // The threads to be used
+        threads_count: usize,
+        // Treats reverse complementary kmers as different
+        forward_only: bool,
+        // Overrides the default m-mers (minimizers) length
+        minimizer_length: Option<usize>,
+
+        // Enable colors
+        colors: bool,
+
+        // Computes the run-length encoded hits of each query kmer
+        emit_kmers_hits: bool,
+
+        // Filters the reported queries (uncolored) or colors (colored)
+        results_filter: QueryResultsFilter,
+
+        output_function: impl Fn(u64, &[u8], u64, u64, Option<&[(ColorIndexType, u64)]>, Option<&[QueryKmersRun]>)
+            + Send
+            + Sync,
+    ) -> Result<(), GGCATError> {
+        self.run_query(
             input_graph,
-            input_query,
-            output_file_prefix,
-            temp_dir.clone(),
-            *debug::BUCKETS_COUNT_LOG_FORCE.lock(),
+            input_queries,
+            QueryOutput::Callback(&output_function),
+            kmer_length,
             threads_count,
-            self.0.intermediate_compression_level,
-            color_output_format,
+            forward_only,
+            minimizer_length,
+            colors,
+            emit_kmers_hits,
+            results_filter,
         )?;
+        Ok(())
+    }
+
+    fn run_query(
+        &self,
+        input_graph: PathBuf,
+        input_queries: Vec<GeneralSequenceBlockData>,
+        output: QueryOutput<'_>,
+        kmer_length: usize,
+        threads_count: usize,
+        forward_only: bool,
+        minimizer_length: Option<usize>,
+        colors: bool,
+        emit_kmers_hits: bool,
+        results_filter: QueryResultsFilter,
+    ) -> Result<Option<PathBuf>, GGCATError> {
+        SequencesReader::check_file(&input_graph)?;
+        colors::graph_stream::check_graph_kmer_length(&input_graph, kmer_length)?;
+        check_input_streams(&input_queries)?;
+
+        let output_file = self.run_operation(|debug_config| {
+            let merging_hash_dispatch =
+                utils::get_hash_static_id(debug_config.hash_type, kmer_length, forward_only);
+
+            let colors_hash = if colors {
+                ColorBundleGraphQuerying::dynamic_dispatch_id()
+            } else {
+                NonColoredManager::dynamic_dispatch_id()
+            };
 
-        remove_tempdir(temp_dir);
+            let temp_dir = create_tempdir(self.config.temp_dir.clone());
 
-        Ok(output_file)
+            let output_file = querier::dynamic_dispatch::run_query(
+                (merging_hash_dispatch, colors_hash),
+                kmer_length,
+                minimizer_length.unwrap_or(::utils::compute_best_m(kmer_length)),
+                debug_config.querier_first_step,
+                input_graph,
+                input_queries,
+                output,
+                Some(temp_dir.clone()),
+                debug_config.buckets_count_log_force,
+                threads_count,
+                self.config.intermediate_compression_level,
+                emit_kmers_hits,
+                results_filter,
+            );
+
+            remove_tempdir(temp_dir, &output_file);
+
+            output_file
+        });
+
+        Ok(output_file?)
     }
 
     /// Obtains the standard colormap file path from a graph file path
@@ -394,7 +1229,7 @@ impl GGCATInstance {
     pub fn dump_colors(
         // The input colormap
         input_colormap: impl AsRef<Path>,
-    ) -> anyhow::Result<impl Iterator<Item = String>> {
+    ) -> Result<impl Iterator<Item = String>, GGCATError> {
         use colors::colors_manager::ColorMapReader;
         use colors::storage::deserializer::ColorsDeserializer;
         use colors::DefaultColorsSerializer;
@@ -409,6 +1244,75 @@ impl GGCATInstance {
         }))
     }
 
+    /// Computes the statistics of a FASTA or GFA graph.
+    /// If colors is true, the color subsets are read from the colormap associated to the graph,
+    /// while NG50 is reported only if the expected genome size is specified
+    pub fn graph_stats(
+        graph_input: impl AsRef<Path>,
+        // Specifies the k-mers length
+        kmer_length: usize,
+        colors: bool,
+        genome_size: Option<u64>,
+    ) -> Result<GraphStats, GGCATError> {
+        let graph_input = graph_input.as_ref();
+        SequencesReader::check_file(graph_input)?;
+        colors::graph_stream::check_graph_kmer_length(graph_input, kmer_length)?;
+
+        let colormap_file = colors.then(|| Self::get_colormap_file(graph_input));
+        Ok(dumper::compute_graph_stats(
+            graph_input,
+            kmer_length,
+            genome_size,
+            colormap_file.as_deref(),
+        )?)
+    }
+
+    /// Reads the header fields and the chunks index of a colormap
+    pub fn colormap_info(input_colormap: impl AsRef<Path>) -> Result<ColormapInfo, GGCATError> {
+        use colors::storage::inspector::read_colormap_info;
+
+        Ok(read_colormap_info(input_colormap)?)
+    }
+
+    /// Decodes all the color subsets of a colormap, checking its consistency.
+    /// Returns the list of the issues found, empty if the colormap is valid
+    pub fn verify_colormap(input_colormap: impl AsRef<Path>) -> Result<Vec<String>, GGCATError> {
+        use colors::storage::inspector::verify_colormap;
+
+        Ok(verify_colormap(input_colormap)?)
+    }
+
+    /// Rewrites a colormap created by an older ggcat release with the current storage format,
+    /// without rebuilding its graph. The colormap is upgraded in place if no output file is given.
+    /// Returns false if the colormap is already up to date and nothing was written
+    pub fn upgrade_colormap(
+        input_colormap: impl AsRef<Path>,
+        output_colormap: Option<PathBuf>,
+        // Converts the colormap to a different encoding, by default its own encoding is kept
+        encoding: Option<ColormapEncoding>,
+    ) -> Result<bool, GGCATError> {
+        use colors::storage::versions::upgrade_colormap;
+
+        let input_colormap = input_colormap.as_ref();
+        Ok(upgrade_colormap(
+            input_colormap,
+            output_colormap.as_deref().unwrap_or(input_colormap),
+            encoding,
+        )?)
+    }
+
+    /// Returns an iterator over all the color subsets of a colormap, with their indices
+    pub fn dump_colormap_subsets(
+        input_colormap: impl AsRef<Path>,
+    ) -> Result<impl Iterator<Item = (ColorIndexType, Vec<ColorIndexType>)>, GGCATError> {
+        use colors::storage::inspector::read_colormap_subsets;
+        use colors::DefaultColorsSerializer;
+
+        Ok(read_colormap_subsets::<DefaultColorsSerializer>(
+            input_colormap,
+        )?)
+    }
+
     /// Queries specified color subsets of the colormap, returning
     /// the color indices corresponding to the colors of each subset
     pub fn query_colormap(
@@ -421,13 +1325,15 @@ impl GGCATInstance {
         // avoiding the need for synchronization in the user code
         single_thread_output_function: bool,
         output_function: impl Fn(ColorIndexType, &[ColorIndexType]) + Send + Sync,
-    ) -> anyhow::Result<()> {
-        dumper::dump_colormap_query(
-            colormap_file,
-            subsets,
-            single_thread_output_function,
-            output_function,
-        )
+    ) -> Result<(), GGCATError> {
+        Ok(self.run_operation(|_| {
+            dumper::dump_colormap_query(
+                colormap_file,
+                subsets,
+                single_thread_output_function,
+                output_function,
+            )
+        })?)
     }
 
     /// Dumps the unitigs of the given graph, optionally with colors
@@ -448,35 +1354,472 @@ impl GGCATInstance {
         // avoiding the need for synchronization in the user code
         single_thread_output_function: bool,
         output_function: impl Fn(&[u8], &[ColorIndexType], bool) + Send + Sync,
-    ) -> anyhow::Result<()> {
-        PriorityScheduler::set_max_threads_count(threads_count);
+    ) -> Result<(), GGCATError> {
+        SequencesReader::check_file(&graph_input)?;
+        colors::graph_stream::check_graph_kmer_length(graph_input.as_ref(), kmer_length)?;
+
+        let graph_input = graph_input.as_ref().to_path_buf();
+
+        let result = self.run_operation(|debug_config| {
+            let temp_dir = create_tempdir(self.config.temp_dir.clone());
+
+            let result = if colors {
+                dumper::dump_unitigs(
+                    kmer_length,
+                    minimizer_length.unwrap_or(::utils::compute_best_m(kmer_length)),
+                    &graph_input,
+                    Some(temp_dir.clone()),
+                    debug_config.buckets_count_log_force,
+                    threads_count,
+                    single_thread_output_function,
+                    self.config.intermediate_compression_level,
+                    output_function,
+                )
+            } else {
+                GeneralSequencesStream::new().read_block(
+                    &GeneralSequenceBlockData::from_file(graph_input.clone(), None),
+                    false,
+                    Some(kmer_length - 1),
+                    |seq, _info| {
+                        output_function(seq.seq, &[], false);
+                    },
+                );
+                Ok(())
+            };
+
+            remove_tempdir(temp_dir, &result);
+            result
+        });
 
-        let temp_dir = create_tempdir(self.0.temp_dir.clone());
+        Ok(result?)
+    }
+}
 
-        if colors {
-            dumper::dump_unitigs(
-                kmer_length,
-                minimizer_length.unwrap_or(::utils::compute_best_m(kmer_length)),
-                &graph_input,
-                temp_dir.clone(),
-                *debug::BUCKETS_COUNT_LOG_FORCE.lock(),
-                threads_count,
-                single_thread_output_function,
-                self.0.intermediate_compression_level,
-                output_function,
-            )?;
-        } else {
-            FastaFileSequencesStream::new().read_block(
-                &(graph_input.as_ref().to_path_buf(), None),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::utils::temp_dir::TempDir;
+
+    fn test_config(temp_dir: Option<PathBuf>) -> GGCATConfig {
+        GGCATConfig {
+            temp_dir,
+            memory: 1.0,
+            prefer_memory: true,
+            total_threads_count: 2,
+            intermediate_compression_level: None,
+            stats_file: None,
+            messages_callback: Some(|_, _| {}),
+            disk_optimization_level: 0,
+            progress_callback: None,
+            cancellation_token: None,
+        }
+    }
+
+    /// Pseudo-random bases, deterministic to keep the kmers of the test sequences distinct
+    fn random_bases(length: usize, mut seed: u64) -> Vec<u8> {
+        (0..length)
+            .map(|_| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                b"ACGT"[(seed >> 62) as usize]
+            })
+            .collect()
+    }
+
+    fn read_fasta_sequences(file: &Path) -> Vec<String> {
+        std::fs::read_to_string(file)
+            .unwrap()
+            .lines()
+            .filter(|line| !line.starts_with('>'))
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    fn write_reads(file: &Path, reads: &[&[u8]]) {
+        let mut fasta = String::new();
+        for (index, read) in reads.iter().enumerate() {
+            fasta.push_str(&format!(
+                ">{}\n{}\n",
+                index,
+                std::str::from_utf8(read).unwrap()
+            ));
+        }
+        std::fs::write(file, fasta).unwrap();
+    }
+
+    /// Checks if the output sequence is the expected one, in any of the two orientations
+    fn is_same_sequence(output: &str, expected: &[u8]) -> bool {
+        let reverse_complement: String = expected
+            .iter()
+            .rev()
+            .map(|base| match base {
+                b'A' => 'T',
+                b'C' => 'G',
+                b'G' => 'C',
+                _ => 'A',
+            })
+            .collect();
+        output.as_bytes() == expected || output == reverse_complement
+    }
+
+    #[test]
+    fn clean_graph_without_abundances() {
+        let temp_dir = TempDir::new("ggcat-api-clean-graph");
+        let instance = GGCATInstance::create(test_config(Some(temp_dir.join("tmp")))).unwrap();
+
+        let k = 21;
+        // A SNP in the middle of the sequence creates a bubble, with the branch of the variant
+        // read only once while the main sequence is read ten times
+        let sequence = random_bases(300, 7);
+        let mut variant = sequence.clone();
+        variant[150] = if variant[150] == b'A' { b'C' } else { b'A' };
+
+        let input_file = temp_dir.join("reads.fa");
+        let mut reads = String::new();
+        for (index, read) in std::iter::repeat(&sequence)
+            .take(10)
+            .chain([&variant])
+            .enumerate()
+        {
+            reads.push_str(&format!(
+                ">{}\n{}\n",
+                index,
+                std::str::from_utf8(read).unwrap()
+            ));
+        }
+        std::fs::write(&input_file, reads).unwrap();
+
+        let output_file = temp_dir.join("cleaned.fa");
+        instance
+            .build_graph(
+                vec![GeneralSequenceBlockData::FASTA((input_file, None))],
+                output_file.clone(),
+                None,
+                k,
+                2,
+                false,
+                None,
+                false,
+                ColormapEncoding::default(),
+                MinMultiplicity::Fixed(1),
+                usize::MAX,
+                false,
+                None,
+                None,
+                AbundanceOutputMode::None,
+                Some(GraphCleaningConfig::new(k)),
+                ExtraElaboration::None,
+                None,
+                0,
+            )
+            .unwrap();
+
+        let sequences = read_fasta_sequences(&output_file);
+        assert_eq!(sequences.len(), 1);
+        assert!(is_same_sequence(&sequences[0], &sequence));
+    }
+
+    #[test]
+    fn colored_multiplicity_thresholds() {
+        let temp_dir = TempDir::new("ggcat-api-colored-multiplicity");
+        let instance = GGCATInstance::create(test_config(Some(temp_dir.join("tmp")))).unwrap();
+
+        let k = 21;
+        // Only the first sequence is inside the thresholds, the second one is too rare and the third one too frequent
+        let solid = random_bases(300, 5);
+        let rare = random_bases(100, 13);
+        let frequent = random_bases(100, 17);
+
+        let input_files = [temp_dir.join("a.fa"), temp_dir.join("b.fa")];
+        write_reads(&input_files[0], &[&solid, &solid, &rare]);
+        write_reads(
+            &input_files[1],
+            &[&solid, &frequent, &frequent, &frequent, &frequent],
+        );
+
+        let output_file = temp_dir.join("colored.fa");
+        instance
+            .build_graph(
+                input_files
+                    .iter()
+                    .enumerate()
+                    .map(|(color, file)| {
+                        GeneralSequenceBlockData::FASTA((file.clone(), Some(color as u32)))
+                    })
+                    .collect(),
+                output_file.clone(),
+                Some(&["a".to_string(), "b".to_string()]),
+                k,
+                2,
+                false,
+                None,
+                true,
+                ColormapEncoding::default(),
+                MinMultiplicity::Fixed(2),
+                3,
+                false,
+                None,
+                None,
+                AbundanceOutputMode::None,
+                None,
+                ExtraElaboration::None,
+                None,
+                0,
+            )
+            .unwrap();
+
+        let sequences = read_fasta_sequences(&output_file);
+        assert_eq!(sequences.len(), 1);
+        assert!(is_same_sequence(&sequences[0], &solid));
+    }
+
+    #[test]
+    fn query_names_across_blocks() {
+        let temp_dir = TempDir::new("ggcat-api-query-names");
+        let instance = GGCATInstance::create(test_config(Some(temp_dir.join("tmp")))).unwrap();
+
+        let k = 21;
+        let sequence = random_bases(200, 3);
+        let missing = random_bases(40, 11);
+        let bases = |range: std::ops::Range<usize>| std::str::from_utf8(&sequence[range]).unwrap();
+
+        let input_file = temp_dir.join("reads.fa");
+        std::fs::write(&input_file, format!(">read\n{}\n", bases(0..200))).unwrap();
+        let graph_file = temp_dir.join("graph.fa");
+        instance
+            .build_graph(
+                vec![GeneralSequenceBlockData::FASTA((input_file, None))],
+                graph_file.clone(),
+                None,
+                k,
+                2,
+                false,
+                None,
+                false,
+                ColormapEncoding::default(),
+                MinMultiplicity::Fixed(1),
+                usize::MAX,
                 false,
-                Some(kmer_length - 1),
-                |seq, _info| {
-                    output_function(seq.ident_data, &[], false);
+                None,
+                None,
+                AbundanceOutputMode::None,
+                None,
+                ExtraElaboration::None,
+                None,
+                0,
+            )
+            .unwrap();
+
+        // The queries are numbered across the two files, the third query is shorter than k
+        let queries_files = [temp_dir.join("queries1.fa"), temp_dir.join("queries2.fa")];
+        std::fs::write(
+            &queries_files[0],
+            format!(
+                ">first description\n{}\n>second\n{}\n",
+                bases(0..50),
+                std::str::from_utf8(&missing).unwrap()
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            &queries_files[1],
+            format!(">short\nACGT\n>third\n{}\n", bases(100..160)),
+        )
+        .unwrap();
+
+        let results = Mutex::new(vec![]);
+        instance
+            .query_graph_with_callback(
+                graph_file,
+                queries_files
+                    .iter()
+                    .map(|file| GeneralSequenceBlockData::FASTA((file.clone(), None)))
+                    .collect(),
+                k,
+                2,
+                false,
+                None,
+                false,
+                false,
+                QueryResultsFilter::default(),
+                |query_index, query_name, matched_kmers, query_kmers, _, _| {
+                    results.lock().push((
+                        query_index,
+                        String::from_utf8(query_name.to_vec()).unwrap(),
+                        matched_kmers,
+                        query_kmers,
+                    ));
                 },
-            );
+            )
+            .unwrap();
+
+        assert_eq!(
+            results.into_inner(),
+            [
+                (0, "first".to_string(), 30, 30),
+                (1, "second".to_string(), 0, 20),
+                (2, "short".to_string(), 0, 0),
+                (3, "third".to_string(), 40, 40),
+            ]
+        );
+    }
+
+    #[test]
+    fn update_graph_colors() {
+        let temp_dir = TempDir::new("ggcat-api-update-graph");
+        let instance = GGCATInstance::create(test_config(Some(temp_dir.join("tmp")))).unwrap();
+
+        let k = 21;
+        let shared = random_bases(300, 7);
+        let repeated = random_bases(200, 11);
+        let rare = random_bases(100, 19);
+
+        let input_files = [temp_dir.join("a.fa"), temp_dir.join("b.fa")];
+        write_reads(&input_files[0], &[&shared]);
+        write_reads(&input_files[1], &[&shared, &repeated]);
+
+        let graph_file = temp_dir.join("graph.fa");
+        instance
+            .build_graph(
+                input_files
+                    .iter()
+                    .enumerate()
+                    .map(|(color, file)| {
+                        GeneralSequenceBlockData::FASTA((file.clone(), Some(color as u32)))
+                    })
+                    .collect(),
+                graph_file.clone(),
+                Some(&["a".to_string(), "b".to_string()]),
+                k,
+                2,
+                false,
+                None,
+                true,
+                ColormapEncoding::default(),
+                MinMultiplicity::Fixed(1),
+                usize::MAX,
+                false,
+                None,
+                None,
+                AbundanceOutputMode::None,
+                None,
+                ExtraElaboration::None,
+                None,
+                0,
+            )
+            .unwrap();
+
+        // Only the repeated sequence is found enough times in the new sample to get its color
+        let new_input = temp_dir.join("c.fa");
+        write_reads(&new_input, &[&shared, &repeated, &repeated, &rare]);
+
+        let updated_file = temp_dir.join("updated.fa");
+        instance
+            .update_graph(
+                graph_file,
+                None,
+                vec![GeneralSequenceBlockData::FASTA((new_input, None))],
+                &["c".to_string()],
+                updated_file.clone(),
+                k,
+                2,
+                false,
+                None,
+                2,
+                ExtraElaboration::None,
+                None,
+                0,
+            )
+            .unwrap();
+
+        let unitigs = Mutex::new(vec![]);
+        instance
+            .dump_unitigs(
+                &updated_file,
+                k,
+                None,
+                true,
+                2,
+                true,
+                |sequence, colors, _| {
+                    unitigs.lock().push((
+                        String::from_utf8(sequence.to_vec()).unwrap(),
+                        colors.to_vec(),
+                    ))
+                },
+            )
+            .unwrap();
+
+        let unitigs = unitigs.into_inner();
+        assert_eq!(unitigs.len(), 2);
+        for (sequence, colors) in unitigs {
+            if is_same_sequence(&sequence, &shared) {
+                assert_eq!(colors, vec![0, 1]);
+            } else {
+                assert!(is_same_sequence(&sequence, &repeated));
+                assert_eq!(colors, vec![1, 2]);
+            }
         }
+    }
 
-        remove_tempdir(temp_dir);
-        Ok(())
+    #[test]
+    fn concurrent_memory_only_budgets() {
+        let temp_dir = TempDir::new("ggcat-api-concurrent-budgets");
+
+        let k = 21;
+        let sequence = random_bases(5000, 1);
+        let input_file = temp_dir.join("reads.fa");
+        write_reads(&input_file, &[sequence.as_slice(); 4]);
+
+        // Both instances keep their temporary files in memory, with different budgets and threads
+        let large_instance = GGCATInstance::create(test_config(None)).unwrap();
+        let small_instance = GGCATInstance::create(GGCATConfig {
+            memory: 0.000001,
+            total_threads_count: 1,
+            ..test_config(None)
+        })
+        .unwrap();
+
+        let build = |instance: &GGCATInstance, output_file: PathBuf| {
+            instance.build_graph(
+                vec![GeneralSequenceBlockData::FASTA((input_file.clone(), None))],
+                output_file,
+                None,
+                k,
+                instance.config.total_threads_count,
+                false,
+                None,
+                false,
+                ColormapEncoding::default(),
+                MinMultiplicity::Fixed(1),
+                usize::MAX,
+                false,
+                None,
+                None,
+                AbundanceOutputMode::None,
+                None,
+                ExtraElaboration::None,
+                None,
+                0,
+            )
+        };
+
+        let large_output = temp_dir.join("large.fa");
+        let (small_result, large_result) = std::thread::scope(|scope| {
+            let small = scope.spawn(|| build(&small_instance, temp_dir.join("small.fa")));
+            let large = scope.spawn(|| build(&large_instance, large_output.clone()));
+            (small.join().unwrap(), large.join().unwrap())
+        });
+
+        assert!(matches!(
+            small_result,
+            Err(GGCATError::MemoryBudgetExceeded(_))
+        ));
+        large_result.unwrap();
+
+        let sequences = read_fasta_sequences(&large_output);
+        assert_eq!(sequences.len(), 1);
+        assert!(is_same_sequence(&sequences[0], &sequence));
     }
 }
@@ -18,7 +18,7 @@ pub(crate) fn get_hash_static_id(
 
     let hash_type = match hash_type {
         HashType::Auto => {
-            if k <= 64 {
+            if k <= 128 {
                 HashType::SeqHash
             } else {
                 HashType::RabinKarp128
@@ -53,8 +53,14 @@ pub(crate) fn get_hash_static_id(
                 } else {
                     cn_seqhash::u128::CanonicalSeqHashFactory::dynamic_dispatch_id()
                 }
+            } else if k <= 128 {
+                if forward_only {
+                    fw_seqhash::u256::ForwardSeqHashFactory::dynamic_dispatch_id()
+                } else {
+                    cn_seqhash::u256::CanonicalSeqHashFactory::dynamic_dispatch_id()
+                }
             } else {
-                panic!("Cannot use sequence hash for k > 64!");
+                panic!("Cannot use sequence hash for k > 128!");
             }
         }
         HashType::RabinKarp32 => {
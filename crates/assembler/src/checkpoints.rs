@@ -0,0 +1,433 @@
+use crate::AssemblerStartingStep;
+use config::DEFAULT_OUTPUT_BUFFER_SIZE;
+use io::sequences_stream::general::GeneralSequenceBlockData;
+use io::FileIoError;
+use parallel_processor::buckets::{MultiChunkBucket, SingleBucket};
+use parallel_processor::memory_fs::file::internal::MemoryFileMode;
+use parallel_processor::memory_fs::file::writer::FileWriter;
+use parallel_processor::memory_fs::{MemoryFs, RemoveFileMode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest file written in the temporary directory after each completed phase
+const BUILD_MANIFEST_FILE_NAME: &str = "build-manifest.json";
+
+/// A build that cannot be resumed from the given temporary directory
+#[derive(Debug)]
+pub struct BuildResumeError {
+    pub temp_dir: PathBuf,
+    pub message: String,
+}
+
+impl Display for BuildResumeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot resume the build from '{}': {}",
+            self.temp_dir.display(),
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for BuildResumeError {}
+
+/// Returns true if the temporary directory contains the checkpoints of a build that can be resumed
+pub fn is_resumable_build_dir(temp_dir: impl AsRef<Path>) -> bool {
+    temp_dir.as_ref().join(BUILD_MANIFEST_FILE_NAME).is_file()
+}
+
+/// The parameters that must match to resume a build, the threads count can change between the runs
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub(crate) struct BuildParameters {
+    pub k: usize,
+    pub m: usize,
+    /// Path, color and size of each input file
+    pub inputs: Vec<String>,
+    pub color_names: Vec<String>,
+    pub colormap_encoding: String,
+    pub min_multiplicity: String,
+    pub max_multiplicity: usize,
+    pub per_color_min_multiplicity: bool,
+    pub min_base_quality: Option<u8>,
+    pub buckets_count_log: usize,
+    pub minimizer_bucketing_chunk_size: Option<u64>,
+    pub hash_type: String,
+    pub colored: bool,
+    pub output_type: String,
+    pub output_file: PathBuf,
+    pub abundance_mode: String,
+    pub compute_tigs_mode: String,
+    pub generate_maximal_unitigs_links: bool,
+    pub graph_cleaning: String,
+    pub kmers_counts: String,
+}
+
+/// Describes the input blocks with their sizes, to detect the files changed before resuming
+pub(crate) fn describe_inputs(input_blocks: &[GeneralSequenceBlockData]) -> Vec<String> {
+    input_blocks
+        .iter()
+        .map(|block| match block {
+            GeneralSequenceBlockData::FASTA((file, color))
+            | GeneralSequenceBlockData::GFA((file, color)) => format!(
+                "{} (color: {:?}, size: {})",
+                file.display(),
+                color,
+                std::fs::metadata(file).map(|meta| meta.len()).unwrap_or(0)
+            ),
+            GeneralSequenceBlockData::Dynamic((_, block)) => {
+                format!("dynamic stream block {}", block)
+            }
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct CheckpointBucket {
+    pub index: usize,
+    pub chunks: Vec<PathBuf>,
+    pub was_compacted: bool,
+}
+
+impl From<&MultiChunkBucket> for CheckpointBucket {
+    fn from(bucket: &MultiChunkBucket) -> Self {
+        Self {
+            index: bucket.index,
+            chunks: bucket.chunks.clone(),
+            was_compacted: bucket.was_compacted,
+        }
+    }
+}
+
+impl From<&SingleBucket> for CheckpointBucket {
+    fn from(bucket: &SingleBucket) -> Self {
+        Self {
+            index: bucket.index,
+            chunks: vec![bucket.path.clone()],
+            was_compacted: false,
+        }
+    }
+}
+
+impl CheckpointBucket {
+    pub fn to_multi_chunk(&self) -> MultiChunkBucket {
+        MultiChunkBucket {
+            index: self.index,
+            chunks: self.chunks.clone(),
+            was_compacted: self.was_compacted,
+        }
+    }
+
+    pub fn to_single(&self) -> SingleBucket {
+        SingleBucket {
+            index: self.index,
+            path: self.chunks[0].clone(),
+        }
+    }
+}
+
+/// The temporary files needed by the phases following the last completed one
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct CheckpointFiles {
+    pub buckets: Vec<CheckpointBucket>,
+    pub buckets_counters: Option<PathBuf>,
+    pub sequences: Vec<CheckpointBucket>,
+    pub hashes: Vec<CheckpointBucket>,
+    pub links: Vec<CheckpointBucket>,
+    pub unitigs_map: Vec<CheckpointBucket>,
+    pub reads_map: Vec<CheckpointBucket>,
+    /// The partitions of the solid kmers with their counts, in the kmers counting mode
+    pub kmers_counts: Vec<CheckpointBucket>,
+}
+
+impl CheckpointFiles {
+    fn all_paths(&self) -> impl Iterator<Item = &PathBuf> {
+        [
+            &self.buckets,
+            &self.sequences,
+            &self.hashes,
+            &self.links,
+            &self.unitigs_map,
+            &self.reads_map,
+            &self.kmers_counts,
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|bucket| bucket.chunks.iter())
+        .chain(self.buckets_counters.iter())
+    }
+}
+
+/// The colormap written by the kmers merge phase, its size is checked before resuming
+#[derive(Serialize, Deserialize, Debug)]
+struct ColormapState {
+    file: PathBuf,
+    size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BuildManifest {
+    parameters: BuildParameters,
+    completed_step: Option<AssemblerStartingStep>,
+    colormap: Option<ColormapState>,
+    files: CheckpointFiles,
+    /// The size of each checkpoint file when it was saved, to detect truncated files before resuming
+    files_sizes: BTreeMap<PathBuf, u64>,
+}
+
+/// Saves the manifest of the completed phases of a build, to restart it from the last one
+pub(crate) struct BuildCheckpoints {
+    temp_dir: PathBuf,
+    manifest: BuildManifest,
+    remove_unused_files: bool,
+}
+
+impl BuildCheckpoints {
+    /// Starts the checkpoints of a new build, with no completed phases
+    pub fn create(
+        temp_dir: &Path,
+        parameters: BuildParameters,
+        remove_unused_files: bool,
+    ) -> anyhow::Result<Self> {
+        let checkpoints = Self {
+            temp_dir: temp_dir.to_path_buf(),
+            manifest: BuildManifest {
+                parameters,
+                completed_step: None,
+                colormap: None,
+                files: CheckpointFiles::default(),
+                files_sizes: BTreeMap::new(),
+            },
+            remove_unused_files,
+        };
+        checkpoints.write_manifest()?;
+        config::set_checkpoint_files([]);
+        Ok(checkpoints)
+    }
+
+    /// Loads the checkpoints of an interrupted build, checking that it used the same parameters
+    /// and that the files of its last completed phase are still available
+    pub fn resume(
+        temp_dir: &Path,
+        parameters: BuildParameters,
+        remove_unused_files: bool,
+    ) -> anyhow::Result<Self> {
+        let resume_error = |message: String| BuildResumeError {
+            temp_dir: temp_dir.to_path_buf(),
+            message,
+        };
+
+        let manifest_path = temp_dir.join(BUILD_MANIFEST_FILE_NAME);
+        let manifest_file = File::open(&manifest_path).map_err(|_| {
+            resume_error(format!(
+                "the build manifest '{}' does not exist",
+                manifest_path.display()
+            ))
+        })?;
+        let manifest: BuildManifest = serde_json::from_reader(BufReader::new(manifest_file))
+            .map_err(|err| resume_error(format!("invalid build manifest: {}", err)))?;
+
+        if manifest.parameters != parameters {
+            let saved = serde_json::to_value(&manifest.parameters)?;
+            let current = serde_json::to_value(&parameters)?;
+            let differing = current
+                .as_object()
+                .unwrap()
+                .iter()
+                .find(|(name, value)| saved.get(name.as_str()) != Some(value));
+
+            return Err(resume_error(match differing {
+                Some((name, value)) => format!(
+                    "parameter '{}' is {} but the interrupted build used {}",
+                    name,
+                    value,
+                    saved.get(name.as_str()).unwrap_or(&serde_json::Value::Null)
+                ),
+                None => "the parameters differ from the ones of the interrupted build".to_string(),
+            })
+            .into());
+        }
+
+        // Restore the files of an interrupted import
+        for path in manifest.files.all_paths() {
+            let import_path = Self::import_path(path);
+            if import_path.is_file() {
+                std::fs::rename(&import_path, path).map_err(|err| FileIoError::new(path, err))?;
+            }
+        }
+
+        for path in manifest.files.all_paths() {
+            let size = std::fs::metadata(path)
+                .ok()
+                .filter(|meta| meta.is_file())
+                .map(|meta| meta.len());
+
+            match size {
+                None => {
+                    return Err(resume_error(format!(
+                        "the temporary file '{}' is missing",
+                        path.display()
+                    ))
+                    .into());
+                }
+                Some(size) if manifest.files_sizes.get(path) != Some(&size) => {
+                    return Err(resume_error(format!(
+                        "the temporary file '{}' is incomplete or was modified after the interruption",
+                        path.display()
+                    ))
+                    .into());
+                }
+                Some(_) => {}
+            }
+        }
+
+        if let Some(colormap) = &manifest.colormap {
+            let size = std::fs::metadata(&colormap.file).map(|m| m.len()).ok();
+            if size != Some(colormap.size) {
+                return Err(resume_error(format!(
+                    "the colormap '{}' was modified after the interruption",
+                    colormap.file.display()
+                ))
+                .into());
+            }
+        }
+
+        Self::import_files(&manifest.files)?;
+        config::set_checkpoint_files(manifest.files.all_paths().cloned());
+
+        Ok(Self {
+            temp_dir: temp_dir.to_path_buf(),
+            manifest,
+            remove_unused_files,
+        })
+    }
+
+    fn import_path(path: &Path) -> PathBuf {
+        let mut import_path = path.as_os_str().to_os_string();
+        import_path.push(".import");
+        PathBuf::from(import_path)
+    }
+
+    /// Rewrites the files of the checkpoint through the memory fs, as the size of the files
+    /// removed by the pipelines is subtracted from its stats, that would not include the files read from disk
+    fn import_files(files: &CheckpointFiles) -> anyhow::Result<()> {
+        for path in files.all_paths() {
+            let import_path = Self::import_path(path);
+            std::fs::rename(path, &import_path).map_err(|err| FileIoError::new(path, err))?;
+
+            let mut original =
+                File::open(&import_path).map_err(|err| FileIoError::new(&import_path, err))?;
+            let mut imported = BufWriter::with_capacity(
+                DEFAULT_OUTPUT_BUFFER_SIZE,
+                FileWriter::create(path, MemoryFileMode::DiskOnly),
+            );
+            std::io::copy(&mut original, &mut imported)
+                .map_err(|err| FileIoError::new(path, err))?;
+            drop(imported);
+
+            std::fs::remove_file(&import_path)
+                .map_err(|err| FileIoError::new(&import_path, err))?;
+        }
+        MemoryFs::flush_all_to_disk();
+        Ok(())
+    }
+
+    /// The first phase that was not completed by the resumed build
+    pub fn next_step(&self) -> AssemblerStartingStep {
+        match self.manifest.completed_step {
+            None => AssemblerStartingStep::MinimizerBucketing,
+            Some(AssemblerStartingStep::MinimizerBucketing) => AssemblerStartingStep::KmersMerge,
+            Some(AssemblerStartingStep::KmersMerge) => AssemblerStartingStep::HashesSorting,
+            Some(AssemblerStartingStep::HashesSorting) => AssemblerStartingStep::LinksCompaction,
+            Some(_) => AssemblerStartingStep::ReorganizeReads,
+        }
+    }
+
+    pub fn files(&self) -> &CheckpointFiles {
+        &self.manifest.files
+    }
+
+    /// Records the colormap completed by the kmers merge phase
+    pub fn set_colormap(&mut self, file: PathBuf) {
+        self.manifest.colormap = std::fs::metadata(&file).ok().map(|meta| ColormapState {
+            file,
+            size: meta.len(),
+        });
+    }
+
+    /// Saves the completed phase with the files needed by the next ones,
+    /// then removes the temporary files that are not needed anymore
+    pub fn save(
+        &mut self,
+        completed_step: AssemblerStartingStep,
+        files: CheckpointFiles,
+    ) -> anyhow::Result<()> {
+        // The files must be completely on disk before the manifest refers to them
+        MemoryFs::flush_all_to_disk();
+
+        let mut files_sizes = BTreeMap::new();
+        for path in files.all_paths() {
+            let size = std::fs::metadata(path)
+                .map_err(|err| FileIoError::new(path, err))?
+                .len();
+            files_sizes.insert(path.clone(), size);
+        }
+
+        self.manifest.completed_step = Some(completed_step);
+        self.manifest.files = files;
+        self.manifest.files_sizes = files_sizes;
+        self.write_manifest()?;
+
+        // The files of the previous checkpoint are not needed anymore and can be removed by the pipelines
+        config::set_checkpoint_files(self.manifest.files.all_paths().cloned());
+
+        if self.remove_unused_files {
+            let used_files: HashSet<_> = self.manifest.files.all_paths().collect();
+            for entry in std::fs::read_dir(&self.temp_dir)?.flatten() {
+                let path = entry.path();
+                if path.is_file()
+                    && !used_files.contains(&path)
+                    && path.file_name() != Some(BUILD_MANIFEST_FILE_NAME.as_ref())
+                {
+                    let _ =
+                        MemoryFs::remove_file(&path, RemoveFileMode::Remove { remove_fs: true });
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Called when the build is completed, removing the files of the last checkpoint
+    pub fn finish(self) {
+        config::clear_checkpoint_files();
+        if self.remove_unused_files {
+            for path in self.manifest.files.all_paths() {
+                let _ = MemoryFs::remove_file(path, RemoveFileMode::Remove { remove_fs: true });
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    /// Writes the manifest to a temporary file before renaming it, to keep the previous one if interrupted
+    fn write_manifest(&self) -> anyhow::Result<()> {
+        let manifest_path = self.temp_dir.join(BUILD_MANIFEST_FILE_NAME);
+        let temp_manifest_path = manifest_path.with_extension("json.tmp");
+
+        let mut writer = BufWriter::new(
+            File::create(&temp_manifest_path)
+                .map_err(|err| FileIoError::new(&temp_manifest_path, err))?,
+        );
+        serde_json::to_writer_pretty(&mut writer, &self.manifest)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+
+        std::fs::rename(&temp_manifest_path, &manifest_path)?;
+        Ok(())
+    }
+}
@@ -1,20 +1,30 @@
 #![cfg_attr(feature = "devel-build", allow(unused_imports))]
 
+use crate::checkpoints::{BuildCheckpoints, BuildParameters, CheckpointBucket, CheckpointFiles};
 use crate::pipeline::build_unitigs::build_unitigs;
 use crate::pipeline::compute_matchtigs::MatchtigHelperTrait;
 use crate::pipeline::compute_matchtigs::{compute_matchtigs_thread, MatchtigsStorageBackend};
+use crate::pipeline::graph_cleaning::clean_graph;
 use crate::pipeline::hashes_sorting::hashes_sorting;
+use crate::pipeline::kmers_counts_output::write_kmers_counts;
 use crate::pipeline::links_compaction::links_compaction;
 use crate::pipeline::maximal_unitig_links::build_maximal_unitigs_links;
+use crate::pipeline::maximal_unitig_links::maximal_unitig_index::DoubleMaximalUnitigLinks;
 use crate::pipeline::reorganize_reads::reorganize_reads;
 use ::dynamic_dispatch::dynamic_dispatch;
+use ::structs::unitigs_counters::{UnitigsCounters, UnitigsCountersStorage};
+use assembler_kmers_merge::kmers_counts::kmers_counts_partition_bases;
 use assembler_kmers_merge::structs::RetType;
 use colors::colors_manager::ColorsManager;
 use colors::colors_manager::ColorsMergeManager;
+use colors::storage::ColormapEncoding;
+use config::operation::{MemoryTrackedBucket, OperationContext};
+use config::progress::{check_cancelled, ProgressPhase, ProgressUnit};
 use config::{
-    get_compression_level_info, get_memory_mode, SwapPriority, DEFAULT_PER_CPU_BUFFER_SIZE,
-    INTERMEDIATE_COMPRESSION_LEVEL_FAST, INTERMEDIATE_COMPRESSION_LEVEL_SLOW, KEEP_FILES,
-    MAXIMUM_SECOND_BUCKETS_LOG, MINIMUM_LOG_DELTA_TIME, PRIORITY_SCHEDULING_BASE,
+    check_memory_only_budget, get_compression_level_info, get_memory_mode, is_memory_only,
+    keep_file_on_disk, keep_files, set_intermediate_compression_level, SwapPriority,
+    DEFAULT_PER_CPU_BUFFER_SIZE, MAXIMUM_SECOND_BUCKETS_LOG, MINIMUM_LOG_DELTA_TIME,
+    PRIORITY_SCHEDULING_BASE,
 };
 use hashes::HashFunctionFactory;
 use io::concurrent::structured_sequences::binary::StructSeqBinaryWriter;
@@ -22,11 +32,11 @@ use io::concurrent::structured_sequences::fasta::FastaWriterWrapper;
 use io::concurrent::structured_sequences::gfa::GFAWriterWrapperV1;
 use io::concurrent::structured_sequences::gfa::GFAWriterWrapperV2;
 use io::concurrent::structured_sequences::{
-    IdentSequenceWriter, StructuredSequenceBackend, StructuredSequenceBackendInit,
-    StructuredSequenceBackendWrapper, StructuredSequenceWriter,
+    AbundanceOutputMode, IdentSequenceWriter, StructuredSequenceBackend,
+    StructuredSequenceBackendInit, StructuredSequenceBackendWrapper, StructuredSequenceWriter,
 };
-use io::sequences_stream::general::GeneralSequenceBlockData;
-use io::{compute_stats_from_input_blocks, generate_bucket_names};
+use io::sequences_stream::general::{find_color_subsets, GeneralSequenceBlockData};
+use io::{compute_stats_from_input_blocks, generate_bucket_names, FileIoError};
 use parallel_processor::buckets::concurrent::BucketsThreadBuffer;
 use parallel_processor::buckets::writers::compressed_binary_writer::CompressedCheckpointSize;
 use parallel_processor::buckets::writers::lock_free_binary_writer::LockFreeBinaryWriter;
@@ -37,18 +47,23 @@ use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
 use parallel_processor::scheduler::PriorityScheduler;
 use parallel_processor::utils::scoped_thread_local::ScopedThreadLocal;
 use pipeline::eulertigs::build_eulertigs;
+use serde::{Deserialize, Serialize};
 use std::fs::remove_file;
 use std::path::PathBuf;
-use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Instant;
 
+mod checkpoints;
 mod pipeline;
 mod structs;
 
+pub use assembler_kmers_merge::abundance_histogram::MinMultiplicity;
+pub use assembler_kmers_merge::kmers_counts::{KmersCountsFormat, KmersCountsOutput};
+pub use checkpoints::{is_resumable_build_dir, BuildResumeError};
 pub use pipeline::compute_matchtigs::MatchtigMode;
+pub use pipeline::graph_cleaning::GraphCleaningConfig;
 
-#[derive(Clone, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum AssemblerStartingStep {
     MinimizerBucketing = 0,
     KmersMerge = 1,
@@ -65,15 +80,16 @@ fn get_writer<
     W: StructuredSequenceBackend<C, L> + StructuredSequenceBackendInit,
 >(
     output_file: &PathBuf,
-) -> W {
-    match output_file.extension() {
+) -> anyhow::Result<W> {
+    let writer = match output_file.extension() {
         Some(ext) => match ext.to_string_lossy().to_string().as_str() {
             "lz4" => W::new_compressed_lz4(&output_file, 2),
             "gz" => W::new_compressed_gzip(&output_file, 2),
             _ => W::new_plain(&output_file),
         },
         None => W::new_plain(&output_file),
-    }
+    };
+    Ok(writer.map_err(|err| FileIoError::new(output_file, err))?)
 }
 
 #[dynamic_dispatch(MergingHash = [
@@ -81,6 +97,7 @@ fn get_writer<
     #[cfg(not(feature = "devel-build"))] hashes::fw_seqhash::u32::ForwardSeqHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::fw_seqhash::u64::ForwardSeqHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::fw_seqhash::u128::ForwardSeqHashFactory,
+    #[cfg(not(feature = "devel-build"))] hashes::fw_seqhash::u256::ForwardSeqHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::fw_rkhash::u32::ForwardRabinKarpHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::fw_rkhash::u64::ForwardRabinKarpHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::fw_rkhash::u128::ForwardRabinKarpHashFactory,
@@ -88,6 +105,7 @@ fn get_writer<
     #[cfg(not(feature = "devel-build"))] hashes::cn_seqhash::u32::CanonicalSeqHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::cn_seqhash::u64::CanonicalSeqHashFactory,
     hashes::cn_seqhash::u128::CanonicalSeqHashFactory,
+    #[cfg(not(feature = "devel-build"))] hashes::cn_seqhash::u256::CanonicalSeqHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::cn_rkhash::u32::CanonicalRabinKarpHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::cn_rkhash::u64::CanonicalRabinKarpHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::cn_rkhash::u128::CanonicalRabinKarpHashFactory,
@@ -110,16 +128,128 @@ pub fn run_assembler<
     last_step: AssemblerStartingStep,
     input_blocks: Vec<GeneralSequenceBlockData>,
     color_names: &[String],
+    colormap_encoding: ColormapEncoding,
+    output_file: PathBuf,
+    temp_dir: Option<PathBuf>,
+    resume: bool,
+    threads_count: usize,
+    min_multiplicity: MinMultiplicity,
+    max_multiplicity: usize,
+    per_color_min_multiplicity: bool,
+    abundance_histogram_file: Option<PathBuf>,
+    kmers_counts: Option<KmersCountsOutput>,
+    min_base_quality: Option<u8>,
+    abundance_mode: AbundanceOutputMode,
+    buckets_count_log: Option<usize>,
+    loopit_number: Option<usize>,
+    default_compression_level: Option<u32>,
+    generate_maximal_unitigs_links: bool,
+    compute_tigs_mode: Option<MatchtigMode>,
+    graph_cleaning: Option<GraphCleaningConfig>,
+    only_bstats: bool,
+    minimizer_bucketing_chunk_size: Option<u64>,
+) -> anyhow::Result<PathBuf> {
+    // The graph cleaning ranks the tips and the bubble branches by their coverage and the kmers counts
+    // are written after the colors are assigned, so they need the multiplicities also without abundances
+    if abundance_mode != AbundanceOutputMode::None
+        || graph_cleaning.is_some()
+        || kmers_counts.is_some()
+    {
+        run_assembler_with_counters::<
+            MergingHash,
+            AssemblerColorsManager,
+            OutputMode,
+            UnitigsCounters,
+        >(
+            k,
+            m,
+            step,
+            last_step,
+            input_blocks,
+            color_names,
+            colormap_encoding,
+            output_file,
+            temp_dir,
+            resume,
+            threads_count,
+            min_multiplicity,
+            max_multiplicity,
+            per_color_min_multiplicity,
+            abundance_histogram_file,
+            kmers_counts,
+            min_base_quality,
+            abundance_mode,
+            buckets_count_log,
+            loopit_number,
+            default_compression_level,
+            generate_maximal_unitigs_links,
+            compute_tigs_mode,
+            graph_cleaning,
+            only_bstats,
+            minimizer_bucketing_chunk_size,
+        )
+    } else {
+        run_assembler_with_counters::<MergingHash, AssemblerColorsManager, OutputMode, ()>(
+            k,
+            m,
+            step,
+            last_step,
+            input_blocks,
+            color_names,
+            colormap_encoding,
+            output_file,
+            temp_dir,
+            resume,
+            threads_count,
+            min_multiplicity,
+            max_multiplicity,
+            per_color_min_multiplicity,
+            abundance_histogram_file,
+            kmers_counts,
+            min_base_quality,
+            abundance_mode,
+            buckets_count_log,
+            loopit_number,
+            default_compression_level,
+            generate_maximal_unitigs_links,
+            compute_tigs_mode,
+            graph_cleaning,
+            only_bstats,
+            minimizer_bucketing_chunk_size,
+        )
+    }
+}
+
+fn run_assembler_with_counters<
+    MergingHash: HashFunctionFactory,
+    AssemblerColorsManager: ColorsManager,
+    OutputMode: StructuredSequenceBackendWrapper,
+    Counters: UnitigsCountersStorage,
+>(
+    k: usize,
+    m: usize,
+    step: AssemblerStartingStep,
+    last_step: AssemblerStartingStep,
+    input_blocks: Vec<GeneralSequenceBlockData>,
+    color_names: &[String],
+    colormap_encoding: ColormapEncoding,
     output_file: PathBuf,
     temp_dir: Option<PathBuf>,
+    resume: bool,
     threads_count: usize,
-    min_multiplicity: usize,
+    min_multiplicity: MinMultiplicity,
     max_multiplicity: usize,
+    per_color_min_multiplicity: bool,
+    abundance_histogram_file: Option<PathBuf>,
+    kmers_counts: Option<KmersCountsOutput>,
+    min_base_quality: Option<u8>,
+    abundance_mode: AbundanceOutputMode,
     buckets_count_log: Option<usize>,
     loopit_number: Option<usize>,
     default_compression_level: Option<u32>,
     generate_maximal_unitigs_links: bool,
     compute_tigs_mode: Option<MatchtigMode>,
+    graph_cleaning: Option<GraphCleaningConfig>,
     only_bstats: bool,
     minimizer_bucketing_chunk_size: Option<u64>,
 ) -> anyhow::Result<PathBuf> {
@@ -127,23 +257,88 @@ pub fn run_assembler<
 
     PHASES_TIMES_MONITOR.write().init();
 
+    check_cancelled()?;
+
     let file_stats = compute_stats_from_input_blocks(&input_blocks)?;
 
     let buckets_count_log = buckets_count_log.unwrap_or_else(|| file_stats.best_buckets_count_log);
 
     if let Some(default_compression_level) = default_compression_level {
-        INTERMEDIATE_COMPRESSION_LEVEL_SLOW.store(default_compression_level, Ordering::Relaxed);
-        INTERMEDIATE_COMPRESSION_LEVEL_FAST.store(default_compression_level, Ordering::Relaxed);
+        set_intermediate_compression_level(default_compression_level);
     }
 
     let buckets_count = 1 << buckets_count_log;
 
-    let global_colors_table = Arc::new(
-        AssemblerColorsManager::ColorsMergeManagerType::create_colors_table(
-            output_file.with_extension("colors.dat"),
-            color_names,
-        )?,
-    );
+    // The checkpoints need the temporary files to be on disk to resume the build after an interruption
+    let checkpoints_supported =
+        !is_memory_only() && !OperationContext::current().settings().prefer_memory;
+    let keep_files = keep_files();
+    config::clear_checkpoint_files();
+
+    let mut checkpoints =
+        if checkpoints_supported && (resume || step == AssemblerStartingStep::MinimizerBucketing) {
+            let parameters = BuildParameters {
+                k,
+                m,
+                inputs: checkpoints::describe_inputs(&input_blocks),
+                color_names: color_names.to_vec(),
+                colormap_encoding: format!("{:?}", colormap_encoding),
+                min_multiplicity: format!("{:?}", min_multiplicity),
+                max_multiplicity,
+                per_color_min_multiplicity,
+                min_base_quality,
+                buckets_count_log,
+                minimizer_bucketing_chunk_size,
+                hash_type: MergingHash::NAME.to_string(),
+                colored: AssemblerColorsManager::COLORS_ENABLED,
+                output_type: OutputMode::NAME.to_string(),
+                output_file: output_file.clone(),
+                abundance_mode: format!("{:?}", abundance_mode),
+                compute_tigs_mode: format!("{:?}", compute_tigs_mode),
+                generate_maximal_unitigs_links,
+                graph_cleaning: format!("{:?}", graph_cleaning),
+                kmers_counts: format!("{:?}", kmers_counts),
+            };
+
+            Some(if resume {
+                BuildCheckpoints::resume(&temp_dir, parameters, !keep_files)?
+            } else {
+                BuildCheckpoints::create(&temp_dir, parameters, !keep_files)?
+            })
+        } else if resume {
+            return Err(BuildResumeError {
+                temp_dir,
+                message: "the temporary files of the build are not kept on disk".to_string(),
+            }
+            .into());
+        } else {
+            None
+        };
+
+    let step = match &checkpoints {
+        Some(checkpoints) if resume => {
+            let step = checkpoints.next_step();
+            ggcat_logging::info!("Resuming the build from the {:?} step", step);
+            step
+        }
+        _ => step,
+    };
+
+    // The color subsets of the input graphs are expanded when merging the kmers
+    let color_subsets = find_color_subsets(&input_blocks)?;
+
+    // The colormap is complete if the kmers merge was already done
+    let global_colors_table = if step <= AssemblerStartingStep::KmersMerge {
+        Some(Arc::new(
+            AssemblerColorsManager::ColorsMergeManagerType::create_colors_table(
+                output_file.with_extension("colors.dat"),
+                color_names,
+                colormap_encoding,
+            )?,
+        ))
+    } else {
+        None
+    };
 
     let (buckets, counters) = if step <= AssemblerStartingStep::MinimizerBucketing {
         assembler_minimizer_bucketing::static_dispatch::minimizer_bucketing::<AssemblerColorsManager>(
@@ -153,8 +348,19 @@ pub fn run_assembler<
             threads_count,
             k,
             m,
+            min_base_quality,
             minimizer_bucketing_chunk_size,
         )
+    } else if let Some(checkpoints) = &checkpoints {
+        let files = checkpoints.files();
+        (
+            files
+                .buckets
+                .iter()
+                .map(CheckpointBucket::to_multi_chunk)
+                .collect(),
+            files.buckets_counters.clone().unwrap_or_default(),
+        )
     } else {
         (
             generate_bucket_names(temp_dir.join("bucket"), buckets_count, None)
@@ -176,6 +382,9 @@ pub fn run_assembler<
         buckets.iter().map(|x| x.chunks.len()).sum::<usize>()
     );
 
+    check_memory_only_budget()?;
+    check_cancelled()?;
+
     if last_step <= AssemblerStartingStep::MinimizerBucketing {
         PHASES_TIMES_MONITOR
             .write()
@@ -186,6 +395,19 @@ pub fn run_assembler<
         MemoryFs::free_memory();
     }
 
+    if let Some(checkpoints) = &mut checkpoints {
+        if step <= AssemblerStartingStep::MinimizerBucketing {
+            checkpoints.save(
+                AssemblerStartingStep::MinimizerBucketing,
+                CheckpointFiles {
+                    buckets: buckets.iter().map(CheckpointBucket::from).collect(),
+                    buckets_counters: Some(counters.clone()),
+                    ..Default::default()
+                },
+            )?;
+        }
+    }
+
     if only_bstats {
         use rayon::prelude::*;
         buckets.par_iter().enumerate().for_each(|(index, bucket)| {
@@ -206,26 +428,148 @@ pub fn run_assembler<
         return Ok(PathBuf::new());
     }
 
-    let RetType { sequences, hashes } = if step <= AssemblerStartingStep::KmersMerge {
-        assembler_kmers_merge::kmers_merge::<MergingHash, AssemblerColorsManager, _>(
+    let RetType {
+        sequences,
+        hashes,
+        abundance_histogram,
+        kmers_counts: kmers_counts_partitions,
+    } = if step <= AssemblerStartingStep::KmersMerge {
+        assembler_kmers_merge::kmers_merge::<MergingHash, AssemblerColorsManager, Counters, _>(
             buckets,
             counters,
-            global_colors_table.clone(),
+            global_colors_table.clone().unwrap(),
+            color_subsets,
             buckets_count,
             min_multiplicity,
             max_multiplicity,
+            per_color_min_multiplicity,
             temp_dir.as_path(),
             k,
             m,
             compute_tigs_mode.needs_simplitigs(),
+            abundance_mode,
+            abundance_histogram_file.is_some(),
+            kmers_counts,
             threads_count,
         )
+    } else if let Some(checkpoints) = &checkpoints {
+        let files = checkpoints.files();
+        RetType {
+            sequences: files
+                .sequences
+                .iter()
+                .map(CheckpointBucket::to_single)
+                .collect(),
+            hashes: files
+                .hashes
+                .iter()
+                .map(CheckpointBucket::to_single)
+                .collect(),
+            abundance_histogram: None,
+            kmers_counts: files
+                .kmers_counts
+                .iter()
+                .map(CheckpointBucket::to_single)
+                .collect(),
+        }
     } else {
         RetType {
             sequences: generate_bucket_names(temp_dir.join("result"), buckets_count, None),
             hashes: generate_bucket_names(temp_dir.join("hashes"), buckets_count, None),
+            abundance_histogram: None,
+            kmers_counts: match kmers_counts {
+                Some(_) => generate_bucket_names(
+                    temp_dir.join("kmers-counts"),
+                    1 << (2 * kmers_counts_partition_bases(buckets_count)),
+                    None,
+                ),
+                None => vec![],
+            },
         }
     };
+
+    if let Some(histogram_file) = &abundance_histogram_file {
+        match &abundance_histogram {
+            Some(histogram) => {
+                let file = std::fs::File::create(histogram_file)
+                    .map_err(|err| FileIoError::new(histogram_file, err))?;
+                histogram
+                    .write_tsv(std::io::BufWriter::new(file))
+                    .map_err(|err| FileIoError::new(histogram_file, err))?;
+                ggcat_logging::info!(
+                    "Kmers abundance histogram saved to: {}",
+                    histogram_file.display()
+                );
+            }
+            None => ggcat_logging::warn!(
+                "The kmers abundance histogram is not available, as the kmers merge was not executed"
+            ),
+        }
+    }
+
+    check_memory_only_budget()?;
+    check_cancelled()?;
+
+    // In the kmers counting mode the build stops after the kmers merge
+    if let Some(kmers_counts) = kmers_counts {
+        MemoryFs::flush_all_to_disk();
+        MemoryFs::free_memory();
+
+        // No unitigs fragments are written, so their hashes buckets are empty
+        for bucket in hashes {
+            let _ = MemoryFs::remove_file(
+                &bucket.path,
+                RemoveFileMode::Remove {
+                    remove_fs: !keep_file_on_disk(&bucket.path),
+                },
+            );
+        }
+
+        if let Some(global_colors_table) = global_colors_table {
+            AssemblerColorsManager::ColorsMergeManagerType::print_color_stats(&global_colors_table);
+            drop(global_colors_table);
+
+            if let Some(checkpoints) = &mut checkpoints {
+                checkpoints.set_colormap(output_file.with_extension("colors.dat"));
+                checkpoints.save(
+                    AssemblerStartingStep::KmersMerge,
+                    CheckpointFiles {
+                        kmers_counts: kmers_counts_partitions
+                            .iter()
+                            .map(CheckpointBucket::from)
+                            .collect(),
+                        ..Default::default()
+                    },
+                )?;
+            }
+        }
+
+        let kmers_count = write_kmers_counts(
+            kmers_counts_partitions,
+            k,
+            AssemblerColorsManager::COLORS_ENABLED,
+            kmers_counts,
+            &output_file,
+            threads_count,
+        )?;
+
+        check_memory_only_budget()?;
+        check_cancelled()?;
+
+        // The partitions were kept to resume the output if interrupted
+        if let Some(checkpoints) = checkpoints {
+            checkpoints.finish();
+        }
+
+        let _ = std::fs::remove_dir(temp_dir.as_path());
+
+        PHASES_TIMES_MONITOR.write().print_stats(format!(
+            "Kmers counting completed with {} distinct kmers.",
+            kmers_count
+        ));
+        return Ok(output_file);
+    }
+
     if last_step <= AssemblerStartingStep::KmersMerge {
         PHASES_TIMES_MONITOR
             .write()
@@ -236,15 +580,39 @@ pub fn run_assembler<
         MemoryFs::free_memory();
     }
 
-    AssemblerColorsManager::ColorsMergeManagerType::print_color_stats(&global_colors_table);
+    if let Some(global_colors_table) = global_colors_table {
+        AssemblerColorsManager::ColorsMergeManagerType::print_color_stats(&global_colors_table);
+        drop(global_colors_table);
 
-    drop(global_colors_table);
+        if let Some(checkpoints) = &mut checkpoints {
+            checkpoints.set_colormap(output_file.with_extension("colors.dat"));
+            checkpoints.save(
+                AssemblerStartingStep::KmersMerge,
+                CheckpointFiles {
+                    sequences: sequences.iter().map(CheckpointBucket::from).collect(),
+                    hashes: hashes.iter().map(CheckpointBucket::from).collect(),
+                    ..Default::default()
+                },
+            )?;
+        }
+    }
 
     let mut links = if step <= AssemblerStartingStep::HashesSorting {
         hashes_sorting::<MergingHash, _>(hashes, temp_dir.as_path(), buckets_count)
+    } else if let Some(checkpoints) = &checkpoints {
+        checkpoints
+            .files()
+            .links
+            .iter()
+            .map(CheckpointBucket::to_single)
+            .collect()
     } else {
         generate_bucket_names(temp_dir.join("links"), buckets_count, None)
     };
+
+    check_memory_only_budget()?;
+    check_cancelled()?;
+
     if last_step <= AssemblerStartingStep::HashesSorting {
         PHASES_TIMES_MONITOR
             .write()
@@ -255,6 +623,19 @@ pub fn run_assembler<
         MemoryFs::free_memory();
     }
 
+    if let Some(checkpoints) = &mut checkpoints {
+        if step <= AssemblerStartingStep::HashesSorting {
+            checkpoints.save(
+                AssemblerStartingStep::HashesSorting,
+                CheckpointFiles {
+                    sequences: sequences.iter().map(CheckpointBucket::from).collect(),
+                    links: links.iter().map(CheckpointBucket::from).collect(),
+                    ..Default::default()
+                },
+            )?;
+        }
+    }
+
     let mut loop_iteration = loopit_number.unwrap_or(0);
 
     let unames = generate_bucket_names(temp_dir.join("unitigs_map"), buckets_count, None);
@@ -271,7 +652,9 @@ pub fn run_assembler<
             let _ = remove_file(bucket.path);
         }
 
-        let result_map_buckets = Arc::new(MultiThreadBuckets::<LockFreeBinaryWriter>::new(
+        let result_map_buckets = Arc::new(MultiThreadBuckets::<
+            MemoryTrackedBucket<LockFreeBinaryWriter>,
+        >::new(
             buckets_count,
             temp_dir.join("results_map"),
             None,
@@ -282,7 +665,9 @@ pub fn run_assembler<
             &(),
         ));
 
-        let final_buckets = Arc::new(MultiThreadBuckets::<LockFreeBinaryWriter>::new(
+        let final_buckets = Arc::new(MultiThreadBuckets::<
+            MemoryTrackedBucket<LockFreeBinaryWriter>,
+        >::new(
             buckets_count,
             temp_dir.join("unitigs_map"),
             None,
@@ -315,6 +700,14 @@ pub fn run_assembler<
         });
 
         let result = loop {
+            config::progress::start_progress_phase(
+                ProgressPhase::LinksCompaction {
+                    iteration: loop_iteration,
+                },
+                ProgressUnit::Buckets,
+                links.len() as u64,
+            );
+
             let do_logging = if log_timer.elapsed() > MINIMUM_LOG_DELTA_TIME {
                 log_timer = Instant::now();
                 true
@@ -348,6 +741,9 @@ pub fn run_assembler<
                 );
             }
 
+            check_memory_only_budget()?;
+            check_cancelled()?;
+
             links = new_links;
             if remaining == 0 {
                 ggcat_logging::info!("Completed compaction with {} iters", loop_iteration);
@@ -363,16 +759,34 @@ pub fn run_assembler<
             MemoryFs::remove_file(
                 &link_bucket.path,
                 RemoveFileMode::Remove {
-                    remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                    remove_fs: !keep_file_on_disk(&link_bucket.path),
                 },
             )
             .unwrap();
         }
+
         result
+    } else if let Some(checkpoints) = &checkpoints {
+        let files = checkpoints.files();
+        (
+            files
+                .unitigs_map
+                .iter()
+                .map(CheckpointBucket::to_single)
+                .collect(),
+            files
+                .reads_map
+                .iter()
+                .map(CheckpointBucket::to_single)
+                .collect(),
+        )
     } else {
         (unames, rnames)
     };
 
+    check_memory_only_budget()?;
+    check_cancelled()?;
+
     if last_step <= AssemblerStartingStep::LinksCompaction {
         PHASES_TIMES_MONITOR
             .write()
@@ -383,13 +797,41 @@ pub fn run_assembler<
         MemoryFs::free_memory();
     }
 
-    let final_unitigs_file = StructuredSequenceWriter::new(
-        get_writer::<_, _, OutputMode::Backend<_, _>>(&output_file),
-        k,
-    );
+    // The following phases write the final output, so they are restarted together from this checkpoint
+    if let Some(checkpoints) = &mut checkpoints {
+        if step <= AssemblerStartingStep::LinksCompaction {
+            checkpoints.save(
+                AssemblerStartingStep::LinksCompaction,
+                CheckpointFiles {
+                    sequences: sequences.iter().map(CheckpointBucket::from).collect(),
+                    unitigs_map: unitigs_map.iter().map(CheckpointBucket::from).collect(),
+                    reads_map: reads_map.iter().map(CheckpointBucket::from).collect(),
+                    ..Default::default()
+                },
+            )?;
+        }
+    }
+
+    // The unitigs with their links are written by the last step, that creates its own output file
+    let links_output_only = generate_maximal_unitigs_links
+        && !compute_tigs_mode.needs_matchtigs_library()
+        && compute_tigs_mode != Some(MatchtigMode::FastEulerTigs);
+
+    let final_unitigs_file = if !links_output_only {
+        Some(
+            StructuredSequenceWriter::new(
+                get_writer::<_, _, OutputMode::Backend<_, _>>(&output_file)?,
+                k,
+            )
+            .with_abundance_mode(abundance_mode),
+        )
+    } else {
+        None
+    };
 
     // Temporary file to store maximal unitigs data without links info, if further processing is requested
     let compressed_temp_unitigs_file = if generate_maximal_unitigs_links
+        || graph_cleaning.is_some()
         || compute_tigs_mode.needs_matchtigs_library()
         || compute_tigs_mode == Some(MatchtigMode::FastEulerTigs)
     {
@@ -426,40 +868,55 @@ pub fn run_assembler<
         None
     };
 
-    let (reorganized_reads, _final_unitigs_bucket) = if step
-        <= AssemblerStartingStep::ReorganizeReads
-    {
-        if generate_maximal_unitigs_links || compute_tigs_mode.needs_temporary_tigs() {
-            reorganize_reads::<MergingHash, AssemblerColorsManager, StructSeqBinaryWriter<_, _>>(
-                k,
-                sequences,
-                reads_map,
-                temp_dir.as_path(),
-                compressed_temp_unitigs_file.as_ref().unwrap(),
-                circular_temp_unitigs_file.as_ref(),
-                buckets_count,
-            )
+    let (reorganized_reads, _final_unitigs_bucket) =
+        if step <= AssemblerStartingStep::ReorganizeReads {
+            if generate_maximal_unitigs_links
+                || graph_cleaning.is_some()
+                || compute_tigs_mode.needs_temporary_tigs()
+            {
+                reorganize_reads::<
+                    MergingHash,
+                    AssemblerColorsManager,
+                    Counters,
+                    StructSeqBinaryWriter<_, _>,
+                >(
+                    k,
+                    sequences,
+                    reads_map,
+                    temp_dir.as_path(),
+                    compressed_temp_unitigs_file.as_ref().unwrap(),
+                    circular_temp_unitigs_file.as_ref(),
+                    buckets_count,
+                )
+            } else {
+                reorganize_reads::<
+                    MergingHash,
+                    AssemblerColorsManager,
+                    Counters,
+                    OutputMode::Backend<_, _>,
+                >(
+                    k,
+                    sequences,
+                    reads_map,
+                    temp_dir.as_path(),
+                    final_unitigs_file.as_ref().unwrap(),
+                    None,
+                    buckets_count,
+                )
+            }
         } else {
-            reorganize_reads::<MergingHash, AssemblerColorsManager, OutputMode::Backend<_, _>>(
-                k,
-                sequences,
-                reads_map,
-                temp_dir.as_path(),
-                &final_unitigs_file,
-                None,
-                buckets_count,
+            (
+                generate_bucket_names(temp_dir.join("reads_bucket"), buckets_count, Some("tmp")),
+                (generate_bucket_names(temp_dir.join("reads_bucket_lonely"), 1, Some("tmp"))
+                    .into_iter()
+                    .next()
+                    .unwrap()
+                    .path),
             )
-        }
-    } else {
-        (
-            generate_bucket_names(temp_dir.join("reads_bucket"), buckets_count, Some("tmp")),
-            (generate_bucket_names(temp_dir.join("reads_bucket_lonely"), 1, Some("tmp"))
-                .into_iter()
-                .next()
-                .unwrap()
-                .path),
-        )
-    };
+        };
+
+    check_memory_only_budget()?;
+    check_cancelled()?;
 
     if last_step <= AssemblerStartingStep::ReorganizeReads {
         PHASES_TIMES_MONITOR
@@ -474,10 +931,16 @@ pub fn run_assembler<
     // links_manager.compute_id_offsets();
     if step <= AssemblerStartingStep::BuildUnitigs {
         if generate_maximal_unitigs_links
+            || graph_cleaning.is_some()
             || compute_tigs_mode.needs_matchtigs_library()
             || compute_tigs_mode == Some(MatchtigMode::FastEulerTigs)
         {
-            build_unitigs::<MergingHash, AssemblerColorsManager, StructSeqBinaryWriter<_, _>>(
+            build_unitigs::<
+                MergingHash,
+                AssemblerColorsManager,
+                Counters,
+                StructSeqBinaryWriter<_, _>,
+            >(
                 reorganized_reads,
                 unitigs_map,
                 temp_dir.as_path(),
@@ -490,17 +953,20 @@ pub fn run_assembler<
                 k,
             );
         } else {
-            build_unitigs::<MergingHash, AssemblerColorsManager, OutputMode::Backend<_, _>>(
+            build_unitigs::<MergingHash, AssemblerColorsManager, Counters, OutputMode::Backend<_, _>>(
                 reorganized_reads,
                 unitigs_map,
                 temp_dir.as_path(),
-                &final_unitigs_file,
+                final_unitigs_file.as_ref().unwrap(),
                 None,
                 k,
             );
         }
     }
 
+    check_memory_only_budget()?;
+    check_cancelled()?;
+
     if step <= AssemblerStartingStep::MaximalUnitigsLinks {
         if compute_tigs_mode == Some(MatchtigMode::FastEulerTigs) {
             let circular_temp_unitigs_file = circular_temp_unitigs_file.unwrap();
@@ -515,22 +981,66 @@ pub fn run_assembler<
                 circular_temp_path,
                 temp_path,
                 temp_dir.as_path(),
-                &final_unitigs_file,
+                final_unitigs_file.as_ref().unwrap(),
                 k,
             );
+        } else if let Some(graph_cleaning) = &graph_cleaning {
+            let compressed_temp_unitigs_file = compressed_temp_unitigs_file.unwrap();
+            let temp_path = compressed_temp_unitigs_file.get_path();
+            compressed_temp_unitigs_file.finalize();
+
+            // The cleaning needs the whole graph, so the links are fully built before starting it
+            let storage_backend = MatchtigsStorageBackend::new();
+            let storage_receiver = storage_backend.get_receiver();
+            let storage_writer = StructuredSequenceWriter::new(storage_backend, k);
+
+            build_maximal_unitigs_links::<
+                MergingHash,
+                AssemblerColorsManager,
+                MatchtigsStorageBackend<_>,
+            >(temp_path, temp_dir.as_path(), &storage_writer, k);
+            storage_writer.finalize();
+
+            if generate_maximal_unitigs_links {
+                let final_unitigs_file = StructuredSequenceWriter::new(
+                    get_writer::<_, _, OutputMode::Backend<_, _>>(&output_file)?,
+                    k,
+                )
+                .with_abundance_mode(abundance_mode);
+
+                clean_graph::<AssemblerColorsManager, DoubleMaximalUnitigLinks, _>(
+                    k,
+                    storage_receiver,
+                    &final_unitigs_file,
+                    graph_cleaning,
+                );
+                final_unitigs_file.finalize();
+            } else {
+                let final_unitigs_file = final_unitigs_file.unwrap();
+                clean_graph::<AssemblerColorsManager, _, _>(
+                    k,
+                    storage_receiver,
+                    &final_unitigs_file,
+                    graph_cleaning,
+                );
+                final_unitigs_file.finalize();
+            }
         } else if generate_maximal_unitigs_links || compute_tigs_mode.needs_matchtigs_library() {
             let compressed_temp_unitigs_file = compressed_temp_unitigs_file.unwrap();
             let temp_path = compressed_temp_unitigs_file.get_path();
             compressed_temp_unitigs_file.finalize();
 
             if let Some(compute_tigs_mode) = compute_tigs_mode.get_matchtigs_mode() {
+                let final_unitigs_file = final_unitigs_file.unwrap();
                 let matchtigs_backend = MatchtigsStorageBackend::new();
 
                 let matchtigs_receiver = matchtigs_backend.get_receiver();
 
+                let operation = OperationContext::current();
                 let handle = std::thread::Builder::new()
                     .name("greedy_matchtigs".to_string())
                     .spawn(move || {
+                        OperationContext::set_current(Some(operation));
                         compute_matchtigs_thread::<AssemblerColorsManager, _>(
                             k,
                             threads_count,
@@ -554,12 +1064,11 @@ pub fn run_assembler<
 
                 handle.join().unwrap();
             } else if generate_maximal_unitigs_links {
-                final_unitigs_file.finalize();
-
                 let final_unitigs_file = StructuredSequenceWriter::new(
-                    get_writer::<_, _, OutputMode::Backend<_, _>>(&output_file),
+                    get_writer::<_, _, OutputMode::Backend<_, _>>(&output_file)?,
                     k,
-                );
+                )
+                .with_abundance_mode(abundance_mode);
 
                 build_maximal_unitigs_links::<
                     MergingHash,
@@ -568,13 +1077,20 @@ pub fn run_assembler<
                 >(temp_path, temp_dir.as_path(), &final_unitigs_file, k);
                 final_unitigs_file.finalize();
             }
-        } else {
+        } else if let Some(final_unitigs_file) = final_unitigs_file {
             final_unitigs_file.finalize();
         }
-    } else {
+    } else if let Some(final_unitigs_file) = final_unitigs_file {
         final_unitigs_file.finalize();
     }
 
+    check_memory_only_budget()?;
+    check_cancelled()?;
+
+    if let Some(checkpoints) = checkpoints {
+        checkpoints.finish();
+    }
+
     let _ = std::fs::remove_dir(temp_dir.as_path());
 
     PHASES_TIMES_MONITOR
@@ -1,7 +1,9 @@
 pub mod build_unitigs;
 pub mod compute_matchtigs;
 pub mod eulertigs;
+pub mod graph_cleaning;
 pub mod hashes_sorting;
+pub mod kmers_counts_output;
 pub mod links_compaction;
 pub mod maximal_unitig_links;
 pub mod reorganize_reads;
@@ -2,7 +2,10 @@ use crate::pipeline::reorganize_reads::ReorganizedReadsExtraData;
 use colors::colors_manager::color_types::PartialUnitigsColorStructure;
 use colors::colors_manager::ColorsMergeManager;
 use colors::colors_manager::{color_types, ColorsManager};
-use config::{BucketIndexType, DEFAULT_OUTPUT_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT, KEEP_FILES};
+use config::progress::{ProgressPhase, ProgressUnit};
+use config::{
+    keep_file_on_disk, BucketIndexType, DEFAULT_OUTPUT_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT,
+};
 use hashbrown::HashMap;
 use hashes::{HashFunctionFactory, HashableSequence};
 use io::compressed_read::CompressedReadIndipendent;
@@ -23,10 +26,9 @@ use parallel_processor::memory_fs::RemoveFileMode;
 use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
 use rayon::prelude::*;
 use std::path::Path;
-use std::sync::atomic::Ordering;
 
-#[cfg(feature = "support_kmer_counters")]
 use io::concurrent::structured_sequences::SequenceAbundance;
+use structs::unitigs_counters::UnitigsCountersStorage;
 
 #[derive(Copy, Clone, Debug)]
 struct FinalUnitigInfo {
@@ -35,8 +37,8 @@ struct FinalUnitigInfo {
     flags: UnitigFlags,
 }
 
-type CompressedReadsDataSerializerUnitigsBuilding<CX> = CompressedReadsBucketDataSerializer<
-    ReorganizedReadsExtraData<PartialUnitigsColorStructure<CX>>,
+type CompressedReadsDataSerializerUnitigsBuilding<CX, C> = CompressedReadsBucketDataSerializer<
+    ReorganizedReadsExtraData<PartialUnitigsColorStructure<CX>, C>,
     typenum::U0,
     NoSecondBucket,
     NoMultiplicity,
@@ -45,6 +47,7 @@ type CompressedReadsDataSerializerUnitigsBuilding<CX> = CompressedReadsBucketDat
 pub fn build_unitigs<
     MH: HashFunctionFactory,
     CX: ColorsManager,
+    C: UnitigsCountersStorage,
     BK: StructuredSequenceBackend<PartialUnitigsColorStructure<CX>, ()>,
 >(
     mut read_buckets_files: Vec<SingleBucket>,
@@ -57,6 +60,11 @@ pub fn build_unitigs<
     PHASES_TIMES_MONITOR
         .write()
         .start_phase("phase: unitigs building".to_string());
+    config::progress::start_progress_phase(
+        ProgressPhase::BuildUnitigs,
+        ProgressUnit::Buckets,
+        read_buckets_files.len() as u64,
+    );
 
     read_buckets_files.sort_by_key(|b| b.index);
     unitig_map_files.sort_by_key(|b| b.index);
@@ -71,6 +79,10 @@ pub fn build_unitigs<
             .par_iter()
             .enumerate()
             .for_each(|(_index, (read_file, unitigs_map_file))| {
+                if config::should_interrupt_phase() {
+                    return;
+                }
+
                 let mut tmp_final_unitigs_buffer =
                     FastaWriterConcurrentBuffer::new(out_file, DEFAULT_OUTPUT_BUFFER_SIZE, true);
 
@@ -90,7 +102,7 @@ pub fn build_unitigs<
                 let mut unitigs_map_reader = LockFreeBinaryReader::new(
                     &unitigs_map_file.path,
                     RemoveFileMode::Remove {
-                        remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                        remove_fs: !keep_file_on_disk(&unitigs_map_file.path),
                     },
                     DEFAULT_PREFETCH_AMOUNT,
                 );
@@ -167,6 +179,7 @@ pub fn build_unitigs<
 
                 let mut color_extra_buffer = ReorganizedReadsExtraData::<
                     color_types::PartialUnitigsColorStructure<CX>,
+                    C,
                 >::new_temp_buffer();
                 let mut final_color_extra_buffer =
                     color_types::PartialUnitigsColorStructure::<CX>::new_temp_buffer();
@@ -174,11 +187,11 @@ pub fn build_unitigs<
                 CompressedBinaryReader::new(
                     &read_file.path,
                     RemoveFileMode::Remove {
-                        remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                        remove_fs: !keep_file_on_disk(&read_file.path),
                     },
                     DEFAULT_PREFETCH_AMOUNT,
                 )
-                .decode_all_bucket_items::<CompressedReadsDataSerializerUnitigsBuilding<CX>, _>(
+                .decode_all_bucket_items::<CompressedReadsDataSerializerUnitigsBuilding<CX, C>, _>(
                     Vec::new(),
                     &mut color_extra_buffer,
                     |(_, _, index, seq, _), _color_extra_buffer| {
@@ -187,7 +200,6 @@ pub fn build_unitigs<
                             CompressedReadIndipendent::from_read(&seq, &mut temp_storage),
                             unitig_info,
                             index.colors,
-                            #[cfg(feature = "support_kmer_counters")]
                             index.counters,
                         ));
                     },
@@ -208,13 +220,7 @@ pub fn build_unitigs<
                     CX::ColorsMergeManagerType::reset_unitig_color_structure(
                         &mut final_unitig_color,
                     );
-                    #[cfg(feature = "support_kmer_counters")]
-                    let mut abundance = SequenceAbundance {
-                        first: 0,
-                        sum: 0,
-                        last: 0,
-                    };
-                    #[cfg(feature = "support_kmer_counters")]
+                    let mut abundance = SequenceAbundance::default();
                     let mut prev_last = 0;
 
                     let mut is_first = true;
@@ -224,12 +230,9 @@ pub fn build_unitigs<
                     } else {
                         itertools::Either::Left(sequence.iter())
                     } {
-                        #[cfg(feature = "support_kmer_counters")]
                         let (read, FinalUnitigInfo { flags, .. }, color, counters) =
                             upart.as_ref().unwrap();
-
-                        #[cfg(not(feature = "support_kmer_counters"))]
-                        let (read, FinalUnitigInfo { flags, .. }, color) = upart.as_ref().unwrap();
+                        let counters = counters.get_counters();
 
                         let compr_read = read.as_reference(&temp_storage);
                         if compr_read.bases_count() == 0 {
@@ -245,11 +248,9 @@ pub fn build_unitigs<
                                     0,
                                     None,
                                 );
-                                #[cfg(feature = "support_kmer_counters")]
-                                {
-                                    abundance.first = counters.last;
-                                    abundance.sum += counters.sum;
-                                }
+                                abundance.first = counters.last;
+                                abundance.sum += counters.sum;
+                                abundance.kmers.extend(counters.kmers.iter().rev());
                             } else {
                                 temp_sequence.extend(compr_read.as_bases_iter());
                                 CX::ColorsMergeManagerType::join_structures::<false>(
@@ -259,11 +260,9 @@ pub fn build_unitigs<
                                     0,
                                     None,
                                 );
-                                #[cfg(feature = "support_kmer_counters")]
-                                {
-                                    abundance.first = counters.first;
-                                    abundance.sum += counters.sum;
-                                }
+                                abundance.first = counters.first;
+                                abundance.sum += counters.sum;
+                                abundance.kmers.extend_from_slice(&counters.kmers);
                             }
                             is_first = false;
                         } else {
@@ -281,10 +280,8 @@ pub fn build_unitigs<
                                     1,
                                     None,
                                 );
-                                #[cfg(feature = "support_kmer_counters")]
-                                {
-                                    abundance.sum += counters.sum - counters.last;
-                                }
+                                abundance.sum += counters.sum - counters.last;
+                                abundance.kmers.extend(counters.kmers.iter().rev().skip(1));
                             } else {
                                 temp_sequence.extend(
                                     compr_read
@@ -299,27 +296,24 @@ pub fn build_unitigs<
                                     1,
                                     None,
                                 );
-                                #[cfg(feature = "support_kmer_counters")]
-                                {
-                                    abundance.sum += counters.sum - counters.first;
-                                }
+                                abundance.sum += counters.sum - counters.first;
+                                abundance.kmers.extend(counters.kmers.iter().skip(1));
                             }
                         }
-                        #[cfg(feature = "support_kmer_counters")]
-                        {
-                            prev_last = abundance.last;
-                            abundance.last = counters.last;
-                        }
+                        prev_last = abundance.last;
+                        abundance.last = if flags.is_reverse_complemented() {
+                            counters.first
+                        } else {
+                            counters.last
+                        };
                     }
 
                     // In case of circular unitigs, remove an extra ending base
                     if is_circular {
                         temp_sequence.pop();
-                        #[cfg(feature = "support_kmer_counters")]
-                        {
-                            abundance.sum -= abundance.last;
-                            abundance.last = prev_last;
-                        }
+                        abundance.sum -= abundance.last;
+                        abundance.last = prev_last;
+                        abundance.kmers.pop();
 
                         CX::ColorsMergeManagerType::pop_base(&mut final_unitig_color);
                     }
@@ -340,7 +334,6 @@ pub fn build_unitigs<
                                 &final_color_extra_buffer,
                                 (),
                                 &(),
-                                #[cfg(feature = "support_kmer_counters")]
                                 abundance,
                             );
                     } else {
@@ -351,7 +344,6 @@ pub fn build_unitigs<
                             &final_color_extra_buffer,
                             (),
                             &(),
-                            #[cfg(feature = "support_kmer_counters")]
                             abundance,
                         );
                     }
@@ -371,6 +363,7 @@ pub fn build_unitigs<
                 // >::clear_temp_buffer(&mut color_extra_buffer);
 
                 tmp_final_unitigs_buffer.finalize();
+                config::progress::advance_progress(1);
             });
     });
 }
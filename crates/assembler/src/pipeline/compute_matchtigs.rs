@@ -1,6 +1,9 @@
-use crate::pipeline::maximal_unitig_links::maximal_unitig_index::DoubleMaximalUnitigLinks;
+use crate::pipeline::maximal_unitig_links::maximal_unitig_index::{
+    DoubleMaximalUnitigLinks, MaximalUnitigIndex,
+};
 use colors::colors_manager::color_types::PartialUnitigsColorStructure;
 use colors::colors_manager::{color_types, ColorsManager, ColorsMergeManager};
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::DEFAULT_OUTPUT_BUFFER_SIZE;
 use crossbeam::channel::{Receiver, Sender};
 use genome_graph::bigraph::implementation::node_bigraph_wrapper::NodeBigraphWrapper;
@@ -9,10 +12,11 @@ use genome_graph::bigraph::traitgraph::implementation::petgraph_impl::PetGraph;
 use genome_graph::bigraph::traitgraph::interface::ImmutableGraphContainer;
 use genome_graph::bigraph::traitgraph::interface::MutableGraphContainer;
 use genome_graph::generic::{GenericEdge, GenericNode};
-use io::compressed_read::CompressedReadIndipendent;
+use io::compressed_read::{CompressedRead, CompressedReadIndipendent};
 use io::concurrent::structured_sequences::concurrent::FastaWriterConcurrentBuffer;
 use io::concurrent::structured_sequences::{
-    IdentSequenceWriter, SequenceAbundanceType, StructuredSequenceBackend, StructuredSequenceWriter,
+    AbundanceOutputMode, IdentSequenceWriter, SequenceAbundance, StructuredSequenceBackend,
+    StructuredSequenceWriter,
 };
 use io::concurrent::temp_reads::extra_data::SequenceExtraDataTempBufferManagement;
 use libmatchtigs::{
@@ -26,9 +30,6 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use traitgraph_algo::dijkstra::DijkstraWeightedEdgeData;
 
-#[cfg(feature = "support_kmer_counters")]
-use io::concurrent::structured_sequences::SequenceAbundance;
-
 const DUMMY_EDGE_VALUE: usize = usize::MAX;
 
 #[derive(Clone)]
@@ -50,7 +51,7 @@ impl<ColorInfo: IdentSequenceWriter> SequenceHandle<ColorInfo> {
         &(
             CompressedReadIndipendent,
             ColorInfo,
-            SequenceAbundanceType,
+            SequenceAbundance,
             DoubleMaximalUnitigLinks,
         ),
         &StructuredUnitigsStorage<ColorInfo>,
@@ -152,7 +153,7 @@ pub struct StructuredUnitigsStorage<ColorInfo: IdentSequenceWriter> {
     sequences: Vec<(
         CompressedReadIndipendent,
         ColorInfo,
-        SequenceAbundanceType,
+        SequenceAbundance,
         DoubleMaximalUnitigLinks,
     )>,
 
@@ -171,6 +172,41 @@ impl<ColorInfo: IdentSequenceWriter> StructuredUnitigsStorage<ColorInfo> {
             color_buffer: ColorInfo::new_temp_buffer(),
         }
     }
+
+    /// Index of the first sequence of the storage
+    pub fn first_sequence_index(&self) -> usize {
+        self.first_sequence_index
+    }
+
+    pub fn sequences_count(&self) -> usize {
+        self.sequences.len()
+    }
+
+    /// Returns the sequence at the given offset from the first one, with its color and its abundance
+    pub fn get_sequence(
+        &self,
+        offset: usize,
+    ) -> (CompressedRead<'_>, &ColorInfo, &SequenceAbundance) {
+        let (sequence, color, abundance, _) = &self.sequences[offset];
+        (
+            sequence.as_reference(&self.sequences_buffer),
+            color,
+            abundance,
+        )
+    }
+
+    /// Returns the links from both the sides of the sequence at the given offset from the first one
+    pub fn get_links(&self, offset: usize) -> impl Iterator<Item = &MaximalUnitigIndex> {
+        self.sequences[offset]
+            .3
+            .links
+            .iter()
+            .flat_map(|link| link.entries.get_slice(&self.links_buffer))
+    }
+
+    pub fn color_buffer(&self) -> &ColorInfo::TempBuffer {
+        &self.color_buffer
+    }
 }
 
 pub struct MatchtigsStorageBackend<ColorInfo: IdentSequenceWriter> {
@@ -212,7 +248,8 @@ impl<ColorInfo: IdentSequenceWriter> StructuredSequenceBackend<ColorInfo, Double
             ColorInfo::TempBuffer,
             <DoubleMaximalUnitigLinks as SequenceExtraDataTempBufferManagement>::TempBuffer,
         ),
-        #[cfg(feature = "support_kmer_counters")] abundance: SequenceAbundanceType,
+        abundance: SequenceAbundance,
+        _abundance_mode: AbundanceOutputMode,
     ) {
         if buffer.first_sequence_index == usize::MAX {
             buffer.first_sequence_index = sequence_index as usize;
@@ -233,17 +270,9 @@ impl<ColorInfo: IdentSequenceWriter> StructuredSequenceBackend<ColorInfo, Double
             &mut buffer.links_buffer,
         );
 
-        buffer.sequences.push((
-            sequence,
-            color_info,
-            match () {
-                #[cfg(feature = "support_kmer_counters")]
-                () => abundance,
-                #[cfg(not(feature = "support_kmer_counters"))]
-                () => (),
-            },
-            links_info,
-        ));
+        buffer
+            .sequences
+            .push((sequence, color_info, abundance, links_info));
     }
 
     fn get_path(&self) -> PathBuf {
@@ -305,7 +334,7 @@ impl<ColorInfo: IdentSequenceWriter + 'static> GenericNode for UnitigEdgeData<Co
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum MatchtigMode {
     FastSimpliTigs,
     FastEulerTigs,
@@ -376,13 +405,12 @@ pub fn compute_matchtigs_thread<
         })
         .flatten();
 
-    #[cfg(feature = "support_kmer_counters")]
+    if matches!(mode, MatchtigMode::GreedyTigs)
+        && out_file.get_abundance_mode() != AbundanceOutputMode::None
     {
-        if matches!(mode, MatchtigMode::GreedyTigs) {
-            ggcat_logging::warn!(
-                "Abundancies support with greedy matchtigs is not accurate for merged unitigs!"
-            );
-        }
+        ggcat_logging::warn!(
+            "Abundancies support with greedy matchtigs is not accurate for merged unitigs!"
+        );
     }
 
     let mut graph: NodeBigraphWrapper<PetGraph<(), UnitigEdgeData<_>>> =
@@ -400,6 +428,7 @@ pub fn compute_matchtigs_thread<
     PHASES_TIMES_MONITOR
         .write()
         .start_phase(format!("phase: {} building [step1]", phase_name));
+    config::progress::start_progress_phase(ProgressPhase::Matchtigs, ProgressUnit::Buckets, 0);
 
     /* assign weight to each edge */
     for edge_index in graph.edge_indices_copied() {
@@ -496,11 +525,11 @@ pub fn compute_matchtigs_thread<
                 None,
             );
         }
-        #[cfg(feature = "support_kmer_counters")]
         let mut abundance = SequenceAbundance {
             first: handle.2.first,
             sum: handle.2.sum,
             last: handle.2.last,
+            kmers: Vec::new(),
         };
 
         let mut previous_data = first_data;
@@ -546,11 +575,8 @@ pub fn compute_matchtigs_thread<
                     kmer_offset,
                     None,
                 );
-                #[cfg(feature = "support_kmer_counters")]
-                {
-                    abundance.sum += handle.2.sum;
-                    abundance.last = handle.2.last;
-                }
+                abundance.sum += handle.2.sum;
+                abundance.last = handle.2.last;
             } else {
                 read_buffer.extend(
                     next_sequence
@@ -564,11 +590,8 @@ pub fn compute_matchtigs_thread<
                     kmer_offset,
                     None,
                 );
-                #[cfg(feature = "support_kmer_counters")]
-                {
-                    abundance.sum += handle.2.sum;
-                    abundance.last = handle.2.last;
-                }
+                abundance.sum += handle.2.sum;
+                abundance.last = handle.2.last;
             }
         }
 
@@ -584,7 +607,6 @@ pub fn compute_matchtigs_thread<
             &final_color_extra_buffer,
             (),
             &(),
-            #[cfg(feature = "support_kmer_counters")]
             abundance,
         );
     }
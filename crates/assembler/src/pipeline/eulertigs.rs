@@ -1,5 +1,6 @@
 use colors::colors_manager::{color_types, ColorsManager};
 use colors::colors_manager::{color_types::PartialUnitigsColorStructure, ColorsMergeManager};
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::{DEFAULT_OUTPUT_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT};
 use dashmap::DashMap;
 use hashes::HashFunctionFactory;
@@ -10,7 +11,7 @@ use io::concurrent::temp_reads::creads_utils::{NoMultiplicity, NoSecondBucket};
 use io::concurrent::temp_reads::extra_data::SequenceExtraDataTempBufferManagement;
 use io::concurrent::{
     structured_sequences::{
-        IdentSequenceWriter, SequenceAbundanceType, StructuredSequenceBackend,
+        AbundanceOutputMode, IdentSequenceWriter, SequenceAbundance, StructuredSequenceBackend,
         StructuredSequenceWriter,
     },
     temp_reads::creads_utils::CompressedReadsBucketDataSerializer,
@@ -106,7 +107,7 @@ impl CircularUnitig {
             (
                 CompressedReadIndipendent,
                 PartialUnitigsColorStructure<CX>,
-                SequenceAbundanceType,
+                SequenceAbundance,
             ),
         >,
         writer: &mut Vec<u8>,
@@ -114,7 +115,7 @@ impl CircularUnitig {
         colors_buffer: &mut <CX::ColorsMergeManagerType as ColorsMergeManager>::TempUnitigColorStructure,
         k: usize,
         write_full: bool,
-        #[allow(dead_code)] _out_abundance: &mut SequenceAbundanceType,
+        out_abundance: &mut SequenceAbundance,
     ) {
         if self.rc {
             self.base_children.reverse();
@@ -122,21 +123,15 @@ impl CircularUnitig {
 
         let children_count = self.base_children.len();
 
-        #[cfg(feature = "support_kmer_counters")]
-        {
-            let first_unitig_entry = unitigs.get(&self.base_children[0].orig_index).unwrap();
-            _out_abundance.first = first_unitig_entry.2.first;
-        }
+        let first_unitig_entry = unitigs.get(&self.base_children[0].orig_index).unwrap();
+        out_abundance.first = first_unitig_entry.2.first;
 
         for child in self.base_children.iter_mut().take(children_count - 1) {
             let unitig_entry = unitigs.get(&child.orig_index).unwrap();
-            let (unitig, src_color, _abundance) = unitig_entry.value();
+            let (unitig, src_color, abundance) = unitig_entry.value();
             let unitig = unitig.as_reference(unitigs_kmers);
 
-            #[cfg(feature = "support_kmer_counters")]
-            {
-                _out_abundance.sum += _abundance.sum;
-            }
+            out_abundance.sum += abundance.sum;
 
             child.rc ^= self.rc;
             let should_rc = child.rc;
@@ -166,16 +161,13 @@ impl CircularUnitig {
 
         let last_part_entry = unitigs.get(&last.orig_index).unwrap();
 
-        #[cfg(feature = "support_kmer_counters")]
-        {
-            let abundance = &last_part_entry.2;
-            _out_abundance.sum += abundance.sum;
-            _out_abundance.last = if last.rc {
-                abundance.first
-            } else {
-                abundance.last
-            };
-        }
+        let abundance = &last_part_entry.2;
+        out_abundance.sum += abundance.sum;
+        out_abundance.last = if last.rc {
+            abundance.first
+        } else {
+            abundance.last
+        };
 
         let last_part_slice =
             last.start_pos + start_offset..last.start_pos + last.length + end_offset;
@@ -207,14 +199,14 @@ impl CircularUnitig {
             (
                 CompressedReadIndipendent,
                 PartialUnitigsColorStructure<CX>,
-                SequenceAbundanceType,
+                SequenceAbundance,
             ),
         >,
     ) -> String {
         let mut colors_buffer = CX::ColorsMergeManagerType::alloc_unitig_color_structure();
         let dummy_buffer = color_types::PartialUnitigsColorStructure::<CX>::new_temp_buffer();
         let mut writer = vec![];
-        let mut abundance = SequenceAbundanceType::default();
+        let mut abundance = SequenceAbundance::default();
         self.write_unpacked::<MH, CX>(
             unitigs_kmers,
             unitigs,
@@ -353,9 +345,16 @@ pub fn build_eulertigs<
     out_file: &StructuredSequenceWriter<PartialUnitigsColorStructure<CX>, L, BK>,
     k: usize,
 ) {
+    if out_file.get_abundance_mode() != AbundanceOutputMode::None {
+        ggcat_logging::warn!(
+            "Abundancies support with fast eulertigs is not accurate for merged unitigs!"
+        );
+    }
+
     PHASES_TIMES_MONITOR
         .write()
         .start_phase("phase: eulertigs building part 1".to_string());
+    config::progress::start_progress_phase(ProgressPhase::Matchtigs, ProgressUnit::Buckets, 0);
 
     let circular_unitigs_reader = CompressedBinaryReader::new(
         &circ_in_file,
@@ -371,7 +370,7 @@ pub fn build_eulertigs<
         (
             CompressedReadIndipendent,
             PartialUnitigsColorStructure<CX>,
-            SequenceAbundanceType,
+            SequenceAbundance,
         ),
     >::new();
 
@@ -395,7 +394,7 @@ pub fn build_eulertigs<
                             u64,
                             PartialUnitigsColorStructure<CX>,
                             (),
-                            SequenceAbundanceType,
+                            SequenceAbundance,
                         )>::new_temp_buffer(),
                         AllowedCheckpointStrategy::DecompressOnly,
                         |(_, _, (_index, mut color, _, abundance), read, _): (
@@ -405,7 +404,7 @@ pub fn build_eulertigs<
                                 _,
                                 PartialUnitigsColorStructure<CX>,
                                 (),
-                                SequenceAbundanceType,
+                                SequenceAbundance,
                             ),
                             _,
                             _,
@@ -543,17 +542,17 @@ pub fn build_eulertigs<
                             u64,
                             PartialUnitigsColorStructure<CX>,
                             (),
-                            SequenceAbundanceType,
+                            SequenceAbundance,
                         )>::new_temp_buffer(),
                         AllowedCheckpointStrategy::DecompressOnly,
-                        |(_, _, (_index, color, _, mut _abundance), read, _): (
+                        |(_, _, (_index, color, _, mut abundance), read, _): (
                             _,
                             _,
                             (
                                 _,
                                 PartialUnitigsColorStructure<CX>,
                                 (),
-                                SequenceAbundanceType,
+                                SequenceAbundance,
                             ),
                             _,
                             _,
@@ -598,7 +597,7 @@ pub fn build_eulertigs<
 
                                     circular_unitig.rotate(index, rotation.get_offset(), should_rc);
 
-                                    let mut circular_abundance = SequenceAbundanceType::default();
+                                    let mut circular_abundance = SequenceAbundance::default();
                                     circular_unitig.write_unpacked::<MH, CX>(
                                         &unitigs_bases,
                                         &unitig_mapping,
@@ -610,10 +609,7 @@ pub fn build_eulertigs<
                                         &mut circular_abundance,
                                     );
 
-                                    #[cfg(feature = "support_kmer_counters")]
-                                    {
-                                        _abundance.sum += circular_abundance.sum;
-                                    }
+                                    abundance.sum += circular_abundance.sum;
 
                                     last_offset = end_base_offset;
                                 }
@@ -643,8 +639,7 @@ pub fn build_eulertigs<
                                 &final_color_extra_buffer,
                                 L::default(),
                                 &default_links_buffer,
-                                #[cfg(feature = "support_kmer_counters")]
-                                _abundance,
+                                abundance,
                             );
                         },
                     )
@@ -675,7 +670,7 @@ pub fn build_eulertigs<
 
         seq_buffer.clear();
 
-        let mut abundance = SequenceAbundanceType::default();
+        let mut abundance = SequenceAbundance::default();
 
         unitig.write_unpacked::<MH, CX>(
             &unitigs_bases,
@@ -700,7 +695,6 @@ pub fn build_eulertigs<
             &final_color_extra_buffer,
             L::default(),
             &default_links_buffer,
-            #[cfg(feature = "support_kmer_counters")]
             abundance,
         );
     }
@@ -738,7 +732,7 @@ mod tests {
             (
                 CompressedReadIndipendent::from_read_inplace(&circular_unitig1, &stream),
                 NonColoredManager,
-                SequenceAbundanceType::default(),
+                SequenceAbundance::default(),
             ),
         );
         unitigs_hashmap.insert(
@@ -746,7 +740,7 @@ mod tests {
             (
                 CompressedReadIndipendent::from_read_inplace(&circular_unitig2, &stream),
                 NonColoredManager,
-                SequenceAbundanceType::default(),
+                SequenceAbundance::default(),
             ),
         );
 
@@ -0,0 +1,656 @@
+use crate::pipeline::compute_matchtigs::StructuredUnitigsStorage;
+use crate::pipeline::maximal_unitig_links::maximal_unitig_index::{
+    DoubleMaximalUnitigLinks, MaximalUnitigFlags, MaximalUnitigIndex, MaximalUnitigLink,
+};
+use colors::colors_manager::color_types::PartialUnitigsColorStructure;
+use colors::colors_manager::{color_types, ColorsManager, ColorsMergeManager};
+use config::progress::{ProgressPhase, ProgressUnit};
+use config::DEFAULT_OUTPUT_BUFFER_SIZE;
+use crossbeam::channel::Receiver;
+use hashbrown::HashMap;
+use io::concurrent::structured_sequences::concurrent::FastaWriterConcurrentBuffer;
+use io::concurrent::structured_sequences::{
+    IdentSequenceWriter, SequenceAbundance, StructuredSequenceBackend, StructuredSequenceWriter,
+};
+use io::concurrent::temp_reads::extra_data::SequenceExtraDataTempBufferManagement;
+use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
+use std::sync::Arc;
+use utils::vec_slice::VecSlice;
+
+/// The thresholds used to remove the tips and the bubbles from the compacted graph
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GraphCleaningConfig {
+    /// Dead-end paths shorter than this length (in bases) are clipped
+    pub tip_length: usize,
+    /// Only the bubbles with branches shorter than this length (in bases) are popped
+    pub bubble_length: usize,
+    /// The branches of a bubble with a mean kmer coverage lower than this fraction
+    /// of the coverage of the best branch are removed
+    pub bubble_coverage_ratio: f64,
+}
+
+impl GraphCleaningConfig {
+    /// Clips the tips shorter than 2k and pops the bubbles shorter than 3k
+    /// having branches with less than 20% of the coverage of the best one
+    pub fn new(k: usize) -> Self {
+        Self {
+            tip_length: 2 * k,
+            bubble_length: 3 * k,
+            bubble_coverage_ratio: 0.2,
+        }
+    }
+}
+
+/// The links written with the cleaned unitigs, empty if they are not requested
+pub trait CleanedUnitigsLinks: IdentSequenceWriter {
+    /// Builds the links of a cleaned unitig from the entries of its beginning and of its ending
+    fn new_links(
+        index: u64,
+        entries: [&[MaximalUnitigIndex]; 2],
+        buffer: &mut Self::TempBuffer,
+    ) -> Self;
+}
+
+impl CleanedUnitigsLinks for () {
+    fn new_links(_: u64, _: [&[MaximalUnitigIndex]; 2], _: &mut ()) -> Self {}
+}
+
+impl CleanedUnitigsLinks for DoubleMaximalUnitigLinks {
+    fn new_links(
+        index: u64,
+        entries: [&[MaximalUnitigIndex]; 2],
+        buffer: &mut Vec<MaximalUnitigIndex>,
+    ) -> Self {
+        Self {
+            links: entries.map(|entries| {
+                MaximalUnitigLink::new(index, VecSlice::new_extend(buffer, entries))
+            }),
+            // Only used when computing the matchtigs, that never follow the graph cleaning
+            is_self_complemental: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct UnitigSide {
+    index: usize,
+    at_end: bool,
+}
+
+impl UnitigSide {
+    fn opposite(self) -> Self {
+        Self {
+            index: self.index,
+            at_end: !self.at_end,
+        }
+    }
+
+    fn slot(self) -> usize {
+        self.index * 2 + self.at_end as usize
+    }
+}
+
+/// A maximal path of unitigs that can be joined together, entered from the entry side of its first
+/// unitig and left from the exit side of its last unitig
+struct UnitigsChain {
+    /// The unitigs of the chain, with true if they are traversed forward
+    unitigs: Vec<(usize, bool)>,
+    entry: UnitigSide,
+    exit: UnitigSide,
+    circular: bool,
+    length: usize,
+    kmers: u64,
+    abundance_sum: u64,
+}
+
+impl UnitigsChain {
+    fn coverage(&self) -> f64 {
+        self.abundance_sum as f64 / self.kmers.max(1) as f64
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.unitigs.iter().any(|(unitig, _)| *unitig == index)
+    }
+}
+
+struct CleaningGraph {
+    k: usize,
+    lengths: Vec<usize>,
+    abundance_sums: Vec<u64>,
+    removed: Vec<bool>,
+    links_offsets: Vec<usize>,
+    links: Vec<UnitigSide>,
+}
+
+impl CleaningGraph {
+    /// Builds the graph from the unitigs lengths and abundances and from the links between their sides,
+    /// the unitigs without a length are considered as missing
+    fn new(
+        k: usize,
+        lengths: Vec<usize>,
+        abundance_sums: Vec<u64>,
+        mut links: Vec<(UnitigSide, UnitigSide)>,
+    ) -> Self {
+        links.sort_unstable_by_key(|(from, to)| (from.slot(), *to));
+        links.dedup();
+
+        let mut links_offsets = vec![0; lengths.len() * 2 + 1];
+        for (from, _) in &links {
+            links_offsets[from.slot() + 1] += 1;
+        }
+        for slot in 1..links_offsets.len() {
+            links_offsets[slot] += links_offsets[slot - 1];
+        }
+
+        Self {
+            k,
+            removed: lengths.iter().map(|length| *length == 0).collect(),
+            lengths,
+            abundance_sums,
+            links_offsets,
+            links: links.into_iter().map(|(_, to)| to).collect(),
+        }
+    }
+
+    fn neighbors(&self, side: UnitigSide) -> impl Iterator<Item = UnitigSide> + '_ {
+        self.links[self.links_offsets[side.slot()]..self.links_offsets[side.slot() + 1]]
+            .iter()
+            .copied()
+            .filter(|other| !self.removed[other.index])
+    }
+
+    fn degree(&self, side: UnitigSide) -> usize {
+        self.neighbors(side).count()
+    }
+
+    /// Returns the only neighbor of a side, if it belongs to another unitig
+    fn unique_neighbor(&self, side: UnitigSide) -> Option<UnitigSide> {
+        let mut neighbors = self.neighbors(side);
+        let neighbor = neighbors.next()?;
+        (neighbors.next().is_none() && neighbor.index != side.index).then_some(neighbor)
+    }
+
+    /// Returns the side of the next unitig if the two unitigs can be joined
+    fn extend(&self, exit: UnitigSide) -> Option<UnitigSide> {
+        let next = self.unique_neighbor(exit)?;
+        (self.unique_neighbor(next)? == exit).then_some(next)
+    }
+
+    fn find_chain(&self, start: usize, visited: &mut [bool]) -> UnitigsChain {
+        let mut entry = UnitigSide {
+            index: start,
+            at_end: false,
+        };
+        let mut circular = false;
+
+        // Go back to the first unitig of the chain
+        while let Some(previous_exit) = self.extend(entry) {
+            if previous_exit.index == start {
+                circular = true;
+                entry = UnitigSide {
+                    index: start,
+                    at_end: false,
+                };
+                break;
+            }
+            entry = previous_exit.opposite();
+        }
+
+        let mut chain = UnitigsChain {
+            unitigs: vec![],
+            entry,
+            exit: entry.opposite(),
+            circular,
+            length: 0,
+            kmers: 0,
+            abundance_sum: 0,
+        };
+
+        let mut current = entry;
+        loop {
+            visited[current.index] = true;
+            let length = self.lengths[current.index];
+            chain.unitigs.push((current.index, !current.at_end));
+            chain.length += if chain.unitigs.len() == 1 {
+                length
+            } else {
+                length - (self.k - 1)
+            };
+            chain.kmers += (length - self.k + 1) as u64;
+            chain.abundance_sum += self.abundance_sums[current.index];
+            chain.exit = current.opposite();
+
+            match self.extend(chain.exit) {
+                Some(next) if !visited[next.index] => current = next,
+                _ => break,
+            }
+        }
+
+        chain
+    }
+
+    fn chains(&self) -> Vec<UnitigsChain> {
+        let mut visited = vec![false; self.lengths.len()];
+        let mut chains = vec![];
+        for index in 0..self.lengths.len() {
+            if !self.removed[index] && !visited[index] {
+                chains.push(self.find_chain(index, &mut visited));
+            }
+        }
+        chains
+    }
+
+    fn remove_chain(&mut self, chain: &UnitigsChain) {
+        for (index, _) in &chain.unitigs {
+            self.removed[*index] = true;
+        }
+    }
+
+    /// Removes the dead-end chains shorter than the tip length, starting from the ones with the lowest coverage.
+    /// A tip is kept if its removal would leave a dead end on the unitig it is attached to
+    fn clip_tips(&mut self, chains: &[UnitigsChain], tip_length: usize) -> (usize, u64) {
+        let mut tips: Vec<_> = chains
+            .iter()
+            .filter(|chain| !chain.circular && chain.length < tip_length)
+            .filter_map(
+                |chain| match (self.degree(chain.entry), self.degree(chain.exit)) {
+                    (0, 0) => None,
+                    (0, _) => Some((chain, chain.exit)),
+                    (_, 0) => Some((chain, chain.entry)),
+                    _ => None,
+                },
+            )
+            .collect();
+
+        tips.sort_by(|(a, _), (b, _)| {
+            a.coverage()
+                .total_cmp(&b.coverage())
+                .then(a.length.cmp(&b.length))
+        });
+
+        let mut removed = (0, 0);
+        for (chain, attached_side) in tips {
+            let Some(junction) = self.unique_neighbor(attached_side) else {
+                continue;
+            };
+            if chain.contains(junction.index) || self.degree(junction) < 2 {
+                continue;
+            }
+            self.remove_chain(chain);
+            removed.0 += 1;
+            removed.1 += chain.kmers;
+        }
+        removed
+    }
+
+    /// Removes the low coverage branches of the bubbles, the chains that join the same two unitig sides
+    fn pop_bubbles(
+        &mut self,
+        chains: &[UnitigsChain],
+        bubble_length: usize,
+        coverage_ratio: f64,
+    ) -> (usize, u64) {
+        let mut bubbles: HashMap<_, Vec<_>> = HashMap::new();
+        for chain in chains
+            .iter()
+            .filter(|chain| !chain.circular && chain.length < bubble_length)
+        {
+            let (Some(start), Some(end)) = (
+                self.unique_neighbor(chain.entry),
+                self.unique_neighbor(chain.exit),
+            ) else {
+                continue;
+            };
+            if chain.contains(start.index) || chain.contains(end.index) {
+                continue;
+            }
+            bubbles
+                .entry((start.min(end), start.max(end)))
+                .or_default()
+                .push(chain);
+        }
+
+        let mut removed = (0, 0);
+        for branches in bubbles.values().filter(|branches| branches.len() > 1) {
+            let best_coverage = branches
+                .iter()
+                .map(|chain| chain.coverage())
+                .fold(0.0, f64::max);
+
+            for chain in branches {
+                if chain.coverage() < best_coverage * coverage_ratio {
+                    self.remove_chain(chain);
+                    removed.0 += 1;
+                    removed.1 += chain.kmers;
+                }
+            }
+        }
+        removed
+    }
+
+    /// Collects the links from the beginning and from the ending of a chain to the other chains
+    fn chain_links(
+        &self,
+        chains: &[UnitigsChain],
+        chains_sides: &HashMap<UnitigSide, (usize, bool)>,
+        chain: &UnitigsChain,
+        links_entries: &mut [Vec<MaximalUnitigIndex>; 2],
+    ) {
+        for (entries, (side, at_end)) in links_entries
+            .iter_mut()
+            .zip([(chain.entry, false), (chain.exit, true)])
+        {
+            entries.clear();
+            // The remaining links of the chain ends can only point to the ends of other chains
+            entries.extend(self.neighbors(side).filter_map(|other| {
+                let (other_index, other_at_end) = *chains_sides.get(&other)?;
+                Some(MaximalUnitigIndex::new(
+                    other_index as u64,
+                    if other_at_end {
+                        (chains[other_index].length - self.k + 1) as u64
+                    } else {
+                        0
+                    },
+                    MaximalUnitigFlags::new_direction(!at_end, other_at_end),
+                ))
+            }));
+            entries.sort_unstable_by_key(|entry| entry.index());
+        }
+    }
+
+    fn clean(&mut self, config: &GraphCleaningConfig) {
+        let mut tips = (0, 0);
+        let mut bubbles = (0, 0);
+
+        // Removing tips and bubbles can create new ones, so repeat until the graph does not change
+        loop {
+            let clipped = self.clip_tips(&self.chains(), config.tip_length);
+            let popped = self.pop_bubbles(
+                &self.chains(),
+                config.bubble_length,
+                config.bubble_coverage_ratio,
+            );
+
+            tips = (tips.0 + clipped.0, tips.1 + clipped.1);
+            bubbles = (bubbles.0 + popped.0, bubbles.1 + popped.1);
+
+            if clipped.0 == 0 && popped.0 == 0 {
+                break;
+            }
+        }
+
+        ggcat_logging::info!(
+            "Graph cleaning removed {} tips ({} kmers) and {} bubble branches ({} kmers)",
+            tips.0,
+            tips.1,
+            bubbles.0,
+            bubbles.1
+        );
+    }
+}
+
+/// Maps the sides of the unitigs at the ends of each chain to the chain index, with true for the ending of the chain
+fn chains_sides(chains: &[UnitigsChain]) -> HashMap<UnitigSide, (usize, bool)> {
+    chains
+        .iter()
+        .enumerate()
+        .flat_map(|(index, chain)| [(chain.entry, (index, false)), (chain.exit, (index, true))])
+        .collect()
+}
+
+/// Removes the tips and the low coverage bubbles from the maximal unitigs, then joins the remaining ones
+/// that are no longer separated by a branch. The links of the written unitigs are derived from the ones
+/// of the maximal unitigs, so they do not need to be computed again
+pub fn clean_graph<
+    CX: ColorsManager,
+    L: CleanedUnitigsLinks,
+    BK: StructuredSequenceBackend<PartialUnitigsColorStructure<CX>, L>,
+>(
+    k: usize,
+    input_data: Receiver<Arc<StructuredUnitigsStorage<PartialUnitigsColorStructure<CX>>>>,
+    out_file: &StructuredSequenceWriter<PartialUnitigsColorStructure<CX>, L, BK>,
+    config: &GraphCleaningConfig,
+) {
+    PHASES_TIMES_MONITOR
+        .write()
+        .start_phase("phase: graph cleaning".to_string());
+    config::progress::start_progress_phase(ProgressPhase::GraphCleaning, ProgressUnit::Buckets, 0);
+
+    let mut storages: Vec<_> = input_data
+        .into_iter()
+        .filter(|storage| storage.sequences_count() > 0)
+        .collect();
+    storages.sort_unstable_by_key(|storage| storage.first_sequence_index());
+
+    let unitigs_count = storages
+        .last()
+        .map(|storage| storage.first_sequence_index() + storage.sequences_count())
+        .unwrap_or(0);
+
+    let mut lengths = vec![0; unitigs_count];
+    let mut abundance_sums = vec![0; unitigs_count];
+    let mut links = vec![];
+    for storage in &storages {
+        for offset in 0..storage.sequences_count() {
+            let index = storage.first_sequence_index() + offset;
+            let (sequence, _, abundance) = storage.get_sequence(offset);
+            lengths[index] = sequence.get_length();
+            abundance_sums[index] = abundance.sum;
+
+            for entry in storage.get_links(offset) {
+                links.push((
+                    UnitigSide {
+                        index,
+                        at_end: !entry.flags.flip_current(),
+                    },
+                    UnitigSide {
+                        index: entry.index() as usize,
+                        at_end: entry.flags.flip_other(),
+                    },
+                ));
+            }
+        }
+    }
+
+    let mut graph = CleaningGraph::new(k, lengths, abundance_sums, links);
+    graph.clean(config);
+
+    let get_unitig = |index: usize| {
+        let storage = &storages
+            [storages.partition_point(|storage| storage.first_sequence_index() <= index) - 1];
+        (
+            storage.get_sequence(index - storage.first_sequence_index()),
+            storage.color_buffer(),
+        )
+    };
+
+    let mut output_buffer =
+        FastaWriterConcurrentBuffer::new(out_file, DEFAULT_OUTPUT_BUFFER_SIZE, true);
+
+    let mut read_buffer = Vec::new();
+    let mut final_unitig_color =
+        color_types::ColorsMergeManagerType::<CX>::alloc_unitig_color_structure();
+    let mut final_color_extra_buffer =
+        color_types::PartialUnitigsColorStructure::<CX>::new_temp_buffer();
+
+    let chains = graph.chains();
+
+    let chains_sides = chains_sides(&chains);
+
+    let mut links_entries = [vec![], vec![]];
+    let mut links_buffer = L::new_temp_buffer();
+
+    for (chain_index, chain) in chains.iter().enumerate() {
+        color_types::ColorsMergeManagerType::<CX>::reset_unitig_color_structure(
+            &mut final_unitig_color,
+        );
+        color_types::PartialUnitigsColorStructure::<CX>::clear_temp_buffer(
+            &mut final_color_extra_buffer,
+        );
+        read_buffer.clear();
+
+        let mut abundance = SequenceAbundance::default();
+
+        for (position, (index, forward)) in chain.unitigs.iter().enumerate() {
+            let ((sequence, color, unitig_abundance), color_buffer) = get_unitig(*index);
+            let skipped_bases = if position == 0 { 0 } else { k - 1 };
+
+            let (first, last) = if *forward {
+                read_buffer.extend(sequence.as_bases_iter().skip(skipped_bases));
+                CX::ColorsMergeManagerType::join_structures::<false>(
+                    &mut final_unitig_color,
+                    color,
+                    color_buffer,
+                    0,
+                    None,
+                );
+                abundance.kmers.extend_from_slice(&unitig_abundance.kmers);
+                (unitig_abundance.first, unitig_abundance.last)
+            } else {
+                read_buffer.extend(
+                    sequence
+                        .as_reverse_complement_bases_iter()
+                        .skip(skipped_bases),
+                );
+                CX::ColorsMergeManagerType::join_structures::<true>(
+                    &mut final_unitig_color,
+                    color,
+                    color_buffer,
+                    0,
+                    None,
+                );
+                abundance.kmers.extend(unitig_abundance.kmers.iter().rev());
+                (unitig_abundance.last, unitig_abundance.first)
+            };
+
+            if position == 0 {
+                abundance.first = first;
+            }
+            abundance.last = last;
+            abundance.sum += unitig_abundance.sum;
+        }
+
+        let writable_color = color_types::ColorsMergeManagerType::<CX>::encode_part_unitigs_colors(
+            &mut final_unitig_color,
+            &mut final_color_extra_buffer,
+        );
+
+        graph.chain_links(&chains, &chains_sides, chain, &mut links_entries);
+
+        L::clear_temp_buffer(&mut links_buffer);
+        let links = L::new_links(
+            chain_index as u64,
+            [&links_entries[0], &links_entries[1]],
+            &mut links_buffer,
+        );
+
+        output_buffer.add_read(
+            &read_buffer,
+            Some(chain_index as u64),
+            writable_color,
+            &final_color_extra_buffer,
+            links,
+            &links_buffer,
+            abundance,
+        );
+    }
+
+    ggcat_logging::info!(
+        "Graph cleaning joined {} maximal unitigs into {} unitigs",
+        storages
+            .iter()
+            .map(|storage| storage.sequences_count())
+            .sum::<usize>(),
+        chains.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chains_sides, CleaningGraph, GraphCleaningConfig, UnitigSide};
+
+    fn side(index: usize, at_end: bool) -> UnitigSide {
+        UnitigSide { index, at_end }
+    }
+
+    #[test]
+    fn tips_and_bubbles_removal() {
+        let k = 5;
+
+        // 0 -> 1 (tip) and 0 -> 2 -> (3 | 4 reverse complemented, low coverage) -> 5
+        let lengths = vec![30, 7, 20, 9, 9, 30];
+        let abundance_sums = vec![260, 3, 160, 50, 5, 260];
+
+        let mut links = vec![];
+        let mut link = |a: UnitigSide, b: UnitigSide| {
+            links.push((a, b));
+            links.push((b, a));
+        };
+        link(side(0, true), side(1, false));
+        link(side(0, true), side(2, false));
+        link(side(2, true), side(3, false));
+        link(side(2, true), side(4, true));
+        link(side(3, true), side(5, false));
+        link(side(4, false), side(5, false));
+
+        let mut graph = CleaningGraph::new(k, lengths, abundance_sums, links);
+        assert_eq!(graph.chains().len(), 6);
+
+        graph.clean(&GraphCleaningConfig::new(k));
+        assert_eq!(graph.removed, vec![false, true, false, false, true, false]);
+
+        let chains = graph.chains();
+        assert_eq!(chains.len(), 1);
+        assert_eq!(
+            chains[0].unitigs,
+            vec![(0, true), (2, true), (3, true), (5, true)]
+        );
+        assert_eq!(chains[0].length, 30 + 20 + 9 + 30 - 3 * (k - 1));
+    }
+
+    #[test]
+    fn cleaned_chains_links() {
+        let k = 5;
+
+        // 0 -> 1 and 0 -> 2 reverse complemented, with 3 joined to the beginning of 0
+        let lengths = vec![30, 20, 20, 10];
+        let abundance_sums = vec![260, 160, 160, 60];
+
+        let mut links = vec![];
+        let mut link = |a: UnitigSide, b: UnitigSide| {
+            links.push((a, b));
+            links.push((b, a));
+        };
+        link(side(3, true), side(0, false));
+        link(side(0, true), side(1, false));
+        link(side(0, true), side(2, true));
+
+        let graph = CleaningGraph::new(k, lengths, abundance_sums, links);
+        let chains = graph.chains();
+        assert_eq!(chains.len(), 3);
+        assert_eq!(chains[0].unitigs, vec![(3, true), (0, true)]);
+
+        let chains_sides = chains_sides(&chains);
+        let mut links_entries = [vec![], vec![]];
+
+        graph.chain_links(&chains, &chains_sides, &chains[0], &mut links_entries);
+        assert!(links_entries[0].is_empty());
+        let ending_links: Vec<_> = links_entries[1]
+            .iter()
+            .map(|entry| {
+                (
+                    entry.index(),
+                    entry.flags.flip_current(),
+                    entry.flags.flip_other(),
+                )
+            })
+            .collect();
+        assert_eq!(ending_links, vec![(1, false, false), (2, false, true)]);
+
+        graph.chain_links(&chains, &chains_sides, &chains[2], &mut links_entries);
+        assert!(links_entries[0].is_empty());
+        assert_eq!(links_entries[1].len(), 1);
+        assert_eq!(links_entries[1][0].index(), 0);
+        assert!(links_entries[1][0].flags.flip_other());
+    }
+}
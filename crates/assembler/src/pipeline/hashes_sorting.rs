@@ -1,9 +1,11 @@
 use std::path::Path;
-use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use config::operation::MemoryTrackedBucket;
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::{
-    get_memory_mode, SwapPriority, DEFAULT_PER_CPU_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT, KEEP_FILES,
+    get_memory_mode, keep_file_on_disk, SwapPriority, DEFAULT_PER_CPU_BUFFER_SIZE,
+    DEFAULT_PREFETCH_AMOUNT,
 };
 use hashes::HashFunctionFactory;
 use io::structs::hash_entry::{Direction, HashCompare, HashEntrySerializer};
@@ -31,8 +33,15 @@ pub fn hashes_sorting<H: HashFunctionFactory, P: AsRef<Path>>(
     PHASES_TIMES_MONITOR
         .write()
         .start_phase("phase: hashes sorting".to_string());
-
-    let links_buckets = Arc::new(MultiThreadBuckets::<LockFreeBinaryWriter>::new(
+    config::progress::start_progress_phase(
+        ProgressPhase::HashesSorting,
+        ProgressUnit::Buckets,
+        file_hashes_inputs.len() as u64,
+    );
+
+    let links_buckets = Arc::new(MultiThreadBuckets::<
+        MemoryTrackedBucket<LockFreeBinaryWriter>,
+    >::new(
         buckets_count,
         output_dir.as_ref().join("links"),
         None,
@@ -50,6 +59,9 @@ pub fn hashes_sorting<H: HashFunctionFactory, P: AsRef<Path>>(
     file_hashes_inputs
         .par_iter()
         .for_each(|input| {
+            if config::should_interrupt_phase() {
+                return;
+            }
 
             let mut buffers = buckets_thread_buffers.get();
             let mut links_tmp = BucketsThreadDispatcher::<_, UnitigLinkSerializer>::new(
@@ -62,7 +74,7 @@ pub fn hashes_sorting<H: HashFunctionFactory, P: AsRef<Path>>(
             let mut hashes_vec = Vec::new();
 
             LockFreeBinaryReader::new(&input.path, RemoveFileMode::Remove {
-                remove_fs: !KEEP_FILES.load(Ordering::Relaxed)
+                remove_fs: !keep_file_on_disk(&input.path)
             }, DEFAULT_PREFETCH_AMOUNT).decode_all_bucket_items::<HashEntrySerializer<H::HashTypeUnextendable>, _>((), &mut (), |h, _| {
                 hashes_vec.push(h);
             });
@@ -124,6 +136,7 @@ pub fn hashes_sorting<H: HashFunctionFactory, P: AsRef<Path>>(
                 }
             }
             buffers.put_back(links_tmp.finalize().0);
+            config::progress::advance_progress(1);
         });
     links_buckets.finalize_single()
 }
@@ -0,0 +1,237 @@
+use assembler_kmers_merge::kmers_counts::{
+    kmers_counts_record_size, kmers_counts_subpartition, packed_kmer_size, unpack_kmer,
+    KmerCountReadBuffer, KmerCountRecordSerializer, KmersCountsFormat, KmersCountsHeader,
+    KmersCountsOutput,
+};
+use config::operation::MemoryTrackedBucket;
+use config::progress::{ProgressPhase, ProgressUnit};
+use config::{
+    get_memory_mode, keep_file_on_disk, SwapPriority, DEFAULT_PER_CPU_BUFFER_SIZE,
+    DEFAULT_PREFETCH_AMOUNT,
+};
+use io::FileIoError;
+use parallel_processor::buckets::concurrent::{BucketsThreadBuffer, BucketsThreadDispatcher};
+use parallel_processor::buckets::readers::lock_free_binary_reader::LockFreeBinaryReader;
+use parallel_processor::buckets::readers::BucketReader;
+use parallel_processor::buckets::writers::lock_free_binary_writer::LockFreeBinaryWriter;
+use parallel_processor::buckets::{MultiThreadBuckets, SingleBucket};
+use parallel_processor::memory_fs::{MemoryFs, RemoveFileMode};
+use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The partitions larger than this are split again by their following bases before being sorted,
+/// as the partitions by prefix can be very unbalanced (e.g. with long poly-A stretches)
+const MAX_SORTED_PARTITION_SIZE: usize = 1024 * 1024 * 64;
+/// The number of bases used to split again a partition, giving 16 sub-partitions
+const SPLIT_PARTITION_BASES: usize = 2;
+
+fn read_partition(partition: &SingleBucket) -> LockFreeBinaryReader {
+    LockFreeBinaryReader::new(
+        &partition.path,
+        RemoveFileMode::Remove {
+            remove_fs: !keep_file_on_disk(&partition.path),
+        },
+        DEFAULT_PREFETCH_AMOUNT,
+    )
+}
+
+/// Splits the partitions larger than [`MAX_SORTED_PARTITION_SIZE`] by the bases following their
+/// common prefix, recursively, returning the sub-partitions in the order of their kmers
+fn split_partition(
+    partition: SingleBucket,
+    prefix_bases: usize,
+    k: usize,
+    colored: bool,
+) -> Vec<SingleBucket> {
+    let partition_size = MemoryFs::get_file_size(&partition.path).unwrap_or(0);
+    if partition_size <= MAX_SORTED_PARTITION_SIZE || prefix_bases >= k {
+        return vec![partition];
+    }
+
+    // The sub-partitions are named after the partition, as <partition>.<subpartition>
+    let mut split_path = partition.path.clone().into_os_string();
+    split_path.push(".split");
+
+    let subpartitions = Arc::new(MultiThreadBuckets::<
+        MemoryTrackedBucket<LockFreeBinaryWriter>,
+    >::new(
+        1 << (2 * SPLIT_PARTITION_BASES),
+        PathBuf::from(split_path),
+        None,
+        &(
+            get_memory_mode(SwapPriority::KmersCounts),
+            LockFreeBinaryWriter::CHECKPOINT_SIZE_UNLIMITED,
+        ),
+        &(),
+    ));
+
+    let mut subpartitions_tmp = BucketsThreadDispatcher::<_, KmerCountRecordSerializer>::new(
+        &subpartitions,
+        BucketsThreadBuffer::new(DEFAULT_PER_CPU_BUFFER_SIZE, subpartitions.count()),
+    );
+
+    read_partition(&partition).decode_all_bucket_items::<KmerCountRecordSerializer, _>(
+        KmerCountReadBuffer::new(k, colored),
+        &mut (),
+        |record, _| {
+            subpartitions_tmp.add_element(
+                kmers_counts_subpartition(record.packed_kmer, prefix_bases, SPLIT_PARTITION_BASES),
+                &(),
+                &record,
+            );
+        },
+    );
+    subpartitions_tmp.finalize();
+
+    subpartitions
+        .finalize_single()
+        .into_iter()
+        .flat_map(|subpartition| {
+            split_partition(
+                subpartition,
+                prefix_bases + SPLIT_PARTITION_BASES,
+                k,
+                colored,
+            )
+        })
+        .collect()
+}
+
+/// Reads a partition of kmers counts and returns its records sorted by kmer
+fn sort_partition(partition: &SingleBucket, k: usize, colored: bool) -> Vec<u8> {
+    let packed_size = packed_kmer_size(k);
+    let record_size = kmers_counts_record_size(k, colored);
+
+    let mut records = Vec::new();
+    read_partition(partition).decode_all_bucket_items::<KmerCountRecordSerializer, _>(
+        KmerCountReadBuffer::new(k, colored),
+        &mut (),
+        |record, _| {
+            records.extend_from_slice(record.packed_kmer);
+            records.extend_from_slice(&record.count.to_le_bytes());
+            if let Some(colors_subset) = record.colors_subset {
+                records.extend_from_slice(&colors_subset.to_le_bytes());
+            }
+        },
+    );
+
+    let mut order: Vec<_> = (0..records.len() / record_size).collect();
+    order.sort_unstable_by_key(|index| {
+        &records[index * record_size..index * record_size + packed_size]
+    });
+
+    let mut sorted = Vec::with_capacity(records.len());
+    for index in order {
+        sorted.extend_from_slice(&records[index * record_size..(index + 1) * record_size]);
+    }
+    sorted
+}
+
+fn write_tsv_records(
+    writer: &mut impl Write,
+    records: &[u8],
+    k: usize,
+    colored: bool,
+    kmer_buffer: &mut Vec<u8>,
+) -> std::io::Result<()> {
+    let packed_size = packed_kmer_size(k);
+
+    for record in records.chunks_exact(kmers_counts_record_size(k, colored)) {
+        kmer_buffer.clear();
+        unpack_kmer(&record[..packed_size], k, kmer_buffer);
+        writer.write_all(kmer_buffer)?;
+
+        let count = u64::from_le_bytes(record[packed_size..packed_size + 8].try_into().unwrap());
+        if colored {
+            let colors_subset = u32::from_le_bytes(record[packed_size + 8..].try_into().unwrap());
+            writeln!(writer, "\t{}\t{}", count, colors_subset)?;
+        } else {
+            writeln!(writer, "\t{}", count)?;
+        }
+    }
+    Ok(())
+}
+
+/// Sorts the partitions of the solid kmers and writes them in order to the output file,
+/// returning the number of distinct kmers written
+pub fn write_kmers_counts(
+    partitions: Vec<SingleBucket>,
+    k: usize,
+    colored: bool,
+    kmers_counts: KmersCountsOutput,
+    output_file: &Path,
+    threads_count: usize,
+) -> anyhow::Result<u64> {
+    PHASES_TIMES_MONITOR
+        .write()
+        .start_phase("phase: kmers counts output".to_string());
+
+    // The partitions are made by the first bases of the kmers, 4 partitions for each base
+    let partition_bases = partitions.len().ilog2() as usize / 2;
+    let partitions: Vec<_> = partitions
+        .into_par_iter()
+        .map(|partition| split_partition(partition, partition_bases, k, colored))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .flatten()
+        .collect();
+
+    config::progress::start_progress_phase(
+        ProgressPhase::KmersCountsOutput,
+        ProgressUnit::Buckets,
+        partitions.len() as u64,
+    );
+
+    let io_error = |err| FileIoError::new(output_file, err);
+
+    let mut writer = BufWriter::new(File::create(output_file).map_err(io_error)?);
+
+    let mut header = KmersCountsHeader {
+        k,
+        colored,
+        canonical: kmers_counts.canonical,
+        kmers_count: 0,
+    };
+
+    if kmers_counts.format == KmersCountsFormat::Binary {
+        header.write_to(&mut writer).map_err(io_error)?;
+    }
+
+    let record_size = kmers_counts_record_size(k, colored);
+    let mut kmer_buffer = Vec::with_capacity(k);
+
+    // The partitions are sorted in parallel, but written in the order of their kmers
+    for batch in partitions.chunks(threads_count.max(1)) {
+        config::progress::check_cancelled()?;
+
+        let sorted_batch: Vec<_> = batch
+            .par_iter()
+            .map(|partition| sort_partition(partition, k, colored))
+            .collect();
+
+        for records in sorted_batch {
+            header.kmers_count += (records.len() / record_size) as u64;
+            match kmers_counts.format {
+                KmersCountsFormat::Tsv => {
+                    write_tsv_records(&mut writer, &records, k, colored, &mut kmer_buffer)
+                }
+                KmersCountsFormat::Binary => writer.write_all(&records),
+            }
+            .map_err(io_error)?;
+            config::progress::advance_progress(1);
+        }
+    }
+
+    if kmers_counts.format == KmersCountsFormat::Binary {
+        // The kmers count is known only at the end
+        writer.seek(SeekFrom::Start(0)).map_err(io_error)?;
+        header.write_to(&mut writer).map_err(io_error)?;
+    }
+    writer.flush().map_err(io_error)?;
+
+    Ok(header.kmers_count)
+}
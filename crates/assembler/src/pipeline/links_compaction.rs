@@ -1,7 +1,8 @@
 use crate::structs::link_mapping::{LinkMapping, LinkMappingSerializer};
+use config::operation::MemoryTrackedBucket;
 use config::{
-    get_memory_mode, BucketIndexType, SwapPriority, DEFAULT_PER_CPU_BUFFER_SIZE,
-    DEFAULT_PREFETCH_AMOUNT, KEEP_FILES,
+    get_memory_mode, keep_file_on_disk, BucketIndexType, SwapPriority, DEFAULT_PER_CPU_BUFFER_SIZE,
+    DEFAULT_PREFETCH_AMOUNT,
 };
 use io::structs::unitig_link::{UnitigFlags, UnitigIndex, UnitigLink, UnitigLinkSerializer};
 use nightly_quirks::slice_group_by::SliceGroupBy;
@@ -29,15 +30,17 @@ pub fn links_compaction(
     output_dir: impl AsRef<Path>,
     buckets_count: usize,
     elab_index: usize,
-    result_map_buckets: &Arc<MultiThreadBuckets<LockFreeBinaryWriter>>,
-    final_buckets: &Arc<MultiThreadBuckets<LockFreeBinaryWriter>>,
+    result_map_buckets: &Arc<MultiThreadBuckets<MemoryTrackedBucket<LockFreeBinaryWriter>>>,
+    final_buckets: &Arc<MultiThreadBuckets<MemoryTrackedBucket<LockFreeBinaryWriter>>>,
     // links_manager: &UnitigLinksManager,
     link_thread_buffers: &ScopedThreadLocal<BucketsThreadBuffer>,
     result_thread_buffers: &ScopedThreadLocal<BucketsThreadBuffer>,
 ) -> (Vec<SingleBucket>, u64) {
     let totsum = AtomicU64::new(0);
 
-    let links_buckets = Arc::new(MultiThreadBuckets::<LockFreeBinaryWriter>::new(
+    let links_buckets = Arc::new(MultiThreadBuckets::<
+        MemoryTrackedBucket<LockFreeBinaryWriter>,
+    >::new(
         buckets_count,
         output_dir
             .as_ref()
@@ -52,6 +55,10 @@ pub fn links_compaction(
     ));
 
     links_inputs.par_iter().for_each(|input| {
+        if config::should_interrupt_phase() {
+            return;
+        }
+
         let bucket_index = input.index as BucketIndexType;
 
         let mut link_buffers = link_thread_buffers.get();
@@ -77,7 +84,7 @@ pub fn links_compaction(
         let file_reader = LockFreeBinaryReader::new(
             &input.path,
             RemoveFileMode::Remove {
-                remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                remove_fs: !keep_file_on_disk(&input.path),
             },
             DEFAULT_PREFETCH_AMOUNT,
         );
@@ -364,6 +371,7 @@ pub fn links_compaction(
         link_buffers.put_back(links_tmp.finalize().0);
         final_links_tmp.finalize();
         result_buffers.put_back(results_tmp.finalize().0);
+        config::progress::advance_progress(1);
     });
 
     (
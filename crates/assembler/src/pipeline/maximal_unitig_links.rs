@@ -14,9 +14,11 @@ use crate::pipeline::maximal_unitig_links::maximal_unitig_index::{
 };
 use colors::colors_manager::color_types::PartialUnitigsColorStructure;
 use colors::colors_manager::ColorsManager;
+use config::operation::MemoryTrackedBucket;
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::{
-    get_compression_level_info, get_memory_mode, BucketIndexType, SwapPriority,
-    DEFAULT_OUTPUT_BUFFER_SIZE, DEFAULT_PER_CPU_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT, KEEP_FILES,
+    get_compression_level_info, get_memory_mode, keep_file_on_disk, BucketIndexType, SwapPriority,
+    DEFAULT_OUTPUT_BUFFER_SIZE, DEFAULT_PER_CPU_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT,
 };
 use dashmap::DashSet;
 use hashbrown::HashSet;
@@ -24,7 +26,7 @@ use hashes::HashFunctionFactory;
 use hashes::{ExtendableHashTraitType, HashFunction, HashableSequence};
 use io::concurrent::structured_sequences::concurrent::FastaWriterConcurrentBuffer;
 use io::concurrent::structured_sequences::{
-    SequenceAbundanceType, StructuredSequenceBackend, StructuredSequenceWriter,
+    SequenceAbundance, StructuredSequenceBackend, StructuredSequenceWriter,
 };
 use io::concurrent::temp_reads::creads_utils::{
     CompressedReadsBucketDataSerializer, NoMultiplicity, NoSecondBucket,
@@ -76,22 +78,28 @@ pub fn build_maximal_unitigs_links<
         PHASES_TIMES_MONITOR
             .write()
             .start_phase("phase: maximal unitigs links building [step 1]".to_string());
+        config::progress::start_progress_phase(
+            ProgressPhase::MaximalUnitigsLinks,
+            ProgressUnit::Buckets,
+            0,
+        );
 
         let maximal_unitigs_reader_step1 =
             CompressedBinaryReader::new(&in_file, RemoveFileMode::Keep, DEFAULT_PREFETCH_AMOUNT);
 
-        let maximal_unitigs_extremities_hashes_buckets =
-            Arc::new(MultiThreadBuckets::<CompressedBinaryWriter>::new(
-                buckets_count,
-                temp_dir.join("mu-hashes"),
-                None,
-                &(
-                    get_memory_mode(SwapPriority::HashBuckets),
-                    CompressedBinaryWriter::CHECKPOINT_SIZE_UNLIMITED,
-                    get_compression_level_info(),
-                ),
-                &(),
-            ));
+        let maximal_unitigs_extremities_hashes_buckets = Arc::new(MultiThreadBuckets::<
+            MemoryTrackedBucket<CompressedBinaryWriter>,
+        >::new(
+            buckets_count,
+            temp_dir.join("mu-hashes"),
+            None,
+            &(
+                get_memory_mode(SwapPriority::HashBuckets),
+                CompressedBinaryWriter::CHECKPOINT_SIZE_UNLIMITED,
+                get_compression_level_info(),
+            ),
+            &(),
+        ));
 
         rayon::scope(|_s| {
             (0..rayon::current_num_threads())
@@ -119,7 +127,7 @@ pub fn build_maximal_unitigs_links<
                                 u64,
                                 PartialUnitigsColorStructure<CX>,
                                 (),
-                                SequenceAbundanceType,
+                                SequenceAbundance,
                             )>::new_temp_buffer(),
                             AllowedCheckpointStrategy::DecompressOnly,
                             |(_, _, (index, _, _, _), read, _): (
@@ -129,7 +137,7 @@ pub fn build_maximal_unitigs_links<
                                     _,
                                     PartialUnitigsColorStructure<CX>,
                                     (),
-                                    SequenceAbundanceType,
+                                    SequenceAbundance,
                                 ),
                                 _,
                                 _,
@@ -254,7 +262,9 @@ pub fn build_maximal_unitigs_links<
             .write()
             .start_phase("phase: maximal unitigs links building [step 2]".to_string());
 
-        let maximal_links_buckets = Arc::new(MultiThreadBuckets::<CompressedBinaryWriter>::new(
+        let maximal_links_buckets = Arc::new(MultiThreadBuckets::<
+            MemoryTrackedBucket<CompressedBinaryWriter>,
+        >::new(
             buckets_count,
             temp_dir.join("maximal-links"),
             None,
@@ -283,7 +293,7 @@ pub fn build_maximal_unitigs_links<
             CompressedBinaryReader::new(
                 &input.path,
                 RemoveFileMode::Remove {
-                    remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                    remove_fs: !keep_file_on_disk(&input.path),
                 },
                 DEFAULT_PREFETCH_AMOUNT,
             )
@@ -358,7 +368,7 @@ pub fn build_maximal_unitigs_links<
         let maximal_unitigs_reader_step3 = CompressedBinaryReader::new(
             &in_file,
             RemoveFileMode::Remove {
-                remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                remove_fs: !keep_file_on_disk(&in_file),
             },
             DEFAULT_PREFETCH_AMOUNT,
         );
@@ -389,17 +399,17 @@ pub fn build_maximal_unitigs_links<
                                 u64,
                                 PartialUnitigsColorStructure<CX>,
                                 (),
-                                SequenceAbundanceType,
+                                SequenceAbundance,
                             )>::new_temp_buffer(),
                             AllowedCheckpointStrategy::DecompressOnly,
-                            |(_, _, (index, color, _, _abundance), read, _): (
+                            |(_, _, (index, color, _, abundance), read, _): (
                                 _,
                                 _,
                                 (
                                     _,
                                     PartialUnitigsColorStructure<CX>,
                                     (),
-                                    SequenceAbundanceType,
+                                    SequenceAbundance,
                                 ),
                                 _,
                                 _,
@@ -425,8 +435,7 @@ pub fn build_maximal_unitigs_links<
                                     &extra_buffer.0,
                                     links,
                                     links_buffer,
-                                    #[cfg(feature = "support_kmer_counters")]
-                                    _abundance,
+                                    abundance,
                                 );
                             },
                         )
@@ -1,18 +1,19 @@
 use assembler_kmers_merge::structs::PartialUnitigExtraData;
+use config::operation::MemoryTrackedBucket;
 use config::{
-    get_compression_level_info, get_memory_mode, BucketIndexType, SwapPriority,
-    DEFAULT_PER_CPU_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT, KEEP_FILES,
+    get_compression_level_info, get_memory_mode, keep_file_on_disk, BucketIndexType, SwapPriority,
+    DEFAULT_PER_CPU_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT,
 };
 use hashes::{HashFunctionFactory, HashableSequence};
 use io::concurrent::temp_reads::creads_utils::{
     CompressedReadsBucketData, CompressedReadsBucketDataSerializer, NoMultiplicity, NoSecondBucket,
 };
-#[cfg(feature = "support_kmer_counters")]
-use structs::unitigs_counters::UnitigsCounters;
+use structs::unitigs_counters::UnitigsCountersStorage;
 
 use crate::structs::link_mapping::{LinkMapping, LinkMappingSerializer};
 use colors::colors_manager::color_types::PartialUnitigsColorStructure;
 use colors::colors_manager::{color_types, ColorsManager};
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::DEFAULT_OUTPUT_BUFFER_SIZE;
 use io::concurrent::structured_sequences::concurrent::FastaWriterConcurrentBuffer;
 use io::concurrent::structured_sequences::{StructuredSequenceBackend, StructuredSequenceWriter};
@@ -36,18 +37,16 @@ use rayon::iter::ParallelIterator;
 use std::io::{Read, Write};
 use std::mem::transmute;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-#[cfg(feature = "support_kmer_counters")]
-use io::concurrent::structured_sequences::SequenceAbundance;
-
 #[derive(Clone, Debug)]
-pub struct ReorganizedReadsExtraData<CX: SequenceExtraDataConsecutiveCompression> {
+pub struct ReorganizedReadsExtraData<
+    CX: SequenceExtraDataConsecutiveCompression,
+    C: UnitigsCountersStorage,
+> {
     pub unitig: UnitigIndex,
     pub colors: CX,
-    #[cfg(feature = "support_kmer_counters")]
-    pub counters: UnitigsCounters,
+    pub counters: C,
 }
 
 #[repr(transparent)]
@@ -62,8 +61,8 @@ impl<CX: SequenceExtraDataConsecutiveCompression> ReorganizedReadsBuffer<CX> {
     }
 }
 
-impl<CX: SequenceExtraDataConsecutiveCompression> SequenceExtraDataTempBufferManagement
-    for ReorganizedReadsExtraData<CX>
+impl<CX: SequenceExtraDataConsecutiveCompression, C: UnitigsCountersStorage>
+    SequenceExtraDataTempBufferManagement for ReorganizedReadsExtraData<CX, C>
 {
     type TempBuffer = ReorganizedReadsBuffer<CX>;
 
@@ -91,14 +90,13 @@ impl<CX: SequenceExtraDataConsecutiveCompression> SequenceExtraDataTempBufferMan
         Self {
             unitig: extra.unitig,
             colors: changed_color,
-            #[cfg(feature = "support_kmer_counters")]
             counters: extra.counters,
         }
     }
 }
 
-impl<CX: SequenceExtraDataConsecutiveCompression> SequenceExtraDataConsecutiveCompression
-    for ReorganizedReadsExtraData<CX>
+impl<CX: SequenceExtraDataConsecutiveCompression, C: UnitigsCountersStorage>
+    SequenceExtraDataConsecutiveCompression for ReorganizedReadsExtraData<CX, C>
 {
     type LastData = CX::LastData;
 
@@ -111,8 +109,7 @@ impl<CX: SequenceExtraDataConsecutiveCompression> SequenceExtraDataConsecutiveCo
         Some(Self {
             unitig: UnitigIndex::decode(&mut reader, ())?,
             colors: CX::decode_extended(&mut buffer.0, &mut reader, last_data)?,
-            #[cfg(feature = "support_kmer_counters")]
-            counters: <UnitigsCounters as SequenceExtraData>::decode_extended(&mut (), reader)?,
+            counters: <C as SequenceExtraData>::decode_extended(&mut (), reader)?,
         })
     }
 
@@ -126,24 +123,14 @@ impl<CX: SequenceExtraDataConsecutiveCompression> SequenceExtraDataConsecutiveCo
         self.unitig.encode(&mut writer, ());
         self.colors
             .encode_extended(&buffer.0, &mut writer, last_data);
-        #[cfg(feature = "support_kmer_counters")]
-        <UnitigsCounters as SequenceExtraData>::encode_extended(
-            &self.counters,
-            &mut (),
-            &mut writer,
-        );
+        <C as SequenceExtraData>::encode_extended(&self.counters, &mut (), &mut writer);
     }
 
     #[inline(always)]
     fn max_size(&self) -> usize {
         SequenceExtraData::max_size(&self.unitig)
             + self.colors.max_size()
-            + match () {
-                #[cfg(feature = "support_kmer_counters")]
-                () => <UnitigsCounters as SequenceExtraData>::max_size(&self.counters),
-                #[cfg(not(feature = "support_kmer_counters"))]
-                () => 0,
-            }
+            + <C as SequenceExtraData>::max_size(&self.counters)
     }
 
     fn obtain_last_data(&self, last_data: Self::LastData) -> Self::LastData {
@@ -154,6 +141,7 @@ impl<CX: SequenceExtraDataConsecutiveCompression> SequenceExtraDataConsecutiveCo
 pub fn reorganize_reads<
     MH: HashFunctionFactory,
     CX: ColorsManager,
+    C: UnitigsCountersStorage,
     BK: StructuredSequenceBackend<PartialUnitigsColorStructure<CX>, ()>,
 >(
     k: usize,
@@ -167,8 +155,15 @@ pub fn reorganize_reads<
     PHASES_TIMES_MONITOR
         .write()
         .start_phase("phase: reads reorganization".to_string());
-
-    let buckets = Arc::new(MultiThreadBuckets::<CompressedBinaryWriter>::new(
+    config::progress::start_progress_phase(
+        ProgressPhase::ReorganizeReads,
+        ProgressUnit::Buckets,
+        reads.len() as u64,
+    );
+
+    let buckets = Arc::new(MultiThreadBuckets::<
+        MemoryTrackedBucket<CompressedBinaryWriter>,
+    >::new(
         buckets_count,
         temp_path.join("reads_bucket"),
         None,
@@ -190,12 +185,16 @@ pub fn reorganize_reads<
     });
 
     inputs.par_iter().for_each(|(read_file, mapping_file)| {
+        if config::should_interrupt_phase() {
+            return;
+        }
+
         let mut buffers = reads_thread_buffers.get();
 
         let mut tmp_reads_buffer = BucketsThreadDispatcher::<
             _,
             CompressedReadsBucketDataSerializer<
-                ReorganizedReadsExtraData<color_types::PartialUnitigsColorStructure<CX>>,
+                ReorganizedReadsExtraData<color_types::PartialUnitigsColorStructure<CX>, C>,
                 typenum::U0,
                 NoSecondBucket,
                 NoMultiplicity,
@@ -217,7 +216,7 @@ pub fn reorganize_reads<
         LockFreeBinaryReader::new(
             &mapping_file.path,
             RemoveFileMode::Remove {
-                remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                remove_fs: !keep_file_on_disk(&mapping_file.path),
             },
             DEFAULT_PREFETCH_AMOUNT,
         )
@@ -238,12 +237,12 @@ pub fn reorganize_reads<
         CompressedBinaryReader::new(
             &read_file.path,
             RemoveFileMode::Remove {
-                remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                remove_fs: !keep_file_on_disk(&read_file.path),
             },
             DEFAULT_PREFETCH_AMOUNT,
         )
         .decode_all_bucket_items::<CompressedReadsBucketDataSerializer<
-            PartialUnitigExtraData<color_types::PartialUnitigsColorStructure<CX>>,
+            PartialUnitigExtraData<color_types::PartialUnitigsColorStructure<CX>, C>,
             typenum::U0,
             NoSecondBucket,
             NoMultiplicity,
@@ -265,7 +264,6 @@ pub fn reorganize_reads<
                         &ReorganizedReadsExtraData {
                             unitig: UnitigIndex::new(bucket_index, index as usize, false),
                             colors: extra_data.colors,
-                            #[cfg(feature = "support_kmer_counters")]
                             counters: extra_data.counters,
                         },
                         ReorganizedReadsBuffer::from_inner(color_buffer),
@@ -287,12 +285,7 @@ pub fn reorganize_reads<
                                     color_buffer,
                                     (),
                                     &(),
-                                    #[cfg(feature = "support_kmer_counters")]
-                                    SequenceAbundance {
-                                        first: extra_data.counters.first,
-                                        sum: extra_data.counters.sum,
-                                        last: extra_data.counters.last,
-                                    },
+                                    extra_data.counters.into_abundance(),
                                 );
                                 break 'skip_writing;
                             }
@@ -306,12 +299,7 @@ pub fn reorganize_reads<
                             color_buffer,
                             (),
                             &(),
-                            #[cfg(feature = "support_kmer_counters")]
-                            SequenceAbundance {
-                                first: extra_data.counters.first,
-                                sum: extra_data.counters.sum,
-                                last: extra_data.counters.last,
-                            },
+                            extra_data.counters.into_abundance(),
                         );
 
                         break;
@@ -335,7 +323,8 @@ pub fn reorganize_reads<
         buffers.put_back(tmp_reads_buffer.finalize().0);
         tmp_lonely_unitigs_buffer.finalize();
 
-        assert_eq!(map_index, mappings.len())
+        assert_eq!(map_index, mappings.len());
+        config::progress::advance_progress(1);
     });
 
     (buckets.finalize_single(), PathBuf::new())
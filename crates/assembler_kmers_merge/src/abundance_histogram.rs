@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// The minimum multiplicity required to keep a kmer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MinMultiplicity {
+    Fixed(usize),
+    /// Chosen from the first valley of the kmers abundance histogram,
+    /// that is computed with an additional counting pass over the buckets
+    Auto,
+}
+
+/// The multiplicities up to this value are counted in a dense vector
+const DENSE_MULTIPLICITIES: usize = 1 << 16;
+
+/// The number of distinct kmers for each multiplicity value
+#[derive(Clone, Debug, Default)]
+pub struct KmersAbundanceHistogram {
+    counts: Vec<u64>,
+    sparse_counts: BTreeMap<usize, u64>,
+}
+
+impl KmersAbundanceHistogram {
+    #[inline(always)]
+    pub fn add(&mut self, multiplicity: usize) {
+        if multiplicity < DENSE_MULTIPLICITIES {
+            if self.counts.len() <= multiplicity {
+                self.counts.resize(multiplicity + 1, 0);
+            }
+            self.counts[multiplicity] += 1;
+        } else {
+            *self.sparse_counts.entry(multiplicity).or_insert(0) += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += *other_count;
+        }
+        for (multiplicity, count) in &other.sparse_counts {
+            *self.sparse_counts.entry(*multiplicity).or_insert(0) += *count;
+        }
+    }
+
+    /// Returns the number of distinct kmers with the given multiplicity
+    pub fn get(&self, multiplicity: usize) -> u64 {
+        match self.counts.get(multiplicity) {
+            Some(count) => *count,
+            None => self.sparse_counts.get(&multiplicity).copied().unwrap_or(0),
+        }
+    }
+
+    /// Iterates over the multiplicities with at least one kmer, in increasing order
+    pub fn iter(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.counts
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .chain(
+                self.sparse_counts
+                    .iter()
+                    .map(|(multiplicity, count)| (*multiplicity, *count)),
+            )
+    }
+
+    /// Returns the multiplicity of the first local minimum of the spectrum, where the erroneous kmers
+    /// peak at low multiplicities ends, or None if the distinct kmers count never increases again
+    pub fn first_valley(&self) -> Option<usize> {
+        let mut valley = (1..self.counts.len()).find(|m| self.get(m + 1) > self.get(*m))?;
+        // Choose the lowest multiplicity of a flat minimum
+        while valley > 1 && self.get(valley - 1) == self.get(valley) {
+            valley -= 1;
+        }
+        Some(valley)
+    }
+
+    /// Writes the histogram in TSV format, with the multiplicity and the number of distinct kmers on each line
+    pub fn write_tsv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        for (multiplicity, count) in self.iter() {
+            writeln!(writer, "{}\t{}", multiplicity, count)?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KmersAbundanceHistogram;
+
+    fn histogram(counts: &[(usize, u64)]) -> KmersAbundanceHistogram {
+        let mut histogram = KmersAbundanceHistogram::default();
+        for (multiplicity, count) in counts {
+            for _ in 0..*count {
+                histogram.add(*multiplicity);
+            }
+        }
+        histogram
+    }
+
+    #[test]
+    fn spectrum_first_valley() {
+        let reads = histogram(&[
+            (1, 900),
+            (2, 120),
+            (3, 40),
+            (4, 45),
+            (5, 80),
+            (6, 90),
+            (7, 60),
+        ]);
+        assert_eq!(reads.first_valley(), Some(3));
+
+        let gap = histogram(&[(1, 500), (6, 20), (7, 30), (1 << 17, 1)]);
+        assert_eq!(gap.first_valley(), Some(2));
+        assert_eq!(gap.get(1 << 17), 1);
+
+        let genomes = histogram(&[(1, 1000), (2, 30), (3, 2)]);
+        assert_eq!(genomes.first_valley(), None);
+
+        let mut merged = reads.clone();
+        merged.merge(&gap);
+        assert_eq!(merged.get(1), 1400);
+        assert_eq!(merged.iter().count(), 8);
+
+        let mut tsv = vec![];
+        genomes.write_tsv(&mut tsv).unwrap();
+        assert_eq!(String::from_utf8(tsv).unwrap(), "1\t1000\n2\t30\n3\t2\n");
+    }
+}
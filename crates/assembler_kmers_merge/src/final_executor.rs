@@ -1,8 +1,14 @@
+use crate::abundance_histogram::KmersAbundanceHistogram;
+use crate::kmers_counts::{
+    kmers_counts_partition, kmers_counts_partition_bases, pack_kmer, KmerCountRecord,
+    KmerCountRecordSerializer,
+};
 use crate::map_processor::ParallelKmersMergeMapPacket;
 use crate::structs::PartialUnitigExtraData;
 use crate::{GlobalMergeData, ParallelKmersMergeFactory, ResultsBucket};
 use colors::colors_manager::ColorsMergeManager;
 use colors::colors_manager::{color_types, ColorsManager};
+use config::operation::MemoryTrackedBucket;
 use config::DEFAULT_PER_CPU_BUFFER_SIZE;
 use config::{READ_FLAG_INCL_BEGIN, READ_FLAG_INCL_END};
 use core::slice::from_raw_parts;
@@ -19,8 +25,7 @@ use parallel_processor::buckets::writers::lock_free_binary_writer::LockFreeBinar
 use parallel_processor::execution_manager::packet::Packet;
 use std::ops::DerefMut;
 use structs::map_entry::MapEntry;
-#[cfg(feature = "support_kmer_counters")]
-use structs::unitigs_counters::UnitigsCounters;
+use structs::unitigs_counters::UnitigsCountersStorage;
 use utils::Utils;
 
 local_setup_instrumenter!();
@@ -28,27 +33,39 @@ local_setup_instrumenter!();
 pub struct ParallelKmersMergeFinalExecutor<
     MH: HashFunctionFactory,
     CX: ColorsManager,
+    C: UnitigsCountersStorage,
     const COMPUTE_SIMPLITIGS: bool,
 > {
     hashes_tmp: BucketsThreadDispatcher<
-        LockFreeBinaryWriter,
+        MemoryTrackedBucket<LockFreeBinaryWriter>,
         HashEntrySerializer<MH::HashTypeUnextendable>,
     >,
 
     forward_seq: Vec<u8>,
     backward_seq: Vec<u8>,
+    forward_abundances: Vec<u64>,
+    backward_abundances: Vec<u64>,
     unitigs_temp_colors: color_types::TempUnitigColorStructure<CX>,
-    current_bucket: Option<ResultsBucket<color_types::PartialUnitigsColorStructure<CX>>>,
+    current_bucket: Option<ResultsBucket<color_types::PartialUnitigsColorStructure<CX>, C>>,
     temp_color_buffer:
         <color_types::PartialUnitigsColorStructure<CX> as SequenceExtraDataTempBufferManagement>::TempBuffer,
     bucket_counter: usize,
     bucket_change_threshold: usize,
+    abundance_histogram: KmersAbundanceHistogram,
+    kmers_counts_tmp: Option<BucketsThreadDispatcher<MemoryTrackedBucket<LockFreeBinaryWriter>, KmerCountRecordSerializer>>,
+    kmers_counts_partition_bases: usize,
+    packed_kmer: Vec<u8>,
+    packed_rc_kmer: Vec<u8>,
 }
 
-impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
-    ParallelKmersMergeFinalExecutor<MH, CX, COMPUTE_SIMPLITIGS>
+impl<
+        MH: HashFunctionFactory,
+        CX: ColorsManager,
+        C: UnitigsCountersStorage,
+        const COMPUTE_SIMPLITIGS: bool,
+    > ParallelKmersMergeFinalExecutor<MH, CX, C, COMPUTE_SIMPLITIGS>
 {
-    pub fn new(global_data: &GlobalMergeData<CX>) -> Self {
+    pub fn new(global_data: &GlobalMergeData<CX, C>) -> Self {
         let hashes_buffer =
             BucketsThreadBuffer::new(DEFAULT_PER_CPU_BUFFER_SIZE, global_data.buckets_count);
 
@@ -56,29 +73,87 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
             hashes_tmp: BucketsThreadDispatcher::new(&global_data.hashes_buckets, hashes_buffer),
             forward_seq: Vec::with_capacity(global_data.k),
             backward_seq: Vec::with_capacity(global_data.k),
+            forward_abundances: Vec::new(),
+            backward_abundances: Vec::new(),
             unitigs_temp_colors: CX::ColorsMergeManagerType::alloc_unitig_color_structure(),
             current_bucket: None,
             temp_color_buffer: color_types::PartialUnitigsColorStructure::<CX>::new_temp_buffer(),
             bucket_counter: 0,
             bucket_change_threshold: 16, // TODO: Parametrize
+            abundance_histogram: KmersAbundanceHistogram::default(),
+            kmers_counts_tmp: global_data.kmers_counts_buckets.as_ref().map(|buckets| {
+                BucketsThreadDispatcher::new(
+                    buckets,
+                    BucketsThreadBuffer::new(DEFAULT_PER_CPU_BUFFER_SIZE, buckets.count()),
+                )
+            }),
+            kmers_counts_partition_bases: kmers_counts_partition_bases(global_data.buckets_count),
+            packed_kmer: Vec::with_capacity(global_data.k.div_ceil(4)),
+            packed_rc_kmer: Vec::with_capacity(global_data.k.div_ceil(4)),
         }
     }
 
+    /// Writes all the solid kmers of the map with their counts, instead of extending them into unitigs
+    fn write_kmers_counts(
+        &mut self,
+        global_data: &<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
+        map_struct: &ParallelKmersMergeMapPacket<MH, CX, C>,
+    ) {
+        let kmers_counts_tmp = self.kmers_counts_tmp.as_mut().unwrap();
+
+        Self::get_kmers(global_data, map_struct, |_hash, cread, rhentry| {
+            // The kmers joining two sequences are stored in both their buckets, write them only once
+            if rhentry.get_flags() == READ_FLAG_INCL_BEGIN {
+                return;
+            }
+            // Without an invertible hash the same kmer can be found in multiple saved reads
+            rhentry.set_used();
+
+            self.forward_seq.clear();
+            cread.write_unpacked_to_vec(&mut self.forward_seq, false);
+            self.packed_kmer.clear();
+            pack_kmer(&self.forward_seq, &mut self.packed_kmer);
+
+            if global_data.canonical_kmers_counts {
+                self.forward_seq.clear();
+                cread.write_unpacked_to_vec(&mut self.forward_seq, true);
+                self.packed_rc_kmer.clear();
+                pack_kmer(&self.forward_seq, &mut self.packed_rc_kmer);
+                if self.packed_rc_kmer < self.packed_kmer {
+                    std::mem::swap(&mut self.packed_kmer, &mut self.packed_rc_kmer);
+                }
+            }
+
+            kmers_counts_tmp.add_element(
+                kmers_counts_partition(&self.packed_kmer, self.kmers_counts_partition_bases),
+                &(),
+                &KmerCountRecord {
+                    packed_kmer: &self.packed_kmer,
+                    count: rhentry.get_kmer_multiplicity() as u64,
+                    colors_subset: CX::COLORS_ENABLED.then(|| {
+                        CX::ColorsMergeManagerType::get_kmer_color_subset::<C::TempColorIndex>(
+                            rhentry,
+                        )
+                    }),
+                },
+            );
+        });
+    }
+
     fn get_kmers(
-        global_data: &<ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
-        map_struct: &ParallelKmersMergeMapPacket<MH, CX>,
+        global_data: &<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
+        map_struct: &ParallelKmersMergeMapPacket<MH, CX, C>,
         mut callback: impl FnMut(
             MH::HashTypeExtendable,
             CompressedRead,
-            &MapEntry<color_types::HashMapTempColorIndex<CX>>,
+            &MapEntry<color_types::HashMapTempColorIndex<CX, C::TempColorIndex>>,
         ),
     ) {
         let k = global_data.k;
 
         if MH::INVERTIBLE {
             for (hash, rhentry) in map_struct.rhash_map.iter() {
-                let count = rhentry.get_kmer_multiplicity();
-                if count < global_data.min_multiplicity || count > global_data.max_multiplicity{
+                if !global_data.is_solid_kmer(rhentry) {
                     continue;
                 }
 
@@ -134,8 +209,7 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                         }
                     };
 
-                    let count = rhentry.get_kmer_multiplicity();
-                    if count < global_data.min_multiplicity || count > global_data.max_multiplicity{
+                    if !global_data.is_solid_kmer(rhentry) {
                         continue;
                     }
 
@@ -164,24 +238,59 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
 
 // static DEBUG_MAPS_HOLDER: Mutex<Vec<Box<dyn Any + Sync + Send>>> = const_mutex(Vec::new());
 
-impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
-    KmersTransformFinalExecutor<ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS>>
-    for ParallelKmersMergeFinalExecutor<MH, CX, COMPUTE_SIMPLITIGS>
+impl<
+        MH: HashFunctionFactory,
+        CX: ColorsManager,
+        C: UnitigsCountersStorage,
+        const COMPUTE_SIMPLITIGS: bool,
+    > KmersTransformFinalExecutor<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS>>
+    for ParallelKmersMergeFinalExecutor<MH, CX, C, COMPUTE_SIMPLITIGS>
 {
-    type MapStruct = ParallelKmersMergeMapPacket<MH, CX>;
+    type MapStruct = ParallelKmersMergeMapPacket<MH, CX, C>;
 
     #[instrumenter::track(fields(map_capacity = map_struct_packet.rhash_map.capacity(), map_size = map_struct_packet.rhash_map.len()))]
     fn process_map(
         &mut self,
-        global_data: &<ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
+        global_data: &<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
         mut map_struct_packet: Packet<Self::MapStruct>,
     ) -> Packet<Self::MapStruct> {
-        if self.current_bucket.is_none() {
-            self.current_bucket = Some(global_data.output_results_buckets.pop().unwrap());
+        if global_data.abundance_histogram.is_some() {
+            // The kmers joining two sequences are stored in both their buckets, count them only once
+            for entry in map_struct_packet.rhash_map.values() {
+                if entry.get_flags() != READ_FLAG_INCL_BEGIN {
+                    self.abundance_histogram.add(entry.get_kmer_multiplicity());
+                }
+            }
+            if global_data.histogram_only {
+                return map_struct_packet;
+            }
         }
 
         let map_struct = map_struct_packet.deref_mut();
 
+        if self.kmers_counts_tmp.is_some() {
+            if CX::COLORS_ENABLED {
+                CX::ColorsMergeManagerType::process_colors::<MH, C::TempColorIndex>(
+                    &global_data.colors_global_table,
+                    global_data.color_subsets.as_deref(),
+                    &mut map_struct.temp_colors,
+                    &mut map_struct.rhash_map,
+                    global_data.k,
+                    global_data.min_multiplicity,
+                    global_data.max_multiplicity,
+                    global_data.min_color_multiplicity,
+                );
+            }
+            if MH::INVERTIBLE || map_struct.saved_reads.len() * 4 >= global_data.k {
+                self.write_kmers_counts(global_data, map_struct);
+            }
+            return map_struct_packet;
+        }
+
+        if self.current_bucket.is_none() {
+            self.current_bucket = Some(global_data.output_results_buckets.pop().unwrap());
+        }
+
         let k = global_data.k;
         let buckets_count = global_data.buckets_count;
         let buckets_count_bits = buckets_count.ilog2() as usize;
@@ -190,13 +299,15 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
         let bucket_index = current_bucket.get_bucket_index();
 
         if CX::COLORS_ENABLED {
-            CX::ColorsMergeManagerType::process_colors::<MH>(
+            CX::ColorsMergeManagerType::process_colors::<MH, C::TempColorIndex>(
                 &global_data.colors_global_table,
+                global_data.color_subsets.as_deref(),
                 &mut map_struct.temp_colors,
                 &mut map_struct.rhash_map,
                 global_data.k,
                 global_data.min_multiplicity,
-                global_data.max_multiplicity
+                global_data.max_multiplicity,
+                global_data.min_color_multiplicity,
             );
         }
 
@@ -231,21 +342,22 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
             self.backward_seq[..].copy_from_slice(&self.forward_seq[..]);
             self.backward_seq.reverse();
 
-            CX::ColorsMergeManagerType::extend_forward(&mut self.unitigs_temp_colors, rhentry);
+            CX::ColorsMergeManagerType::extend_forward::<C::TempColorIndex>(
+                &mut self.unitigs_temp_colors,
+                rhentry,
+            );
             rhentry.set_used();
 
-            #[cfg(feature = "support_kmer_counters")]
             let first_count = rhentry.get_kmer_multiplicity() as u64;
 
-            #[cfg(feature = "support_kmer_counters")]
-            let mut counters = UnitigsCounters {
-                first: first_count,
-                sum: first_count,
-                last: first_count,
-            };
+            let mut counters = C::new_unitig(first_count);
+
+            self.forward_abundances.clear();
+            self.backward_abundances.clear();
 
             let mut try_extend_function =
                 |output: &mut Vec<u8>,
+                 output_abundances: &mut Vec<u64>,
                  compute_hash_fw: fn(
                     hash: MH::HashTypeExtendable,
                     kmer_length: usize,
@@ -260,16 +372,15 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                 ) -> MH::HashTypeExtendable,
                  colors_function: fn(
                     ts: &mut color_types::TempUnitigColorStructure<CX>,
-                    entry: &MapEntry<color_types::HashMapTempColorIndex<CX>>,
+                    entry: &MapEntry<color_types::HashMapTempColorIndex<CX, C::TempColorIndex>>,
                 ),
-                 #[cfg(feature = "support_kmer_counters")] is_forward: bool| {
+                 is_forward: bool| {
                     let mut temp_data = (hash, 0);
                     let mut current_hash;
 
                     return 'ext_loop: loop {
                         let mut count = 0;
                         current_hash = temp_data.0;
-                        #[cfg(feature = "support_kmer_counters")]
                         let mut multiplicity = 0;
                         for idx in 0..4 {
                             let new_hash = compute_hash_fw(
@@ -281,12 +392,9 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                             if let Some(hash) =
                                 map_struct.rhash_map.get(&new_hash.to_unextendable())
                             {
-                                if hash.get_kmer_multiplicity() >= global_data.min_multiplicity && hash.get_kmer_multiplicity() <= global_data.max_multiplicity{
+                                if global_data.is_solid_kmer(hash) {
                                     // ggcat_logging::info!("Forward match extend read {:x?}!", new_hash);
-                                    #[cfg(feature = "support_kmer_counters")]
-                                    {
-                                        multiplicity = hash.get_kmer_multiplicity() as u64;
-                                    }
+                                    multiplicity = hash.get_kmer_multiplicity() as u64;
                                     count += 1;
                                     temp_data = (new_hash, idx);
 
@@ -325,8 +433,7 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                                     if let Some(hash) =
                                         map_struct.rhash_map.get(&bw_hash.to_unextendable())
                                     {
-                                        if hash.get_kmer_multiplicity() >= global_data.min_multiplicity && hash.get_kmer_multiplicity() <= global_data.max_multiplicity
-                                        {
+                                        if global_data.is_solid_kmer(hash) {
                                             if ocount > 0 {
                                                 break 'ext_loop (current_hash, false);
                                             }
@@ -358,8 +465,7 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
 
                             output.push(Utils::decompress_base(temp_data.1));
 
-                            #[cfg(feature = "support_kmer_counters")]
-                            {
+                            if let Some(counters) = counters.get_counters_mut() {
                                 counters.sum += multiplicity;
                                 if is_forward {
                                     counters.last = multiplicity;
@@ -367,6 +473,9 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                                     counters.first = multiplicity;
                                 }
                             }
+                            if global_data.abundance_mode.needs_kmers_abundances() {
+                                output_abundances.push(multiplicity);
+                            }
 
                             // Found a continuation into another bucket
                             let contig_break = (entryref.get_flags() == READ_FLAG_INCL_BEGIN)
@@ -386,10 +495,10 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                 } else {
                     let (fw_hash, end_ignored) = try_extend_function(
                         &mut self.forward_seq,
+                        &mut self.forward_abundances,
                         MH::manual_roll_forward,
                         MH::manual_roll_reverse,
-                        CX::ColorsMergeManagerType::extend_forward,
-                        #[cfg(feature = "support_kmer_counters")]
+                        CX::ColorsMergeManagerType::extend_forward::<C::TempColorIndex>,
                         true,
                     );
                     (fw_hash, end_ignored)
@@ -402,10 +511,10 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                 } else {
                     let (bw_hash, begin_ignored) = try_extend_function(
                         &mut self.backward_seq,
+                        &mut self.backward_abundances,
                         MH::manual_roll_reverse,
                         MH::manual_roll_forward,
-                        CX::ColorsMergeManagerType::extend_backward,
-                        #[cfg(feature = "support_kmer_counters")]
+                        CX::ColorsMergeManagerType::extend_backward::<C::TempColorIndex>,
                         false,
                     );
                     (bw_hash, begin_ignored)
@@ -418,17 +527,25 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                 &self.backward_seq[..]
             };
 
+            if let Some(counters) = counters
+                .get_counters_mut()
+                .filter(|_| global_data.abundance_mode.needs_kmers_abundances())
+            {
+                // Same order of the kmers in the output sequence
+                counters
+                    .kmers
+                    .reserve(self.backward_abundances.len() + 1 + self.forward_abundances.len());
+                counters.kmers.extend(self.backward_abundances.iter().rev());
+                counters.kmers.push(first_count);
+                counters.kmers.extend_from_slice(&self.forward_abundances);
+            }
+
             let colors = color_types::ColorsMergeManagerType::<CX>::encode_part_unitigs_colors(
                 &mut self.unitigs_temp_colors,
                 &mut self.temp_color_buffer,
             );
 
-            let extra_data = PartialUnitigExtraData {
-                colors,
-
-                #[cfg(feature = "support_kmer_counters")]
-                counters,
-            };
+            let extra_data = PartialUnitigExtraData { colors, counters };
 
             let read_index = current_bucket.add_read(extra_data, out_seq, &self.temp_color_buffer);
 
@@ -470,8 +587,14 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
 
     fn finalize(
         self,
-        _global_data: &<ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
+        global_data: &<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
     ) {
+        if let Some(abundance_histogram) = &global_data.abundance_histogram {
+            abundance_histogram.lock().merge(&self.abundance_histogram);
+        }
+        if let Some(kmers_counts_tmp) = self.kmers_counts_tmp {
+            kmers_counts_tmp.finalize();
+        }
         self.hashes_tmp.finalize();
     }
 }
@@ -0,0 +1,289 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use config::ColorIndexType;
+use io::varint::{decode_varint, encode_varint, VARINT_MAX_SIZE};
+use parallel_processor::buckets::bucket_writer::BucketItemSerializer;
+use std::io::{Read, Write};
+
+/// The format of the kmers counts table, where the solid kmers are sorted in lexicographic order
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KmersCountsFormat {
+    /// Text lines with the kmer, its count and optionally its colors, separated by tabs
+    Tsv,
+    /// A header followed by fixed size records, with the kmer packed in 2 bits per base,
+    /// its count and optionally its colors subset index
+    Binary,
+}
+
+/// Writes the solid kmers with their counts instead of building the graph
+#[derive(Copy, Clone, Debug)]
+pub struct KmersCountsOutput {
+    pub format: KmersCountsFormat,
+    /// The kmers are counted together with their reverse complements,
+    /// and the lexicographically smaller of the two is written
+    pub canonical: bool,
+}
+
+pub const KMERS_COUNTS_MAGIC: [u8; 8] = *b"GGCATKC\0";
+pub const KMERS_COUNTS_VERSION: u32 = 2;
+/// The records have the colors subset index of the kmer after its count
+pub const KMERS_COUNTS_FLAG_COLORED: u32 = 1;
+/// The reverse complement kmers are counted together
+pub const KMERS_COUNTS_FLAG_CANONICAL: u32 = 2;
+
+const PACKED_BASES: [u8; 4] = *b"ACGT";
+
+/// The header of the binary kmers counts table
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KmersCountsHeader {
+    pub k: usize,
+    pub colored: bool,
+    pub canonical: bool,
+    pub kmers_count: u64,
+}
+
+impl KmersCountsHeader {
+    pub const SIZE: usize = 8 + 4 + 4 + 4 + 8;
+
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let mut flags = 0;
+        if self.colored {
+            flags |= KMERS_COUNTS_FLAG_COLORED;
+        }
+        if self.canonical {
+            flags |= KMERS_COUNTS_FLAG_CANONICAL;
+        }
+        writer.write_all(&KMERS_COUNTS_MAGIC)?;
+        writer.write_u32::<LittleEndian>(KMERS_COUNTS_VERSION)?;
+        writer.write_u32::<LittleEndian>(flags)?;
+        writer.write_u32::<LittleEndian>(self.k as u32)?;
+        writer.write_u64::<LittleEndian>(self.kmers_count)
+    }
+
+    pub fn read_from(mut reader: impl Read) -> std::io::Result<Self> {
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != KMERS_COUNTS_MAGIC {
+            return Err(invalid("not a kmers counts file"));
+        }
+        if reader.read_u32::<LittleEndian>()? != KMERS_COUNTS_VERSION {
+            return Err(invalid("unsupported kmers counts file version"));
+        }
+        let flags = reader.read_u32::<LittleEndian>()?;
+        let k = reader.read_u32::<LittleEndian>()? as usize;
+        let kmers_count = reader.read_u64::<LittleEndian>()?;
+
+        Ok(Self {
+            k,
+            colored: flags & KMERS_COUNTS_FLAG_COLORED != 0,
+            canonical: flags & KMERS_COUNTS_FLAG_CANONICAL != 0,
+            kmers_count,
+        })
+    }
+}
+
+/// The bytes used by a packed kmer
+pub fn packed_kmer_size(k: usize) -> usize {
+    k.div_ceil(4)
+}
+
+/// The size of a record with the packed kmer, its 64 bit count and the optional colors subset index
+pub fn kmers_counts_record_size(k: usize, colored: bool) -> usize {
+    packed_kmer_size(k) + 8 + if colored { 4 } else { 0 }
+}
+
+/// The kmers are split in partitions by their first bases, so that each partition can be sorted separately
+pub fn kmers_counts_partition_bases(buckets_count: usize) -> usize {
+    (buckets_count.ilog2() as usize / 2).clamp(1, 4)
+}
+
+/// Returns the partition of a packed kmer, the partitions are ordered as their kmers
+#[inline(always)]
+pub fn kmers_counts_partition(packed_kmer: &[u8], partition_bases: usize) -> u16 {
+    (packed_kmer[0] >> (8 - 2 * partition_bases)) as u16
+}
+
+/// Returns the sub-partition of a packed kmer given by the `bases` following its first `skip_bases`,
+/// used to split again the partitions too large to be sorted at once
+#[inline(always)]
+pub fn kmers_counts_subpartition(packed_kmer: &[u8], skip_bases: usize, bases: usize) -> u16 {
+    (skip_bases..skip_bases + bases).fold(0, |subpartition, index| {
+        // The bases after the end of the kmer are read as A, as in the padding of the last byte
+        let code = packed_kmer
+            .get(index / 4)
+            .map_or(0, |byte| (byte >> (6 - 2 * (index % 4))) & 0x3);
+        (subpartition << 2) | code as u16
+    })
+}
+
+/// Packs the bases in 2 bits each, with the first base in the highest bits,
+/// so that the packed kmers compare in the same order as their bases
+pub fn pack_kmer(bases: &[u8], output: &mut Vec<u8>) {
+    for chunk in bases.chunks(4) {
+        let mut byte = 0;
+        for (index, base) in chunk.iter().enumerate() {
+            let code = match base {
+                b'A' => 0,
+                b'C' => 1,
+                b'G' => 2,
+                _ => 3,
+            };
+            byte |= code << (6 - 2 * index);
+        }
+        output.push(byte);
+    }
+}
+
+pub fn unpack_kmer(packed: &[u8], k: usize, output: &mut Vec<u8>) {
+    output.extend(
+        (0..k).map(|index| {
+            PACKED_BASES[((packed[index / 4] >> (6 - 2 * (index % 4))) & 0x3) as usize]
+        }),
+    );
+}
+
+/// A solid kmer with its count, as written in the temporary partitions
+pub struct KmerCountRecord<'a> {
+    pub packed_kmer: &'a [u8],
+    pub count: u64,
+    /// The colors subset index of the kmer, for colored builds. It refers to the subsets of the
+    /// colormap written with the counts, and the count is not split across the colors
+    pub colors_subset: Option<ColorIndexType>,
+}
+
+/// The buffer used to read the records, holding the size of the packed kmers
+pub struct KmerCountReadBuffer {
+    packed_kmer: Vec<u8>,
+    colored: bool,
+}
+
+impl KmerCountReadBuffer {
+    pub fn new(k: usize, colored: bool) -> Self {
+        Self {
+            packed_kmer: vec![0; packed_kmer_size(k)],
+            colored,
+        }
+    }
+}
+
+pub struct KmerCountRecordSerializer;
+
+impl BucketItemSerializer for KmerCountRecordSerializer {
+    type InputElementType<'a> = KmerCountRecord<'a>;
+    type ExtraData = ();
+    type ReadBuffer = KmerCountReadBuffer;
+    type ExtraDataBuffer = ();
+    type ReadType<'a> = KmerCountRecord<'a>;
+
+    type CheckpointData = ();
+
+    #[inline(always)]
+    fn new() -> Self {
+        Self
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {}
+
+    #[inline(always)]
+    fn write_to(
+        &mut self,
+        element: &Self::InputElementType<'_>,
+        bucket: &mut Vec<u8>,
+        _extra_data: &Self::ExtraData,
+        _: &Self::ExtraDataBuffer,
+    ) {
+        bucket.extend_from_slice(element.packed_kmer);
+        encode_varint(|b| bucket.write_all(b), element.count).unwrap();
+        if let Some(colors_subset) = element.colors_subset {
+            encode_varint(|b| bucket.write_all(b), colors_subset as u64).unwrap();
+        }
+    }
+
+    fn read_from<'a, S: Read>(
+        &mut self,
+        mut stream: S,
+        read_buffer: &'a mut Self::ReadBuffer,
+        _: &mut Self::ExtraDataBuffer,
+    ) -> Option<Self::ReadType<'a>> {
+        stream.read_exact(&mut read_buffer.packed_kmer).ok()?;
+        let count = decode_varint(|| stream.read_u8().ok())?;
+        let colors_subset = if read_buffer.colored {
+            Some(decode_varint(|| stream.read_u8().ok())? as ColorIndexType)
+        } else {
+            None
+        };
+        Some(KmerCountRecord {
+            packed_kmer: &read_buffer.packed_kmer,
+            count,
+            colors_subset,
+        })
+    }
+
+    #[inline(always)]
+    fn get_size(&self, element: &Self::InputElementType<'_>, _: &()) -> usize {
+        element.packed_kmer.len() + VARINT_MAX_SIZE * 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        kmers_counts_partition, kmers_counts_subpartition, pack_kmer, unpack_kmer,
+        KmersCountsHeader,
+    };
+
+    #[test]
+    fn packed_kmers_order() {
+        let mut kmers: Vec<&[u8]> =
+            vec![b"TTACG", b"ACGTA", b"ACGTC", b"GATTA", b"CAAAA", b"AAAAA"];
+
+        let mut packed: Vec<_> = kmers
+            .iter()
+            .map(|kmer| {
+                let mut packed = vec![];
+                pack_kmer(kmer, &mut packed);
+                assert_eq!(packed.len(), 2);
+                packed
+            })
+            .collect();
+
+        kmers.sort();
+        packed.sort();
+
+        for (kmer, packed) in kmers.iter().zip(packed.iter()) {
+            let mut unpacked = vec![];
+            unpack_kmer(packed, kmer.len(), &mut unpacked);
+            assert_eq!(&unpacked[..], *kmer);
+        }
+
+        assert_eq!(kmers_counts_partition(&packed[0], 1), 0);
+        assert_eq!(kmers_counts_partition(&packed[5], 1), 3);
+        assert_eq!(kmers_counts_partition(&packed[4], 2), 0b1000);
+
+        // GATTA: the bases after the first 2 are TTA, then the padding
+        assert_eq!(kmers_counts_subpartition(&packed[4], 0, 2), 0b1000);
+        assert_eq!(kmers_counts_subpartition(&packed[4], 2, 2), 0b1111);
+        assert_eq!(kmers_counts_subpartition(&packed[4], 4, 2), 0b0000);
+        assert_eq!(kmers_counts_subpartition(&packed[4], 3, 4), 0b11000000);
+    }
+
+    #[test]
+    fn header_roundtrip() {
+        let header = KmersCountsHeader {
+            k: 31,
+            colored: true,
+            canonical: false,
+            kmers_count: 12345,
+        };
+
+        let mut buffer = vec![];
+        header.write_to(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), KmersCountsHeader::SIZE);
+        assert_eq!(KmersCountsHeader::read_from(&buffer[..]).unwrap(), header);
+
+        buffer[0] = b'X';
+        assert!(KmersCountsHeader::read_from(&buffer[..]).is_err());
+    }
+}
@@ -1,12 +1,18 @@
+use crate::abundance_histogram::{KmersAbundanceHistogram, MinMultiplicity};
 use crate::final_executor::ParallelKmersMergeFinalExecutor;
-use crate::map_processor::{ParallelKmersMergeMapProcessor, KMERGE_TEMP_DIR};
+use crate::kmers_counts::{kmers_counts_partition_bases, KmersCountsOutput};
+use crate::map_processor::ParallelKmersMergeMapProcessor;
 use crate::structs::{ResultsBucket, RetType};
+use ::structs::map_entry::{MapEntry, TempColorIndexStorage};
+use ::structs::unitigs_counters::UnitigsCountersStorage;
 use assembler_minimizer_bucketing::rewrite_bucket::RewriteBucketComputeAssembler;
 use assembler_minimizer_bucketing::AssemblerMinimizerBucketingExecutorFactory;
 use colors::colors_manager::color_types::{
     GlobalColorsTableWriter, MinimizerBucketingSeqColorDataType,
 };
 use colors::colors_manager::{color_types, ColorsManager};
+use config::operation::MemoryTrackedBucket;
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::{
     get_compression_level_info, get_memory_mode, BucketIndexType, SwapPriority,
     MINIMUM_SUBBUCKET_KMERS_COUNT, RESPLITTING_MAX_K_M_DIFFERENCE,
@@ -14,6 +20,8 @@ use config::{
 use crossbeam::queue::*;
 use hashes::default::MNHFactory;
 use hashes::HashFunctionFactory;
+use io::concurrent::structured_sequences::AbundanceOutputMode;
+use io::sequences_stream::ColorSubsets;
 use io::structs::hash_entry::HashEntry;
 use io::structs::hash_entry::{Direction, HashEntrySerializer};
 use kmers_transform::processor::KmersTransformProcessor;
@@ -30,6 +38,7 @@ use parallel_processor::buckets::{
 };
 use parallel_processor::execution_manager::memory_tracker::MemoryTracker;
 use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
+use parking_lot::Mutex;
 use std::cmp::min;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
@@ -37,27 +46,63 @@ use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use utils::owned_drop::OwnedDrop;
 
+pub mod abundance_histogram;
 mod final_executor;
+pub mod kmers_counts;
 mod map_processor;
 pub mod structs;
 
-pub struct GlobalMergeData<CX: ColorsManager> {
+pub struct GlobalMergeData<CX: ColorsManager, C: UnitigsCountersStorage> {
     k: usize,
     m: usize,
     buckets_count: usize,
     min_multiplicity: usize,
     max_multiplicity: usize,
+    /// The colors where a kmer appears fewer times are removed from it
+    min_color_multiplicity: usize,
+    /// The multiplicity of each kmer is stored in the unitigs counters only if the per-kmer abundances are written
+    abundance_mode: AbundanceOutputMode,
+    /// Collects the multiplicities of all the kmers, solid or not
+    abundance_histogram: Option<Mutex<KmersAbundanceHistogram>>,
+    /// Only the histogram is collected, without building the unitigs fragments
+    histogram_only: bool,
     colors_global_table: Arc<GlobalColorsTableWriter<CX>>,
+    /// The directory of the temporary files of the colors
+    temp_dir: PathBuf,
+    /// Expands the color subsets assigned to the input sequences
+    color_subsets: Option<Arc<dyn ColorSubsets>>,
     output_results_buckets:
-        ArrayQueue<ResultsBucket<color_types::PartialUnitigsColorStructure<CX>>>,
-    hashes_buckets: Arc<MultiThreadBuckets<LockFreeBinaryWriter>>,
+        ArrayQueue<ResultsBucket<color_types::PartialUnitigsColorStructure<CX>, C>>,
+    hashes_buckets: Arc<MultiThreadBuckets<MemoryTrackedBucket<LockFreeBinaryWriter>>>,
+    /// The partitions of the solid kmers with their counts, written instead of the unitigs fragments
+    kmers_counts_buckets:
+        Option<Arc<MultiThreadBuckets<MemoryTrackedBucket<LockFreeBinaryWriter>>>>,
+    /// The kmers counts are written with the lexicographically smaller orientation of each kmer
+    canonical_kmers_counts: bool,
     global_resplit_data: Arc<MinimizerBucketingCommonData<()>>,
     sequences_size_total: AtomicU64,
     hasnmap_kmers_total: AtomicU64,
     kmer_batches_count: AtomicU64,
 }
 
-impl<CX: ColorsManager> KmersTransformGlobalExtraData for GlobalMergeData<CX> {
+impl<CX: ColorsManager, C: UnitigsCountersStorage> GlobalMergeData<CX, C> {
+    /// Checks if a kmer is inside the multiplicity thresholds
+    #[inline(always)]
+    pub(crate) fn is_solid_kmer<CHI>(&self, entry: &MapEntry<CHI>) -> bool {
+        // The counters of the colored kmers are replaced by their color index if it's not stored apart,
+        // and the ones outside the thresholds are reset while processing the colors
+        if CX::COLORS_ENABLED && !C::TempColorIndex::KEEPS_MULTIPLICITY {
+            return entry.get_counter() != 0;
+        }
+
+        let count = entry.get_kmer_multiplicity();
+        count >= self.min_multiplicity && count <= self.max_multiplicity
+    }
+}
+
+impl<CX: ColorsManager, C: UnitigsCountersStorage> KmersTransformGlobalExtraData
+    for GlobalMergeData<CX, C>
+{
     #[inline(always)]
     fn get_k(&self) -> usize {
         self.k
@@ -71,19 +116,24 @@ impl<CX: ColorsManager> KmersTransformGlobalExtraData for GlobalMergeData<CX> {
 pub struct ParallelKmersMergeFactory<
     MH: HashFunctionFactory,
     CX: ColorsManager,
+    C: UnitigsCountersStorage,
     const COMPUTE_SIMPLITIGS: bool,
->(PhantomData<(MH, CX)>);
-
-impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
-    KmersTransformExecutorFactory for ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS>
+>(PhantomData<(MH, CX, C)>);
+
+impl<
+        MH: HashFunctionFactory,
+        CX: ColorsManager,
+        C: UnitigsCountersStorage,
+        const COMPUTE_SIMPLITIGS: bool,
+    > KmersTransformExecutorFactory for ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS>
 {
     type SequencesResplitterFactory = AssemblerMinimizerBucketingExecutorFactory<CX>;
-    type GlobalExtraData = GlobalMergeData<CX>;
+    type GlobalExtraData = GlobalMergeData<CX, C>;
     type AssociatedExtraData = MinimizerBucketingSeqColorDataType<CX>;
 
     type PreprocessorType = RewriteBucketComputeAssembler;
-    type MapProcessorType = ParallelKmersMergeMapProcessor<MH, CX, COMPUTE_SIMPLITIGS>;
-    type FinalExecutorType = ParallelKmersMergeFinalExecutor<MH, CX, COMPUTE_SIMPLITIGS>;
+    type MapProcessorType = ParallelKmersMergeMapProcessor<MH, CX, C, COMPUTE_SIMPLITIGS>;
+    type FinalExecutorType = ParallelKmersMergeFinalExecutor<MH, CX, C, COMPUTE_SIMPLITIGS>;
 
     #[allow(non_camel_case_types)]
     type FLAGS_COUNT = typenum::U2;
@@ -107,13 +157,17 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
     }
 }
 
-impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
-    ParallelKmersMergeFinalExecutor<MH, CX, COMPUTE_SIMPLITIGS>
+impl<
+        MH: HashFunctionFactory,
+        CX: ColorsManager,
+        C: UnitigsCountersStorage,
+        const COMPUTE_SIMPLITIGS: bool,
+    > ParallelKmersMergeFinalExecutor<MH, CX, C, COMPUTE_SIMPLITIGS>
 {
     #[inline(always)]
     fn write_hashes(
         hashes_tmp: &mut BucketsThreadDispatcher<
-            LockFreeBinaryWriter,
+            MemoryTrackedBucket<LockFreeBinaryWriter>,
             HashEntrySerializer<MH::HashTypeUnextendable>,
         >,
         hash: MH::HashTypeUnextendable,
@@ -133,28 +187,143 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
     }
 }
 
-pub fn kmers_merge<MH: HashFunctionFactory, CX: ColorsManager, P: AsRef<Path> + Sync>(
+fn run_kmers_transform<
+    MH: HashFunctionFactory,
+    CX: ColorsManager,
+    C: UnitigsCountersStorage,
+    const COMPUTE_SIMPLITIGS: bool,
+>(
+    file_inputs: Vec<MultiChunkBucket>,
+    buckets_counters_path: PathBuf,
+    global_data: Arc<GlobalMergeData<CX, C>>,
+    out_directory: &Path,
+    threads_count: usize,
+    keep_input_files: bool,
+) {
+    let buckets_count = global_data.buckets_count;
+    let k = global_data.k;
+    KmersTransform::<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS>>::new(
+        file_inputs,
+        out_directory,
+        buckets_counters_path,
+        buckets_count,
+        global_data,
+        threads_count,
+        k,
+        MINIMUM_SUBBUCKET_KMERS_COUNT as u64,
+        keep_input_files,
+    )
+    .parallel_kmers_transform();
+}
+
+fn new_resplit_data(
+    k: usize,
+    m: usize,
+    buckets_count: usize,
+) -> Arc<MinimizerBucketingCommonData<()>> {
+    Arc::new(MinimizerBucketingCommonData::new(
+        k,
+        if k > RESPLITTING_MAX_K_M_DIFFERENCE + 1 {
+            k - RESPLITTING_MAX_K_M_DIFFERENCE
+        } else {
+            min(m, 2)
+        }, // m
+        buckets_count,
+        k,
+        1,
+        (),
+    ))
+}
+
+/// Counts the multiplicities of all the kmers in the buckets, keeping the buckets for the merge
+fn compute_abundance_histogram<MH: HashFunctionFactory, CX: ColorsManager>(
+    file_inputs: &[MultiChunkBucket],
+    buckets_counters_path: &Path,
+    colors_global_table: &Arc<GlobalColorsTableWriter<CX>>,
+    hashes_buckets: &Arc<MultiThreadBuckets<MemoryTrackedBucket<LockFreeBinaryWriter>>>,
+    buckets_count: usize,
+    out_directory: &Path,
+    k: usize,
+    m: usize,
+    threads_count: usize,
+) -> KmersAbundanceHistogram {
+    PHASES_TIMES_MONITOR
+        .write()
+        .start_phase("phase: kmers abundance histogram".to_string());
+    config::progress::start_progress_phase(
+        ProgressPhase::KmersHistogram,
+        ProgressUnit::Buckets,
+        file_inputs.len() as u64,
+    );
+
+    // Only the kmers multiplicities are needed, so no unitigs counters are collected
+    let global_data = Arc::new(GlobalMergeData::<CX, ()> {
+        k,
+        m,
+        buckets_count,
+        min_multiplicity: 1,
+        max_multiplicity: usize::MAX,
+        min_color_multiplicity: 1,
+        abundance_mode: AbundanceOutputMode::None,
+        abundance_histogram: Some(Mutex::new(KmersAbundanceHistogram::default())),
+        histogram_only: true,
+        colors_global_table: colors_global_table.clone(),
+        temp_dir: out_directory.to_path_buf(),
+        color_subsets: None,
+        output_results_buckets: ArrayQueue::new(1),
+        hashes_buckets: hashes_buckets.clone(),
+        kmers_counts_buckets: None,
+        canonical_kmers_counts: false,
+        global_resplit_data: new_resplit_data(k, m, buckets_count),
+        sequences_size_total: AtomicU64::new(0),
+        hasnmap_kmers_total: AtomicU64::new(0),
+        kmer_batches_count: AtomicU64::new(0),
+    });
+
+    // The buckets are read again by the merge, so they must not be removed after this pass
+    run_kmers_transform::<MH, CX, (), false>(
+        file_inputs.to_vec(),
+        buckets_counters_path.to_path_buf(),
+        global_data.clone(),
+        out_directory,
+        threads_count,
+        true,
+    );
+
+    let histogram = std::mem::take(&mut *global_data.abundance_histogram.as_ref().unwrap().lock());
+    histogram
+}
+
+/// The kmers multiplicities are collected in the unitigs counters only if `C` is not `()`
+pub fn kmers_merge<
+    MH: HashFunctionFactory,
+    CX: ColorsManager,
+    C: UnitigsCountersStorage,
+    P: AsRef<Path> + Sync,
+>(
     file_inputs: Vec<MultiChunkBucket>,
     buckets_counters_path: PathBuf,
     colors_global_table: Arc<GlobalColorsTableWriter<CX>>,
+    color_subsets: Option<Arc<dyn ColorSubsets>>,
     buckets_count: usize,
-    min_multiplicity: usize,
+    min_multiplicity: MinMultiplicity,
     max_multiplicity: usize,
+    per_color_min_multiplicity: bool,
     out_directory: P,
     k: usize,
     m: usize,
     compute_simplitigs: bool,
+    abundance_mode: AbundanceOutputMode,
+    compute_histogram: bool,
+    kmers_counts: Option<KmersCountsOutput>,
     threads_count: usize,
 ) -> RetType {
-    PHASES_TIMES_MONITOR
-        .write()
-        .start_phase("phase: kmers merge".to_string());
-
     MNHFactory::initialize(k);
     MH::initialize(k);
-    *KMERGE_TEMP_DIR.write() = Some(out_directory.as_ref().to_path_buf());
 
-    let hashes_buckets = Arc::new(MultiThreadBuckets::<LockFreeBinaryWriter>::new(
+    let hashes_buckets = Arc::new(MultiThreadBuckets::<
+        MemoryTrackedBucket<LockFreeBinaryWriter>,
+    >::new(
         buckets_count,
         out_directory.as_ref().join("hashes"),
         None,
@@ -165,93 +334,167 @@ pub fn kmers_merge<MH: HashFunctionFactory, CX: ColorsManager, P: AsRef<Path> +
         &(),
     ));
 
-    let mut sequences = Vec::new();
+    let (min_multiplicity, mut abundance_histogram) = match min_multiplicity {
+        MinMultiplicity::Fixed(min_multiplicity) => (min_multiplicity, None),
+        MinMultiplicity::Auto => {
+            let histogram = compute_abundance_histogram::<MH, CX>(
+                &file_inputs,
+                &buckets_counters_path,
+                &colors_global_table,
+                &hashes_buckets,
+                buckets_count,
+                out_directory.as_ref(),
+                k,
+                m,
+                threads_count,
+            );
 
-    let reads_buckets = MultiThreadBuckets::<CompressedBinaryWriter>::new(
-        buckets_count,
-        out_directory.as_ref().join("result"),
-        None,
-        &(
-            get_memory_mode(SwapPriority::ResultBuckets),
-            CompressedBinaryWriter::CHECKPOINT_SIZE_UNLIMITED,
-            get_compression_level_info(),
-        ),
-        &(),
+            let min_multiplicity = match histogram.first_valley() {
+                Some(valley) => {
+                    ggcat_logging::info!(
+                        "Automatic minimum multiplicity: {} (first valley of the kmers abundance histogram)",
+                        valley
+                    );
+                    valley
+                }
+                None => {
+                    ggcat_logging::info!(
+                        "The kmers abundance histogram has no valley, all the kmers are kept"
+                    );
+                    1
+                }
+            };
+            (min_multiplicity, Some(histogram))
+        }
+    };
+
+    PHASES_TIMES_MONITOR
+        .write()
+        .start_phase("phase: kmers merge".to_string());
+    config::progress::start_progress_phase(
+        ProgressPhase::KmersMerge,
+        ProgressUnit::Buckets,
+        file_inputs.len() as u64,
     );
 
-    let output_results_buckets = ArrayQueue::new(reads_buckets.count());
-    for (index, bucket) in reads_buckets.into_buckets().enumerate() {
-        let bucket_read = ResultsBucket::<color_types::PartialUnitigsColorStructure<CX>> {
-            read_index: 0,
-            reads_writer: OwnedDrop::new(bucket),
-            temp_buffer: Vec::with_capacity(256),
-            bucket_index: index as BucketIndexType,
-            _phantom: PhantomData,
-            serializer: BucketItemSerializer::new(),
-        };
-        sequences.push(SingleBucket {
-            index,
-            path: bucket_read.reads_writer.get_path(),
-        });
-        let res = output_results_buckets.push(bucket_read).is_ok();
-        assert!(res);
-    }
+    let mut sequences = Vec::new();
+
+    // In the kmers counting mode the solid kmers are written instead of the unitigs fragments
+    let output_results_buckets = if kmers_counts.is_some() {
+        ArrayQueue::new(1)
+    } else {
+        let reads_buckets = MultiThreadBuckets::<MemoryTrackedBucket<CompressedBinaryWriter>>::new(
+            buckets_count,
+            out_directory.as_ref().join("result"),
+            None,
+            &(
+                get_memory_mode(SwapPriority::ResultBuckets),
+                CompressedBinaryWriter::CHECKPOINT_SIZE_UNLIMITED,
+                get_compression_level_info(),
+            ),
+            &(),
+        );
+
+        let output_results_buckets = ArrayQueue::new(reads_buckets.count());
+        for (index, bucket) in reads_buckets.into_buckets().enumerate() {
+            let bucket_read = ResultsBucket::<color_types::PartialUnitigsColorStructure<CX>, C> {
+                read_index: 0,
+                reads_writer: OwnedDrop::new(bucket),
+                temp_buffer: Vec::with_capacity(256),
+                bucket_index: index as BucketIndexType,
+                _phantom: PhantomData,
+                serializer: BucketItemSerializer::new(),
+            };
+            sequences.push(SingleBucket {
+                index,
+                path: bucket_read.reads_writer.get_path(),
+            });
+            let res = output_results_buckets.push(bucket_read).is_ok();
+            assert!(res);
+        }
+        output_results_buckets
+    };
+
+    let kmers_counts_buckets = kmers_counts.map(|_| {
+        Arc::new(MultiThreadBuckets::<
+            MemoryTrackedBucket<LockFreeBinaryWriter>,
+        >::new(
+            1 << (2 * kmers_counts_partition_bases(buckets_count)),
+            out_directory.as_ref().join("kmers-counts"),
+            None,
+            &(
+                get_memory_mode(SwapPriority::KmersCounts),
+                LockFreeBinaryWriter::CHECKPOINT_SIZE_UNLIMITED,
+            ),
+            &(),
+        ))
+    });
 
-    let global_data = Arc::new(GlobalMergeData::<CX> {
+    let global_data = Arc::new(GlobalMergeData::<CX, C> {
         k,
         m,
         buckets_count,
-        min_multiplicity,
+        // The kmers are kept if they are solid in at least one of their colors
+        min_multiplicity: if per_color_min_multiplicity {
+            1
+        } else {
+            min_multiplicity
+        },
         max_multiplicity,
+        min_color_multiplicity: if per_color_min_multiplicity {
+            min_multiplicity
+        } else {
+            1
+        },
+        abundance_mode,
+        abundance_histogram: (compute_histogram && abundance_histogram.is_none())
+            .then(|| Mutex::new(KmersAbundanceHistogram::default())),
+        histogram_only: false,
         colors_global_table,
+        temp_dir: out_directory.as_ref().to_path_buf(),
+        color_subsets,
         output_results_buckets,
         hashes_buckets: hashes_buckets.clone(),
-        global_resplit_data: Arc::new(MinimizerBucketingCommonData::new(
-            k,
-            if k > RESPLITTING_MAX_K_M_DIFFERENCE + 1 {
-                k - RESPLITTING_MAX_K_M_DIFFERENCE
-            } else {
-                min(m, 2)
-            }, // m
-            buckets_count,
-            k,
-            1,
-            (),
-        )),
+        kmers_counts_buckets: kmers_counts_buckets.clone(),
+        canonical_kmers_counts: kmers_counts.is_some_and(|kmers_counts| kmers_counts.canonical),
+        global_resplit_data: new_resplit_data(k, m, buckets_count),
         sequences_size_total: AtomicU64::new(0),
         hasnmap_kmers_total: AtomicU64::new(0),
         kmer_batches_count: AtomicU64::new(0),
     });
 
     if compute_simplitigs {
-        KmersTransform::<ParallelKmersMergeFactory<MH, CX, true>>::new(
+        run_kmers_transform::<MH, CX, C, true>(
             file_inputs,
-            out_directory.as_ref(),
             buckets_counters_path,
-            buckets_count,
-            global_data,
+            global_data.clone(),
+            out_directory.as_ref(),
             threads_count,
-            k,
-            MINIMUM_SUBBUCKET_KMERS_COUNT as u64,
-        )
-        .parallel_kmers_transform();
+            false,
+        );
     } else {
-        KmersTransform::<ParallelKmersMergeFactory<MH, CX, false>>::new(
+        run_kmers_transform::<MH, CX, C, false>(
             file_inputs,
-            out_directory.as_ref(),
             buckets_counters_path,
-            buckets_count,
-            global_data,
+            global_data.clone(),
+            out_directory.as_ref(),
             threads_count,
-            k,
-            MINIMUM_SUBBUCKET_KMERS_COUNT as u64,
-        )
-        .parallel_kmers_transform();
+            false,
+        );
+    }
+
+    if let Some(histogram) = &global_data.abundance_histogram {
+        abundance_histogram = Some(std::mem::take(&mut *histogram.lock()));
     }
+    drop(global_data);
 
     RetType {
         sequences,
         hashes: hashes_buckets.finalize_single(),
+        abundance_histogram: abundance_histogram.filter(|_| compute_histogram),
+        kmers_counts: kmers_counts_buckets
+            .map(|kmers_counts_buckets| kmers_counts_buckets.finalize_single())
+            .unwrap_or_default(),
     }
 }
 
@@ -259,7 +502,8 @@ pub fn kmers_merge<MH: HashFunctionFactory, CX: ColorsManager, P: AsRef<Path> +
 mod tests {
     use colors::colors_manager::{ColorsManager, ColorsMergeManager};
     use colors::non_colored::NonColoredManager;
-    use config::{FLUSH_QUEUE_FACTOR, KEEP_FILES, PREFER_MEMORY};
+    use config::operation::{OperationContext, OperationSettings};
+    use config::FLUSH_QUEUE_FACTOR;
     use io::generate_bucket_names;
     use parallel_processor::buckets::SingleBucket;
     use parallel_processor::memory_data_size::MemoryDataSize;
@@ -267,7 +511,6 @@ mod tests {
     use rayon::ThreadPoolBuilder;
     use std::cmp::max;
     use std::path::Path;
-    use std::sync::atomic::Ordering;
     use std::sync::Arc;
 
     #[ignore]
@@ -297,7 +540,7 @@ mod tests {
         let counters = Path::new(TEMP_DIR).join("buckets-counters.dat");
 
         let global_colors_table = Arc::new(
-            <<NonColoredManager as ColorsManager>::ColorsMergeManagerType as ColorsMergeManager>::create_colors_table("", &[]).unwrap(),
+            <<NonColoredManager as ColorsManager>::ColorsMergeManagerType as ColorsMergeManager>::create_colors_table("", &[], Default::default()).unwrap(),
         );
 
         let k = 63;
@@ -309,13 +552,19 @@ mod tests {
         // Increase the maximum allowed number of open files
         let _ = fdlimit::raise_fd_limit();
 
-        KEEP_FILES.store(true, Ordering::Relaxed);
-
-        PREFER_MEMORY.store(false, Ordering::Relaxed);
+        let operation = OperationContext::new(OperationSettings {
+            keep_files: true,
+            prefer_memory: false,
+            ..Default::default()
+        });
+        OperationContext::set_current(Some(operation.clone()));
 
         ThreadPoolBuilder::new()
             .num_threads(threads_count)
             .thread_name(|i| format!("rayon-thread-{}", i))
+            .start_handler(move |_| {
+                OperationContext::set_current(Some(operation.clone()));
+            })
             .build_global()
             .unwrap();
 
@@ -340,17 +589,27 @@ mod tests {
 
         // #[cfg(feature = "mem-analysis")]
         // debug_print_allocations("/tmp/allocations", Duration::from_secs(5));
-        crate::kmers_merge::<hashes::cn_seqhash::u128::CanonicalSeqHashFactory, NonColoredManager, _>(
+        crate::kmers_merge::<
+            hashes::cn_seqhash::u128::CanonicalSeqHashFactory,
+            NonColoredManager,
+            (),
+            _,
+        >(
             buckets,
             counters,
             global_colors_table.clone(),
+            None,
             buckets_count,
-            min_multiplicity,
+            crate::abundance_histogram::MinMultiplicity::Fixed(min_multiplicity),
             max_multiplicity,
+            false,
             Path::new(TEMP_DIR),
             k,
             m,
             false,
+            io::concurrent::structured_sequences::AbundanceOutputMode::None,
+            false,
+            None,
             threads_count,
         );
     }
@@ -1,4 +1,4 @@
-use crate::ParallelKmersMergeFactory;
+use crate::{GlobalMergeData, ParallelKmersMergeFactory};
 use colors::colors_manager::color_types::MinimizerBucketingSeqColorDataType;
 use colors::colors_manager::{color_types, ColorsManager};
 use colors::colors_manager::{ColorsMergeManager, MinimizerBucketingSeqColorData};
@@ -19,22 +19,26 @@ use parallel_processor::execution_manager::objects_pool::PoolObjectTrait;
 use parallel_processor::execution_manager::packet::{Packet, PacketTrait};
 use parallel_processor::mt_debug_counters::counter::{AtomicCounter, AvgMode, MaxMode};
 use parallel_processor::mt_debug_counters::{declare_avg_counter_i64, declare_counter_i64};
-use parking_lot::RwLock;
 use rustc_hash::{FxBuildHasher, FxHashMap};
 use std::cmp::{max, min};
 use std::mem::size_of;
-use std::ops::{Deref, DerefMut};
-use std::path::PathBuf;
+use std::ops::DerefMut;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use structs::map_entry::MapEntry;
+use structs::unitigs_counters::UnitigsCountersStorage;
 
 instrumenter::use_instrumenter!();
 
-pub(crate) static KMERGE_TEMP_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
-
-pub struct ParallelKmersMergeMapPacket<MH: HashFunctionFactory, CX: ColorsManager> {
-    pub rhash_map:
-        FxHashMap<MH::HashTypeUnextendable, MapEntry<color_types::HashMapTempColorIndex<CX>>>,
+pub struct ParallelKmersMergeMapPacket<
+    MH: HashFunctionFactory,
+    CX: ColorsManager,
+    C: UnitigsCountersStorage,
+> {
+    pub rhash_map: FxHashMap<
+        MH::HashTypeUnextendable,
+        MapEntry<color_types::HashMapTempColorIndex<CX, C::TempColorIndex>>,
+    >,
     pub saved_reads: Vec<u8>,
     pub encoded_saved_reads_indexes: Vec<u8>,
     pub temp_colors: color_types::ColorsBufferTempStructure<CX>,
@@ -54,18 +58,18 @@ fn clear_hashmap<K, V>(hashmap: &mut FxHashMap<K, V>, suggested_size: usize) {
     }
 }
 
-impl<MH: HashFunctionFactory, CX: ColorsManager> PoolObjectTrait
-    for ParallelKmersMergeMapPacket<MH, CX>
+impl<MH: HashFunctionFactory, CX: ColorsManager, C: UnitigsCountersStorage> PoolObjectTrait
+    for ParallelKmersMergeMapPacket<MH, CX, C>
 {
-    type InitData = ();
+    type InitData = Arc<GlobalMergeData<CX, C>>;
 
-    fn allocate_new(_init_data: &Self::InitData) -> Self {
+    fn allocate_new(global_data: &Self::InitData) -> Self {
         Self {
             rhash_map: FxHashMap::with_capacity_and_hasher(4096, FxBuildHasher),
             saved_reads: vec![],
             encoded_saved_reads_indexes: vec![],
             temp_colors: CX::ColorsMergeManagerType::allocate_temp_buffer_structure(
-                KMERGE_TEMP_DIR.read().deref().as_ref().unwrap(),
+                &global_data.temp_dir,
             ),
             average_hasmap_size: 0,
             average_sequences_size: 0,
@@ -96,14 +100,14 @@ impl<MH: HashFunctionFactory, CX: ColorsManager> PoolObjectTrait
     }
 }
 
-impl<MH: HashFunctionFactory, CX: ColorsManager> PacketTrait
-    for ParallelKmersMergeMapPacket<MH, CX>
+impl<MH: HashFunctionFactory, CX: ColorsManager, C: UnitigsCountersStorage> PacketTrait
+    for ParallelKmersMergeMapPacket<MH, CX, C>
 {
     fn get_size(&self) -> usize {
         self.rhash_map.len()
             * (size_of::<(
                 MH::HashTypeUnextendable,
-                MapEntry<color_types::HashMapTempColorIndex<CX>>,
+                MapEntry<color_types::HashMapTempColorIndex<CX, C::TempColorIndex>>,
             )>() + 1)
             + self.saved_reads.len()
     }
@@ -112,27 +116,32 @@ impl<MH: HashFunctionFactory, CX: ColorsManager> PacketTrait
 pub struct ParallelKmersMergeMapProcessor<
     MH: HashFunctionFactory,
     CX: ColorsManager,
+    C: UnitigsCountersStorage,
     const COMPUTE_SIMPLITIGS: bool,
 > {
     map_packet: Option<
         Packet<
             <Self as KmersTransformMapProcessor<
-                ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS>,
+                ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS>,
             >>::MapStruct,
         >,
     >,
     last_saved_len: usize,
     mem_tracker: MemoryTracker<
-        KmersTransformProcessor<ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS>>,
+        KmersTransformProcessor<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS>>,
     >,
 }
 
-impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
-    ParallelKmersMergeMapProcessor<MH, CX, COMPUTE_SIMPLITIGS>
+impl<
+        MH: HashFunctionFactory,
+        CX: ColorsManager,
+        C: UnitigsCountersStorage,
+        const COMPUTE_SIMPLITIGS: bool,
+    > ParallelKmersMergeMapProcessor<MH, CX, C, COMPUTE_SIMPLITIGS>
 {
     pub fn new(
         mem_tracker: MemoryTracker<
-            KmersTransformProcessor<ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS>>,
+            KmersTransformProcessor<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS>>,
         >,
     ) -> Self {
         Self {
@@ -143,18 +152,22 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
     }
 }
 
-impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
-    KmersTransformMapProcessor<ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS>>
-    for ParallelKmersMergeMapProcessor<MH, CX, COMPUTE_SIMPLITIGS>
+impl<
+        MH: HashFunctionFactory,
+        CX: ColorsManager,
+        C: UnitigsCountersStorage,
+        const COMPUTE_SIMPLITIGS: bool,
+    > KmersTransformMapProcessor<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS>>
+    for ParallelKmersMergeMapProcessor<MH, CX, C, COMPUTE_SIMPLITIGS>
 {
-    type MapStruct = ParallelKmersMergeMapPacket<MH, CX>;
+    type MapStruct = ParallelKmersMergeMapPacket<MH, CX, C>;
     const MAP_SIZE: usize = size_of::<MH::HashTypeUnextendable>()
-        + size_of::<MapEntry<color_types::HashMapTempColorIndex<CX>>>();
+        + size_of::<MapEntry<color_types::HashMapTempColorIndex<CX, C::TempColorIndex>>>();
 
     fn process_group_start(
         &mut self,
         map_struct: Packet<Self::MapStruct>,
-        _global_data: &<ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
+        _global_data: &<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
     ) {
         self.map_packet = Some(map_struct);
         self.last_saved_len = 0;
@@ -163,7 +176,7 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
     #[instrumenter::track]
     fn process_group_batch_sequences(
         &mut self,
-        global_data: &<ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
+        global_data: &<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
         batch: &ReadsVector<MinimizerBucketingSeqColorDataType<CX>>,
         extra_data_buffer: &<MinimizerBucketingSeqColorDataType<CX> as SequenceExtraDataTempBufferManagement>::TempBuffer,
         ref_sequences: &Vec<u8>,
@@ -200,7 +213,9 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                     .entry(hash.to_unextendable())
                     .or_insert_with(|| {
                         unique_kmers_count += 1;
-                        MapEntry::new(CX::ColorsMergeManagerType::new_color_index())
+                        MapEntry::new(CX::ColorsMergeManagerType::new_color_index::<
+                            C::TempColorIndex,
+                        >())
                     });
 
                 entry.update_flags(
@@ -211,9 +226,10 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                 let crossed_min_abundance =
                     entry.incr_by_and_check(multiplicity, global_data.min_multiplicity);
 
-                let crossed_max_abundance = entry.get_kmer_multiplicity() > global_data.max_multiplicity;
+                let crossed_max_abundance =
+                    entry.get_kmer_multiplicity() > global_data.max_multiplicity;
 
-                CX::ColorsMergeManagerType::add_temp_buffer_structure_el::<MH>(
+                CX::ColorsMergeManagerType::add_temp_buffer_structure_el::<MH, C::TempColorIndex>(
                     &mut map_packet.temp_colors,
                     &kmer_color,
                     (idx, hash.to_unextendable()),
@@ -233,6 +249,7 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
                 global_data.k,
                 global_data.m,
                 flags,
+                multiplicity,
             );
 
             if !MH::INVERTIBLE {
@@ -263,7 +280,7 @@ impl<MH: HashFunctionFactory, CX: ColorsManager, const COMPUTE_SIMPLITIGS: bool>
     #[instrumenter::track]
     fn process_group_finalize(
         &mut self,
-        global_data: &<ParallelKmersMergeFactory<MH, CX, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
+        global_data: &<ParallelKmersMergeFactory<MH, CX, C, COMPUTE_SIMPLITIGS> as KmersTransformExecutorFactory>::GlobalExtraData,
     ) -> Packet<Self::MapStruct> {
         static COUNTER_KMERS_MAX: AtomicCounter<MaxMode> =
             declare_counter_i64!("kmers_cardinality_max", MaxMode, false);
@@ -1,25 +1,27 @@
+use crate::abundance_histogram::KmersAbundanceHistogram;
+use config::operation::MemoryTrackedBucket;
 use config::BucketIndexType;
 use io::concurrent::temp_reads::creads_utils::{
     CompressedReadsBucketData, CompressedReadsBucketDataSerializer, NoMultiplicity, NoSecondBucket,
 };
 use io::concurrent::temp_reads::extra_data::{
-    SequenceExtraDataConsecutiveCompression, SequenceExtraDataTempBufferManagement,
+    SequenceExtraData, SequenceExtraDataConsecutiveCompression,
+    SequenceExtraDataTempBufferManagement,
 };
 use parallel_processor::buckets::bucket_writer::BucketItemSerializer;
 use parallel_processor::buckets::writers::compressed_binary_writer::CompressedBinaryWriter;
 use parallel_processor::buckets::{LockFreeBucket, SingleBucket};
 use std::marker::PhantomData;
-#[cfg(feature = "support_kmer_counters")]
-use structs::unitigs_counters::UnitigsCounters;
+use structs::unitigs_counters::UnitigsCountersStorage;
 use utils::owned_drop::OwnedDrop;
 
-pub struct ResultsBucket<X: SequenceExtraDataConsecutiveCompression> {
+pub struct ResultsBucket<X: SequenceExtraDataConsecutiveCompression, C: UnitigsCountersStorage> {
     pub read_index: u64,
-    pub reads_writer: OwnedDrop<CompressedBinaryWriter>,
+    pub reads_writer: OwnedDrop<MemoryTrackedBucket<CompressedBinaryWriter>>,
     pub temp_buffer: Vec<u8>,
     pub bucket_index: BucketIndexType,
     pub serializer: CompressedReadsBucketDataSerializer<
-        PartialUnitigExtraData<X>,
+        PartialUnitigExtraData<X, C>,
         typenum::U0,
         NoSecondBucket,
         NoMultiplicity,
@@ -27,15 +29,18 @@ pub struct ResultsBucket<X: SequenceExtraDataConsecutiveCompression> {
     pub _phantom: PhantomData<X>,
 }
 
+/// The counters are `()` if the kmers multiplicities are not collected
 #[derive(Clone, Debug)]
-pub struct PartialUnitigExtraData<X: SequenceExtraDataConsecutiveCompression> {
-    #[cfg(feature = "support_kmer_counters")]
-    pub counters: UnitigsCounters,
+pub struct PartialUnitigExtraData<
+    X: SequenceExtraDataConsecutiveCompression,
+    C: UnitigsCountersStorage,
+> {
+    pub counters: C,
     pub colors: X,
 }
 
-impl<X: SequenceExtraDataConsecutiveCompression> SequenceExtraDataTempBufferManagement
-    for PartialUnitigExtraData<X>
+impl<X: SequenceExtraDataConsecutiveCompression, C: UnitigsCountersStorage>
+    SequenceExtraDataTempBufferManagement for PartialUnitigExtraData<X, C>
 {
     type TempBuffer = X::TempBuffer;
 
@@ -54,14 +59,13 @@ impl<X: SequenceExtraDataConsecutiveCompression> SequenceExtraDataTempBufferMana
     fn copy_extra_from(extra: Self, src: &Self::TempBuffer, dst: &mut Self::TempBuffer) -> Self {
         Self {
             colors: X::copy_extra_from(extra.colors, src, dst),
-            #[cfg(feature = "support_kmer_counters")]
             counters: extra.counters,
         }
     }
 }
 
-impl<X: SequenceExtraDataConsecutiveCompression> SequenceExtraDataConsecutiveCompression
-    for PartialUnitigExtraData<X>
+impl<X: SequenceExtraDataConsecutiveCompression, C: UnitigsCountersStorage>
+    SequenceExtraDataConsecutiveCompression for PartialUnitigExtraData<X, C>
 {
     type LastData = X::LastData;
 
@@ -71,11 +75,9 @@ impl<X: SequenceExtraDataConsecutiveCompression> SequenceExtraDataConsecutiveCom
         last_data: Self::LastData,
     ) -> Option<Self> {
         let color = X::decode_extended(buffer, reader, last_data)?;
-        #[cfg(feature = "support_kmer_counters")]
-        let counter = UnitigsCounters::decode_extended(&mut (), reader, ())?;
+        let counter = <C as SequenceExtraData>::decode_extended(&mut (), reader)?;
         Some(Self {
             colors: color,
-            #[cfg(feature = "support_kmer_counters")]
             counters: counter,
         })
     }
@@ -87,8 +89,7 @@ impl<X: SequenceExtraDataConsecutiveCompression> SequenceExtraDataConsecutiveCom
         last_data: Self::LastData,
     ) {
         self.colors.encode_extended(buffer, writer, last_data);
-        #[cfg(feature = "support_kmer_counters")]
-        self.counters.encode_extended(&(), writer, ());
+        <C as SequenceExtraData>::encode_extended(&self.counters, &(), writer);
     }
 
     fn obtain_last_data(&self, last_data: Self::LastData) -> Self::LastData {
@@ -96,20 +97,14 @@ impl<X: SequenceExtraDataConsecutiveCompression> SequenceExtraDataConsecutiveCom
     }
 
     fn max_size(&self) -> usize {
-        self.colors.max_size()
-            + match () {
-                #[cfg(feature = "support_kmer_counters")]
-                () => self.counters.max_size(),
-                #[cfg(not(feature = "support_kmer_counters"))]
-                () => 0,
-            }
+        self.colors.max_size() + <C as SequenceExtraData>::max_size(&self.counters)
     }
 }
 
-impl<X: SequenceExtraDataConsecutiveCompression> ResultsBucket<X> {
+impl<X: SequenceExtraDataConsecutiveCompression, C: UnitigsCountersStorage> ResultsBucket<X, C> {
     pub fn add_read(
         &mut self,
-        el: PartialUnitigExtraData<X>,
+        el: PartialUnitigExtraData<X, C>,
         read: &[u8],
         extra_buffer: &X::TempBuffer,
     ) -> u64 {
@@ -132,7 +127,9 @@ impl<X: SequenceExtraDataConsecutiveCompression> ResultsBucket<X> {
     }
 }
 
-impl<X: SequenceExtraDataConsecutiveCompression> Drop for ResultsBucket<X> {
+impl<X: SequenceExtraDataConsecutiveCompression, C: UnitigsCountersStorage> Drop
+    for ResultsBucket<X, C>
+{
     fn drop(&mut self) {
         unsafe { self.reads_writer.take().finalize() }
     }
@@ -141,4 +138,8 @@ impl<X: SequenceExtraDataConsecutiveCompression> Drop for ResultsBucket<X> {
 pub struct RetType {
     pub sequences: Vec<SingleBucket>,
     pub hashes: Vec<SingleBucket>,
+    /// The kmers abundance histogram, if requested
+    pub abundance_histogram: Option<KmersAbundanceHistogram>,
+    /// The partitions of the solid kmers with their counts, in the kmers counting mode
+    pub kmers_counts: Vec<SingleBucket>,
 }
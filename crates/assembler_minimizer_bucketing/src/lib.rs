@@ -4,6 +4,7 @@ use ::dynamic_dispatch::dynamic_dispatch;
 use colors::colors_manager::color_types::MinimizerBucketingSeqColorDataType;
 use colors::colors_manager::{ColorsManager, MinimizerBucketingSeqColorData};
 use colors::parsers::{SequenceIdent, SingleSequenceInfo};
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::{BucketIndexType, ColorIndexType};
 use config::{READ_FLAG_INCL_BEGIN, READ_FLAG_INCL_END};
 use hashes::default::MNHFactory;
@@ -227,6 +228,7 @@ pub fn minimizer_bucketing<CX: ColorsManager>(
     threads_count: usize,
     k: usize,
     m: usize,
+    min_base_quality: Option<u8>,
     minimizer_bucketing_chunk_size: Option<u64>,
 ) -> (Vec<MultiChunkBucket>, PathBuf) {
     MNHFactory::initialize(k);
@@ -235,6 +237,15 @@ pub fn minimizer_bucketing<CX: ColorsManager>(
         .write()
         .start_phase("phase: reads bucketing".to_string());
 
+    config::progress::start_progress_phase(
+        ProgressPhase::MinimizerBucketing,
+        ProgressUnit::Bases,
+        input_blocks
+            .iter()
+            .map(|block| block.estimated_bases_count().unwrap_or(0))
+            .sum(),
+    );
+
     let mut input_files: Vec<_> = input_blocks
         .into_iter()
         .enumerate()
@@ -268,6 +279,7 @@ pub fn minimizer_bucketing<CX: ColorsManager>(
         Some(k - 1),
         false,
         k,
+        min_base_quality,
         minimizer_bucketing_chunk_size,
     )
 }
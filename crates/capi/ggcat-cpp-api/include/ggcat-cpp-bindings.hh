@@ -168,6 +168,12 @@ private:
   std::array<std::uintptr_t, 2> repr;
 };
 
+#ifdef __cpp_deduction_guides
+template <typename C>
+explicit Slice(C &c)
+    -> Slice<std::remove_reference_t<decltype(*std::declval<C>().data())>>;
+#endif // __cpp_deduction_guides
+
 template <typename T>
 class Slice<T>::iterator final {
 public:
@@ -745,9 +751,9 @@ struct GGCATInstanceFFI;
 
 #ifndef CXXBRIDGE1_STRUCT_GGCATConfigFFI
 #define CXXBRIDGE1_STRUCT_GGCATConfigFFI
-// Main config of GGCAT. This config is global and should be passed to GGCATInstance::create
+// Main config of GGCAT, passed to GGCATInstance::create. Each instance keeps its own config
 struct GGCATConfigFFI final {
-  // If false, a memory only mode is attempted. May crash for large input data if there is no enough RAM memory.
+  // If false, all the temporary files are kept in memory, failing if they need more than the given memory.
   bool use_temp_dir;
   // Directory for temporary files
   ::rust::String temp_dir;
@@ -803,7 +809,8 @@ private:
 };
 #endif // CXXBRIDGE1_STRUCT_GGCATInstanceFFI
 
-// Creates a new GGCATInstance. If an instance already exists, it will be returned, ignoring the new config.
+// Creates a new GGCATInstance with the given config, returning null on failure.
+// Multiple instances with different configs can coexist, their operations are run one at a time
 ::GGCATInstanceFFI const *ggcat_create(::GGCATConfigFFI config) noexcept;
 
 // Builds a new graph from the given input files, with the specified parameters
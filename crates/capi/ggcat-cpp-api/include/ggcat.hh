@@ -5,6 +5,8 @@
 #include <memory>
 #include <cstdint>
 
+struct GGCATInstanceFFI;
+
 namespace ggcat
 {
 
@@ -86,10 +88,10 @@ namespace ggcat
         // void estimated_base_count(void *block);
     };
 
-    // Main config of GGCAT. This config is global and should be passed to GGCATInstance::create
+    // Main config of GGCAT, passed to GGCATInstance::create. Each instance keeps its own config
     struct GGCATConfig final
     {
-        // If false, a memory only mode is attempted. May crash for large input data if there is no enough RAM memory.
+        // If false, all the temporary files are kept in memory, failing if they need more than the given memory.
         bool use_temp_dir;
         // Directory for temporary files
         std::string temp_dir;
@@ -133,7 +135,9 @@ namespace ggcat
     {
 
     private:
-        static GGCATInstance instance;
+        GGCATInstanceFFI const *ffi_instance;
+
+        GGCATInstance(GGCATInstanceFFI const *ffi_instance) : ffi_instance(ffi_instance) {}
 
         void dump_unitigs_internal(
             std::string graph_input,
@@ -198,6 +202,8 @@ namespace ggcat
             uint32_t disk_optimization_level);
 
     public:
+        /// Creates a new instance with the given config, returns nullptr on failure.
+        /// Multiple instances with different configs can coexist, their operations are run one at a time
         static GGCATInstance *create(GGCATConfig config);
 
         /// Builds a new graph from the given input files, with the specified parameters
@@ -4,31 +4,28 @@
 
 using namespace ggcat;
 
-GGCATInstanceFFI const *ffi_instance = nullptr;
-GGCATInstance ggcat::GGCATInstance::instance = GGCATInstance();
-
 static_assert(sizeof(size_t) == sizeof(uintptr_t), "size_t and uintptr_t must be the same size");
 
 GGCATInstance *GGCATInstance::create(GGCATConfig config)
 {
+    GGCATConfigFFI ffi_config;
+
+    ffi_config.use_temp_dir = config.use_temp_dir,
+    ffi_config.temp_dir = rust::String(config.temp_dir.c_str()),
+    ffi_config.memory = config.memory,
+    ffi_config.prefer_memory = config.prefer_memory,
+    ffi_config.total_threads_count = config.total_threads_count,
+    ffi_config.intermediate_compression_level = config.intermediate_compression_level,
+    ffi_config.use_stats_file = config.use_stats_file,
+    ffi_config.stats_file = rust::String(config.stats_file.c_str()),
+    ffi_config.messages_callback = (size_t)config.messages_callback;
+
+    GGCATInstanceFFI const *ffi_instance = ggcat_create(ffi_config);
     if (ffi_instance == nullptr)
     {
-
-        GGCATConfigFFI ffi_config;
-
-        ffi_config.use_temp_dir = config.use_temp_dir,
-        ffi_config.temp_dir = rust::String(config.temp_dir.c_str()),
-        ffi_config.memory = config.memory,
-        ffi_config.prefer_memory = config.prefer_memory,
-        ffi_config.total_threads_count = config.total_threads_count,
-        ffi_config.intermediate_compression_level = config.intermediate_compression_level,
-        ffi_config.use_stats_file = config.use_stats_file,
-        ffi_config.stats_file = rust::String(config.stats_file.c_str()),
-        ffi_config.messages_callback = (size_t)config.messages_callback;
-
-        ffi_instance = ggcat_create(ffi_config);
+        return nullptr;
     }
-    return &instance;
+    return new GGCATInstance(ffi_instance);
 }
 
 std::string GGCATInstance::build_graph_from_files(
@@ -3,19 +3,25 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::{mem::transmute, path::PathBuf};
 
+use ggcat_api::{
+    AbundanceOutputMode, ColormapEncoding, ExtraElaboration, GGCATConfig, GGCATInstance,
+    GeneralSequenceBlockData, MinMultiplicity,
+};
 use ggcat_api::{
     ColorIndexType, DnaSequence, DnaSequencesFileType, DynamicSequencesStream, GfaVersion,
     SequenceInfo,
 };
-use ggcat_api::{ExtraElaboration, GGCATConfig, GGCATInstance, GeneralSequenceBlockData};
 
 #[repr(transparent)]
 struct GGCATInstanceFFI(GGCATInstance);
 
+/// The C messages callback is shared by all the instances, set by the last one created with a callback
 static FFI_MESSAGES_CALLBACK_PTR: AtomicUsize = AtomicUsize::new(0);
 
 fn ggcat_create(config: ffi::GGCATConfigFFI) -> *const GGCATInstanceFFI {
-    FFI_MESSAGES_CALLBACK_PTR.store(config.messages_callback, Ordering::SeqCst);
+    if config.messages_callback != 0 {
+        FFI_MESSAGES_CALLBACK_PTR.store(config.messages_callback, Ordering::SeqCst);
+    }
 
     let instance = GGCATInstance::create(GGCATConfig {
         temp_dir: if config.use_temp_dir {
@@ -50,9 +56,15 @@ fn ggcat_create(config: ffi::GGCATConfigFFI) -> *const GGCATInstanceFFI {
             })
         },
         disk_optimization_level: config.disk_optimization_level,
-    })
-    .ok();
-    unsafe { std::mem::transmute(instance) }
+        progress_callback: None,
+        cancellation_token: None,
+    });
+
+    match instance {
+        // The instance is never released, as the bindings borrow it for the whole process lifetime
+        Ok(instance) => Box::into_raw(Box::new(GGCATInstanceFFI(instance))),
+        Err(_) => std::ptr::null(),
+    }
 }
 
 fn ggcat_build(
@@ -119,8 +131,14 @@ fn ggcat_build(
                 Some(minimizer_length)
             },
             colors,
-            min_multiplicity,
+            ColormapEncoding::RunLength,
+            MinMultiplicity::Fixed(min_multiplicity),
             max_multiplicity,
+            false,
+            None,
+            None,
+            AbundanceOutputMode::None,
+            None,
             match extra_elab {
                 EXTRA_ELABORATION_STEP_NONE => ExtraElaboration::None,
                 EXTRA_ELABORATION_STEP_UNITIG_LINKS => ExtraElaboration::UnitigLinks,
@@ -186,14 +204,7 @@ fn ggcat_build_from_files(
         instance,
         input_files
             .iter()
-            .map(|f| {
-                if f.ends_with(".gfa") {
-                    todo!("GFA support is not implemented yet");
-                    // GeneralSequenceBlockData::GFA() // PathBuf::from(f))
-                } else {
-                    GeneralSequenceBlockData::FASTA((PathBuf::from(f), None))
-                }
-            })
+            .map(|f| GeneralSequenceBlockData::from_file(PathBuf::from(f), None))
             .collect(),
         output_file,
         color_names,
@@ -287,6 +298,7 @@ fn ggcat_build_from_streams(
                             from_raw_parts(sequence.ident_data, sequence.ident_data_len)
                         },
                         seq: unsafe { from_raw_parts(sequence.seq, sequence.seq_len) },
+                        qual: None,
                         format: match sequence.format {
                             DnaSequencesFileTypeFFI::FASTA => DnaSequencesFileType::FASTA,
                             DnaSequencesFileTypeFFI::FASTQ => DnaSequencesFileType::FASTQ,
@@ -380,7 +392,10 @@ fn ggcat_query_graph(
         .0
         .query_graph(
             PathBuf::from(input_graph),
-            PathBuf::from(input_query),
+            vec![GeneralSequenceBlockData::from_file(
+                PathBuf::from(input_query),
+                None,
+            )],
             PathBuf::from(output_file_prefix),
             kmer_length,
             threads_count,
@@ -400,6 +415,9 @@ fn ggcat_query_graph(
                 }
                 _ => panic!("Invalid color_output_format value: {}", color_output_format),
             },
+            false,
+            false,
+            ggcat_api::QueryResultsFilter::default(),
         )
         .unwrap_or_default()
         .to_str()
@@ -538,9 +556,9 @@ pub struct SequenceInfoFFI {
 #[cxx::bridge]
 mod ffi {
 
-    /// Main config of GGCAT. This config is global and should be passed to GGCATInstance::create
+    /// Main config of GGCAT, passed to GGCATInstance::create. Each instance keeps its own config
     pub struct GGCATConfigFFI {
-        /// If false, a memory only mode is attempted. May crash for large input data if there is no enough RAM memory.
+        /// If false, all the temporary files are kept in memory, failing if they need more than the given memory.
         pub use_temp_dir: bool,
 
         /// Directory for temporary files
@@ -589,7 +607,8 @@ mod ffi {
     extern "Rust" {
         type GGCATInstanceFFI;
 
-        /// Creates a new GGCATInstance. If an instance already exists, it will be returned, ignoring the new config.
+        /// Creates a new GGCATInstance with the given config, returning null on failure.
+        /// Multiple instances with different configs can coexist, their operations are run one at a time
         fn ggcat_create(config: GGCATConfigFFI) -> *const GGCATInstanceFFI;
 
         /// Builds a new graph from the given input files, with the specified parameters
@@ -5,14 +5,17 @@ extern crate alloc;
 // mod benchmarks;
 
 use ahash::HashMap;
-use ggcat_api::{ExtraElaboration, GGCATConfig, GGCATInstance, GfaVersion};
+use ggcat_api::{
+    AbundanceOutputMode, ColormapEncoding, ExtraElaboration, GGCATConfig, GGCATError,
+    GGCATInstance, GfaVersion, GraphCleaningConfig, GraphInputFile, KmersCountsFormat,
+    MinMultiplicity, QueryResultsFilter,
+};
 use ggcat_logging::UnrecoverableErrorLogging;
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Write};
 use std::panic;
 use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::sync::atomic::Ordering;
 use std::time::Duration;
 use structopt::StructOpt;
 
@@ -63,8 +66,13 @@ use structopt::clap::{arg_enum, ArgGroup};
 #[derive(StructOpt, Debug)]
 enum CliArgs {
     Build(AssemblerArgs),
+    Update(UpdateArgs),
+    Merge(MergeArgs),
+    Count(CountArgs),
     Query(QueryArgs),
     DumpColors(DumpColorsArgs),
+    Stats(StatsArgs),
+    Colormap(ColormapCommand),
     Matches(MatchesArgs),
     // Utils(CmdUtilsArgs),
 }
@@ -118,6 +126,11 @@ struct CommonArgs {
     #[structopt(short = "p", long = "prefer-memory")]
     pub prefer_memory: bool,
 
+    /// Keep all the temporary files in memory without using the temporary directory,
+    /// failing if they need more than the given memory
+    #[structopt(long = "memory-only")]
+    pub memory_only: bool,
+
     /// The log2 of the number of buckets
     #[structopt(short = "b", long = "buckets-count-log")]
     pub buckets_count_log: Option<usize>,
@@ -131,8 +144,7 @@ struct CommonArgs {
 }
 
 #[derive(StructOpt, Debug)]
-#[structopt(group = ArgGroup::with_name("output-mode").required(false))]
-struct AssemblerArgs {
+struct InputFilesArgs {
     /// The input files
     pub input: Vec<PathBuf>,
 
@@ -143,34 +155,11 @@ struct AssemblerArgs {
     /// The lists of input files with colors in format <COLOR_NAME><TAB><FILE_PATH>
     #[structopt(short = "d", long = "colored-input-lists")]
     pub colored_input_lists: Vec<PathBuf>,
+}
 
-    /// Enable colors
-    #[structopt(short, long)]
-    pub colors: bool,
-
-    /// Minimum multiplicity required to keep a kmer
-    #[structopt(short = "s", long = "min-multiplicity", default_value = "2")]
-    pub min_multiplicity: usize,
-
-    /// Maximum multiplicity required to keep a kmer
-    #[structopt(short = "z", long = "max-multiplicity", default_value = "999999")]
-    pub max_multiplicity: usize,
-
-    // /// Minimum correctness probability for each kmer (using fastq quality checks)
-    // #[structopt(short = "q", long = "quality-threshold")]
-    // pub quality_threshold: Option<f64>,
-    #[structopt(short = "n", long, default_value = "0", hidden = true)]
-    pub number: usize,
-
-    #[structopt(short = "o", long = "output-file", default_value = "output.fasta.lz4")]
-    pub output_file: PathBuf,
-
-    #[structopt(long, default_value = "MinimizerBucketing")]
-    pub step: AssemblerStartingStep,
-
-    #[structopt(long = "last-step", default_value = "BuildUnitigs")]
-    pub last_step: AssemblerStartingStep,
-
+#[derive(StructOpt, Debug)]
+#[structopt(group = ArgGroup::with_name("output-mode").required(false))]
+struct GraphOutputArgs {
     /// Generate maximal unitigs connections references, in BCALM2 format L:<+/->:<other id>:<+/->
     #[structopt(
         short = "e",
@@ -199,9 +188,6 @@ struct AssemblerArgs {
     #[structopt(long = "pathtigs", group = "output-mode")]
     pub pathtigs: bool,
 
-    #[structopt(flatten)]
-    pub common_args: CommonArgs,
-
     /// Output the graph in GFA format v1
     #[structopt(long = "gfa-v1")]
     pub gfa_output_v1: bool,
@@ -209,6 +195,306 @@ struct AssemblerArgs {
     /// Output the graph in GFA format v2
     #[structopt(long = "gfa-v2")]
     pub gfa_output_v2: bool,
+}
+
+impl GraphOutputArgs {
+    fn extra_elaboration(&self) -> ExtraElaboration {
+        if self.generate_maximal_unitigs_links {
+            ExtraElaboration::UnitigLinks
+        } else if self.greedy_matchtigs {
+            ExtraElaboration::GreedyMatchtigs
+        } else if self.eulertigs {
+            ExtraElaboration::Eulertigs
+        } else if self.pathtigs {
+            ExtraElaboration::Pathtigs
+        } else if self.fast_simplitigs {
+            ExtraElaboration::FastSimplitigs
+        } else if self.fast_eulertigs {
+            ExtraElaboration::FastEulertigs
+        } else {
+            ExtraElaboration::None
+        }
+    }
+
+    fn gfa_version(&self) -> Option<GfaVersion> {
+        if self.gfa_output_v1 && self.gfa_output_v2 {
+            println!("Cannot specify both GFA v1 and GFA v2 output");
+            exit(1);
+        }
+
+        if self.gfa_output_v1 {
+            Some(GfaVersion::V1)
+        } else if self.gfa_output_v2 {
+            Some(GfaVersion::V2)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+struct AssemblerArgs {
+    #[structopt(flatten)]
+    pub input_files: InputFilesArgs,
+
+    /// Enable colors
+    #[structopt(short, long)]
+    pub colors: bool,
+
+    /// Encoding of the color subsets in the colormap, roaring bitmaps are usually smaller
+    /// when many colors share the same kmers
+    #[structopt(
+        long = "colormap-encoding",
+        default_value = "run-length",
+        possible_values = COLORMAP_ENCODINGS,
+        parse(try_from_str = parse_colormap_encoding)
+    )]
+    pub colormap_encoding: ColormapEncoding,
+
+    /// Minimum multiplicity required to keep a kmer, or 'auto' to choose it from the first valley
+    /// of the kmers abundance histogram, with an additional counting pass
+    #[structopt(
+        short = "s",
+        long = "min-multiplicity",
+        default_value = "2",
+        parse(try_from_str = parse_min_multiplicity)
+    )]
+    pub min_multiplicity: MinMultiplicity,
+
+    /// Maximum multiplicity required to keep a kmer
+    #[structopt(short = "z", long = "max-multiplicity", default_value = "999999")]
+    pub max_multiplicity: usize,
+
+    /// Apply the minimum multiplicity separately to the kmers of each color, a kmer keeps a color
+    /// only if it appears at least that many times in the inputs with that color
+    #[structopt(long = "per-color-min-multiplicity")]
+    pub per_color_min_multiplicity: bool,
+
+    /// Write the kmers abundance histogram to this file, as TSV lines with the multiplicity
+    /// and the number of distinct kmers having it
+    #[structopt(long = "write-histogram")]
+    pub write_histogram: Option<PathBuf>,
+
+    /// Minimum phred quality of the bases of FASTQ reads, lower quality bases split the reads
+    /// and the kmers containing them are discarded
+    #[structopt(short = "q", long = "min-base-quality")]
+    pub min_base_quality: Option<u8>,
+
+    #[structopt(flatten)]
+    pub abundance_args: AbundanceArgs,
+
+    #[structopt(flatten)]
+    pub cleaning_args: GraphCleaningArgs,
+
+    #[structopt(short = "n", long, default_value = "0", hidden = true)]
+    pub number: usize,
+
+    #[structopt(short = "o", long = "output-file", default_value = "output.fasta.lz4")]
+    pub output_file: PathBuf,
+
+    #[structopt(long, default_value = "MinimizerBucketing")]
+    pub step: AssemblerStartingStep,
+
+    #[structopt(long = "last-step", default_value = "BuildUnitigs")]
+    pub last_step: AssemblerStartingStep,
+
+    #[structopt(flatten)]
+    pub output_args: GraphOutputArgs,
+
+    #[structopt(flatten)]
+    pub common_args: CommonArgs,
+
+    /// Sets the level of disk optimization (0 disabled)
+    #[structopt(long = "disk-optimization-level", default_value = "5")]
+    pub disk_optimization_level: u32,
+
+    /// Resumes an interrupted build from its temporary directory, restarting from the last completed phase.
+    /// The other parameters must be the same of the interrupted build
+    #[structopt(long = "resume")]
+    pub resume: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+struct AbundanceArgs {
+    /// Write the total kmer count (KC:i:) and the mean kmer coverage (km:f:) of each output sequence
+    #[structopt(long = "emit-abundances")]
+    pub emit_abundances: bool,
+
+    /// Also write the multiplicity of every kmer of each output sequence (ka:B:I), implies --emit-abundances
+    #[structopt(long = "emit-kmer-abundances")]
+    pub emit_kmer_abundances: bool,
+}
+
+impl AbundanceArgs {
+    fn abundance_mode(&self) -> AbundanceOutputMode {
+        if self.emit_kmer_abundances {
+            AbundanceOutputMode::PerKmer
+        } else if self.emit_abundances {
+            AbundanceOutputMode::Summary
+        } else {
+            AbundanceOutputMode::None
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+struct GraphCleaningArgs {
+    /// Remove the tips and the low coverage bubbles from the graph, joining the unitigs left
+    /// without branches. Supported only for unitigs output. The whole graph is kept in memory,
+    /// outside the -m limit: 2 bits per base plus about 200 bytes per unitig and 50 per link
+    #[structopt(long = "clean-graph")]
+    pub clean_graph: bool,
+
+    /// Dead-end paths shorter than this length are removed by the graph cleaning (default 2k)
+    #[structopt(long = "tip-length")]
+    pub tip_length: Option<usize>,
+
+    /// Maximum length of the bubble branches removed by the graph cleaning (default 3k)
+    #[structopt(long = "bubble-length")]
+    pub bubble_length: Option<usize>,
+
+    /// Bubble branches with a mean coverage lower than this fraction of the best branch
+    /// coverage are removed by the graph cleaning
+    #[structopt(long = "bubble-coverage-ratio", default_value = "0.2")]
+    pub bubble_coverage_ratio: f64,
+}
+
+impl GraphCleaningArgs {
+    fn graph_cleaning(&self, k: usize) -> Option<GraphCleaningConfig> {
+        let defaults = GraphCleaningConfig::new(k);
+        self.clean_graph.then(|| GraphCleaningConfig {
+            tip_length: self.tip_length.unwrap_or(defaults.tip_length),
+            bubble_length: self.bubble_length.unwrap_or(defaults.bubble_length),
+            bubble_coverage_ratio: self.bubble_coverage_ratio,
+        })
+    }
+}
+
+#[derive(StructOpt, Debug)]
+struct UpdateArgs {
+    /// The colored graph to be updated
+    #[structopt(long = "input-graph")]
+    pub input_graph: PathBuf,
+
+    /// The colormap of the input graph (default <INPUT_GRAPH>.colors.dat)
+    #[structopt(long = "input-colormap")]
+    pub input_colormap: Option<PathBuf>,
+
+    /// The new samples to be added to the graph, with colors appended after the existing ones
+    #[structopt(flatten)]
+    pub input_files: InputFilesArgs,
+
+    /// Minimum multiplicity of a kmer in each new sample to assign it the color of the sample,
+    /// the kmers already in the input graph are always kept
+    #[structopt(short = "s", long = "min-multiplicity", default_value = "1")]
+    pub min_multiplicity: usize,
+
+    #[structopt(short = "o", long = "output-file", default_value = "output.fasta.lz4")]
+    pub output_file: PathBuf,
+
+    #[structopt(flatten)]
+    pub output_args: GraphOutputArgs,
+
+    #[structopt(flatten)]
+    pub common_args: CommonArgs,
+
+    /// Sets the level of disk optimization (0 disabled)
+    #[structopt(long = "disk-optimization-level", default_value = "5")]
+    pub disk_optimization_level: u32,
+}
+
+#[derive(StructOpt, Debug)]
+struct MergeArgs {
+    /// The input graphs
+    pub input_graphs: Vec<PathBuf>,
+
+    /// Enable colors, each input graph is colored if its colormap (<GRAPH>.colors.dat) is present,
+    /// otherwise it's assigned to a single color named after the graph file
+    #[structopt(short, long)]
+    pub colors: bool,
+
+    #[structopt(short = "o", long = "output-file", default_value = "output.fasta.lz4")]
+    pub output_file: PathBuf,
+
+    #[structopt(flatten)]
+    pub output_args: GraphOutputArgs,
+
+    #[structopt(flatten)]
+    pub common_args: CommonArgs,
+
+    /// Sets the level of disk optimization (0 disabled)
+    #[structopt(long = "disk-optimization-level", default_value = "5")]
+    pub disk_optimization_level: u32,
+}
+
+arg_enum! {
+    /// Format of the kmers counts table
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum CountsFormat {
+        Tsv,
+        Binary,
+    }
+}
+
+#[derive(StructOpt, Debug)]
+struct CountArgs {
+    #[structopt(flatten)]
+    pub input_files: InputFilesArgs,
+
+    /// Enable colors, writing the colors subset index of each kmer in the colormap (<OUTPUT_FILE>.colors.dat).
+    /// The index can be decoded only with that colormap, using 'ggcat colormap dump-subsets',
+    /// and the count of each kmer is still the total across all the colors
+    #[structopt(short, long)]
+    pub colors: bool,
+
+    /// Encoding of the color subsets in the colormap
+    #[structopt(
+        long = "colormap-encoding",
+        default_value = "run-length",
+        possible_values = COLORMAP_ENCODINGS,
+        parse(try_from_str = parse_colormap_encoding)
+    )]
+    pub colormap_encoding: ColormapEncoding,
+
+    /// Minimum multiplicity required to keep a kmer, or 'auto' to choose it from the first valley
+    /// of the kmers abundance histogram, with an additional counting pass
+    #[structopt(
+        short = "s",
+        long = "min-multiplicity",
+        default_value = "2",
+        parse(try_from_str = parse_min_multiplicity)
+    )]
+    pub min_multiplicity: MinMultiplicity,
+
+    /// Maximum multiplicity required to keep a kmer
+    #[structopt(short = "z", long = "max-multiplicity", default_value = "999999")]
+    pub max_multiplicity: usize,
+
+    /// Apply the minimum multiplicity separately to the kmers of each color, a kmer keeps a color
+    /// only if it appears at least that many times in the inputs with that color
+    #[structopt(long = "per-color-min-multiplicity")]
+    pub per_color_min_multiplicity: bool,
+
+    /// Write the kmers abundance histogram to this file, as TSV lines with the multiplicity
+    /// and the number of distinct kmers having it
+    #[structopt(long = "write-histogram")]
+    pub write_histogram: Option<PathBuf>,
+
+    /// Minimum phred quality of the bases of FASTQ reads, lower quality bases split the reads
+    /// and the kmers containing them are discarded
+    #[structopt(short = "q", long = "min-base-quality")]
+    pub min_base_quality: Option<u8>,
+
+    /// Format of the kmers counts, TSV lines with the kmer, its count and the colors subset index,
+    /// or a header followed by fixed size records with the kmer packed in 2 bits per base
+    #[structopt(short = "F", long = "format", default_value = "Tsv")]
+    pub format: CountsFormat,
+
+    #[structopt(short = "o", long = "output-file", default_value = "output.counts.tsv")]
+    pub output_file: PathBuf,
+
+    #[structopt(flatten)]
+    pub common_args: CommonArgs,
 
     /// Sets the level of disk optimization (0 disabled)
     #[structopt(long = "disk-optimization-level", default_value = "5")]
@@ -221,6 +507,87 @@ struct DumpColorsArgs {
     output_file: PathBuf,
 }
 
+arg_enum! {
+    /// Format of the color subsets dump
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum ColorSubsetsFormat {
+        Jsonl,
+        Tsv,
+    }
+}
+
+fn parse_min_multiplicity(multiplicity: &str) -> Result<MinMultiplicity, String> {
+    match multiplicity {
+        "auto" => Ok(MinMultiplicity::Auto),
+        _ => multiplicity
+            .parse()
+            .map(MinMultiplicity::Fixed)
+            .map_err(|_| format!("Invalid minimum multiplicity: {}", multiplicity)),
+    }
+}
+
+const COLORMAP_ENCODINGS: &[&str] = &["run-length", "roaring"];
+
+fn parse_colormap_encoding(encoding: &str) -> Result<ColormapEncoding, String> {
+    match encoding {
+        "run-length" => Ok(ColormapEncoding::RunLength),
+        "roaring" => Ok(ColormapEncoding::Roaring),
+        _ => Err(format!("Unknown colormap encoding: {}", encoding)),
+    }
+}
+
+#[derive(StructOpt, Debug)]
+enum ColormapCommand {
+    /// Prints the header fields of a colormap and its compression ratio
+    Info { input_colormap: PathBuf },
+    /// Decodes all the color subsets of a colormap, checking its consistency
+    Verify { input_colormap: PathBuf },
+    /// Rewrites a colormap created by an older release with the current storage format,
+    /// optionally converting it to a different encoding
+    Upgrade {
+        input_colormap: PathBuf,
+
+        /// Writes the upgraded colormap to this file instead of replacing the input one
+        #[structopt(short = "o", long = "output-file")]
+        output_file: Option<PathBuf>,
+
+        /// Converts the color subsets to this encoding, by default the encoding of the input is kept
+        #[structopt(
+            short = "e",
+            long = "encoding",
+            possible_values = COLORMAP_ENCODINGS,
+            parse(try_from_str = parse_colormap_encoding)
+        )]
+        encoding: Option<ColormapEncoding>,
+    },
+    /// Writes the colors of each subset of a colormap
+    DumpSubsets {
+        input_colormap: PathBuf,
+        output_file: PathBuf,
+
+        #[structopt(short, long, default_value = "Jsonl")]
+        format: ColorSubsetsFormat,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+struct StatsArgs {
+    /// The input graph, in FASTA or GFA format
+    pub input_graph: PathBuf,
+
+    /// Specifies the k-mers length
+    #[structopt(short, long = "kmer-length")]
+    pub kmer_length: usize,
+
+    /// Enable colors, reading the color subsets from the graph colormap (<GRAPH>.colors.dat)
+    #[structopt(short, long)]
+    pub colors: bool,
+
+    /// Expected genome size, used to compute NG50
+    #[structopt(short = "g", long = "genome-size")]
+    pub genome_size: Option<u64>,
+}
+
 arg_enum! {
     /// Format of the queries output
     #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -235,8 +602,10 @@ struct QueryArgs {
     /// The input graph
     pub input_graph: PathBuf,
 
-    /// The input query as a .fasta file
-    pub input_query: PathBuf,
+    /// The input queries as .fasta/.fastq files (optionally compressed).
+    /// Queries are numbered consecutively across all the files
+    #[structopt(required = true)]
+    pub input_queries: Vec<PathBuf>,
 
     /// Enable colors
     #[structopt(short, long)]
@@ -248,6 +617,28 @@ struct QueryArgs {
     #[structopt(long = "colored-query-output-format")]
     pub colored_query_output_format: Option<ColoredQueryOutputFormat>,
 
+    /// Adds the run-length encoded hits of each query kmer to the output,
+    /// as comma separated <length>:<hit> runs where hit is '-' if the kmers are missing,
+    /// '+' if present in an uncolored graph or the color subset index otherwise
+    #[structopt(long = "emit-kmer-hits")]
+    pub emit_kmer_hits: bool,
+
+    /// Adds the query names column to the csv output of an uncolored query
+    #[structopt(long = "emit-query-names")]
+    pub emit_query_names: bool,
+
+    /// Reports only the matches (colors, if colored) containing at least this fraction of the query kmers
+    #[structopt(long = "min-fraction")]
+    pub min_fraction: Option<f64>,
+
+    /// Reports only the N colors with the most matched kmers for each query
+    #[structopt(long = "top-n")]
+    pub top_n: Option<usize>,
+
+    /// Reports only the matches (colors, if colored) containing at least this number of query kmers
+    #[structopt(long = "min-kmers")]
+    pub min_kmers: Option<u64>,
+
     #[structopt(short = "x", long, default_value = "MinimizerBucketing")]
     pub step: QuerierStartingStep,
 
@@ -266,9 +657,13 @@ fn initialize(
     args: &CommonArgs,
     out_file: &PathBuf,
     disk_optimization_level: u32,
-) -> &'static GGCATInstance {
+) -> GGCATInstance {
     let instance = GGCATInstance::create(GGCATConfig {
-        temp_dir: Some(args.temp_dir.clone()),
+        temp_dir: if args.memory_only {
+            None
+        } else {
+            Some(args.temp_dir.clone())
+        },
         memory: args.memory,
         prefer_memory: args.prefer_memory,
         total_threads_count: args.threads_count,
@@ -276,18 +671,24 @@ fn initialize(
         stats_file: Some(out_file.with_extension("stats.log")),
         messages_callback: None,
         disk_optimization_level,
-    });
-
-    ggcat_api::debug::DEBUG_KEEP_FILES.store(args.keep_temp_files, Ordering::Relaxed);
-    *ggcat_api::debug::BUCKETS_COUNT_LOG_FORCE.lock() = args.buckets_count_log;
-    ggcat_api::debug::DEBUG_ONLY_BSTATS.store(args.only_bstats, Ordering::Relaxed);
-    *ggcat_api::debug::DEBUG_HASH_TYPE.lock() = match args.hash_type {
-        HashType::Auto => ggcat_api::HashType::Auto,
-        HashType::SeqHash => ggcat_api::HashType::SeqHash,
-        HashType::RabinKarp32 => ggcat_api::HashType::RabinKarp32,
-        HashType::RabinKarp64 => ggcat_api::HashType::RabinKarp64,
-        HashType::RabinKarp128 => ggcat_api::HashType::RabinKarp128,
-    };
+        progress_callback: None,
+        cancellation_token: None,
+    })
+    .unwrap_or_else(exit_on_error);
+
+    {
+        let mut debug_config = instance.debug_config();
+        debug_config.keep_files = args.keep_temp_files;
+        debug_config.buckets_count_log_force = args.buckets_count_log;
+        debug_config.only_bstats = args.only_bstats;
+        debug_config.hash_type = match args.hash_type {
+            HashType::Auto => ggcat_api::HashType::Auto,
+            HashType::SeqHash => ggcat_api::HashType::SeqHash,
+            HashType::RabinKarp32 => ggcat_api::HashType::RabinKarp32,
+            HashType::RabinKarp64 => ggcat_api::HashType::RabinKarp64,
+            HashType::RabinKarp128 => ggcat_api::HashType::RabinKarp128,
+        };
+    }
 
     println!(
         "Using m: {} with k: {}",
@@ -298,7 +699,12 @@ fn initialize(
 
     // #[cfg(feature = "mem-analysis")]
     // debug_print_allocations("/tmp/allocations", Duration::from_secs(5));
-    instance.unwrap()
+    instance
+}
+
+fn exit_on_error<T>(err: GGCATError) -> T {
+    eprintln!("Error: {}", err);
+    exit(1);
 }
 
 fn convert_assembler_step(step: AssemblerStartingStep) -> assembler::AssemblerStartingStep {
@@ -317,7 +723,7 @@ fn convert_assembler_step(step: AssemblerStartingStep) -> assembler::AssemblerSt
     }
 }
 
-fn run_assembler_from_args(instance: &GGCATInstance, args: AssemblerArgs) {
+fn collect_input_files(args: InputFilesArgs) -> (Vec<GeneralSequenceBlockData>, Vec<String>) {
     let mut inputs: Vec<_> = args.input.iter().cloned().map(|f| (f, None)).collect();
 
     if (args.input_lists.len() > 0 || args.input.len() > 0) && args.colored_input_lists.len() > 0 {
@@ -325,11 +731,6 @@ fn run_assembler_from_args(instance: &GGCATInstance, args: AssemblerArgs) {
         exit(1);
     }
 
-    if args.gfa_output_v1 && args.gfa_output_v2 {
-        println!("Cannot specify both GFA v1 and GFA v2 output");
-        exit(1);
-    }
-
     for list in args.input_lists {
         for input in BufReader::new(
             File::open(&list)
@@ -431,15 +832,139 @@ fn run_assembler_from_args(instance: &GGCATInstance, args: AssemblerArgs) {
 
     let inputs = inputs
         .into_iter()
-        .map(|x| GeneralSequenceBlockData::FASTA(x))
+        .map(|(file, color)| GeneralSequenceBlockData::from_file(file, color))
+        .collect();
+
+    (inputs, color_names)
+}
+
+fn run_assembler_from_args(instance: &GGCATInstance, args: AssemblerArgs) {
+    let (inputs, color_names) = collect_input_files(args.input_files);
+
+    {
+        let mut debug_config = instance.debug_config();
+        debug_config.assembler_first_step = convert_assembler_step(args.step);
+        debug_config.assembler_last_step = convert_assembler_step(args.last_step);
+        debug_config.link_phase_iteration_start_step = args.number;
+    }
+
+    let output_file = match args.resume {
+        Some(temp_dir) => instance.resume_build_graph(
+            temp_dir,
+            inputs,
+            args.output_file,
+            Some(&color_names),
+            args.common_args.kmer_length,
+            args.common_args.threads_count,
+            args.common_args.forward_only,
+            args.common_args.minimizer_length,
+            args.colors,
+            args.colormap_encoding,
+            args.min_multiplicity,
+            args.max_multiplicity,
+            args.per_color_min_multiplicity,
+            args.write_histogram,
+            args.min_base_quality,
+            args.abundance_args.abundance_mode(),
+            args.cleaning_args
+                .graph_cleaning(args.common_args.kmer_length),
+            args.output_args.extra_elaboration(),
+            args.output_args.gfa_version(),
+            args.disk_optimization_level,
+        ),
+        None => instance.build_graph(
+            inputs,
+            args.output_file,
+            Some(&color_names),
+            args.common_args.kmer_length,
+            args.common_args.threads_count,
+            args.common_args.forward_only,
+            args.common_args.minimizer_length,
+            args.colors,
+            args.colormap_encoding,
+            args.min_multiplicity,
+            args.max_multiplicity,
+            args.per_color_min_multiplicity,
+            args.write_histogram,
+            args.min_base_quality,
+            args.abundance_args.abundance_mode(),
+            args.cleaning_args
+                .graph_cleaning(args.common_args.kmer_length),
+            args.output_args.extra_elaboration(),
+            args.output_args.gfa_version(),
+            args.disk_optimization_level,
+        ),
+    }
+    .unwrap_or_else(exit_on_error);
+
+    println!("Final output saved to: {}", output_file.display());
+}
+
+fn run_update_from_args(instance: &GGCATInstance, args: UpdateArgs) {
+    let (inputs, color_names) = collect_input_files(args.input_files);
+
+    let output_file = instance
+        .update_graph(
+            args.input_graph,
+            args.input_colormap,
+            inputs,
+            &color_names,
+            args.output_file,
+            args.common_args.kmer_length,
+            args.common_args.threads_count,
+            args.common_args.forward_only,
+            args.common_args.minimizer_length,
+            args.min_multiplicity,
+            args.output_args.extra_elaboration(),
+            args.output_args.gfa_version(),
+            args.disk_optimization_level,
+        )
+        .unwrap_or_else(exit_on_error);
+
+    println!("Final output saved to: {}", output_file.display());
+}
+
+fn run_merge_from_args(instance: &GGCATInstance, args: MergeArgs) {
+    if args.input_graphs.is_empty() {
+        println!("ERROR: No input graphs specified!");
+        exit(1);
+    }
+
+    let input_graphs = args
+        .input_graphs
+        .into_iter()
+        .map(|graph_file| {
+            let colormap_file = GGCATInstance::get_colormap_file(&graph_file);
+            GraphInputFile {
+                colormap_file: (args.colors && colormap_file.exists()).then_some(colormap_file),
+                graph_file,
+            }
+        })
         .collect();
 
-    *ggcat_api::debug::DEBUG_ASSEMBLER_FIRST_STEP.lock() = convert_assembler_step(args.step);
-    *ggcat_api::debug::DEBUG_ASSEMBLER_LAST_STEP.lock() = convert_assembler_step(args.last_step);
-    ggcat_api::debug::DEBUG_LINK_PHASE_ITERATION_START_STEP.store(args.number, Ordering::Relaxed);
+    let output_file = instance
+        .merge_graphs(
+            input_graphs,
+            args.output_file,
+            args.common_args.kmer_length,
+            args.common_args.threads_count,
+            args.common_args.forward_only,
+            args.common_args.minimizer_length,
+            args.colors,
+            args.output_args.extra_elaboration(),
+            args.output_args.gfa_version(),
+            args.disk_optimization_level,
+        )
+        .unwrap_or_else(exit_on_error);
+
+    println!("Final output saved to: {}", output_file.display());
+}
+
+fn run_count_from_args(instance: &GGCATInstance, args: CountArgs) {
+    let (inputs, color_names) = collect_input_files(args.input_files);
 
     let output_file = instance
-        .build_graph(
+        .count_kmers(
             inputs,
             args.output_file,
             Some(&color_names),
@@ -448,35 +973,21 @@ fn run_assembler_from_args(instance: &GGCATInstance, args: AssemblerArgs) {
             args.common_args.forward_only,
             args.common_args.minimizer_length,
             args.colors,
+            args.colormap_encoding,
             args.min_multiplicity,
             args.max_multiplicity,
-            if args.generate_maximal_unitigs_links {
-                ExtraElaboration::UnitigLinks
-            } else if args.greedy_matchtigs {
-                ExtraElaboration::GreedyMatchtigs
-            } else if args.eulertigs {
-                ExtraElaboration::Eulertigs
-            } else if args.pathtigs {
-                ExtraElaboration::Pathtigs
-            } else if args.fast_simplitigs {
-                ExtraElaboration::FastSimplitigs
-            } else if args.fast_eulertigs {
-                ExtraElaboration::FastEulertigs
-            } else {
-                ExtraElaboration::None
-            },
-            if args.gfa_output_v1 {
-                Some(GfaVersion::V1)
-            } else if args.gfa_output_v2 {
-                Some(GfaVersion::V2)
-            } else {
-                None
+            args.per_color_min_multiplicity,
+            args.write_histogram,
+            args.min_base_quality,
+            match args.format {
+                CountsFormat::Tsv => KmersCountsFormat::Tsv,
+                CountsFormat::Binary => KmersCountsFormat::Binary,
             },
             args.disk_optimization_level,
         )
-        .unwrap();
+        .unwrap_or_else(exit_on_error);
 
-    println!("Final output saved to: {}", output_file.display());
+    println!("Kmers counts saved to: {}", output_file.display());
 }
 
 fn convert_querier_step(step: QuerierStartingStep) -> querier::QuerierStartingStep {
@@ -489,12 +1000,15 @@ fn convert_querier_step(step: QuerierStartingStep) -> querier::QuerierStartingSt
 }
 
 fn run_querier_from_args(instance: &GGCATInstance, args: QueryArgs) -> PathBuf {
-    *ggcat_api::debug::DEBUG_QUERIER_FIRST_STEP.lock() = convert_querier_step(args.step);
+    instance.debug_config().querier_first_step = convert_querier_step(args.step);
 
     instance
         .query_graph(
             args.input_graph,
-            args.input_query,
+            args.input_queries
+                .into_iter()
+                .map(|file| GeneralSequenceBlockData::from_file(file, None))
+                .collect(),
             args.output_file_prefix,
             args.common_args.kmer_length,
             args.common_args.threads_count,
@@ -512,8 +1026,15 @@ fn run_querier_from_args(instance: &GGCATInstance, args: QueryArgs) -> PathBuf {
                     querier::ColoredQueryOutputFormat::JsonLinesWithNames
                 }
             },
+            args.emit_kmer_hits,
+            args.emit_query_names,
+            QueryResultsFilter {
+                min_fraction: args.min_fraction,
+                top_n: args.top_n,
+                min_kmers: args.min_kmers,
+            },
         )
-        .unwrap()
+        .unwrap_or_else(exit_on_error)
 }
 
 instrumenter::global_setup_instrumenter!();
@@ -548,6 +1069,48 @@ fn main() {
 
             run_assembler_from_args(&instance, args);
         }
+        CliArgs::Update(args) => {
+            let _guard = instrumenter::initialize_tracing(
+                args.output_file.with_extension("tracing.json"),
+                &["ix86arch::INSTRUCTION_RETIRED", "ix86arch::LLC_MISSES"],
+            );
+
+            let instance = initialize(
+                &args.common_args,
+                &args.output_file,
+                args.disk_optimization_level,
+            );
+
+            run_update_from_args(&instance, args);
+        }
+        CliArgs::Merge(args) => {
+            let _guard = instrumenter::initialize_tracing(
+                args.output_file.with_extension("tracing.json"),
+                &["ix86arch::INSTRUCTION_RETIRED", "ix86arch::LLC_MISSES"],
+            );
+
+            let instance = initialize(
+                &args.common_args,
+                &args.output_file,
+                args.disk_optimization_level,
+            );
+
+            run_merge_from_args(&instance, args);
+        }
+        CliArgs::Count(args) => {
+            let _guard = instrumenter::initialize_tracing(
+                args.output_file.with_extension("tracing.json"),
+                &["ix86arch::INSTRUCTION_RETIRED", "ix86arch::LLC_MISSES"],
+            );
+
+            let instance = initialize(
+                &args.common_args,
+                &args.output_file,
+                args.disk_optimization_level,
+            );
+
+            run_count_from_args(&instance, args);
+        }
         CliArgs::Matches(args) => {
             let colors_file = args.input_file.with_extension("colors.dat");
             let mut colors_deserializer =
@@ -589,7 +1152,7 @@ fn main() {
             let mut output_file = BufWriter::new(File::create(&output_file_name).unwrap());
 
             for (color_idx, color_name) in GGCATInstance::dump_colors(args.input_colormap)
-                .unwrap()
+                .unwrap_or_else(exit_on_error)
                 .enumerate()
             {
                 writeln!(
@@ -605,6 +1168,106 @@ fn main() {
 
             return; // Skip final memory deallocation
         }
+        CliArgs::Colormap(ColormapCommand::Info { input_colormap }) => {
+            let info = GGCATInstance::colormap_info(input_colormap).unwrap_or_else(exit_on_error);
+
+            println!("Encoding: {}", info.encoding);
+            println!("Version: {}", info.version);
+            println!("Colors: {}", info.colors_count);
+            println!("Subsets: {}", info.subsets_count);
+            println!("Chunks: {}", info.chunks_count);
+            println!("File size: {} bytes", info.file_size);
+            println!(
+                "Subsets size: {} bytes ({} bytes uncompressed, compression ratio {:.2})",
+                info.subsets_size,
+                info.subsets_uncompressed_size,
+                info.compression_ratio()
+            );
+            return; // Skip final memory deallocation
+        }
+        CliArgs::Colormap(ColormapCommand::Verify { input_colormap }) => {
+            let issues =
+                GGCATInstance::verify_colormap(&input_colormap).unwrap_or_else(exit_on_error);
+
+            for issue in &issues {
+                println!("Error: {}", issue);
+            }
+            if !issues.is_empty() {
+                println!(
+                    "Colormap {} is corrupted, found {} issues",
+                    input_colormap.display(),
+                    issues.len()
+                );
+                exit(1);
+            }
+            println!("Colormap {} is valid", input_colormap.display());
+            return; // Skip final memory deallocation
+        }
+        CliArgs::Colormap(ColormapCommand::Upgrade {
+            input_colormap,
+            output_file,
+            encoding,
+        }) => {
+            let output_colormap = output_file.clone().unwrap_or(input_colormap.clone());
+            if GGCATInstance::upgrade_colormap(&input_colormap, output_file, encoding)
+                .unwrap_or_else(exit_on_error)
+            {
+                println!("Upgraded colormap written to {}", output_colormap.display());
+            } else {
+                println!(
+                    "Colormap {} is already up to date",
+                    input_colormap.display()
+                );
+            }
+            return; // Skip final memory deallocation
+        }
+        CliArgs::Colormap(ColormapCommand::DumpSubsets {
+            input_colormap,
+            output_file,
+            format,
+        }) => {
+            let output_file_name = output_file.with_extension(match format {
+                ColorSubsetsFormat::Jsonl => "jsonl",
+                ColorSubsetsFormat::Tsv => "tsv",
+            });
+
+            let mut output_file = BufWriter::new(File::create(&output_file_name).unwrap());
+
+            for (subset_idx, colors) in
+                GGCATInstance::dump_colormap_subsets(input_colormap).unwrap_or_else(exit_on_error)
+            {
+                let colors = colors
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                match format {
+                    ColorSubsetsFormat::Jsonl => writeln!(
+                        output_file,
+                        "{{\"subset_index\":{}, \"colors\":[{}] }}",
+                        subset_idx, colors,
+                    ),
+                    ColorSubsetsFormat::Tsv => writeln!(output_file, "{}\t{}", subset_idx, colors),
+                }
+                .unwrap();
+            }
+
+            drop(output_file);
+            println!("Color subsets written to {}", output_file_name.display());
+            return; // Skip final memory deallocation
+        }
+        CliArgs::Stats(args) => {
+            let stats = GGCATInstance::graph_stats(
+                &args.input_graph,
+                args.kmer_length,
+                args.colors,
+                args.genome_size,
+            )
+            .unwrap_or_else(exit_on_error);
+
+            print!("{}", stats);
+            return; // Skip final memory deallocation
+        }
     }
 
     // Ensure termination
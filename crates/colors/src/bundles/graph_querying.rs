@@ -26,6 +26,11 @@ impl ColorsManager for ColorBundleGraphQuerying {
         )
     }
 
+    #[inline(always)]
+    fn get_color_subset(color: &Self::SingleKmerColorDataType) -> Option<ColorIndexType> {
+        Some(*color)
+    }
+
     type ColorsParserType = GraphColorsParser;
     type ColorsMergeManagerType = SingleColorManager;
 }
@@ -26,6 +26,11 @@ impl ColorsManager for ColorBundleMultifileBuilding {
         )
     }
 
+    #[inline(always)]
+    fn get_color_subset(color: &Self::SingleKmerColorDataType) -> Option<ColorIndexType> {
+        Some(*color)
+    }
+
     type ColorsParserType = SeparateColorsParser;
     type ColorsMergeManagerType = MultipleColorsManager;
 }
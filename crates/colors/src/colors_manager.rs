@@ -1,5 +1,6 @@
 use crate::parsers::SingleSequenceInfo;
-use config::{BucketIndexType, ColorCounterType, ColorIndexType};
+use crate::storage::ColormapEncoding;
+use config::{BucketIndexType, ColorCounterType, ColorIndexType, MultiplicityCounterType};
 use dynamic_dispatch::dynamic_dispatch;
 use hashbrown::HashMap;
 use hashes::HashFunctionFactory;
@@ -8,6 +9,7 @@ use io::concurrent::structured_sequences::IdentSequenceWriter;
 use io::concurrent::temp_reads::extra_data::{
     SequenceExtraDataConsecutiveCompression, SequenceExtraDataTempBufferManagement,
 };
+use io::sequences_stream::ColorSubsets;
 use nightly_quirks::prelude::*;
 use parallel_processor::fast_smart_bucket_sort::FastSortable;
 use rustc_hash::FxHashMap;
@@ -16,7 +18,7 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::ops::Range;
 use std::path::Path;
-use structs::map_entry::MapEntry;
+use structs::map_entry::{MapEntry, TempColorIndexStorage};
 
 pub mod color_types {
     #![allow(dead_code)]
@@ -38,13 +40,17 @@ pub mod color_types {
 
     color_manager_type_alias!(GlobalColorsTableWriter);
     color_manager_type_alias!(ColorsBufferTempStructure);
-    color_manager_type_alias!(HashMapTempColorIndex);
     color_manager_type_alias!(PartialUnitigsColorStructure);
     color_manager_type_alias!(TempUnitigColorStructure);
 
     color_parser_type_alias!(SingleKmerColorDataType);
     color_parser_type_alias!(MinimizerBucketingSeqColorDataType);
 
+    pub type HashMapTempColorIndex<C, I> =
+        <<C as ColorsManager>::ColorsMergeManagerType as ColorsMergeManager>::HashMapTempColorIndex<
+            I,
+        >;
+
     pub type ColorsParserType<C> = <C as ColorsManager>::ColorsParserType;
     pub type ColorsMergeManagerType<C> = <C as ColorsManager>::ColorsMergeManagerType;
 }
@@ -123,10 +129,11 @@ pub trait ColorsMergeManager: Sized {
     type GlobalColorsTableWriter: Sync + Send + 'static;
     type GlobalColorsTableReader: ColorMapReader + Sync + Send + 'static;
 
-    /// Creates a new colors table at the given path
+    /// Creates a new colors table at the given path, storing the color subsets with the given encoding
     fn create_colors_table(
         path: impl AsRef<Path>,
         color_names: &[String],
+        encoding: ColormapEncoding,
     ) -> anyhow::Result<Self::GlobalColorsTableWriter>;
 
     /// Creates a new colors table at the given path
@@ -139,33 +146,41 @@ pub trait ColorsMergeManager: Sized {
     type ColorsBufferTempStructure: 'static + Send + Sync;
     fn allocate_temp_buffer_structure(temp_dir: &Path) -> Self::ColorsBufferTempStructure;
     fn reinit_temp_buffer_structure(data: &mut Self::ColorsBufferTempStructure);
-    fn add_temp_buffer_structure_el<MH: HashFunctionFactory>(
+    fn add_temp_buffer_structure_el<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         data: &mut Self::ColorsBufferTempStructure,
         kmer_color: &Self::SingleKmerColorDataType,
         el: (usize, MH::HashTypeUnextendable),
-        entry: &mut MapEntry<Self::HashMapTempColorIndex>,
+        entry: &mut MapEntry<Self::HashMapTempColorIndex<I>>,
     );
 
+    /// Adds a sequence with its color, the multiplicity is the number of times
+    /// it was found (greater than one for compacted buckets)
     fn add_temp_buffer_sequence(
         data: &mut Self::ColorsBufferTempStructure,
         sequence: CompressedRead,
         k: usize,
         m: usize,
         flags: u8,
+        multiplicity: MultiplicityCounterType,
     );
 
-    /// Temporary storage for colors associated with a single kmer in the hashmap (holds the color subset index)
-    type HashMapTempColorIndex: 'static + Send + Sync;
-    fn new_color_index() -> Self::HashMapTempColorIndex;
+    /// Temporary storage for colors associated with a single kmer in the hashmap (holds the color subset index),
+    /// the storage decides if the color index can reuse the kmer counter
+    type HashMapTempColorIndex<I: TempColorIndexStorage>: 'static + Send + Sync;
+    fn new_color_index<I: TempColorIndexStorage>() -> Self::HashMapTempColorIndex<I>;
 
-    /// This step finds the color subset indexes for each map entry
-    fn process_colors<MH: HashFunctionFactory>(
+    /// This step finds the color subset indexes for each map entry,
+    /// the colors where a kmer appears less than min_color_multiplicity times are removed from it.
+    /// The color subsets of the input sequences are expanded with `color_subsets`, and their colors are always kept
+    fn process_colors<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         global_colors_table: &Self::GlobalColorsTableWriter,
+        color_subsets: Option<&dyn ColorSubsets>,
         data: &mut Self::ColorsBufferTempStructure,
-        map: &mut FxHashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex>>,
+        map: &mut FxHashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex<I>>>,
         k: usize,
         min_multiplicity: usize,
         max_multiplicity: usize,
+        min_color_multiplicity: usize,
     );
 
     /// Struct used to hold color information about unitigs
@@ -176,15 +191,20 @@ pub trait ColorsMergeManager: Sized {
     /// These functions are used to keep track of the colors while producing the partial unitigs
     fn alloc_unitig_color_structure() -> Self::TempUnitigColorStructure;
     fn reset_unitig_color_structure(ts: &mut Self::TempUnitigColorStructure);
-    fn extend_forward(
+    fn extend_forward<I: TempColorIndexStorage>(
         ts: &mut Self::TempUnitigColorStructure,
-        entry: &MapEntry<Self::HashMapTempColorIndex>,
+        entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
     );
-    fn extend_backward(
+    fn extend_backward<I: TempColorIndexStorage>(
         ts: &mut Self::TempUnitigColorStructure,
-        entry: &MapEntry<Self::HashMapTempColorIndex>,
+        entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
     );
 
+    /// Returns the color subset index of a single kmer, once its colors are processed
+    fn get_kmer_color_subset<I: TempColorIndexStorage>(
+        entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
+    ) -> ColorIndexType;
+
     fn join_structures<const REVERSE: bool>(
         dest: &mut Self::TempUnitigColorStructure,
         src: &Self::PartialUnitigsColorStructure,
@@ -224,11 +244,11 @@ pub trait ColorsMergeManager: Sized {
     ) -> Self::PartialUnitigsColorStructure;
 
     fn debug_tucs(str: &Self::TempUnitigColorStructure, seq: &[u8]);
-    fn debug_colors<MH: HashFunctionFactory>(
+    fn debug_colors<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         color: &Self::PartialUnitigsColorStructure,
         colors_buffer: &<Self::PartialUnitigsColorStructure as SequenceExtraDataTempBufferManagement>::TempBuffer,
         seq: &[u8],
-        hmap: &HashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex>>,
+        hmap: &HashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex<I>>>,
     );
 }
 
@@ -271,6 +291,9 @@ pub trait ColorsManager: 'static + Clone + Debug + Sync + Send + Sized {
         buckets_count_log: u32,
     ) -> BucketIndexType;
 
+    /// Returns the colors subset index of a kmer color, or None if the colors are disabled
+    fn get_color_subset(color: &Self::SingleKmerColorDataType) -> Option<ColorIndexType>;
+
     type ColorsParserType: ColorsParser<SingleKmerColorDataType = Self::SingleKmerColorDataType>;
     type ColorsMergeManagerType: ColorsMergeManager<
         SingleKmerColorDataType = Self::SingleKmerColorDataType,
@@ -1,6 +1,6 @@
 // use crate::storage::roaring::ColorsStorage;
-use crate::storage::serializer::ColorsSerializer;
-use crate::storage::ColorsSerializerTrait;
+use crate::storage::serializer::DynamicColorsSerializer;
+use crate::storage::ColormapEncoding;
 use config::ColorIndexType;
 use dashmap::DashMap;
 use hashes::dummy_hasher::DummyHasherBuilder;
@@ -9,14 +9,18 @@ use siphasher::sip128::{Hasher128, SipHasher13};
 use std::hash::Hash;
 use std::path::Path;
 
-pub struct ColorsMemMapWriter<C: ColorsSerializerTrait> {
+pub struct ColorsMemMapWriter {
     colors: DashMap<u128, ColorIndexType, DummyHasherBuilder>,
-    colors_storage: ColorsSerializer<C>,
+    colors_storage: DynamicColorsSerializer,
     hash_keys: (u64, u64),
 }
 
-impl<C: ColorsSerializerTrait> ColorsMemMapWriter<C> {
-    pub fn new(file: impl AsRef<Path>, color_names: &[String]) -> anyhow::Result<Self> {
+impl ColorsMemMapWriter {
+    pub fn new(
+        file: impl AsRef<Path>,
+        color_names: &[String],
+        encoding: ColormapEncoding,
+    ) -> anyhow::Result<Self> {
         let mut rng = thread_rng();
         Ok(Self {
             colors: DashMap::with_hasher_and_shard_amount(
@@ -24,7 +28,7 @@ impl<C: ColorsSerializerTrait> ColorsMemMapWriter<C> {
                 // Increase the number of shards to decrease stall while inserting new colors
                 rayon::current_num_threads() * 8,
             ),
-            colors_storage: ColorsSerializer::new(file, color_names)?,
+            colors_storage: DynamicColorsSerializer::new(file, color_names, encoding)?,
             hash_keys: (rng.next_u64(), rng.next_u64()),
         })
     }
@@ -0,0 +1,306 @@
+use crate::colors_manager::ColorMapReader;
+use crate::managers::multiple::{UnitigColorData, UnitigsSerializerTempBuffer};
+use crate::storage::deserializer::ColorsDeserializer;
+use crate::DefaultColorsSerializer;
+use bstr::ByteSlice;
+use config::ColorIndexType;
+use io::concurrent::structured_sequences::IdentSequenceWriter;
+use io::sequences_reader::{DnaSequence, DnaSequencesFileType, SequencesReader};
+use io::sequences_stream::fasta::FastaFileSequencesStream;
+use io::sequences_stream::general::DynamicSequencesStream;
+use io::sequences_stream::{ColorSubsets, SequenceInfo, COLOR_SUBSET_FLAG};
+use parking_lot::Mutex;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Source of the colors assigned to the kmers of an already built graph
+pub enum GraphColorsSource {
+    /// All the kmers of the graph are assigned to a single color
+    Static(ColorIndexType),
+    /// The color subsets of each unitig are expanded using the colormap, and each
+    /// original color `c` is mapped to `colors_remap[c]`
+    Colormap {
+        colormap_file: PathBuf,
+        colors_remap: Vec<ColorIndexType>,
+    },
+}
+
+pub struct GraphInput {
+    pub graph_file: PathBuf,
+    pub colors: GraphColorsSource,
+}
+
+/// Reads back the unitigs of existing graphs as input sequences for a new build.
+/// Each block is a graph, and colored unitigs are split into their color runs, each one read once
+/// with its color subset, that is expanded only when the colors are assigned to the kmers.
+pub struct GraphSequencesStream {
+    k: usize,
+    inputs: Vec<GraphInput>,
+    /// The first color subset of each input, the subsets of all the colormaps are numbered together
+    subsets_offsets: Vec<ColorIndexType>,
+    subsets: Arc<GraphColorSubsets>,
+}
+
+struct GraphColormap {
+    first_subset: ColorIndexType,
+    deserializer: Mutex<ColorsDeserializer<DefaultColorsSerializer>>,
+    colors_remap: Vec<ColorIndexType>,
+}
+
+/// Expands the color subsets of all the colormaps of the input graphs
+struct GraphColorSubsets {
+    colormaps: Vec<GraphColormap>,
+}
+
+impl ColorSubsets for GraphColorSubsets {
+    fn get_colors(&self, subset: ColorIndexType, colors: &mut Vec<ColorIndexType>) {
+        let index = self
+            .colormaps
+            .partition_point(|colormap| colormap.first_subset <= subset)
+            - 1;
+        let colormap = &self.colormaps[index];
+
+        let start = colors.len();
+        colormap
+            .deserializer
+            .lock()
+            .get_color_mappings(subset - colormap.first_subset, colors);
+
+        for color in colors[start..].iter_mut() {
+            *color = colormap.colors_remap[*color as usize];
+        }
+    }
+}
+
+/// The color subsets of the input graphs cannot be numbered together
+#[derive(Debug)]
+pub struct TooManyColorSubsets {
+    pub subsets_count: u64,
+}
+
+impl Display for TooManyColorSubsets {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The colormaps of the input graphs have {} color subsets, at most {} are supported",
+            self.subsets_count, COLOR_SUBSET_FLAG
+        )
+    }
+}
+
+impl std::error::Error for TooManyColorSubsets {}
+
+/// The kmer length is not compatible with the one used to build a graph
+#[derive(Debug)]
+pub struct IncompatibleKmerLength {
+    pub graph_file: PathBuf,
+    pub k: usize,
+    /// The kmer length of the graph, if it can be inferred
+    pub graph_k: Option<usize>,
+}
+
+impl Display for IncompatibleKmerLength {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Kmer length {} is not compatible with graph '{}'",
+            self.k,
+            self.graph_file.display()
+        )?;
+        match self.graph_k {
+            Some(graph_k) => write!(f, ", built with k = {}", graph_k),
+            None => write!(f, ", its sequences are shorter than k"),
+        }
+    }
+}
+
+impl std::error::Error for IncompatibleKmerLength {}
+
+/// Checks that the kmer length is compatible with a graph, by looking only at its first sequence.
+/// The kmer length of colored graphs is inferred from the kmers count of the color runs,
+/// for the other graphs only a kmer length greater than the sequence length is detected
+pub fn check_graph_kmer_length(graph_file: &Path, k: usize) -> anyhow::Result<()> {
+    let Some((format, ident, length)) = SequencesReader::read_first_sequence(graph_file)? else {
+        return Ok(());
+    };
+
+    let mut colors_buffer = UnitigsSerializerTempBuffer { colors: vec![] };
+    match format {
+        DnaSequencesFileType::GFA if ident.find(b"CS:Z:").is_some() => {
+            UnitigColorData::parse_as_gfa(&ident, &mut colors_buffer);
+        }
+        DnaSequencesFileType::FASTA if ident.find(b" C:").is_some() => {
+            UnitigColorData::parse_as_ident(&ident, &mut colors_buffer);
+        }
+        _ => {}
+    }
+
+    let kmers_count: usize = colors_buffer.colors.iter().map(|c| c.counter).sum();
+    let graph_k = (kmers_count > 0)
+        .then(|| (length + 1).checked_sub(kmers_count))
+        .flatten();
+
+    if graph_k.map(|graph_k| graph_k != k).unwrap_or(length < k) {
+        return Err(IncompatibleKmerLength {
+            graph_file: graph_file.to_path_buf(),
+            k,
+            graph_k,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+impl GraphSequencesStream {
+    pub fn new(k: usize, inputs: Vec<GraphInput>) -> anyhow::Result<Self> {
+        let mut subsets_offsets = Vec::with_capacity(inputs.len());
+        let mut colormaps = vec![];
+        let mut subsets_count = 0;
+
+        // Ensure that all the graphs and colormaps are readable before starting the build
+        for input in inputs.iter() {
+            check_graph_kmer_length(&input.graph_file, k)?;
+            subsets_offsets.push(subsets_count as ColorIndexType);
+
+            if let GraphColorsSource::Colormap {
+                colormap_file,
+                colors_remap,
+            } = &input.colors
+            {
+                let deserializer =
+                    ColorsDeserializer::<DefaultColorsSerializer>::new(colormap_file, false)?;
+                let first_subset = subsets_count as ColorIndexType;
+                subsets_count += deserializer.colors_subsets_count();
+
+                colormaps.push(GraphColormap {
+                    first_subset,
+                    deserializer: Mutex::new(deserializer),
+                    colors_remap: colors_remap.clone(),
+                });
+            }
+        }
+
+        if subsets_count > COLOR_SUBSET_FLAG as u64 {
+            return Err(TooManyColorSubsets { subsets_count }.into());
+        }
+
+        Ok(Self {
+            k,
+            inputs,
+            subsets_offsets,
+            subsets: Arc::new(GraphColorSubsets { colormaps }),
+        })
+    }
+
+    pub fn blocks_count(&self) -> usize {
+        self.inputs.len()
+    }
+
+    fn read_colored_graph(
+        &self,
+        graph_file: &PathBuf,
+        subsets_offset: ColorIndexType,
+        callback: &mut dyn FnMut(DnaSequence, SequenceInfo),
+    ) {
+        let k = self.k;
+
+        let mut colors_buffer = UnitigsSerializerTempBuffer { colors: vec![] };
+
+        SequencesReader::new().process_file_extended(
+            graph_file,
+            |sequence| {
+                if sequence.seq.len() < k {
+                    return;
+                }
+
+                colors_buffer.colors.clear();
+                match sequence.format {
+                    DnaSequencesFileType::GFA => {
+                        UnitigColorData::parse_as_gfa(sequence.ident_data, &mut colors_buffer)
+                    }
+                    _ => UnitigColorData::parse_as_ident(sequence.ident_data, &mut colors_buffer),
+                };
+
+                let kmers_count = sequence.seq.len() - k + 1;
+                let mut kmer_position = 0;
+
+                for run in colors_buffer.colors.iter() {
+                    let run_end = (kmer_position + run.counter).min(kmers_count);
+                    if kmer_position < run_end {
+                        callback(
+                            DnaSequence {
+                                ident_data: &[],
+                                seq: &sequence.seq[kmer_position..(run_end + k - 1)],
+                                qual: None,
+                                format: DnaSequencesFileType::FASTA,
+                            },
+                            SequenceInfo {
+                                color: Some(COLOR_SUBSET_FLAG | (subsets_offset + run.color)),
+                            },
+                        );
+                    }
+                    kmer_position += run.counter;
+                }
+
+                if kmer_position != kmers_count {
+                    ggcat_logging::warn!(
+                        "Colors of unitig {} cover {} kmers, expected {}",
+                        String::from_utf8_lossy(sequence.ident_data),
+                        kmer_position,
+                        kmers_count
+                    );
+                }
+            },
+            None,
+            true,
+            false,
+        );
+    }
+}
+
+impl DynamicSequencesStream for GraphSequencesStream {
+    fn read_block(
+        &self,
+        block: usize,
+        _copy_ident_data: bool,
+        _partial_read_copyback: Option<usize>,
+        callback: &mut dyn FnMut(DnaSequence, SequenceInfo),
+    ) {
+        let input = &self.inputs[block];
+        match &input.colors {
+            GraphColorsSource::Static(color) => {
+                SequencesReader::new().process_file_extended(
+                    &input.graph_file,
+                    |sequence| {
+                        callback(
+                            DnaSequence {
+                                ident_data: &[],
+                                ..sequence
+                            },
+                            SequenceInfo {
+                                color: Some(*color),
+                            },
+                        )
+                    },
+                    None,
+                    false,
+                    false,
+                );
+            }
+            GraphColorsSource::Colormap { .. } => {
+                self.read_colored_graph(&input.graph_file, self.subsets_offsets[block], callback);
+            }
+        }
+    }
+
+    fn estimated_base_count(&self, block: usize) -> u64 {
+        FastaFileSequencesStream::get_estimated_bases_count(&self.inputs[block].graph_file)
+            .unwrap_or(0)
+    }
+
+    fn color_subsets(&self) -> Option<Arc<dyn ColorSubsets>> {
+        Some(self.subsets.clone())
+    }
+}
@@ -3,6 +3,7 @@ use crate::storage::run_length::RunLengthColorsSerializer;
 pub mod bundles;
 pub mod colors_manager;
 pub mod colors_memmap_writer;
+pub mod graph_stream;
 pub mod managers;
 pub mod non_colored;
 pub mod parsers;
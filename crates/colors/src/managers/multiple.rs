@@ -1,12 +1,14 @@
 use crate::colors_manager::ColorsMergeManager;
 use crate::colors_memmap_writer::ColorsMemMapWriter;
-use crate::DefaultColorsSerializer;
+use crate::storage::ColormapEncoding;
 use atoi::{FromRadix10, FromRadix16};
 use bstr::ByteSlice;
 use byteorder::ReadBytesExt;
+use config::operation::MemoryTrackedBucket;
 use config::{
     get_compression_level_info, get_memory_mode, ColorCounterType, ColorIndexType, MinimizerType,
-    SwapPriority, PARTIAL_VECS_CHECKPOINT_SIZE, READ_FLAG_INCL_BEGIN, READ_FLAG_INCL_END,
+    MultiplicityCounterType, SwapPriority, PARTIAL_VECS_CHECKPOINT_SIZE, READ_FLAG_INCL_BEGIN,
+    READ_FLAG_INCL_END,
 };
 use hashbrown::HashMap;
 use hashes::default::MNHFactory;
@@ -17,6 +19,7 @@ use io::concurrent::structured_sequences::IdentSequenceWriter;
 use io::concurrent::temp_reads::extra_data::{
     SequenceExtraData, SequenceExtraDataTempBufferManagement,
 };
+use io::sequences_stream::{ColorSubsets, COLOR_SUBSET_FLAG};
 use io::varint::{
     decode_varint, decode_varint_flags, encode_varint, encode_varint_flags, VARINT_MAX_SIZE,
 };
@@ -33,13 +36,13 @@ use std::mem::size_of;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use structs::map_entry::{MapEntry, COUNTER_BITS};
+use structs::map_entry::{MapEntry, TempColorIndexStorage, COUNTER_BITS};
 
 const COLOR_SEQUENCES_SUBBUKETS: usize = 32;
 
 struct SequencesStorage {
     buffer: Vec<u8>,
-    file: Option<CompressedBinaryWriter>,
+    file: Option<MemoryTrackedBucket<CompressedBinaryWriter>>,
 }
 
 struct SequencesStorageStream<'a> {
@@ -90,7 +93,7 @@ impl SequencesStorage {
         if self.buffer.len() >= READS_BUFFERS_MAX_CAPACITY {
             if self.file.is_none() {
                 static COLOR_STORAGE_INDEX: AtomicUsize = AtomicUsize::new(0);
-                self.file = Some(CompressedBinaryWriter::new(
+                self.file = Some(MemoryTrackedBucket::<CompressedBinaryWriter>::new(
                     temp_dir.join("color-storage-temp").as_path(),
                     &(
                         get_memory_mode(SwapPriority::KmersMergeTempColors),
@@ -114,39 +117,61 @@ pub struct MultipleColorsManager {
     kmers_count: usize,
     sequences_count: usize,
     temp_colors_buffer: Vec<ColorIndexType>,
+    /// The colors of a kmer, once the color subsets of the input graphs are expanded
+    expanded_colors: Vec<ColorIndexType>,
+    subsets_cache: FxHashMap<ColorIndexType, Vec<ColorIndexType>>,
+    subsets_cache_colors: usize,
     temp_dir: PathBuf,
 }
 
 const VISITED_BIT: usize = 1 << (COUNTER_BITS - 1);
 const TEMP_BUFFER_START_SIZE: usize = 1024 * 64;
 const READS_BUFFERS_MAX_CAPACITY: usize = 1024 * 32;
+const SUBSETS_CACHE_MAX_COLORS: usize = 1024 * 1024;
 
-#[cfg(feature = "support_kmer_counters")]
-type HashMapTempColorIndex = usize;
+#[inline]
+fn get_entry_color<I: TempColorIndexStorage>(entry: &MapEntry<I>) -> ColorIndexType {
+    (I::get_color_index(entry) & !VISITED_BIT) as ColorIndexType
+}
 
-#[cfg(not(feature = "support_kmer_counters"))]
-type HashMapTempColorIndex = ();
+/// Dedups the sorted colors, keeping only the ones repeated at least min_count times,
+/// and returns the number of colors kept at the start of the slice and the number of their occurrences
+fn retain_frequent_colors(colors: &mut [ColorIndexType], min_count: usize) -> (usize, usize) {
+    let mut kept = 0;
+    let mut kept_occurrences = 0;
+    let mut run_start = 0;
+    for index in 0..colors.len() {
+        if index + 1 == colors.len() || colors[index + 1] != colors[index] {
+            if index + 1 - run_start >= min_count {
+                colors[kept] = colors[index];
+                kept += 1;
+                kept_occurrences += index + 1 - run_start;
+            }
+            run_start = index + 1;
+        }
+    }
+    (kept, kept_occurrences)
+}
 
-#[inline]
-fn get_entry_color(entry: &MapEntry<HashMapTempColorIndex>) -> ColorIndexType {
-    (match () {
-        #[cfg(not(feature = "support_kmer_counters"))]
-        () => entry.get_counter() & !VISITED_BIT,
-        #[cfg(feature = "support_kmer_counters")]
-        () => entry.color_index & !VISITED_BIT,
-    }) as ColorIndexType
+/// Removes from the kmer counter the occurrences of the colors where the kmer is not solid,
+/// so that its multiplicity counts only the colors it's kept with
+fn remove_dropped_occurrences<I: TempColorIndexStorage>(entry: &mut MapEntry<I>, dropped: usize) {
+    // The kmers having both the partial sequences endings are counted two times
+    let is_bidirectional = entry.get_flags() == (READ_FLAG_INCL_BEGIN | READ_FLAG_INCL_END);
+    entry.set_counter_after_check(entry.get_counter() - (dropped << is_bidirectional as usize));
 }
 
 impl ColorsMergeManager for MultipleColorsManager {
     type SingleKmerColorDataType = ColorIndexType;
-    type GlobalColorsTableWriter = ColorsMemMapWriter<DefaultColorsSerializer>;
+    type GlobalColorsTableWriter = ColorsMemMapWriter;
     type GlobalColorsTableReader = ();
 
     fn create_colors_table(
         path: impl AsRef<Path>,
         color_names: &[String],
+        encoding: ColormapEncoding,
     ) -> anyhow::Result<Self::GlobalColorsTableWriter> {
-        ColorsMemMapWriter::new(path, color_names)
+        ColorsMemMapWriter::new(path, color_names, encoding)
     }
 
     fn open_colors_table(_path: impl AsRef<Path>) -> anyhow::Result<Self::GlobalColorsTableReader> {
@@ -168,6 +193,9 @@ impl ColorsMergeManager for MultipleColorsManager {
             kmers_count: 0,
             sequences_count: 0,
             temp_colors_buffer: vec![],
+            expanded_colors: vec![],
+            subsets_cache: FxHashMap::default(),
+            subsets_cache_colors: 0,
             temp_dir: temp_dir.to_path_buf(),
         }
     }
@@ -180,11 +208,11 @@ impl ColorsMergeManager for MultipleColorsManager {
         data.sequences_count = 0;
     }
 
-    fn add_temp_buffer_structure_el<MH: HashFunctionFactory>(
+    fn add_temp_buffer_structure_el<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         data: &mut Self::ColorsBufferTempStructure,
         kmer_color: &ColorIndexType,
         _el: (usize, MH::HashTypeUnextendable),
-        _entry: &mut MapEntry<Self::HashMapTempColorIndex>,
+        _entry: &mut MapEntry<Self::HashMapTempColorIndex<I>>,
     ) {
         data.last_color = *kmer_color;
     }
@@ -196,6 +224,7 @@ impl ColorsMergeManager for MultipleColorsManager {
         k: usize,
         m: usize,
         flags: u8,
+        multiplicity: MultiplicityCounterType,
     ) {
         let decr_val =
             ((sequence.bases_count() == k) && (flags & READ_FLAG_INCL_END) == 0) as usize;
@@ -214,6 +243,10 @@ impl ColorsMergeManager for MultipleColorsManager {
         data.sequences[bucket]
             .buffer
             .extend_from_slice(&data.last_color.to_ne_bytes());
+        encode_varint(
+            |b| data.sequences[bucket].buffer.extend_from_slice(b),
+            multiplicity as u64,
+        );
 
         let kmer_length_dist_flag = if sequence.bases_count() > k {
             0
@@ -230,23 +263,21 @@ impl ColorsMergeManager for MultipleColorsManager {
         data.sequences[bucket].flush(&data.temp_dir)
     }
 
-    #[cfg(feature = "support_kmer_counters")]
-    type HashMapTempColorIndex = usize;
+    type HashMapTempColorIndex<I: TempColorIndexStorage> = I;
 
-    #[cfg(not(feature = "support_kmer_counters"))]
-    type HashMapTempColorIndex = ();
-
-    fn new_color_index() -> Self::HashMapTempColorIndex {
+    fn new_color_index<I: TempColorIndexStorage>() -> Self::HashMapTempColorIndex<I> {
         Default::default()
     }
 
-    fn process_colors<MH: HashFunctionFactory>(
+    fn process_colors<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         global_colors_table: &Self::GlobalColorsTableWriter,
+        color_subsets: Option<&dyn ColorSubsets>,
         data: &mut Self::ColorsBufferTempStructure,
-        map: &mut FxHashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex>>,
+        map: &mut FxHashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex<I>>>,
         k: usize,
         min_multiplicity: usize,
         max_multiplicity: usize,
+        min_color_multiplicity: usize,
     ) {
         for buffer in data.sequences.iter_mut() {
             data.temp_colors_buffer.clear();
@@ -266,6 +297,8 @@ impl ColorsMergeManager for MultipleColorsManager {
                 }
 
                 let color = ColorIndexType::from_ne_bytes(color_buf);
+                // The kmers counters include the multiplicity, so the color is added once for each occurrence
+                let multiplicity = decode_varint(|| stream.read_u8().ok()).unwrap() as usize;
 
                 let (read_length, only_extra_ending) =
                     decode_varint_flags::<_, typenum::U1>(|| stream.read_u8().ok()).unwrap();
@@ -296,19 +329,23 @@ impl ColorsMergeManager for MultipleColorsManager {
                         tmp
                     };
 
-                    if entry.get_kmer_multiplicity() < min_multiplicity || entry.get_kmer_multiplicity() > max_multiplicity{
-                        continue;
+                    let missing_temp_color = (I::get_color_index(entry) & VISITED_BIT) == 0;
+
+                    // Without a separate color index the counter is replaced by it on the first visit,
+                    // so the multiplicity can be checked only before that
+                    if missing_temp_color || I::KEEPS_MULTIPLICITY {
+                        let multiplicity = entry.get_kmer_multiplicity();
+                        if multiplicity < min_multiplicity || multiplicity > max_multiplicity {
+                            // The reset counter marks the kmer as not solid when extending the unitigs
+                            if !I::KEEPS_MULTIPLICITY {
+                                entry.set_counter_after_check(0);
+                            }
+                            continue;
+                        }
                     }
 
                     let mut entry_count = entry.get_counter();
 
-                    let missing_temp_color = match () {
-                        #[cfg(not(feature = "support_kmer_counters"))]
-                        () => entry_count & VISITED_BIT == 0,
-                        #[cfg(feature = "support_kmer_counters")]
-                        () => (entry.color_index & VISITED_BIT) == 0,
-                    };
-
                     const BIDIRECTIONAL_FLAGS: u8 = READ_FLAG_INCL_BEGIN | READ_FLAG_INCL_END;
 
                     if entry.get_flags() & BIDIRECTIONAL_FLAGS == BIDIRECTIONAL_FLAGS {
@@ -323,17 +360,7 @@ impl ColorsMergeManager for MultipleColorsManager {
                         let colors_count = entry_count;
                         let start_temp_color_index = data.temp_colors_buffer.len();
 
-                        match () {
-                            #[cfg(not(feature = "support_kmer_counters"))]
-                            () => {
-                                entry_count = VISITED_BIT | start_temp_color_index;
-                                entry.set_counter_after_check(entry_count);
-                            }
-                            #[cfg(feature = "support_kmer_counters")]
-                            () => {
-                                entry.color_index = VISITED_BIT | start_temp_color_index;
-                            }
-                        };
+                        I::set_color_index(entry, VISITED_BIT | start_temp_color_index);
 
                         data.temp_colors_buffer
                             .resize(data.temp_colors_buffer.len() + colors_count + 1, 0);
@@ -341,52 +368,99 @@ impl ColorsMergeManager for MultipleColorsManager {
                         data.temp_colors_buffer[start_temp_color_index] = 1;
                     }
 
-                    let position = match () {
-                        #[cfg(not(feature = "support_kmer_counters"))]
-                        () => entry_count & !VISITED_BIT,
-                        #[cfg(feature = "support_kmer_counters")]
-                        () => entry.color_index & !VISITED_BIT,
-                    };
+                    let position = I::get_color_index(entry) & !VISITED_BIT;
 
                     let col_count = data.temp_colors_buffer[position] as usize;
-                    data.temp_colors_buffer[position] += 1;
+                    data.temp_colors_buffer[position] += multiplicity as ColorIndexType;
+
+                    let colors_end = position + col_count + multiplicity;
 
                     assert_eq!(data.temp_colors_buffer[position + col_count], 0);
-                    data.temp_colors_buffer[position + col_count] = color;
+                    data.temp_colors_buffer[(position + col_count)..colors_end].fill(color);
 
-                    let has_all_colors = (position + col_count + 1)
-                        == data.temp_colors_buffer.len()
-                        || data.temp_colors_buffer[position + col_count + 1] != 0;
+                    let has_all_colors = colors_end == data.temp_colors_buffer.len()
+                        || data.temp_colors_buffer[colors_end] != 0;
 
                     // All colors were added, let's assign the final color
                     if has_all_colors {
-                        let colors_range = &mut data.temp_colors_buffer
-                            [(position + 1)..(position + col_count + 1)];
+                        let colors_range = &mut data.temp_colors_buffer[(position + 1)..colors_end];
 
                         colors_range.sort_unstable();
 
+                        // The color subsets of the input graphs are sorted after the single colors
+                        let single_colors_count =
+                            colors_range.partition_point(|color| color & COLOR_SUBSET_FLAG == 0);
+                        let (single_colors, subsets) =
+                            colors_range.split_at_mut(single_colors_count);
+                        let has_subsets = !subsets.is_empty();
+
+                        let (unique_colors_count, dropped_occurrences) =
+                            if min_color_multiplicity > 1 {
+                                let (kept_colors, kept_occurrences) =
+                                    retain_frequent_colors(single_colors, min_color_multiplicity);
+                                (kept_colors, single_colors.len() - kept_occurrences)
+                            } else {
+                                (single_colors.nq_partition_dedup().0.len(), 0)
+                            };
+
                         // Get the new partition indexes, start to dedup last element
-                        let new_partition = (position + 1)
-                            ..(position + 1 + colors_range.nq_partition_dedup().0.len());
+                        let new_partition = (position + 1)..(position + 1 + unique_colors_count);
+
+                        // The kmers of the input graphs are already solid, so the colors of their subsets are always kept
+                        if has_subsets {
+                            let subsets_count = subsets.nq_partition_dedup().0.len();
+                            let color_subsets = color_subsets.expect(
+                                "The color subsets of the input sequences are not available",
+                            );
+
+                            data.expanded_colors.clear();
+                            data.expanded_colors
+                                .extend_from_slice(&single_colors[..unique_colors_count]);
+
+                            for subset in &subsets[..subsets_count] {
+                                let subset = subset & !COLOR_SUBSET_FLAG;
+                                if !data.subsets_cache.contains_key(&subset) {
+                                    if data.subsets_cache_colors >= SUBSETS_CACHE_MAX_COLORS {
+                                        data.subsets_cache.clear();
+                                        data.subsets_cache_colors = 0;
+                                    }
+                                    let mut colors = vec![];
+                                    color_subsets.get_colors(subset, &mut colors);
+                                    data.subsets_cache_colors += colors.len();
+                                    data.subsets_cache.insert(subset, colors);
+                                }
+                                data.expanded_colors
+                                    .extend_from_slice(&data.subsets_cache[&subset]);
+                            }
 
-                        let unique_colors = &data.temp_colors_buffer[new_partition.clone()];
+                            data.expanded_colors.sort_unstable();
+                            data.expanded_colors.dedup();
+                        }
+
+                        // The kmer is not solid in any of its colors
+                        if new_partition.is_empty() && !has_subsets {
+                            entry.set_counter_after_check(0);
+                            continue;
+                        }
+
+                        // The counter is replaced by the color index if the multiplicities are not kept
+                        if dropped_occurrences > 0 && I::KEEPS_MULTIPLICITY {
+                            remove_dropped_occurrences(entry, dropped_occurrences);
+                        }
 
                         // Assign the subset color index to the current kmer
-                        if unique_colors != &data.temp_colors_buffer[last_partition.clone()] {
-                            last_color = global_colors_table.get_id(unique_colors);
+                        if has_subsets {
+                            last_color = global_colors_table.get_id(&data.expanded_colors);
+                            last_partition = 0..0;
+                        } else if data.temp_colors_buffer[new_partition.clone()]
+                            != data.temp_colors_buffer[last_partition.clone()]
+                        {
+                            last_color = global_colors_table
+                                .get_id(&data.temp_colors_buffer[new_partition.clone()]);
                             last_partition = new_partition;
                         }
 
-                        match () {
-                            #[cfg(not(feature = "support_kmer_counters"))]
-                            () => {
-                                entry.set_counter_after_check(VISITED_BIT | (last_color as usize));
-                            }
-                            #[cfg(feature = "support_kmer_counters")]
-                            () => {
-                                entry.color_index = VISITED_BIT | (last_color as usize);
-                            }
-                        };
+                        I::set_color_index(entry, VISITED_BIT | (last_color as usize));
                     }
                 }
             }
@@ -406,9 +480,9 @@ impl ColorsMergeManager for MultipleColorsManager {
         ts.colors.clear();
     }
 
-    fn extend_forward(
+    fn extend_forward<I: TempColorIndexStorage>(
         ts: &mut Self::TempUnitigColorStructure,
-        entry: &MapEntry<Self::HashMapTempColorIndex>,
+        entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
     ) {
         let kmer_color = get_entry_color(entry);
 
@@ -425,9 +499,9 @@ impl ColorsMergeManager for MultipleColorsManager {
         });
     }
 
-    fn extend_backward(
+    fn extend_backward<I: TempColorIndexStorage>(
         ts: &mut Self::TempUnitigColorStructure,
-        entry: &MapEntry<Self::HashMapTempColorIndex>,
+        entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
     ) {
         let kmer_color = get_entry_color(entry);
 
@@ -444,6 +518,13 @@ impl ColorsMergeManager for MultipleColorsManager {
         });
     }
 
+    #[inline(always)]
+    fn get_kmer_color_subset<I: TempColorIndexStorage>(
+        entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
+    ) -> ColorIndexType {
+        get_entry_color(entry)
+    }
+
     fn join_structures<const REVERSE: bool>(
         dest: &mut Self::TempUnitigColorStructure,
         src: &Self::PartialUnitigsColorStructure,
@@ -532,11 +613,11 @@ impl ColorsMergeManager for MultipleColorsManager {
         }
     }
 
-    fn debug_colors<MH: HashFunctionFactory>(
+    fn debug_colors<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         color: &Self::PartialUnitigsColorStructure,
         colors_buffer: &<Self::PartialUnitigsColorStructure as SequenceExtraDataTempBufferManagement>::TempBuffer,
         seq: &[u8],
-        hmap: &HashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex>>,
+        hmap: &HashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex<I>>>,
     ) {
         let mut storage = vec![];
         let rind = CompressedReadIndipendent::from_plain(seq, &mut storage);
@@ -691,8 +772,10 @@ impl IdentSequenceWriter for UnitigColorData {
         stream: &mut impl Write,
         extra_buffer: &Self::TempBuffer,
     ) {
+        // Color runs are written as an optional tag of the segment line:
+        // CS:Z:<color subset (hex)>:<kmers count>[:<color subset (hex)>:<kmers count>...]
         if self.slice.len() > 0 {
-            write!(stream, "CS",).unwrap();
+            write!(stream, "\tCS:Z").unwrap();
         }
 
         for i in self.slice.clone() {
@@ -709,6 +792,11 @@ impl IdentSequenceWriter for UnitigColorData {
     fn parse_as_ident<'a>(ident: &[u8], colors_buffer: &mut Self::TempBuffer) -> Option<Self> {
         let mut colors_count = 0;
         for col_pos in ident.find_iter(b"C:") {
+            // Skip other tags ending with 'C', as KC:i:
+            if col_pos > 0 && ident[col_pos - 1] != b' ' {
+                continue;
+            }
+
             let (color_index, next_pos) = ColorIndexType::from_radix_16(&ident[(col_pos + 2)..]);
 
             let kmers_count = ColorCounterType::from_radix_10(&ident[(col_pos + next_pos + 3)..]).0;
@@ -730,10 +818,15 @@ impl IdentSequenceWriter for UnitigColorData {
     #[allow(unused_variables)]
     fn parse_as_gfa<'a>(ident: &[u8], extra_buffer: &mut Self::TempBuffer) -> Option<Self> {
         let mut colors_count = 0;
-        if let Some(mut col_pos) = ident.find(b"CA:") {
-            col_pos += 3;
+        if let Some(mut col_pos) = ident.find(b"CS:Z:") {
+            col_pos += 5;
+            let tag_end = ident[col_pos..]
+                .iter()
+                .position(|c| *c == b'\t')
+                .map(|p| col_pos + p)
+                .unwrap_or(ident.len());
 
-            for (col_string, col_len) in ident[col_pos..].split(|c| *c == b':').tuples() {
+            for (col_string, col_len) in ident[col_pos..tag_end].split(|c| *c == b':').tuples() {
                 let color_index = ColorIndexType::from_radix_16(col_string).0;
                 let kmers_count = ColorCounterType::from_radix_10(col_len).0;
                 extra_buffer.colors.push(KmerSerializedColor {
@@ -752,3 +845,42 @@ impl IdentSequenceWriter for UnitigColorData {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{remove_dropped_occurrences, retain_frequent_colors};
+    use config::{READ_FLAG_INCL_BEGIN, READ_FLAG_INCL_END};
+    use structs::map_entry::MapEntry;
+
+    #[test]
+    fn kmer_solid_only_in_one_color() {
+        // The kmer appears 3 times with color 2 and once with color 5
+        let mut colors = [2, 2, 2, 5];
+        let (kept_colors, kept_occurrences) = retain_frequent_colors(&mut colors, 2);
+        assert_eq!(&colors[..kept_colors], &[2]);
+        assert_eq!(kept_occurrences, 3);
+
+        let mut entry = MapEntry::new(0usize);
+        for _ in 0..colors.len() {
+            entry.incr();
+        }
+        remove_dropped_occurrences(&mut entry, colors.len() - kept_occurrences);
+        assert_eq!(entry.get_kmer_multiplicity(), 3);
+
+        // With both the partial sequences endings each occurrence is counted two times
+        let mut entry = MapEntry::new(0usize);
+        entry.update_flags(READ_FLAG_INCL_BEGIN | READ_FLAG_INCL_END);
+        for _ in 0..colors.len() * 2 {
+            entry.incr();
+        }
+        remove_dropped_occurrences(&mut entry, colors.len() - kept_occurrences);
+        assert_eq!(entry.get_kmer_multiplicity(), 3);
+    }
+
+    #[test]
+    fn kmer_solid_in_all_colors() {
+        let mut colors = [1, 1, 4, 4, 4];
+        assert_eq!(retain_frequent_colors(&mut colors, 2), (2, 5));
+        assert_eq!(&colors[..2], &[1, 4]);
+    }
+}
@@ -1,8 +1,9 @@
 use crate::colors_manager::ColorsMergeManager;
 use crate::storage::deserializer::ColorsDeserializer;
+use crate::storage::ColormapEncoding;
 use crate::DefaultColorsSerializer;
 use byteorder::ReadBytesExt;
-use config::{ColorCounterType, ColorIndexType};
+use config::{ColorCounterType, ColorIndexType, MultiplicityCounterType};
 use hashbrown::HashMap;
 use hashes::HashFunctionFactory;
 use io::compressed_read::CompressedRead;
@@ -10,13 +11,14 @@ use io::concurrent::structured_sequences::IdentSequenceWriter;
 use io::concurrent::temp_reads::extra_data::{
     SequenceExtraData, SequenceExtraDataTempBufferManagement,
 };
+use io::sequences_stream::ColorSubsets;
 use io::varint::{decode_varint, encode_varint, VARINT_MAX_SIZE};
 use rustc_hash::FxHashMap;
 use std::collections::VecDeque;
 use std::io::{Read, Write};
 use std::ops::Range;
 use std::path::Path;
-use structs::map_entry::MapEntry;
+use structs::map_entry::{MapEntry, TempColorIndexStorage};
 
 pub struct SingleColorManager;
 
@@ -28,6 +30,7 @@ impl ColorsMergeManager for SingleColorManager {
     fn create_colors_table(
         _path: impl AsRef<Path>,
         _color_names: &[String],
+        _encoding: ColormapEncoding,
     ) -> anyhow::Result<Self::GlobalColorsTableWriter> {
         Ok(())
     }
@@ -46,11 +49,11 @@ impl ColorsMergeManager for SingleColorManager {
 
     fn reinit_temp_buffer_structure(_data: &mut Self::ColorsBufferTempStructure) {}
 
-    fn add_temp_buffer_structure_el<MH: HashFunctionFactory>(
+    fn add_temp_buffer_structure_el<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         _data: &mut Self::ColorsBufferTempStructure,
         kmer_color: &ColorIndexType,
         _el: (usize, MH::HashTypeUnextendable),
-        entry: &mut MapEntry<Self::HashMapTempColorIndex>,
+        entry: &mut MapEntry<Self::HashMapTempColorIndex<I>>,
     ) {
         assert!(
             entry.color_index.color_index == ColorIndexType::MAX
@@ -66,24 +69,27 @@ impl ColorsMergeManager for SingleColorManager {
         _k: usize,
         _m: usize,
         _flags: u8,
+        _multiplicity: MultiplicityCounterType,
     ) {
     }
 
-    type HashMapTempColorIndex = SingleHashMapTempColorIndex;
+    type HashMapTempColorIndex<I: TempColorIndexStorage> = SingleHashMapTempColorIndex;
 
-    fn new_color_index() -> Self::HashMapTempColorIndex {
+    fn new_color_index<I: TempColorIndexStorage>() -> Self::HashMapTempColorIndex<I> {
         SingleHashMapTempColorIndex {
             color_index: ColorIndexType::MAX,
         }
     }
 
-    fn process_colors<MH: HashFunctionFactory>(
+    fn process_colors<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         _global_colors_table: &Self::GlobalColorsTableWriter,
+        _color_subsets: Option<&dyn ColorSubsets>,
         _data: &mut Self::ColorsBufferTempStructure,
-        _map: &mut FxHashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex>>,
+        _map: &mut FxHashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex<I>>>,
         _k: usize,
         _min_multiplicity: usize,
         _max_multiplicity: usize,
+        _min_color_multiplicity: usize,
     ) {
     }
 
@@ -100,20 +106,26 @@ impl ColorsMergeManager for SingleColorManager {
         ts.colors.clear();
     }
 
-    fn extend_forward(
+    fn extend_forward<I: TempColorIndexStorage>(
         _ts: &mut Self::TempUnitigColorStructure,
-        _entry: &MapEntry<Self::HashMapTempColorIndex>,
+        _entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
     ) {
         panic!("Unsupported!");
     }
 
-    fn extend_backward(
+    fn extend_backward<I: TempColorIndexStorage>(
         _ts: &mut Self::TempUnitigColorStructure,
-        _entry: &MapEntry<Self::HashMapTempColorIndex>,
+        _entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
     ) {
         panic!("Unsupported!");
     }
 
+    fn get_kmer_color_subset<I: TempColorIndexStorage>(
+        _entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
+    ) -> ColorIndexType {
+        panic!("Unsupported!");
+    }
+
     fn join_structures<const REVERSE: bool>(
         _dest: &mut Self::TempUnitigColorStructure,
         _src: &Self::PartialUnitigsColorStructure,
@@ -139,11 +151,11 @@ impl ColorsMergeManager for SingleColorManager {
         unimplemented!();
     }
 
-    fn debug_colors<MH: HashFunctionFactory>(
+    fn debug_colors<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         _color: &Self::PartialUnitigsColorStructure,
         _colors_buffer: &<Self::PartialUnitigsColorStructure as SequenceExtraDataTempBufferManagement>::TempBuffer,
         _seq: &[u8],
-        _hmap: &HashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex>>,
+        _hmap: &HashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex<I>>>,
     ) {
         unimplemented!()
     }
@@ -2,7 +2,8 @@ use crate::colors_manager::{
     ColorsManager, ColorsMergeManager, ColorsParser, MinimizerBucketingSeqColorData,
 };
 use crate::parsers::SingleSequenceInfo;
-use config::{BucketIndexType, ColorCounterType};
+use crate::storage::ColormapEncoding;
+use config::{BucketIndexType, ColorCounterType, ColorIndexType, MultiplicityCounterType};
 use dynamic_dispatch::dynamic_dispatch;
 use hashbrown::HashMap;
 use hashes::HashFunctionFactory;
@@ -11,12 +12,13 @@ use io::concurrent::structured_sequences::IdentSequenceWriter;
 use io::concurrent::temp_reads::extra_data::{
     HasEmptyExtraBuffer, SequenceExtraData, SequenceExtraDataTempBufferManagement,
 };
+use io::sequences_stream::ColorSubsets;
 use parallel_processor::fast_smart_bucket_sort::FastSortable;
 use rustc_hash::FxHashMap;
 use std::io::{Read, Write};
 use std::ops::Range;
 use std::path::Path;
-use structs::map_entry::MapEntry;
+use structs::map_entry::{MapEntry, TempColorIndexStorage};
 
 #[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default)]
 pub struct NonColoredManager;
@@ -35,6 +37,10 @@ impl ColorsManager for NonColoredManager {
         panic!("Cannot get color bucket for non colored manager!");
     }
 
+    fn get_color_subset(_color: &Self::SingleKmerColorDataType) -> Option<ColorIndexType> {
+        None
+    }
+
     type ColorsParserType = NonColoredManager;
     type ColorsMergeManagerType = NonColoredManager;
 }
@@ -126,6 +132,7 @@ impl ColorsMergeManager for NonColoredManager {
     fn create_colors_table(
         _path: impl AsRef<Path>,
         _color_names: &[String],
+        _encoding: ColormapEncoding,
     ) -> anyhow::Result<Self::GlobalColorsTableWriter> {
         Ok(())
     }
@@ -147,11 +154,11 @@ impl ColorsMergeManager for NonColoredManager {
     fn reinit_temp_buffer_structure(_data: &mut Self::ColorsBufferTempStructure) {}
 
     #[inline(always)]
-    fn add_temp_buffer_structure_el<MH: HashFunctionFactory>(
+    fn add_temp_buffer_structure_el<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         _data: &mut Self::ColorsBufferTempStructure,
         _kmer_color: &Self::SingleKmerColorDataType,
         _el: (usize, <MH as HashFunctionFactory>::HashTypeUnextendable),
-        _entry: &mut MapEntry<Self::HashMapTempColorIndex>,
+        _entry: &mut MapEntry<Self::HashMapTempColorIndex<I>>,
     ) {
     }
 
@@ -162,27 +169,30 @@ impl ColorsMergeManager for NonColoredManager {
         _k: usize,
         _m: usize,
         _flags: u8,
+        _multiplicity: MultiplicityCounterType,
     ) {
     }
 
-    type HashMapTempColorIndex = NonColoredManager;
+    type HashMapTempColorIndex<I: TempColorIndexStorage> = NonColoredManager;
 
     #[inline(always)]
-    fn new_color_index() -> Self::HashMapTempColorIndex {
+    fn new_color_index<I: TempColorIndexStorage>() -> Self::HashMapTempColorIndex<I> {
         NonColoredManager
     }
 
     #[inline(always)]
-    fn process_colors<MH: HashFunctionFactory>(
+    fn process_colors<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         _global_colors_table: &Self::GlobalColorsTableWriter,
+        _color_subsets: Option<&dyn ColorSubsets>,
         _data: &mut Self::ColorsBufferTempStructure,
         _map: &mut FxHashMap<
             <MH as HashFunctionFactory>::HashTypeUnextendable,
-            MapEntry<Self::HashMapTempColorIndex>,
+            MapEntry<Self::HashMapTempColorIndex<I>>,
         >,
         _k: usize,
         _min_multiplicity: usize,
         _max_multiplicity: usize,
+        _min_color_multiplicity: usize,
     ) {
         unreachable!()
     }
@@ -199,19 +209,26 @@ impl ColorsMergeManager for NonColoredManager {
     fn reset_unitig_color_structure(_ts: &mut Self::TempUnitigColorStructure) {}
 
     #[inline(always)]
-    fn extend_forward(
+    fn extend_forward<I: TempColorIndexStorage>(
         _ts: &mut Self::TempUnitigColorStructure,
-        _entry: &MapEntry<Self::HashMapTempColorIndex>,
+        _entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
     ) {
     }
 
     #[inline(always)]
-    fn extend_backward(
+    fn extend_backward<I: TempColorIndexStorage>(
         _ts: &mut Self::TempUnitigColorStructure,
-        _entry: &MapEntry<Self::HashMapTempColorIndex>,
+        _entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
     ) {
     }
 
+    #[inline(always)]
+    fn get_kmer_color_subset<I: TempColorIndexStorage>(
+        _entry: &MapEntry<Self::HashMapTempColorIndex<I>>,
+    ) -> ColorIndexType {
+        0
+    }
+
     #[inline(always)]
     fn join_structures<const REVERSE: bool>(
         _dest: &mut Self::TempUnitigColorStructure,
@@ -234,11 +251,11 @@ impl ColorsMergeManager for NonColoredManager {
     }
 
     fn debug_tucs(_str: &Self::TempUnitigColorStructure, _seq: &[u8]) {}
-    fn debug_colors<MH: HashFunctionFactory>(
+    fn debug_colors<MH: HashFunctionFactory, I: TempColorIndexStorage>(
         _color: &Self::PartialUnitigsColorStructure,
         _colors_buffer: &<Self::PartialUnitigsColorStructure as SequenceExtraDataTempBufferManagement>::TempBuffer,
         _seq: &[u8],
-        _hmap: &HashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex>>,
+        _hmap: &HashMap<MH::HashTypeUnextendable, MapEntry<Self::HashMapTempColorIndex<I>>>,
     ) {
     }
 }
@@ -342,4 +342,31 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn graph_multiple_colors_gfa_tag() {
+        let input_tags = "LN:i:73\tCS:Z:1:12:a:1:3:60\tKC:i:80";
+
+        let mut extra_buffer = UnitigsSerializerTempBuffer { colors: vec![] };
+
+        let colors = MinBkMultipleColors::create(
+            SingleSequenceInfo {
+                static_color: 0,
+                sequence_ident: SequenceIdent::GFA {
+                    colors: input_tags.as_bytes(),
+                },
+            },
+            &mut extra_buffer,
+        );
+
+        assert_eq!(colors.debug_count(), 73);
+        assert_eq!(
+            extra_buffer
+                .colors
+                .iter()
+                .map(|c| (c.color, c.counter))
+                .collect::<Vec<_>>(),
+            vec![(1, 12), (10, 1), (3, 60)]
+        );
+    }
 }
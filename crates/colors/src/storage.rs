@@ -1,16 +1,21 @@
+use crate::storage::roaring::RoaringColorsSerializer;
+use crate::storage::run_length::RunLengthColorsSerializer;
 use crate::storage::serializer::ColorsFlushProcessing;
 use config::ColorIndexType;
 use std::io::Read;
 
 pub mod deserializer;
+pub mod inspector;
 pub mod roaring;
 pub mod run_length;
 pub mod serializer;
+pub mod versions;
 
 pub trait ColorsSerializerTrait: 'static {
     const MAGIC: [u8; 16];
 
-    fn decode_color(reader: impl Read, out_vec: Option<&mut Vec<ColorIndexType>>);
+    /// Decodes the next subset, returning None if its encoding is truncated or invalid
+    fn decode_color(reader: impl Read, out_vec: Option<&mut Vec<ColorIndexType>>) -> Option<()>;
     // fn decode_colors(reader: impl Read) -> ;
 
     fn new(writer: ColorsFlushProcessing, checkpoint_distance: usize, colors_count: u64) -> Self;
@@ -19,3 +24,29 @@ pub trait ColorsSerializerTrait: 'static {
     fn print_stats(&self);
     fn finalize(self) -> ColorsFlushProcessing;
 }
+
+/// The encodings that can be used to store the color subsets of a colormap
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColormapEncoding {
+    /// Differences between consecutive colors, best for small or sparse subsets
+    #[default]
+    RunLength,
+    /// Roaring bitmaps, best for large and dense subsets
+    Roaring,
+}
+
+impl ColormapEncoding {
+    /// The magic stored in the header of the colormaps with this encoding
+    pub fn magic(&self) -> [u8; 16] {
+        match self {
+            ColormapEncoding::RunLength => RunLengthColorsSerializer::MAGIC,
+            ColormapEncoding::Roaring => RoaringColorsSerializer::MAGIC,
+        }
+    }
+
+    pub fn from_magic(magic: &[u8; 16]) -> Option<Self> {
+        [ColormapEncoding::RunLength, ColormapEncoding::Roaring]
+            .into_iter()
+            .find(|encoding| &encoding.magic() == magic)
+    }
+}
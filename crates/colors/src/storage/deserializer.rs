@@ -1,15 +1,40 @@
 use crate::colors_manager::ColorMapReader;
-use crate::storage::serializer::{ColorsFileHeader, ColorsIndexEntry, ColorsIndexMap};
-use crate::storage::ColorsSerializerTrait;
+use crate::storage::serializer::{
+    ColorsFileHeader, ColorsIndexEntry, ColorsIndexMap, STORAGE_VERSION,
+};
+use crate::storage::versions::{find_colormap_decoder, ColormapDecoder};
+use crate::storage::{ColormapEncoding, ColorsSerializerTrait};
 use config::ColorIndexType;
 use desse::Desse;
 use desse::DesseSized;
 use ggcat_logging::UnrecoverableErrorLogging;
+use io::FileIoError;
 use replace_with::replace_with_or_abort;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::marker::PhantomData;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// A colormap file that cannot be decoded
+#[derive(Debug)]
+pub struct CorruptedColormapError {
+    pub file: PathBuf,
+    pub message: String,
+}
+
+impl Display for CorruptedColormapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Colormap file '{}' is corrupted: {}",
+            self.file.display(),
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for CorruptedColormapError {}
 
 pub struct ColorsDeserializer<DS: ColorsSerializerTrait> {
     colormap_file: lz4::Decoder<BufReader<File>>,
@@ -19,6 +44,9 @@ pub struct ColorsDeserializer<DS: ColorsSerializerTrait> {
     current_chunk: ColorsIndexEntry,
     current_chunk_size: ColorIndexType,
     current_index: ColorIndexType,
+    encoding_magic: [u8; 16],
+    storage_version: u64,
+    dynamic_decoder: Option<&'static ColormapDecoder>,
     _phantom: PhantomData<DS>,
 }
 
@@ -27,40 +55,57 @@ unsafe impl<DS: ColorsSerializerTrait> Send for ColorsDeserializer<DS> {}
 
 impl<DS: ColorsSerializerTrait> ColorsDeserializer<DS> {
     pub fn new(path: impl AsRef<Path>, read_color_names: bool) -> anyhow::Result<Self> {
-        let mut file = File::open(path.as_ref()).log_unrecoverable_error_with_data(
-            "Cannot open colors file",
-            path.as_ref().display(),
-        )?;
+        let corrupted = |message: String| CorruptedColormapError {
+            file: path.as_ref().to_path_buf(),
+            message,
+        };
+
+        let mut file = File::open(path.as_ref())
+            .log_unrecoverable_error_with_data("Cannot open colors file", path.as_ref().display())
+            .map_err(|err| FileIoError::new(path.as_ref(), err))?;
 
         let mut header_buffer = [0; ColorsFileHeader::SIZE];
         file.read_exact(&mut header_buffer)
-            .log_unrecoverable_error_with_data("Cannot read header", path.as_ref().display())?;
+            .log_unrecoverable_error_with_data("Cannot read header", path.as_ref().display())
+            .map_err(|err| corrupted(format!("cannot read header: {}", err)))?;
 
         let header: ColorsFileHeader = ColorsFileHeader::deserialize_from(&header_buffer);
-        if header.magic != DS::MAGIC {
-            anyhow::Result::Err(anyhow::anyhow!(
-                "Header mismatch: expected {:?}, got {:?}",
-                DS::MAGIC,
-                header.magic
-            ))
+        let decoder = find_colormap_decoder(path.as_ref(), &header.magic, header.version)
             .log_unrecoverable_error_with_data(
-                "Colors file is corrupted",
+                "Unsupported colors file",
                 path.as_ref().display(),
             )?;
+
+        if header.version < STORAGE_VERSION {
+            ggcat_logging::warn!(
+                "Colormap file '{}' has the outdated storage version {}, it can be converted to the current version with `ggcat colormap upgrade`",
+                path.as_ref().display(),
+                header.version
+            );
         }
 
+        // The colormaps with the current version and the DS encoding are decoded statically,
+        // all the others (other encodings or older versions) with the registered decoders
+        let dynamic_decoder = if header.magic != DS::MAGIC || header.version != STORAGE_VERSION {
+            Some(decoder)
+        } else {
+            None
+        };
+
         let color_names = if read_color_names {
             let mut compressed_stream = lz4::Decoder::new(BufReader::new(file))
                 .log_unrecoverable_error_with_data(
                     "Cannot create LZ4 decoder",
                     path.as_ref().display(),
-                )?;
+                )
+                .map_err(|err| corrupted(format!("cannot decode color names: {}", err)))?;
 
             let color_names: Vec<String> = bincode::deserialize_from(&mut compressed_stream)
                 .log_unrecoverable_error_with_data(
                     "Cannot deserialize color names",
                     path.as_ref().display(),
-                )?;
+                )
+                .map_err(|err| corrupted(format!("cannot decode color names: {}", err)))?;
             file = compressed_stream.finish().0.into_inner();
             color_names
         } else {
@@ -69,19 +114,22 @@ impl<DS: ColorsSerializerTrait> ColorsDeserializer<DS> {
 
         let colors_index: ColorsIndexMap = {
             file.seek(SeekFrom::Start(header.index_offset))
+                .log_unrecoverable_error_with_data("Cannot seek color map", path.as_ref().display())
+                .map_err(|err| corrupted(format!("cannot find the colors index: {}", err)))?;
+            bincode::deserialize_from(&mut file)
                 .log_unrecoverable_error_with_data(
-                    "Cannot seek color map",
+                    "Cannot deserialize color index",
                     path.as_ref().display(),
-                )?;
-            bincode::deserialize_from(&mut file).log_unrecoverable_error_with_data(
-                "Cannot deserialize color index",
-                path.as_ref().display(),
-            )?
+                )
+                .map_err(|err| corrupted(format!("cannot decode the colors index: {}", err)))?
         };
 
-        let first_chunk = colors_index.pairs[0];
+        let Some(&first_chunk) = colors_index.pairs.first() else {
+            return Err(corrupted("the colors index is empty".to_string()).into());
+        };
         file.seek(SeekFrom::Start(first_chunk.file_offset))
-            .log_unrecoverable_error_with_data("Cannot seek color map", path.as_ref().display())?;
+            .log_unrecoverable_error_with_data("Cannot seek color map", path.as_ref().display())
+            .map_err(|err| corrupted(format!("cannot find the colors subsets: {}", err)))?;
 
         let current_chunk_size = colors_index
             .pairs
@@ -103,6 +151,9 @@ impl<DS: ColorsSerializerTrait> ColorsDeserializer<DS> {
             current_chunk: first_chunk,
             current_chunk_size,
             current_index: first_chunk.start_index,
+            encoding_magic: header.magic,
+            storage_version: header.version,
+            dynamic_decoder,
             _phantom: Default::default(),
         })
     }
@@ -147,17 +198,34 @@ impl<DS: ColorsSerializerTrait> ColorsDeserializer<DS> {
         }
     }
 
+    /// Returns the encoding of the color subsets, or None if it is only supported by older versions
+    pub fn encoding(&self) -> Option<ColormapEncoding> {
+        ColormapEncoding::from_magic(&self.encoding_magic)
+    }
+
+    /// Returns true if the colormap has the current storage version
+    pub fn is_current_version(&self) -> bool {
+        self.storage_version == STORAGE_VERSION
+    }
+
+    fn decode_color(&mut self, out_vec: Option<&mut Vec<ColorIndexType>>) {
+        match self.dynamic_decoder {
+            None => DS::decode_color(&mut self.colormap_file, out_vec),
+            Some(decoder) => (decoder.decode_color)(&mut self.colormap_file, out_vec),
+        };
+    }
+
     pub fn get_color_mappings(&mut self, color: ColorIndexType, out_vec: &mut Vec<ColorIndexType>) {
         self.maybe_change_block(color);
 
         while self.current_index < color {
             // Skip the colors
-            DS::decode_color(&mut self.colormap_file, None);
+            self.decode_color(None);
             self.current_index += 1;
         }
 
         // Decode the requested color
-        DS::decode_color(&mut self.colormap_file, Some(out_vec));
+        self.decode_color(Some(out_vec));
         self.current_index += 1;
     }
 }
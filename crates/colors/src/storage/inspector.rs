@@ -0,0 +1,339 @@
+use crate::storage::deserializer::CorruptedColormapError;
+use crate::storage::serializer::{ColorsFileHeader, ColorsIndexEntry, ColorsIndexMap};
+use crate::storage::versions::{find_colormap_decoder, ColormapDecoder};
+use crate::storage::ColorsSerializerTrait;
+use config::ColorIndexType;
+use desse::{Desse, DesseSized};
+use io::FileIoError;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// The header fields and the chunks index of a colormap file
+#[derive(Clone, Debug)]
+pub struct ColormapInfo {
+    /// The encoding of the color subsets, from the file magic
+    pub encoding: String,
+    pub version: u64,
+    pub colors_count: u64,
+    pub subsets_count: u64,
+    pub chunks_count: usize,
+    pub file_size: u64,
+    /// The compressed size of all the color subsets chunks
+    pub subsets_size: u64,
+    pub subsets_uncompressed_size: u64,
+}
+
+impl ColormapInfo {
+    pub fn compression_ratio(&self) -> f64 {
+        self.subsets_uncompressed_size as f64 / self.subsets_size.max(1) as f64
+    }
+}
+
+struct CountingReader<R: Read> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.count += count as u64;
+        Ok(count)
+    }
+}
+
+fn read_header_and_index(path: &Path) -> anyhow::Result<(File, ColorsFileHeader, ColorsIndexMap)> {
+    let corrupted = |message: String| CorruptedColormapError {
+        file: path.to_path_buf(),
+        message,
+    };
+
+    let mut file = File::open(path).map_err(|err| FileIoError::new(path, err))?;
+
+    let mut header_buffer = [0; ColorsFileHeader::SIZE];
+    file.read_exact(&mut header_buffer)
+        .map_err(|err| corrupted(format!("cannot read header: {}", err)))?;
+    let header: ColorsFileHeader = ColorsFileHeader::deserialize_from(&header_buffer);
+
+    file.seek(SeekFrom::Start(header.index_offset))
+        .map_err(|err| corrupted(format!("cannot find the colors index: {}", err)))?;
+    let index: ColorsIndexMap = bincode::deserialize_from(BufReader::new(&mut file))
+        .map_err(|err| corrupted(format!("cannot decode the colors index: {}", err)))?;
+
+    Ok((file, header, index))
+}
+
+/// Reads the header and the chunks index of a colormap, without decoding the color subsets
+pub fn read_colormap_info(path: impl AsRef<Path>) -> anyhow::Result<ColormapInfo> {
+    let path = path.as_ref();
+    let (file, header, index) = read_header_and_index(path)?;
+
+    let file_size = file
+        .metadata()
+        .map_err(|err| FileIoError::new(path, err))?
+        .len();
+
+    Ok(ColormapInfo {
+        encoding: String::from_utf8_lossy(&header.magic).to_string(),
+        version: header.version,
+        colors_count: header.colors_count,
+        subsets_count: header.subsets_count,
+        chunks_count: index.pairs.len(),
+        file_size,
+        subsets_size: index
+            .pairs
+            .first()
+            .map(|chunk| header.index_offset.saturating_sub(chunk.file_offset))
+            .unwrap_or(0),
+        subsets_uncompressed_size: header.total_uncompressed_size,
+    })
+}
+
+fn check_index(header: &ColorsFileHeader, index: &ColorsIndexMap, issues: &mut Vec<String>) {
+    if index.subsets_count != header.subsets_count {
+        issues.push(format!(
+            "the index has {} subsets, but the header declares {}",
+            index.subsets_count, header.subsets_count
+        ));
+    }
+
+    match index.pairs.first() {
+        None if header.subsets_count > 0 => issues.push("the colors index is empty".to_string()),
+        Some(first) if first.start_index != 0 => issues.push(format!(
+            "the first chunk starts at subset {} instead of 0",
+            first.start_index
+        )),
+        _ => {}
+    }
+
+    for (chunk_idx, pair) in index.pairs.windows(2).enumerate() {
+        if pair[1].start_index <= pair[0].start_index || pair[1].file_offset <= pair[0].file_offset
+        {
+            issues.push(format!(
+                "chunk {} (subset {}, offset {}) is not after chunk {} (subset {}, offset {})",
+                chunk_idx + 1,
+                pair[1].start_index,
+                pair[1].file_offset,
+                chunk_idx,
+                pair[0].start_index,
+                pair[0].file_offset
+            ));
+        }
+    }
+
+    for (chunk_idx, chunk) in index.pairs.iter().enumerate() {
+        if chunk.file_offset < ColorsFileHeader::SIZE as u64
+            || chunk.file_offset >= header.index_offset
+        {
+            issues.push(format!(
+                "chunk {} has offset {} outside of the subsets data",
+                chunk_idx, chunk.file_offset
+            ));
+        }
+        if chunk.start_index as u64 >= header.subsets_count {
+            issues.push(format!(
+                "chunk {} starts at subset {}, but the colormap has only {} subsets",
+                chunk_idx, chunk.start_index, header.subsets_count
+            ));
+        }
+    }
+}
+
+fn verify_chunk(
+    decoder: &ColormapDecoder,
+    file: &mut File,
+    chunk: &ColorsIndexEntry,
+    subsets_end: u64,
+    file_offset_end: u64,
+    colors_count: u64,
+    uncompressed_size: &mut u64,
+) -> Result<(), String> {
+    file.seek(SeekFrom::Start(chunk.file_offset))
+        .map_err(|err| format!("cannot seek to the chunk: {}", err))?;
+
+    let mut reader = CountingReader {
+        inner: lz4::Decoder::new(BufReader::new(
+            file.take(file_offset_end - chunk.file_offset),
+        ))
+        .map_err(|err| format!("cannot decompress the chunk: {}", err))?,
+        count: 0,
+    };
+
+    let mut subset = vec![];
+    for subset_index in chunk.start_index as u64..subsets_end {
+        (decoder.decode_color)(&mut reader, Some(&mut subset)).ok_or_else(|| {
+            format!(
+                "cannot decode subset {}, the data is truncated or corrupted",
+                subset_index
+            )
+        })?;
+
+        if subset.is_empty() || subset.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(format!("subset {} is not sorted", subset_index));
+        }
+        if let Some(&color) = subset.last().filter(|c| **c as u64 >= colors_count) {
+            return Err(format!(
+                "subset {} contains color {}, but the colormap has only {} colors",
+                subset_index, color, colors_count
+            ));
+        }
+    }
+
+    // Also ensures that the lz4 frame checksum is verified
+    let mut trailing = [0];
+    match reader.read(&mut trailing) {
+        Ok(0) => {}
+        Ok(_) => return Err("unexpected data after the last subset".to_string()),
+        Err(err) => return Err(format!("cannot decompress the chunk: {}", err)),
+    }
+
+    *uncompressed_size += reader.count;
+    Ok(())
+}
+
+/// Checks that a colormap is complete and that all its color subsets can be decoded.
+/// Returns the list of the issues found, empty if the colormap is valid
+pub fn verify_colormap(path: impl AsRef<Path>) -> anyhow::Result<Vec<String>> {
+    let path = path.as_ref();
+    let (mut file, header, index) = read_header_and_index(path)?;
+    let mut issues = vec![];
+
+    let decoder = match find_colormap_decoder(path, &header.magic, header.version) {
+        Ok(decoder) => decoder,
+        Err(err) => {
+            issues.push(err.to_string());
+            return Ok(issues);
+        }
+    };
+
+    let file_size = file
+        .metadata()
+        .map_err(|err| FileIoError::new(path, err))?
+        .len();
+    if file_size != header.total_size {
+        issues.push(format!(
+            "the file size is {} bytes, but the header declares {} bytes",
+            file_size, header.total_size
+        ));
+    }
+
+    file.seek(SeekFrom::Start(ColorsFileHeader::SIZE as u64))
+        .map_err(|err| FileIoError::new(path, err))?;
+    let color_names: Result<Vec<String>, String> = lz4::Decoder::new(BufReader::new(&mut file))
+        .map_err(|err| err.to_string())
+        .and_then(|mut decoder| {
+            bincode::deserialize_from(&mut decoder).map_err(|err| err.to_string())
+        });
+    match color_names {
+        Ok(names) if names.len() as u64 != header.colors_count => issues.push(format!(
+            "the colormap has {} color names, but the header declares {} colors",
+            names.len(),
+            header.colors_count
+        )),
+        Ok(_) => {}
+        Err(err) => issues.push(format!("cannot decode the color names: {}", err)),
+    }
+
+    check_index(&header, &index, &mut issues);
+    if !issues.is_empty() {
+        // The chunks cannot be located reliably
+        return Ok(issues);
+    }
+
+    let mut uncompressed_size = 0;
+    for (chunk_idx, chunk) in index.pairs.iter().enumerate() {
+        let next_chunk = index.pairs.get(chunk_idx + 1);
+        let subsets_end = next_chunk
+            .map(|c| c.start_index as u64)
+            .unwrap_or(header.subsets_count);
+
+        if let Err(issue) = verify_chunk(
+            decoder,
+            &mut file,
+            chunk,
+            subsets_end,
+            next_chunk
+                .map(|c| c.file_offset)
+                .unwrap_or(header.index_offset),
+            header.colors_count,
+            &mut uncompressed_size,
+        ) {
+            issues.push(format!(
+                "chunk {} (subsets {}..{}): {}",
+                chunk_idx, chunk.start_index, subsets_end, issue
+            ));
+        }
+    }
+
+    if issues.is_empty() && uncompressed_size != header.total_uncompressed_size {
+        issues.push(format!(
+            "the subsets have {} uncompressed bytes, but the header declares {} bytes",
+            uncompressed_size, header.total_uncompressed_size
+        ));
+    }
+
+    Ok(issues)
+}
+
+/// Iterates over all the color subsets of a colormap, in order
+pub fn read_colormap_subsets<DS: ColorsSerializerTrait>(
+    path: impl AsRef<Path>,
+) -> anyhow::Result<impl Iterator<Item = (ColorIndexType, Vec<ColorIndexType>)>> {
+    use crate::colors_manager::ColorMapReader;
+    use crate::storage::deserializer::ColorsDeserializer;
+
+    let mut deserializer = ColorsDeserializer::<DS>::new(path, false)?;
+    let subsets_count = deserializer.colors_subsets_count() as ColorIndexType;
+
+    Ok((0..subsets_count).map(move |subset_index| {
+        let mut colors = vec![];
+        deserializer.get_color_mappings(subset_index, &mut colors);
+        (subset_index, colors)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::inspector::{read_colormap_info, read_colormap_subsets, verify_colormap};
+    use crate::storage::serializer::ColorsSerializer;
+    use crate::DefaultColorsSerializer;
+    use utils::temp_dir::TempDir;
+
+    #[test]
+    fn verify_colormap_corruption() {
+        let dir = TempDir::new("ggcat-colormap-test");
+        let colormap = dir.join("graph.colors.dat");
+
+        let subsets = vec![vec![0], vec![1, 2], vec![0, 1, 2, 3], vec![3]];
+        {
+            let names: Vec<_> = (0..4).map(|c| format!("color{}", c)).collect();
+            let serializer =
+                ColorsSerializer::<DefaultColorsSerializer>::new(&colormap, &names).unwrap();
+            for subset in &subsets {
+                serializer.serialize_colors(subset);
+            }
+        }
+
+        let info = read_colormap_info(&colormap).unwrap();
+        assert_eq!(info.colors_count, 4);
+        assert_eq!(info.subsets_count, subsets.len() as u64);
+        assert!(verify_colormap(&colormap).unwrap().is_empty());
+        assert_eq!(
+            read_colormap_subsets::<DefaultColorsSerializer>(&colormap)
+                .unwrap()
+                .map(|(_, colors)| colors)
+                .collect::<Vec<_>>(),
+            subsets
+        );
+
+        let mut data = std::fs::read(&colormap).unwrap();
+        data.push(0);
+        std::fs::write(&colormap, &data).unwrap();
+        assert_eq!(verify_colormap(&colormap).unwrap().len(), 1);
+
+        data.truncate(data.len() - 10);
+        std::fs::write(&colormap, &data).unwrap();
+        assert!(verify_colormap(&colormap).is_err());
+    }
+}
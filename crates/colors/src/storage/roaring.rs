@@ -1,155 +1,71 @@
-#![allow(warnings)]
+use crate::async_slice_queue::AsyncSliceQueue;
 use crate::storage::serializer::ColorsFlushProcessing;
 use crate::storage::ColorsSerializerTrait;
 use config::ColorIndexType;
-use io::chunks_writer::ChunksWriter;
-use parking_lot::Mutex;
+use config::DEFAULT_OUTPUT_BUFFER_SIZE;
 use roaring::RoaringBitmap;
+use std::cell::UnsafeCell;
 use std::io::Read;
-use std::sync::atomic::{AtomicU32, Ordering};
 
-struct RoaringBitmapInstance {
-    bitmap: RoaringBitmap,
-    offset: ColorIndexType,
-    colors_count: u64,
-    checkpoint_distance: u64,
-    stride: ColorIndexType,
-    last_color: ColorIndexType,
-}
-
-impl RoaringBitmapInstance {
-    fn new(
-        colors_count: u64,
-        checkpoint_distance: u64,
-        offset: ColorIndexType,
-        stride: ColorIndexType,
-    ) -> Self {
-        todo!("Fix meaning of 'stride'!");
-        Self {
-            bitmap: RoaringBitmap::new(),
-            offset,
-            colors_count,
-            checkpoint_distance,
-            stride,
-            last_color: 0,
-        }
-    }
-
-    fn try_append(
-        &mut self,
-        color_index: ColorIndexType,
-        colors: impl Iterator<Item = ColorIndexType>,
-        writer: &ColorsFlushProcessing,
-    ) -> bool {
-        let base_color = color_index - self.offset;
-
-        // Another append is in queue and the current is not the first one
-        if base_color > self.last_color + self.stride {
-            return false;
-        }
-
-        self.last_color = base_color;
-
-        assert_eq!(base_color % self.stride, 0);
-        let strided_color = base_color / self.stride;
-
-        let local_position = strided_color * (self.colors_count as u32);
-
-        self.bitmap
-            .append(colors.map(|c| local_position + c))
-            .unwrap();
-
-        // Flush the partial bitmap
-        if strided_color >= self.checkpoint_distance as u32 {
-            ggcat_logging::info!("Flushing with offset: {}", self.offset);
-            self.flush(writer);
-        }
-
-        true
-    }
-
-    fn flush(&mut self, writer: &ColorsFlushProcessing) {
-        let mut pdata = writer.start_processing();
-        self.bitmap
-            .serialize_into(writer.get_stream(&mut pdata))
-            .unwrap();
-        writer.end_processing(pdata, self.offset);
-        self.offset += self.last_color;
-        self.last_color = 0;
-        self.bitmap.clear();
-    }
+/// Stores each color subset as a standalone roaring bitmap, that is usually more compact
+/// than the run-length encoding for large and dense subsets
+pub struct RoaringColorsSerializer {
+    async_buffer: AsyncSliceQueue<u8, ColorsFlushProcessing>,
 }
 
-pub struct RoaringColorsSerializer {
-    colors_count: u64,
-    roaring_bitmaps: Vec<Mutex<RoaringBitmapInstance>>,
-    writer: ColorsFlushProcessing,
-    colors_index: AtomicU32,
+thread_local! {
+    static TEMP_BITMAP_BUFFER: UnsafeCell<Vec<u8>> = const { UnsafeCell::new(Vec::new()) };
 }
 
 impl ColorsSerializerTrait for RoaringColorsSerializer {
     const MAGIC: [u8; 16] = *b"GGCAT_CMAP_ROARG";
 
-    // FIXME: Implement!
-    fn decode_color(_reader: impl Read, _out_vec: Option<&mut Vec<u32>>) {
-        todo!()
+    fn decode_color(reader: impl Read, out_vec: Option<&mut Vec<u32>>) -> Option<()> {
+        // The serialized bitmaps are self-delimiting, so the reader is left at the start of the next subset
+        let bitmap = RoaringBitmap::deserialize_from(reader).ok()?;
+        if bitmap.is_empty() {
+            return None;
+        }
+        if let Some(out_vec) = out_vec {
+            out_vec.clear();
+            out_vec.extend(bitmap.iter());
+        }
+        Some(())
     }
 
-    fn new(writer: ColorsFlushProcessing, checkpoint_distance: usize, colors_count: u64) -> Self {
-        todo!("Fix meaning of 'stride'!");
-        let stride = rayon::current_num_threads() as ColorIndexType;
-
+    fn new(writer: ColorsFlushProcessing, checkpoint_distance: usize, _colors_count: u64) -> Self {
         Self {
-            roaring_bitmaps: (0..stride)
-                .map(|off| {
-                    Mutex::new(RoaringBitmapInstance::new(
-                        colors_count,
-                        checkpoint_distance as u64,
-                        off,
-                        stride,
-                    ))
-                })
-                .collect(),
-            writer,
-            colors_index: AtomicU32::new(0),
-            colors_count,
+            async_buffer: AsyncSliceQueue::new(
+                DEFAULT_OUTPUT_BUFFER_SIZE,
+                rayon::current_num_threads(),
+                checkpoint_distance,
+                writer,
+            ),
         }
     }
 
-    fn serialize_colors(&self, colors: &[ColorIndexType]) -> ColorIndexType {
-        let color_index = self.colors_index.fetch_add(1, Ordering::Relaxed);
-
-        let target_bitmap = color_index % self.roaring_bitmaps.len() as ColorIndexType;
-
-        loop {
-            let mut bitmap_lock = self.roaring_bitmaps[target_bitmap as usize].lock();
-            if bitmap_lock.try_append(color_index, colors.iter().copied(), &self.writer) {
-                break;
-            }
-            drop(bitmap_lock);
-            std::thread::yield_now();
-        }
-
-        color_index
+    #[inline(always)]
+    fn serialize_colors(&self, colors: &[u32]) -> u32 {
+        let bitmap = RoaringBitmap::from_sorted_iter(colors.iter().copied())
+            .expect("The colors of a subset must be sorted");
+
+        TEMP_BITMAP_BUFFER.with(|buffer| {
+            let buffer = unsafe { &mut *buffer.get() };
+            buffer.clear();
+            bitmap.serialize_into(&mut *buffer).unwrap();
+            self.async_buffer.add_data(buffer.as_slice()) as ColorIndexType
+        })
     }
 
     fn get_subsets_count(&self) -> u64 {
-        self.colors_index.load(Ordering::Relaxed) as u64
+        self.async_buffer.get_counter()
     }
 
     fn print_stats(&self) {
-        ggcat_logging::info!(
-            "Subsets count: {} witn {} colors",
-            self.get_subsets_count(),
-            self.colors_count
-        );
+        ggcat_logging::info!("Total color subsets: {}", self.async_buffer.get_counter())
     }
 
-    fn finalize(mut self) -> ColorsFlushProcessing {
-        for bitmap in self.roaring_bitmaps {
-            bitmap.lock().flush(&mut self.writer);
-        }
-
-        self.writer
+    fn finalize(self) -> ColorsFlushProcessing {
+        self.async_buffer.finish()
     }
 }
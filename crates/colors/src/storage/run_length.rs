@@ -64,7 +64,7 @@ impl ColorIndexSerializer {
         mut reader: impl Read,
         mut add_color: impl FnMut(ColorIndexType),
     ) -> Option<()> {
-        add_color((decode_varint(|| reader.read_u8().ok())? - 2) as ColorIndexType);
+        add_color(decode_varint(|| reader.read_u8().ok())?.checked_sub(2)? as ColorIndexType);
         loop {
             let result = decode_varint(|| reader.read_u8().ok())? as ColorIndexType;
             if result == 0 {
@@ -91,7 +91,7 @@ impl ColorIndexSerializer {
 
         let mut last_color = colors[0];
         for i in 1..colors.len() {
-            colors[i] += last_color;
+            colors[i] = colors[i].checked_add(last_color)?;
             last_color = colors[i];
         }
         Some(())
@@ -109,14 +109,10 @@ thread_local! {
 impl ColorsSerializerTrait for RunLengthColorsSerializer {
     const MAGIC: [u8; 16] = *b"GGCAT_CMAP_RNLEN";
 
-    fn decode_color(mut reader: impl Read, out_vec: Option<&mut Vec<u32>>) {
+    fn decode_color(mut reader: impl Read, out_vec: Option<&mut Vec<u32>>) -> Option<()> {
         match out_vec {
-            None => {
-                ColorIndexSerializer::deserialize_colors_diffs(&mut reader, |_| {});
-            }
-            Some(out_vec) => {
-                ColorIndexSerializer::deserialize_colors(&mut reader, out_vec);
-            }
+            None => ColorIndexSerializer::deserialize_colors_diffs(&mut reader, |_| {}),
+            Some(out_vec) => ColorIndexSerializer::deserialize_colors(&mut reader, out_vec),
         }
     }
 
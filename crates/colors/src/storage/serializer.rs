@@ -1,9 +1,12 @@
-use crate::storage::ColorsSerializerTrait;
+use crate::storage::roaring::RoaringColorsSerializer;
+use crate::storage::run_length::RunLengthColorsSerializer;
+use crate::storage::{ColormapEncoding, ColorsSerializerTrait};
 use config::DEFAULT_OUTPUT_BUFFER_SIZE;
 use config::{ColorIndexType, COLORS_SINGLE_BATCH_SIZE};
 use desse::{Desse, DesseSized};
 use ggcat_logging::UnrecoverableErrorLogging;
 use io::chunks_writer::ChunksWriter;
+use io::FileIoError;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::fs::File;
@@ -13,7 +16,7 @@ use std::ops::{Deref, DerefMut};
 use std::path::Path;
 use std::sync::atomic::{AtomicU64, Ordering};
 
-const STORAGE_VERSION: u64 = 1;
+pub(crate) const STORAGE_VERSION: u64 = 1;
 
 #[derive(Debug, Desse, DesseSized, Default)]
 pub(crate) struct ColorsFileHeader {
@@ -45,10 +48,12 @@ pub struct ColorsSerializer<SI: ColorsSerializerTrait> {
 
 impl<SI: ColorsSerializerTrait> ColorsSerializer<SI> {
     pub fn new(file: impl AsRef<Path>, color_names: &[String]) -> anyhow::Result<Self> {
-        let mut colormap_file = File::create(file.as_ref()).log_unrecoverable_error_with_data(
-            "Cannot create colormap file",
-            file.as_ref().display(),
-        )?;
+        let mut colormap_file = File::create(file.as_ref())
+            .log_unrecoverable_error_with_data(
+                "Cannot create colormap file",
+                file.as_ref().display(),
+            )
+            .map_err(|err| FileIoError::new(file.as_ref(), err))?;
 
         colormap_file
             .write_all(&ColorsFileHeader::default().serialize()[..])
@@ -117,6 +122,42 @@ impl<SI: ColorsSerializerTrait> ColorsSerializer<SI> {
     }
 }
 
+/// A colors serializer with the encoding selected at runtime
+pub enum DynamicColorsSerializer {
+    RunLength(ColorsSerializer<RunLengthColorsSerializer>),
+    Roaring(ColorsSerializer<RoaringColorsSerializer>),
+}
+
+impl DynamicColorsSerializer {
+    pub fn new(
+        file: impl AsRef<Path>,
+        color_names: &[String],
+        encoding: ColormapEncoding,
+    ) -> anyhow::Result<Self> {
+        Ok(match encoding {
+            ColormapEncoding::RunLength => {
+                Self::RunLength(ColorsSerializer::new(file, color_names)?)
+            }
+            ColormapEncoding::Roaring => Self::Roaring(ColorsSerializer::new(file, color_names)?),
+        })
+    }
+
+    #[inline(always)]
+    pub fn serialize_colors(&self, colors: &[ColorIndexType]) -> ColorIndexType {
+        match self {
+            Self::RunLength(serializer) => serializer.serialize_colors(colors),
+            Self::Roaring(serializer) => serializer.serialize_colors(colors),
+        }
+    }
+
+    pub fn print_stats(&self) {
+        match self {
+            Self::RunLength(serializer) => serializer.print_stats(),
+            Self::Roaring(serializer) => serializer.print_stats(),
+        }
+    }
+}
+
 fn bincode_serialize_ref<S: Write, D: Serialize>(ser: &mut S, data: &D) {
     bincode::serialize_into(ser, data).unwrap();
 }
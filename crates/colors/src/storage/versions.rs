@@ -0,0 +1,235 @@
+use crate::colors_manager::ColorMapReader;
+use crate::storage::deserializer::{ColorsDeserializer, CorruptedColormapError};
+use crate::storage::inspector::verify_colormap;
+use crate::storage::roaring::RoaringColorsSerializer;
+use crate::storage::run_length::RunLengthColorsSerializer;
+use crate::storage::serializer::DynamicColorsSerializer;
+use crate::storage::{ColormapEncoding, ColorsSerializerTrait};
+use crate::DefaultColorsSerializer;
+use config::ColorIndexType;
+use io::FileIoError;
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Decodes the color subsets of the colormaps with a specific encoding and storage version
+pub struct ColormapDecoder {
+    pub magic: [u8; 16],
+    pub version: u64,
+    pub decode_color: fn(&mut dyn Read, Option<&mut Vec<ColorIndexType>>) -> Option<()>,
+}
+
+fn decode_with<DS: ColorsSerializerTrait>(
+    reader: &mut dyn Read,
+    out_vec: Option<&mut Vec<ColorIndexType>>,
+) -> Option<()> {
+    DS::decode_color(reader, out_vec)
+}
+
+/// The supported colormap formats. When the storage layout changes, the decoders of the previous
+/// versions are kept here, so that the existing colormaps can still be read and upgraded
+static COLORMAP_DECODERS: &[ColormapDecoder] = &[
+    ColormapDecoder {
+        magic: RunLengthColorsSerializer::MAGIC,
+        version: 1,
+        decode_color: decode_with::<RunLengthColorsSerializer>,
+    },
+    ColormapDecoder {
+        magic: RoaringColorsSerializer::MAGIC,
+        version: 1,
+        decode_color: decode_with::<RoaringColorsSerializer>,
+    },
+];
+
+/// A colormap with a known encoding but with a storage version that cannot be read
+#[derive(Debug)]
+pub struct UnsupportedColormapVersion {
+    pub file: PathBuf,
+    pub version: u64,
+    pub supported_versions: Vec<u64>,
+}
+
+impl Display for UnsupportedColormapVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Colormap file '{}' has storage version {}, but only the versions {:?} are supported",
+            self.file.display(),
+            self.version,
+            self.supported_versions
+        )?;
+        if self.supported_versions.iter().all(|v| *v < self.version) {
+            write!(f, ", it was probably created by a newer release of ggcat")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnsupportedColormapVersion {}
+
+/// Finds the decoder for a colormap header, failing if its encoding or its version are not supported
+pub fn find_colormap_decoder(
+    file: &Path,
+    magic: &[u8; 16],
+    version: u64,
+) -> anyhow::Result<&'static ColormapDecoder> {
+    let mut supported_versions = COLORMAP_DECODERS
+        .iter()
+        .filter(|decoder| &decoder.magic == magic)
+        .map(|decoder| decoder.version)
+        .peekable();
+
+    if supported_versions.peek().is_none() {
+        return Err(CorruptedColormapError {
+            file: file.to_path_buf(),
+            message: format!(
+                "unknown encoding '{}'",
+                String::from_utf8_lossy(magic).escape_debug()
+            ),
+        }
+        .into());
+    }
+
+    let supported_versions: Vec<_> = supported_versions.collect();
+    COLORMAP_DECODERS
+        .iter()
+        .find(|decoder| &decoder.magic == magic && decoder.version == version)
+        .ok_or_else(|| {
+            UnsupportedColormapVersion {
+                file: file.to_path_buf(),
+                version,
+                supported_versions,
+            }
+            .into()
+        })
+}
+
+/// Rewrites a colormap with the current storage version, converting it to the given encoding
+/// or keeping its own if none is specified.
+/// The input colormap is verified before the conversion, and the output only replaces an existing file
+/// after it has been verified too. Returns false if the colormap is already up to date and nothing was written
+pub fn upgrade_colormap(
+    input_colormap: &Path,
+    output_colormap: &Path,
+    encoding: Option<ColormapEncoding>,
+) -> anyhow::Result<bool> {
+    let mut deserializer =
+        ColorsDeserializer::<DefaultColorsSerializer>::new(input_colormap, true)?;
+    let encoding = encoding.or(deserializer.encoding()).unwrap_or_default();
+    if deserializer.is_current_version() && deserializer.encoding() == Some(encoding) {
+        return Ok(false);
+    }
+
+    let issues = verify_colormap(input_colormap)?;
+    if !issues.is_empty() {
+        return Err(CorruptedColormapError {
+            file: input_colormap.to_path_buf(),
+            message: issues.join(", "),
+        }
+        .into());
+    }
+
+    let mut temp_colormap = OsString::from(output_colormap);
+    temp_colormap.push(".upgrading");
+    let temp_colormap = PathBuf::from(temp_colormap);
+
+    let color_names: Vec<_> = (0..deserializer.colors_count())
+        .map(|color| {
+            deserializer
+                .get_color_name(color as ColorIndexType, false)
+                .to_string()
+        })
+        .collect();
+
+    {
+        let serializer = DynamicColorsSerializer::new(&temp_colormap, &color_names, encoding)?;
+        let mut subset = vec![];
+        for subset_index in 0..deserializer.colors_subsets_count() as ColorIndexType {
+            subset.clear();
+            deserializer.get_color_mappings(subset_index, &mut subset);
+            let new_index = serializer.serialize_colors(&subset);
+            assert_eq!(new_index, subset_index);
+        }
+    }
+
+    let issues = verify_colormap(&temp_colormap)?;
+    if !issues.is_empty() {
+        let _ = std::fs::remove_file(&temp_colormap);
+        return Err(CorruptedColormapError {
+            file: temp_colormap,
+            message: issues.join(", "),
+        }
+        .into());
+    }
+
+    std::fs::rename(&temp_colormap, output_colormap)
+        .map_err(|err| FileIoError::new(output_colormap, err))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::deserializer::{ColorsDeserializer, CorruptedColormapError};
+    use crate::storage::inspector::read_colormap_subsets;
+    use crate::storage::inspector::verify_colormap;
+    use crate::storage::serializer::{ColorsFileHeader, ColorsSerializer, STORAGE_VERSION};
+    use crate::storage::versions::{upgrade_colormap, UnsupportedColormapVersion};
+    use crate::storage::ColormapEncoding;
+    use crate::DefaultColorsSerializer;
+    use desse::{Desse, DesseSized};
+    use utils::temp_dir::TempDir;
+
+    #[test]
+    fn colormap_versions() {
+        let dir = TempDir::new("ggcat-versions-test");
+        let colormap = dir.join("graph.colors.dat");
+
+        {
+            let serializer =
+                ColorsSerializer::<DefaultColorsSerializer>::new(&colormap, &["color".to_string()])
+                    .unwrap();
+            serializer.serialize_colors(&[0]);
+        }
+
+        let deserializer = ColorsDeserializer::<DefaultColorsSerializer>::new(&colormap, true);
+        assert!(deserializer.unwrap().is_current_version());
+        assert!(!upgrade_colormap(&colormap, &colormap, None).unwrap());
+
+        let roaring_colormap = dir.join("roaring.colors.dat");
+        let encoding = Some(ColormapEncoding::Roaring);
+        assert!(upgrade_colormap(&colormap, &roaring_colormap, encoding).unwrap());
+        assert!(!upgrade_colormap(&roaring_colormap, &roaring_colormap, None).unwrap());
+        assert_eq!(
+            read_colormap_subsets::<DefaultColorsSerializer>(&roaring_colormap)
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec![(0, vec![0])]
+        );
+
+        let rewrite_header = |update: &dyn Fn(&mut ColorsFileHeader)| {
+            let mut data = std::fs::read(&colormap).unwrap();
+            let mut header = ColorsFileHeader::deserialize_from(
+                &data[..ColorsFileHeader::SIZE].try_into().unwrap(),
+            );
+            update(&mut header);
+            data[..ColorsFileHeader::SIZE].copy_from_slice(&header.serialize());
+            std::fs::write(&colormap, &data).unwrap();
+        };
+
+        rewrite_header(&|header| header.version = STORAGE_VERSION + 1);
+        let err = ColorsDeserializer::<DefaultColorsSerializer>::new(&colormap, true)
+            .err()
+            .unwrap();
+        let err = err.downcast::<UnsupportedColormapVersion>().unwrap();
+        assert_eq!(err.version, STORAGE_VERSION + 1);
+        assert!(err.to_string().contains("newer release"));
+        assert_eq!(verify_colormap(&colormap).unwrap().len(), 1);
+
+        rewrite_header(&|header| header.magic = *b"GGCAT_CMAP_XXXXX");
+        let err = ColorsDeserializer::<DefaultColorsSerializer>::new(&colormap, true)
+            .err()
+            .unwrap();
+        assert!(err.is::<CorruptedColormapError>());
+    }
+}
@@ -1,10 +1,16 @@
 // use crate::RunLengthColorsSerializer;
+pub mod operation;
+pub mod progress;
+
+use crate::operation::OperationContext;
 use parallel_processor::buckets::writers::compressed_binary_writer::{
     CompressedCheckpointSize, CompressionLevelInfo,
 };
 use parallel_processor::memory_data_size::MemoryDataSize;
 use parallel_processor::memory_fs::file::internal::MemoryFileMode;
-use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
 pub type BucketIndexType = u16;
@@ -90,6 +96,7 @@ impl SwapPriority {
     pub const FinalMaps: usize = 1;
     pub const ResultBuckets: usize = 1;
     pub const HashBuckets: usize = 2;
+    pub const KmersCounts: usize = 2;
     pub const QueryCounters: usize = 2;
     pub const ReorganizeReads: usize = 3;
     pub const LinksBuckets: usize = 3;
@@ -99,23 +106,124 @@ impl SwapPriority {
     pub const KmersMergeBuckets: usize = 6;
 }
 
-// Functions depending on global config parameters set at runtime
-pub static KEEP_FILES: AtomicBool = AtomicBool::new(false);
-pub static INTERMEDIATE_COMPRESSION_LEVEL_SLOW: AtomicU32 = AtomicU32::new(3);
-pub static INTERMEDIATE_COMPRESSION_LEVEL_FAST: AtomicU32 = AtomicU32::new(0);
-pub static PREFER_MEMORY: AtomicBool = AtomicBool::new(false);
+// Functions depending on the config parameters of the running operation
+pub const DEFAULT_INTERMEDIATE_COMPRESSION_LEVEL_SLOW: u32 = 3;
+pub const DEFAULT_INTERMEDIATE_COMPRESSION_LEVEL_FAST: u32 = 0;
+
+/// Returns true if the intermediate files must be kept in the temporary directory
+pub fn keep_files() -> bool {
+    OperationContext::current().settings.keep_files
+}
+
+/// Returns true if the running operation keeps its temporary files in memory, without a temporary directory
+pub fn is_memory_only() -> bool {
+    OperationContext::current().settings.memory_only_budget != 0
+}
+
+/// Sets the temporary files of the last saved checkpoint, replacing the previous ones
+pub fn set_checkpoint_files(files: impl IntoIterator<Item = PathBuf>) {
+    *OperationContext::current().checkpoint_files.write() = Some(files.into_iter().collect());
+}
+
+pub fn clear_checkpoint_files() {
+    *OperationContext::current().checkpoint_files.write() = None;
+}
+
+/// Returns true if a temporary file must be kept on disk after being read,
+/// either to debug the pipeline or because it is needed by the last checkpoint
+pub fn keep_file_on_disk(path: &Path) -> bool {
+    let operation = OperationContext::current();
+    operation.settings.keep_files
+        || operation
+            .checkpoint_files
+            .read()
+            .as_ref()
+            .is_some_and(|files| files.contains(path))
+}
 
 pub fn get_memory_mode(swap_priority: usize) -> MemoryFileMode {
-    if PREFER_MEMORY.load(Ordering::Relaxed) {
+    let settings = &OperationContext::current().settings;
+    if settings.memory_only_budget != 0 {
+        MemoryFileMode::AlwaysMemory
+    } else if settings.prefer_memory {
         MemoryFileMode::PreferMemory { swap_priority }
     } else {
         MemoryFileMode::DiskOnly
     }
 }
 
+#[derive(Debug)]
+pub struct MemoryBudgetExceeded {
+    pub budget: MemoryDataSize,
+    pub used: MemoryDataSize,
+}
+
+impl Display for MemoryBudgetExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Memory budget exceeded in memory-only mode: {:.2} used for temporary files, {:.2} available. \
+            Increase the memory or use a temporary directory",
+            self.used, self.budget
+        )
+    }
+}
+
+impl std::error::Error for MemoryBudgetExceeded {}
+
+/// Returns true if the temporary files of the running operation needed more than the available memory,
+/// when running in memory-only mode. The usage is checked while the files are written
+pub fn is_memory_only_budget_exceeded() -> bool {
+    OperationContext::current()
+        .memory_only_usage
+        .exceeded_usage
+        .load(Ordering::Relaxed)
+        != 0
+}
+
+/// Returns true if the running phase should skip the remaining buckets, because the operation was cancelled
+/// or the memory-only budget was exceeded. The error is reported by the checks following the phase
+pub fn should_interrupt_phase() -> bool {
+    progress::is_cancelled() || is_memory_only_budget_exceeded()
+}
+
+/// Checks that the temporary files never needed more than the available memory, when running in memory-only mode
+pub fn check_memory_only_budget() -> Result<(), MemoryBudgetExceeded> {
+    let operation = OperationContext::current();
+    let used = operation
+        .memory_only_usage
+        .exceeded_usage
+        .load(Ordering::Relaxed);
+
+    if used != 0 {
+        Err(MemoryBudgetExceeded {
+            budget: MemoryDataSize::from_bytes(operation.settings.memory_only_budget as usize),
+            used: MemoryDataSize::from_bytes(used as usize),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Overrides the compression level of the intermediate files of the running operation
+pub fn set_intermediate_compression_level(level: u32) {
+    let operation = OperationContext::current();
+    operation
+        .intermediate_compression_level_slow
+        .store(level, Ordering::Relaxed);
+    operation
+        .intermediate_compression_level_fast
+        .store(level, Ordering::Relaxed);
+}
+
 pub fn get_compression_level_info() -> CompressionLevelInfo {
+    let operation = OperationContext::current();
     CompressionLevelInfo {
-        fast_disk: INTERMEDIATE_COMPRESSION_LEVEL_FAST.load(Ordering::Relaxed),
-        slow_disk: INTERMEDIATE_COMPRESSION_LEVEL_SLOW.load(Ordering::Relaxed),
+        fast_disk: operation
+            .intermediate_compression_level_fast
+            .load(Ordering::Relaxed),
+        slow_disk: operation
+            .intermediate_compression_level_slow
+            .load(Ordering::Relaxed),
     }
 }
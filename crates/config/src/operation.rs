@@ -0,0 +1,248 @@
+use crate::progress::{CancellationToken, OperationProgress, ProgressCallback};
+use crate::{
+    DEFAULT_INTERMEDIATE_COMPRESSION_LEVEL_FAST, DEFAULT_INTERMEDIATE_COMPRESSION_LEVEL_SLOW,
+};
+use ggcat_logging::MessageLevel;
+use parallel_processor::buckets::LockFreeBucket;
+use parallel_processor::memory_fs::file::reader::FileRangeReference;
+use parallel_processor::memory_fs::MemoryFs;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::cmp::max;
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::task::{Context, Poll};
+
+/// The settings of an operation, given by the instance that runs it
+#[derive(Clone)]
+pub struct OperationSettings {
+    /// Keeps the intermediate files in the temporary directory
+    pub keep_files: bool,
+    /// Uses all the memory before writing the temporary files to disk
+    pub prefer_memory: bool,
+    /// The memory available for temporary files when running without a temporary directory, 0 if disabled
+    pub memory_only_budget: u64,
+    pub intermediate_compression_level_slow: u32,
+    pub intermediate_compression_level_fast: u32,
+    /// Receives the messages emitted by the operation, if None they are written to stdout
+    pub messages_callback: Option<fn(MessageLevel, &str)>,
+    pub progress_callback: Option<ProgressCallback>,
+    pub cancellation_token: Option<CancellationToken>,
+}
+
+impl Default for OperationSettings {
+    fn default() -> Self {
+        Self {
+            keep_files: false,
+            prefer_memory: false,
+            memory_only_budget: 0,
+            intermediate_compression_level_slow: DEFAULT_INTERMEDIATE_COMPRESSION_LEVEL_SLOW,
+            intermediate_compression_level_fast: DEFAULT_INTERMEDIATE_COMPRESSION_LEVEL_FAST,
+            messages_callback: None,
+            progress_callback: None,
+            cancellation_token: None,
+        }
+    }
+}
+
+/// Memory used by the in-memory temporary files of an operation running in memory-only mode
+#[derive(Default)]
+pub(crate) struct MemoryOnlyUsage {
+    /// The files written by the operation, the deleted ones are removed when the usage is recomputed
+    files: Mutex<Vec<PathBuf>>,
+    /// The memory used by the files at the last check, plus the data written after it
+    usage: AtomicU64,
+    /// The usage that triggers the next check of the files sizes
+    next_check: AtomicU64,
+    /// The memory used by the files when the budget was exceeded, 0 if it was never exceeded
+    pub(crate) exceeded_usage: AtomicU64,
+}
+
+impl MemoryOnlyUsage {
+    /// Recomputes the usage from the sizes of the files that were not deleted yet
+    fn check(&self, budget: u64) {
+        // Another thread is already checking the files
+        let Some(mut files) = self.files.try_lock() else {
+            return;
+        };
+
+        let mut used = 0;
+        files.retain(|file| match MemoryFs::get_file_size(file) {
+            Some(size) => {
+                used += size as u64;
+                true
+            }
+            None => false,
+        });
+
+        self.usage.store(used, Ordering::Relaxed);
+        if used > budget {
+            self.exceeded_usage.fetch_max(used, Ordering::Relaxed);
+        }
+        // Avoid checking the files at each write when the usage is close to the budget
+        self.next_check
+            .store(max(budget, used + budget / 64), Ordering::Relaxed);
+    }
+}
+
+/// A running operation, with its settings and state. The pipelines access the operation
+/// running on their thread with `OperationContext::current`
+pub struct OperationContext {
+    pub(crate) settings: OperationSettings,
+    pub(crate) intermediate_compression_level_slow: AtomicU32,
+    pub(crate) intermediate_compression_level_fast: AtomicU32,
+    pub(crate) progress: OperationProgress,
+    /// The temporary files referenced by the last checkpoint of a build, needed to resume it if interrupted
+    pub(crate) checkpoint_files: RwLock<Option<HashSet<PathBuf>>>,
+    pub(crate) memory_only_usage: MemoryOnlyUsage,
+}
+
+thread_local! {
+    static CURRENT_OPERATION: RefCell<Option<Arc<OperationContext>>> = const { RefCell::new(None) };
+}
+
+/// Used by the threads that are not running an operation
+static DEFAULT_OPERATION: LazyLock<Arc<OperationContext>> =
+    LazyLock::new(|| OperationContext::new(OperationSettings::default()));
+
+impl OperationContext {
+    pub fn new(settings: OperationSettings) -> Arc<Self> {
+        Arc::new(Self {
+            intermediate_compression_level_slow: AtomicU32::new(
+                settings.intermediate_compression_level_slow,
+            ),
+            intermediate_compression_level_fast: AtomicU32::new(
+                settings.intermediate_compression_level_fast,
+            ),
+            settings,
+            progress: OperationProgress::default(),
+            checkpoint_files: RwLock::new(None),
+            memory_only_usage: MemoryOnlyUsage::default(),
+        })
+    }
+
+    pub fn settings(&self) -> &OperationSettings {
+        &self.settings
+    }
+
+    /// Returns the operation running on the current thread, or one with the default settings
+    pub fn current() -> Arc<Self> {
+        CURRENT_OPERATION
+            .with_borrow(|operation| operation.clone())
+            .unwrap_or_else(|| DEFAULT_OPERATION.clone())
+    }
+
+    /// Sets the operation running on the current thread, returning the previous one
+    pub fn set_current(operation: Option<Arc<Self>>) -> Option<Arc<Self>> {
+        ggcat_logging::set_operation_messages_callback(
+            operation
+                .as_ref()
+                .and_then(|operation| operation.settings.messages_callback),
+        );
+        CURRENT_OPERATION.replace(operation)
+    }
+
+    /// Runs the future as part of this operation, on any thread it is polled from
+    pub fn scope<F: Future>(self: &Arc<Self>, future: F) -> OperationFuture<F> {
+        OperationFuture {
+            operation: self.clone(),
+            future,
+        }
+    }
+
+    /// Charges the data written to an in-memory file of the operation to its memory-only budget
+    pub(crate) fn charge_memory_only_usage(&self, bytes: u64) {
+        let budget = self.settings.memory_only_budget;
+        let usage = &self.memory_only_usage;
+        if usage.usage.fetch_add(bytes, Ordering::Relaxed) + bytes
+            > max(budget, usage.next_check.load(Ordering::Relaxed))
+        {
+            usage.check(budget);
+        }
+    }
+
+    /// Tracks a new in-memory file of the operation, to compute its memory usage
+    pub(crate) fn add_memory_only_file(&self, file: PathBuf) {
+        self.memory_only_usage.files.lock().push(file);
+    }
+
+    /// Ends the operation, resetting its cancellation token if it was cancelled
+    pub fn finish(&self) {
+        if let Some(token) = &self.settings.cancellation_token {
+            token.reset();
+        }
+    }
+}
+
+/// A future that runs as part of an operation, see `OperationContext::scope`
+pub struct OperationFuture<F> {
+    operation: Arc<OperationContext>,
+    future: F,
+}
+
+impl<F: Future> Future for OperationFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: the future is never moved out of the pinned struct
+        let this = unsafe { self.get_unchecked_mut() };
+        let previous = OperationContext::set_current(Some(this.operation.clone()));
+        let result = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx);
+        OperationContext::set_current(previous);
+        result
+    }
+}
+
+/// A bucket writer that charges the written data to the memory-only budget of the operation that created it
+pub struct MemoryTrackedBucket<B: LockFreeBucket> {
+    inner: B,
+    /// The operation that created the bucket, if running in memory-only mode
+    operation: Option<Arc<OperationContext>>,
+}
+
+impl<B: LockFreeBucket> LockFreeBucket for MemoryTrackedBucket<B> {
+    type InitData = B::InitData;
+
+    fn new_serialized_data_format(
+        path: &Path,
+        data: &Self::InitData,
+        index: usize,
+        data_format: &[u8],
+    ) -> Self {
+        let inner = B::new_serialized_data_format(path, data, index, data_format);
+        let operation = Some(OperationContext::current())
+            .filter(|operation| operation.settings.memory_only_budget != 0);
+        if let Some(operation) = &operation {
+            operation.add_memory_only_file(inner.get_path());
+        }
+        Self { inner, operation }
+    }
+
+    fn set_checkpoint_data<T: Serialize>(
+        &self,
+        data: Option<&T>,
+        passtrough_range: Option<FileRangeReference>,
+    ) {
+        self.inner.set_checkpoint_data(data, passtrough_range);
+    }
+
+    fn write_data(&self, bytes: &[u8]) {
+        if let Some(operation) = &self.operation {
+            operation.charge_memory_only_usage(bytes.len() as u64);
+        }
+        self.inner.write_data(bytes);
+    }
+
+    fn get_path(&self) -> PathBuf {
+        self.inner.get_path()
+    }
+
+    fn finalize(self) {
+        self.inner.finalize();
+    }
+}
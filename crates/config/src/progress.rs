@@ -0,0 +1,156 @@
+use crate::operation::OperationContext;
+use parking_lot::RwLock;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// A phase of the pipelines, reported to the progress callback
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressPhase {
+    /// Splitting of the input sequences into buckets by their minimizers
+    MinimizerBucketing,
+    /// Counting of the kmers abundance histogram, to choose the minimum multiplicity automatically
+    KmersHistogram,
+    /// Merging of the kmers of each bucket into unitigs fragments
+    KmersMerge,
+    /// Sorting and writing of the solid kmers with their counts
+    KmersCountsOutput,
+    /// Sorting of the fragments hashes to find the links between them
+    HashesSorting,
+    /// Compaction of the links between the fragments, the iterations start from 0
+    LinksCompaction { iteration: usize },
+    /// Reorganization of the fragments by their final unitig
+    ReorganizeReads,
+    /// Joining of the fragments into the final unitigs
+    BuildUnitigs,
+    /// Building of the links between the maximal unitigs
+    MaximalUnitigsLinks,
+    /// Building of matchtigs, eulertigs or simplitigs from the unitigs
+    Matchtigs,
+    /// Removal of the tips and bubbles from the maximal unitigs
+    GraphCleaning,
+    /// Counting of the query kmers found in the graph
+    QueryKmersCounting,
+    /// Sorting of the counters of each query
+    QueryCountersSorting,
+    /// Reading of the colors of the matched kmers
+    QueryColormapReading,
+    /// Writing of the query results
+    QueryOutput,
+}
+
+/// The unit of the processed and total amounts of a phase
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressUnit {
+    /// Bases of the input sequences, the total is estimated from the input files size
+    Bases,
+    /// Buckets of temporary files
+    Buckets,
+}
+
+/// Progress of the current phase of an operation
+#[derive(Clone, Copy, Debug)]
+pub struct ProgressInfo {
+    pub phase: ProgressPhase,
+    pub unit: ProgressUnit,
+    pub processed: u64,
+    /// The total amount of the phase, 0 if not known
+    pub total: u64,
+}
+
+/// Called with the progress of the current phase, possibly from multiple threads at the same time
+pub type ProgressCallback = Arc<dyn Fn(&ProgressInfo) + Send + Sync>;
+
+/// Token to cancel the running operation it is given to, the cancellation is checked at bucket boundaries
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clears the cancellation, allowing the token to be used by another operation
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+pub struct OperationCancelled;
+
+impl Display for OperationCancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Operation cancelled")
+    }
+}
+
+impl std::error::Error for OperationCancelled {}
+
+/// The progress of the running phase of an operation
+#[derive(Default)]
+pub(crate) struct OperationProgress {
+    current_phase: RwLock<Option<(ProgressPhase, ProgressUnit, u64)>>,
+    processed_amount: AtomicU64,
+}
+
+fn report_progress(operation: &OperationContext, processed: u64) {
+    if let Some(callback) = operation.settings.progress_callback.as_ref() {
+        if let Some((phase, unit, total)) = *operation.progress.current_phase.read() {
+            callback(&ProgressInfo {
+                phase,
+                unit,
+                processed,
+                total,
+            });
+        }
+    }
+}
+
+/// Starts reporting the progress of a new phase, with its total amount (0 if not known)
+pub fn start_progress_phase(phase: ProgressPhase, unit: ProgressUnit, total: u64) {
+    let operation = OperationContext::current();
+    *operation.progress.current_phase.write() = Some((phase, unit, total));
+    operation
+        .progress
+        .processed_amount
+        .store(0, Ordering::Relaxed);
+    report_progress(&operation, 0);
+}
+
+/// Adds the given amount to the processed amount of the current phase
+pub fn advance_progress(amount: u64) {
+    let operation = OperationContext::current();
+    let processed = operation
+        .progress
+        .processed_amount
+        .fetch_add(amount, Ordering::Relaxed)
+        + amount;
+    report_progress(&operation, processed);
+}
+
+/// Returns true if the current operation was cancelled, the pipelines should skip the remaining buckets
+pub fn is_cancelled() -> bool {
+    OperationContext::current()
+        .settings
+        .cancellation_token
+        .as_ref()
+        .is_some_and(|token| token.is_cancelled())
+}
+
+/// Checks that the current operation was not cancelled, to be called between the phases
+pub fn check_cancelled() -> Result<(), OperationCancelled> {
+    if is_cancelled() {
+        Err(OperationCancelled)
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,452 @@
+use colors::colors_manager::ColorMapReader;
+use colors::storage::deserializer::ColorsDeserializer;
+use colors::DefaultColorsSerializer;
+use config::ColorIndexType;
+use hashbrown::HashMap;
+use io::sequences_reader::{DnaSequencesFileType, SequencesReader};
+use io::FileIoError;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::BufRead;
+use std::path::Path;
+
+const LINK_AT_START: u8 = 1;
+const LINK_AT_END: u8 = 2;
+
+/// A range of values of a histogram, with the unitigs (and their kmers) falling into it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistogramBin {
+    pub min: u64,
+    pub max: u64,
+    pub unitigs: u64,
+    pub kmers: u64,
+}
+
+/// Statistics available only for graphs built with links
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinksStats {
+    /// The number of links, as written in the graph (each unitig lists its own links)
+    pub links_count: u64,
+    /// Unitigs having links only at one of their ends
+    pub tips: u64,
+    /// Unitigs without any link
+    pub isolated: u64,
+}
+
+/// Statistics of the colormap associated to a colored graph
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColorsStats {
+    pub colors_count: usize,
+    pub subsets_count: u64,
+    /// Pairs of (subset size, number of subsets with that size), sorted by size
+    pub subset_sizes: Vec<(usize, u64)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GraphStats {
+    pub k: usize,
+    pub unitigs_count: u64,
+    pub total_bases: u64,
+    pub total_kmers: u64,
+    pub min_length: u64,
+    pub max_length: u64,
+    pub n50: u64,
+    /// Available only if the expected genome size is known and the unitigs cover at least half of it
+    pub ng50: Option<u64>,
+    /// Unitig lengths histogram, with power of two bins
+    pub length_histogram: Vec<HistogramBin>,
+    /// Present only if the graph has links
+    pub links: Option<LinksStats>,
+    /// Average kmers abundance histogram with power of two bins, present only if the graph has KC tags
+    pub abundance_histogram: Option<Vec<HistogramBin>>,
+    /// Present only for colored graphs
+    pub colors: Option<ColorsStats>,
+}
+
+#[derive(Default)]
+struct StatsCollector {
+    k: u64,
+    lengths: Vec<u64>,
+    length_bins: BTreeMap<u32, (u64, u64)>,
+    abundance_bins: BTreeMap<u32, (u64, u64)>,
+    has_abundances: bool,
+    has_links: bool,
+    links_count: u64,
+    tips: u64,
+    isolated: u64,
+}
+
+impl StatsCollector {
+    fn power_of_two_bin(value: u64) -> u32 {
+        u64::BITS - 1 - value.max(1).leading_zeros()
+    }
+
+    fn add_unitig(&mut self, length: u64, abundance: Option<u64>) {
+        let kmers = (length + 1).saturating_sub(self.k);
+        self.lengths.push(length);
+
+        let bin = self
+            .length_bins
+            .entry(Self::power_of_two_bin(length))
+            .or_default();
+        bin.0 += 1;
+        bin.1 += kmers;
+
+        if let Some(abundance) = abundance {
+            self.has_abundances = true;
+            let average = (abundance + kmers / 2) / kmers.max(1);
+            let bin = self
+                .abundance_bins
+                .entry(Self::power_of_two_bin(average))
+                .or_default();
+            bin.0 += 1;
+            bin.1 += kmers;
+        }
+    }
+
+    fn add_links_sides(&mut self, sides: u8) {
+        match sides {
+            0 => self.isolated += 1,
+            LINK_AT_START | LINK_AT_END => self.tips += 1,
+            _ => {}
+        }
+    }
+
+    fn histogram(bins: &BTreeMap<u32, (u64, u64)>) -> Vec<HistogramBin> {
+        bins.iter()
+            .map(|(bin, (unitigs, kmers))| HistogramBin {
+                min: 1 << bin,
+                max: (1 << bin) * 2 - 1,
+                unitigs: *unitigs,
+                kmers: *kmers,
+            })
+            .collect()
+    }
+
+    fn finish(mut self, genome_size: Option<u64>) -> GraphStats {
+        self.lengths.sort_unstable_by(|a, b| b.cmp(a));
+        let total_bases = self.lengths.iter().sum::<u64>();
+
+        let nx50 = |target: u64| {
+            let mut covered = 0;
+            self.lengths
+                .iter()
+                .find(|length| {
+                    covered += **length;
+                    covered * 2 >= target
+                })
+                .copied()
+        };
+
+        GraphStats {
+            k: self.k as usize,
+            unitigs_count: self.lengths.len() as u64,
+            total_bases,
+            total_kmers: self.length_bins.values().map(|(_, kmers)| kmers).sum(),
+            min_length: self.lengths.last().copied().unwrap_or(0),
+            max_length: self.lengths.first().copied().unwrap_or(0),
+            n50: nx50(total_bases).unwrap_or(0),
+            ng50: genome_size.and_then(nx50),
+            length_histogram: Self::histogram(&self.length_bins),
+            links: self.has_links.then_some(LinksStats {
+                links_count: self.links_count,
+                tips: self.tips,
+                isolated: self.isolated,
+            }),
+            abundance_histogram: self
+                .has_abundances
+                .then(|| Self::histogram(&self.abundance_bins)),
+            colors: None,
+        }
+    }
+}
+
+fn parse_number(value: &[u8]) -> Option<u64> {
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+fn link_side(orientation: u8) -> u8 {
+    if orientation == b'-' {
+        LINK_AT_START
+    } else {
+        LINK_AT_END
+    }
+}
+
+fn flush_fasta_unitig(stats: &mut StatsCollector, header: &mut Option<Vec<u8>>, length: &mut u64) {
+    let Some(header) = header.take() else {
+        return;
+    };
+
+    let mut abundance = None;
+    let mut sides = 0;
+    for tag in header.split(|c| *c == b' ').skip(1) {
+        if let Some(value) = tag.strip_prefix(b"KC:i:") {
+            abundance = parse_number(value);
+        } else if let Some(link) = tag.strip_prefix(b"L:") {
+            stats.has_links = true;
+            stats.links_count += 1;
+            sides |= link_side(link.first().copied().unwrap_or(b'+'));
+        }
+    }
+
+    stats.add_unitig(*length, abundance);
+    stats.add_links_sides(sides);
+    *length = 0;
+}
+
+fn read_fasta_graph(source: &Path, stats: &mut StatsCollector) -> anyhow::Result<()> {
+    let mut reader = SequencesReader::open_buffered(source)?;
+    let mut line = Vec::new();
+    let mut header = None;
+    let mut length = 0;
+
+    loop {
+        line.clear();
+        if reader
+            .read_until(b'\n', &mut line)
+            .map_err(|err| FileIoError::new(source, err))?
+            == 0
+        {
+            break;
+        }
+        while line.last().map(|c| *c == b'\n' || *c == b'\r') == Some(true) {
+            line.pop();
+        }
+
+        match line.first() {
+            Some(b'>') => {
+                flush_fasta_unitig(stats, &mut header, &mut length);
+                header = Some(line[1..].to_vec());
+            }
+            Some(b';') | None => {}
+            Some(_) => length += line.len() as u64,
+        }
+    }
+    flush_fasta_unitig(stats, &mut header, &mut length);
+
+    Ok(())
+}
+
+fn read_gfa_graph(source: &Path, stats: &mut StatsCollector) -> anyhow::Result<()> {
+    let mut reader = SequencesReader::open_buffered(source)?;
+    let mut line = Vec::new();
+    // The links can be written in any order with respect to the segments, so they are matched by name
+    let mut links_sides: HashMap<Vec<u8>, u8> = HashMap::new();
+    let mut segments_count = 0;
+
+    loop {
+        line.clear();
+        if reader
+            .read_until(b'\n', &mut line)
+            .map_err(|err| FileIoError::new(source, err))?
+            == 0
+        {
+            break;
+        }
+        while line.last().map(|c| *c == b'\n' || *c == b'\r') == Some(true) {
+            line.pop();
+        }
+
+        if let Some((_, sequence, tags)) = SequencesReader::parse_gfa_segment(&line) {
+            let abundance = tags
+                .split(|c| *c == b'\t')
+                .find_map(|tag| tag.strip_prefix(b"KC:i:"))
+                .and_then(parse_number);
+            stats.add_unitig(sequence.len() as u64, abundance);
+            segments_count += 1;
+            continue;
+        }
+
+        let mut fields = line.split(|c| *c == b'\t');
+        let (segment, orientation) = match fields.next() {
+            // L <index> <+/-> <other_index> <+/-> <overlap>
+            Some(b"L") => match (fields.next(), fields.next()) {
+                (Some(segment), Some(orientation)) => {
+                    (segment, orientation.first().copied().unwrap_or(b'+'))
+                }
+                _ => continue,
+            },
+            // E * <index><+/-> <other_index><+/-> <b1> <e1> <b2> <e2>
+            Some(b"E") => match fields.nth(1) {
+                Some([segment @ .., orientation]) => (segment, *orientation),
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        stats.has_links = true;
+        stats.links_count += 1;
+        *links_sides.entry(segment.to_vec()).or_default() |= link_side(orientation);
+    }
+
+    stats.isolated = segments_count - links_sides.len() as u64;
+    stats.tips = links_sides
+        .values()
+        .filter(|sides| **sides != (LINK_AT_START | LINK_AT_END))
+        .count() as u64;
+
+    Ok(())
+}
+
+fn compute_colors_stats(colormap_file: &Path) -> anyhow::Result<ColorsStats> {
+    let mut deserializer = ColorsDeserializer::<DefaultColorsSerializer>::new(colormap_file, true)?;
+
+    let subsets_count = deserializer.colors_subsets_count();
+    let mut subset_sizes = BTreeMap::new();
+    let mut subset = vec![];
+    for index in 0..subsets_count {
+        subset.clear();
+        deserializer.get_color_mappings(index as ColorIndexType, &mut subset);
+        *subset_sizes.entry(subset.len()).or_insert(0) += 1;
+    }
+
+    Ok(ColorsStats {
+        colors_count: deserializer.colors_count(),
+        subsets_count,
+        subset_sizes: subset_sizes.into_iter().collect(),
+    })
+}
+
+/// Computes the statistics of a FASTA or GFA graph, reading the color subsets from the colormap if provided.
+/// NG50 is computed only if the expected genome size is specified
+pub fn compute_graph_stats(
+    graph_file: &Path,
+    k: usize,
+    genome_size: Option<u64>,
+    colormap_file: Option<&Path>,
+) -> anyhow::Result<GraphStats> {
+    let mut stats = StatsCollector {
+        k: k as u64,
+        ..Default::default()
+    };
+
+    match SequencesReader::check_file(graph_file)? {
+        DnaSequencesFileType::GFA => read_gfa_graph(graph_file, &mut stats)?,
+        _ => read_fasta_graph(graph_file, &mut stats)?,
+    }
+
+    let mut graph_stats = stats.finish(genome_size);
+    graph_stats.colors = colormap_file.map(compute_colors_stats).transpose()?;
+    Ok(graph_stats)
+}
+
+impl Display for GraphStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Unitigs: {}", self.unitigs_count)?;
+        writeln!(f, "Total bases: {}", self.total_bases)?;
+        writeln!(f, "Total {}-mers: {}", self.k, self.total_kmers)?;
+        writeln!(f, "Min length: {}", self.min_length)?;
+        writeln!(f, "Max length: {}", self.max_length)?;
+        writeln!(f, "N50: {}", self.n50)?;
+        if let Some(ng50) = self.ng50 {
+            writeln!(f, "NG50: {}", ng50)?;
+        }
+
+        if let Some(links) = &self.links {
+            writeln!(f, "Links: {}", links.links_count)?;
+            writeln!(f, "Tips: {}", links.tips)?;
+            writeln!(f, "Isolated unitigs: {}", links.isolated)?;
+        }
+
+        if let Some(colors) = &self.colors {
+            writeln!(f, "Colors: {}", colors.colors_count)?;
+            writeln!(f, "Color subsets: {}", colors.subsets_count)?;
+        }
+
+        writeln!(f, "\nLength histogram (length: unitigs, kmers):")?;
+        for bin in &self.length_histogram {
+            writeln!(f, "{}-{}\t{}\t{}", bin.min, bin.max, bin.unitigs, bin.kmers)?;
+        }
+
+        if let Some(abundance_histogram) = &self.abundance_histogram {
+            writeln!(
+                f,
+                "\nAverage abundance histogram (abundance: unitigs, kmers):"
+            )?;
+            for bin in abundance_histogram {
+                writeln!(f, "{}-{}\t{}\t{}", bin.min, bin.max, bin.unitigs, bin.kmers)?;
+            }
+        }
+
+        if let Some(colors) = &self.colors {
+            writeln!(f, "\nColor subset sizes (size: subsets):")?;
+            for (size, count) in &colors.subset_sizes {
+                writeln!(f, "{}\t{}", size, count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph_stats::{compute_graph_stats, HistogramBin, LinksStats};
+    use utils::temp_dir::TempDir;
+
+    #[test]
+    fn fasta_and_gfa_graph_stats() {
+        let dir = TempDir::new("ggcat-stats-test");
+
+        let fasta = dir.join("graph.fa");
+        std::fs::write(
+            &fasta,
+            ">0 LN:i:7 KC:i:9 km:f:3.0 L:+:1:+\nACGTACG\n\
+             >1 LN:i:5 KC:i:2 km:f:1.0 L:-:0:- L:+:2:-\nACGTA\n\
+             >2 LN:i:5 KC:i:4 km:f:4.0 L:+:1:-\nACGTT\n\
+             >3 LN:i:4 KC:i:1 km:f:1.0\nACGT\n",
+        )
+        .unwrap();
+
+        let gfa = dir.join("graph.gfa");
+        std::fs::write(
+            &gfa,
+            "H\tVN:Z:1.0\n\
+             S\t0\tACGTACG\tLN:i:7\tKC:i:9\tkm:f:3.0\n\
+             L\t0\t+\t1\t+\t3M\n\
+             S\t1\tACGTA\tLN:i:5\tKC:i:2\tkm:f:1.0\n\
+             L\t1\t-\t0\t-\t3M\n\
+             L\t1\t+\t2\t-\t3M\n\
+             S\t2\tACGTT\tLN:i:5\tKC:i:4\tkm:f:4.0\n\
+             L\t2\t+\t1\t-\t3M\n\
+             S\t3\tACGT\tLN:i:4\tKC:i:1\tkm:f:1.0\n",
+        )
+        .unwrap();
+
+        for graph in [&fasta, &gfa] {
+            let stats = compute_graph_stats(graph, 4, Some(40), None).unwrap();
+            assert_eq!(stats.unitigs_count, 4);
+            assert_eq!(stats.total_bases, 21);
+            assert_eq!(stats.total_kmers, 9);
+            assert_eq!(stats.n50, 5);
+            assert_eq!(stats.ng50, Some(4));
+            assert_eq!(
+                stats.links,
+                Some(LinksStats {
+                    links_count: 4,
+                    tips: 2,
+                    isolated: 1
+                })
+            );
+            assert_eq!(
+                stats.length_histogram[0],
+                HistogramBin {
+                    min: 4,
+                    max: 7,
+                    unitigs: 4,
+                    kmers: 9
+                }
+            );
+            assert_eq!(
+                stats
+                    .abundance_histogram
+                    .unwrap()
+                    .iter()
+                    .map(|bin| bin.unitigs)
+                    .collect::<Vec<_>>(),
+                vec![2, 2]
+            );
+        }
+    }
+}
@@ -2,21 +2,21 @@ use crate::pipeline::dumper_minimizer_bucketing::minimizer_bucketing;
 use colors::bundles::graph_querying::ColorBundleGraphQuerying;
 use colors::colors_manager::{ColorMapReader, ColorsManager, ColorsMergeManager};
 use colors::DefaultColorsSerializer;
-use config::{
-    ColorIndexType, INTERMEDIATE_COMPRESSION_LEVEL_FAST, INTERMEDIATE_COMPRESSION_LEVEL_SLOW,
-};
+use config::progress::check_cancelled;
+use config::{check_memory_only_budget, set_intermediate_compression_level, ColorIndexType};
 use io::compute_stats_from_input_blocks;
 use io::sequences_stream::general::GeneralSequenceBlockData;
-use parallel_processor::memory_fs::MemoryFs;
+use parallel_processor::memory_fs::{MemoryFs, RemoveFileMode};
 use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
 use pipeline::dumper_colormap_querying::colormap_query;
 use pipeline::dumper_colormap_reading::colormap_reading;
-use std::fs::remove_file;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::Ordering;
 
+mod graph_stats;
 mod pipeline;
 
+pub use graph_stats::{compute_graph_stats, ColorsStats, GraphStats, HistogramBin, LinksStats};
+
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
 pub enum QuerierStartingStep {
     MinimizerBucketing = 0,
@@ -46,22 +46,22 @@ pub fn dump_unitigs(
 
     PHASES_TIMES_MONITOR.write().init();
 
+    check_cancelled()?;
+
     let color_map =
         <ColorBundleGraphQuerying as ColorsManager>::ColorsMergeManagerType::open_colors_table(
             graph_input.as_ref().with_extension("colors.dat"),
         )?;
 
-    // TODO: Support GFA input
-    let file_stats = compute_stats_from_input_blocks(&[GeneralSequenceBlockData::FASTA((
+    let file_stats = compute_stats_from_input_blocks(&[GeneralSequenceBlockData::from_file(
         graph_input.as_ref().to_path_buf(),
         None,
-    ))])?;
+    )])?;
 
     let buckets_count_log = buckets_count_log.unwrap_or_else(|| file_stats.best_buckets_count_log);
 
     if let Some(default_compression_level) = default_compression_level {
-        INTERMEDIATE_COMPRESSION_LEVEL_SLOW.store(default_compression_level, Ordering::Relaxed);
-        INTERMEDIATE_COMPRESSION_LEVEL_FAST.store(default_compression_level, Ordering::Relaxed);
+        set_intermediate_compression_level(default_compression_level);
     }
 
     let buckets_count = 1 << buckets_count_log;
@@ -75,10 +75,12 @@ pub fn dump_unitigs(
         m,
         color_map.colors_subsets_count(),
     );
-    let _ = remove_file(buckets_stats);
+    let _ = MemoryFs::remove_file(buckets_stats, RemoveFileMode::Remove { remove_fs: true });
 
     MemoryFs::flush_all_to_disk();
     MemoryFs::free_memory();
+    check_memory_only_budget()?;
+    check_cancelled()?;
 
     let colormap_file = graph_input.as_ref().with_extension("colors.dat");
     colormap_reading::<ColorBundleGraphQuerying, DefaultColorsSerializer>(
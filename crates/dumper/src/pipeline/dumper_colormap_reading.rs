@@ -3,7 +3,7 @@ use colors::colors_manager::color_types::SingleKmerColorDataType;
 use colors::colors_manager::ColorsManager;
 use colors::storage::deserializer::ColorsDeserializer;
 use colors::storage::ColorsSerializerTrait;
-use config::{ColorIndexType, DEFAULT_PREFETCH_AMOUNT, KEEP_FILES};
+use config::{keep_files, ColorIndexType, DEFAULT_PREFETCH_AMOUNT};
 use io::compressed_read::CompressedReadIndipendent;
 use io::concurrent::temp_reads::creads_utils::{
     CompressedReadsBucketDataSerializer, NoMultiplicity, NoSecondBucket,
@@ -20,7 +20,6 @@ use parking_lot::Mutex;
 use rayon::prelude::*;
 use std::marker::PhantomData;
 use std::path::PathBuf;
-use std::sync::atomic::Ordering;
 
 pub fn colormap_reading<
     CX: ColorsManager<SingleKmerColorDataType = ColorIndexType>,
@@ -55,7 +54,7 @@ pub fn colormap_reading<
         CompressedBinaryReader::new(
             &input.path,
             RemoveFileMode::Remove {
-                remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                remove_fs: !keep_files(),
             },
             DEFAULT_PREFETCH_AMOUNT,
         )
@@ -9,7 +9,7 @@ use io::concurrent::temp_reads::extra_data::{
     SequenceExtraDataTempBufferManagement,
 };
 use io::sequences_reader::{DnaSequence, DnaSequencesFileType};
-use io::sequences_stream::fasta::FastaFileSequencesStream;
+use io::sequences_stream::general::{GeneralSequenceBlockData, GeneralSequencesStream};
 use io::sequences_stream::SequenceInfo;
 use minimizer_bucketing::resplit_bucket::RewriteBucketCompute;
 use minimizer_bucketing::{
@@ -284,11 +284,11 @@ pub fn minimizer_bucketing<CX: ColorsManager>(
         .write()
         .start_phase("phase: unitigs reorganization".to_string());
 
-    let input_files = vec![((graph_file, None), ())];
+    let input_files = vec![(GeneralSequenceBlockData::from_file(graph_file, None), ())];
 
     GenericMinimizerBucketing::do_bucketing_no_max_usage::<
         DumperMinimizerBucketingExecutorFactory<CX>,
-        FastaFileSequencesStream,
+        GeneralSequencesStream,
     >(
         input_files.into_iter(),
         temp_dir,
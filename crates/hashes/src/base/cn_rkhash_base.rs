@@ -174,6 +174,7 @@ impl HashFunctionFactory for CanonicalRabinKarpHashFactory {
         DummyHasherBuilder
     }
 
+    const NAME: &'static str = HASH_NAME;
     const NULL_BASE: u8 = 0;
     const USABLE_HASH_BITS: usize = size_of::<Self::HashTypeUnextendable>() * 8 - 1; // -1 because the hash is always odd
 
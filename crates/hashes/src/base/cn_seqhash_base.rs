@@ -1,3 +1,4 @@
+use crate::seqhash_int::SeqHashInt;
 use crate::{ExtendableHashTraitType, HashFunction, HashFunctionFactory, HashableSequence};
 use config::BucketIndexType;
 use dynamic_dispatch::dynamic_dispatch;
@@ -14,7 +15,7 @@ pub struct CanonicalSeqHashIterator<N: HashableSequence> {
 
 #[inline(always)]
 fn get_mask(k: usize) -> HashIntegerType {
-    HashIntegerType::MAX >> ((((size_of::<HashIntegerType>() * 4) - k) * 2) as HashIntegerType)
+    HashIntegerType::MAX >> (((size_of::<HashIntegerType>() * 4) - k) * 2)
 }
 
 impl<N: HashableSequence> CanonicalSeqHashIterator<N> {
@@ -23,11 +24,11 @@ impl<N: HashableSequence> CanonicalSeqHashIterator<N> {
             return Err("K out of range!");
         }
 
-        let mut fh = 0;
-        let mut bw = 0;
+        let mut fh = HashIntegerType::default();
+        let mut bw = HashIntegerType::default();
         for i in 0..(k - 1) {
-            fh |= unsafe { seq.get_unchecked_cbase(i) as HashIntegerType } << (i * 2);
-            bw = (bw << 2) | unsafe { xrc(seq.get_unchecked_cbase(i) as HashIntegerType) };
+            fh |= HashIntegerType::from(unsafe { seq.get_unchecked_cbase(i) }) << (i * 2);
+            bw = (bw << 2) | unsafe { xrc(seq.get_unchecked_cbase(i)) };
         }
 
         let mask = get_mask(k);
@@ -46,12 +47,11 @@ impl<N: HashableSequence> CanonicalSeqHashIterator<N> {
         assert!(unsafe { self.seq.get_unchecked_cbase(index) } < 4);
 
         self.fh = (self.fh >> 2)
-            | ((unsafe { self.seq.get_unchecked_cbase(index) as HashIntegerType })
+            | (HashIntegerType::from(unsafe { self.seq.get_unchecked_cbase(index) })
                 << (self.k_minus1 * 2));
 
-        self.rc = ((self.rc << 2)
-            | unsafe { xrc(self.seq.get_unchecked_cbase(index) as HashIntegerType) })
-            & self.mask;
+        self.rc =
+            ((self.rc << 2) | unsafe { xrc(self.seq.get_unchecked_cbase(index)) }) & self.mask;
 
         ExtCanonicalSeqHash(self.fh, self.rc)
     }
@@ -114,6 +114,7 @@ impl HashFunctionFactory for CanonicalSeqHashFactory {
         ahash::RandomState::new()
     }
 
+    const NAME: &'static str = HASH_NAME;
     const NULL_BASE: u8 = 0;
     const USABLE_HASH_BITS: usize = size_of::<Self::HashTypeUnextendable>() * 8;
 
@@ -129,20 +130,20 @@ impl HashFunctionFactory for CanonicalSeqHashFactory {
         requested_bits: usize,
         hash: Self::HashTypeUnextendable,
     ) -> BucketIndexType {
-        ((hash >> used_bits) % (1 << requested_bits)) as BucketIndexType
+        ((hash >> used_bits).to_u64_lossy() % (1 << requested_bits)) as BucketIndexType
     }
 
     fn get_shifted(hash: Self::HashTypeUnextendable, shift: u8) -> u8 {
-        (hash >> shift) as u8
+        (hash >> (shift as usize)).to_u64_lossy() as u8
     }
 
     #[inline(always)]
     fn get_u64(hash: Self::HashTypeUnextendable) -> u64 {
-        hash as u64
+        hash.to_u64_lossy()
     }
 
     fn debug_eq_to_u128(hash: Self::HashTypeUnextendable, value: u128) -> bool {
-        hash as u128 == value
+        hash.to_u128_lossy() == value
     }
 
     fn manual_roll_forward(
@@ -157,8 +158,8 @@ impl HashFunctionFactory for CanonicalSeqHashFactory {
         // 00CCAA
         let mask = get_mask(k);
         ExtCanonicalSeqHash(
-            (hash.0 >> 2) | ((in_base as HashIntegerType) << ((k - 1) * 2)),
-            ((hash.1 << 2) | (xrc(in_base as HashIntegerType))) & mask,
+            (hash.0 >> 2) | (HashIntegerType::from(in_base) << ((k - 1) * 2)),
+            ((hash.1 << 2) | xrc(in_base)) & mask,
         )
     }
 
@@ -175,8 +176,8 @@ impl HashFunctionFactory for CanonicalSeqHashFactory {
 
         let mask = get_mask(k);
         ExtCanonicalSeqHash(
-            ((hash.0 << 2) | (in_base as HashIntegerType)) & mask,
-            (hash.1 >> 2) | (xrc(in_base as HashIntegerType) << ((k - 1) * 2)),
+            ((hash.0 << 2) | HashIntegerType::from(in_base)) & mask,
+            (hash.1 >> 2) | (xrc(in_base) << ((k - 1) * 2)),
         )
     }
 
@@ -214,8 +215,8 @@ impl HashFunctionFactory for CanonicalSeqHashFactory {
 
 // Returns the complement of a compressed format base
 #[inline(always)]
-fn xrc(base: HashIntegerType) -> HashIntegerType {
-    base ^ 2
+fn xrc(base: u8) -> HashIntegerType {
+    HashIntegerType::from(base ^ 2)
 }
 
 #[cfg(test)]
@@ -1,3 +1,4 @@
+use crate::seqhash_int::SeqHashInt;
 use crate::{ExtendableHashTraitType, HashFunction, HashFunctionFactory, HashableSequence};
 use config::BucketIndexType;
 use dynamic_dispatch::dynamic_dispatch;
@@ -11,7 +12,7 @@ pub struct ForwardSeqHashIterator<N: HashableSequence> {
 
 #[inline(always)]
 fn get_mask(k: usize) -> HashIntegerType {
-    HashIntegerType::MAX >> ((((size_of::<HashIntegerType>() * 4) - k) * 2) as HashIntegerType)
+    HashIntegerType::MAX >> (((size_of::<HashIntegerType>() * 4) - k) * 2)
 }
 
 impl<N: HashableSequence> ForwardSeqHashIterator<N> {
@@ -20,9 +21,9 @@ impl<N: HashableSequence> ForwardSeqHashIterator<N> {
             return Err("K out of range!");
         }
 
-        let mut fh = 0;
+        let mut fh = HashIntegerType::default();
         for i in 0..(k - 1) {
-            fh |= unsafe { seq.get_unchecked_cbase(i) as HashIntegerType } << (i * 2);
+            fh |= HashIntegerType::from(unsafe { seq.get_unchecked_cbase(i) }) << (i * 2);
         }
 
         Ok(ForwardSeqHashIterator {
@@ -37,7 +38,7 @@ impl<N: HashableSequence> ForwardSeqHashIterator<N> {
         assert!(unsafe { self.seq.get_unchecked_cbase(index) } < 4);
 
         self.fh = (self.fh >> 2)
-            | ((unsafe { self.seq.get_unchecked_cbase(index) as HashIntegerType })
+            | (HashIntegerType::from(unsafe { self.seq.get_unchecked_cbase(index) })
                 << (self.k_minus1 * 2));
 
         ExtForwardSeqHash(self.fh)
@@ -102,6 +103,7 @@ impl HashFunctionFactory for ForwardSeqHashFactory {
         ahash::RandomState::new()
     }
 
+    const NAME: &'static str = HASH_NAME;
     const NULL_BASE: u8 = 0;
     const USABLE_HASH_BITS: usize = size_of::<Self::HashTypeUnextendable>() * 8;
 
@@ -117,20 +119,20 @@ impl HashFunctionFactory for ForwardSeqHashFactory {
         requested_bits: usize,
         hash: Self::HashTypeUnextendable,
     ) -> BucketIndexType {
-        ((hash >> used_bits) % (1 << requested_bits)) as BucketIndexType
+        ((hash >> used_bits).to_u64_lossy() % (1 << requested_bits)) as BucketIndexType
     }
 
     fn get_shifted(hash: Self::HashTypeUnextendable, shift: u8) -> u8 {
-        (hash >> shift) as u8
+        (hash >> (shift as usize)).to_u64_lossy() as u8
     }
 
     #[inline(always)]
     fn get_u64(hash: Self::HashTypeUnextendable) -> u64 {
-        hash as u64
+        hash.to_u64_lossy()
     }
 
     fn debug_eq_to_u128(hash: Self::HashTypeUnextendable, value: u128) -> bool {
-        hash as u128 == value
+        hash.to_u128_lossy() == value
     }
 
     fn manual_roll_forward(
@@ -144,7 +146,7 @@ impl HashFunctionFactory for ForwardSeqHashFactory {
         // 00AABB => roll CC
         // 00CCAA
 
-        ExtForwardSeqHash((hash.0 >> 2) | ((in_base as HashIntegerType) << ((k - 1) * 2)))
+        ExtForwardSeqHash((hash.0 >> 2) | (HashIntegerType::from(in_base) << ((k - 1) * 2)))
     }
 
     fn manual_roll_reverse(
@@ -159,7 +161,7 @@ impl HashFunctionFactory for ForwardSeqHashFactory {
         // 00BBCC
 
         let mask = get_mask(k);
-        ExtForwardSeqHash(((hash.0 << 2) | (in_base as HashIntegerType)) & mask)
+        ExtForwardSeqHash(((hash.0 << 2) | HashIntegerType::from(in_base)) & mask)
     }
 
     fn manual_remove_only_forward(
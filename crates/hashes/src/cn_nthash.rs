@@ -115,6 +115,7 @@ impl HashFunctionFactory for CanonicalNtHashIteratorFactory {
         DummyHasherBuilder
     }
 
+    const NAME: &'static str = "cn_nthash";
     // Corresponds to 'N' hash (zero)
     const NULL_BASE: u8 = 4;
     const USABLE_HASH_BITS: usize = size_of::<Self::HashTypeUnextendable>() * 8;
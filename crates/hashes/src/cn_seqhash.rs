@@ -1,19 +1,29 @@
 pub mod u16 {
     type HashIntegerType = u16;
+    const HASH_NAME: &str = "cn_seqhash_u16";
     include!("base/cn_seqhash_base.rs");
 }
 
 pub mod u32 {
     type HashIntegerType = u32;
+    const HASH_NAME: &str = "cn_seqhash_u32";
     include!("base/cn_seqhash_base.rs");
 }
 
 pub mod u64 {
     type HashIntegerType = u64;
+    const HASH_NAME: &str = "cn_seqhash_u64";
     include!("base/cn_seqhash_base.rs");
 }
 
 pub mod u128 {
     type HashIntegerType = u128;
+    const HASH_NAME: &str = "cn_seqhash_u128";
+    include!("base/cn_seqhash_base.rs");
+}
+
+pub mod u256 {
+    type HashIntegerType = crate::seqhash_int::u256;
+    const HASH_NAME: &str = "cn_seqhash_u256";
     include!("base/cn_seqhash_base.rs");
 }
@@ -102,6 +102,7 @@ impl HashFunctionFactory for ForwardNtHashIteratorFactory {
         DummyHasherBuilder {}
     }
 
+    const NAME: &'static str = "fw_nthash";
     // Corresponds to 'N' hash (zero)
     const NULL_BASE: u8 = 4;
     const USABLE_HASH_BITS: usize = size_of::<Self::HashTypeUnextendable>() * 8;
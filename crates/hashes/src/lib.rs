@@ -10,6 +10,7 @@ pub mod cn_rkhash;
 pub mod dummy_hasher;
 pub mod fw_rkhash;
 pub mod rolling;
+pub mod seqhash_int;
 
 use std::fmt::{Debug, Display};
 use std::hash::{BuildHasher, Hash};
@@ -78,6 +79,8 @@ pub trait HashFunctionFactory: Sized + Clone + Debug + Send + Sync + 'static {
     type PreferredRandomState: BuildHasher;
     fn get_random_state() -> Self::PreferredRandomState;
 
+    /// Name of the hash function that does not change between the releases, used to identify it in the saved builds
+    const NAME: &'static str;
     const NULL_BASE: u8;
     const USABLE_HASH_BITS: usize;
 
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::ops::{BitAnd, BitOr, BitOrAssign, Shl, Shr};
+
+/// Integer types used to store the 2-bit packed bases of the sequence hashes
+pub(crate) trait SeqHashInt: Copy {
+    /// Returns the lowest 64 bits of the value
+    fn to_u64_lossy(self) -> u64;
+    /// Returns the lowest 128 bits of the value
+    fn to_u128_lossy(self) -> u128;
+}
+
+macro_rules! impl_seqhash_int {
+    ($($int:ty),*) => {
+        $(
+            impl SeqHashInt for $int {
+                #[inline(always)]
+                fn to_u64_lossy(self) -> u64 {
+                    self as u64
+                }
+
+                #[inline(always)]
+                fn to_u128_lossy(self) -> u128 {
+                    self as u128
+                }
+            }
+        )*
+    };
+}
+
+impl_seqhash_int!(u16, u32, u64, u128);
+
+/// 256 bit unsigned integer, used to store exactly kmers with k up to 128
+#[allow(non_camel_case_types)]
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct u256 {
+    // Field order matters, as the ordering is derived
+    hi: u128,
+    lo: u128,
+}
+
+impl u256 {
+    pub const MAX: Self = Self {
+        hi: u128::MAX,
+        lo: u128::MAX,
+    };
+
+    #[inline(always)]
+    pub const fn from_parts(hi: u128, lo: u128) -> Self {
+        Self { hi, lo }
+    }
+
+    #[inline(always)]
+    pub fn to_le_bytes(self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes[..16].copy_from_slice(&self.lo.to_le_bytes());
+        bytes[16..].copy_from_slice(&self.hi.to_le_bytes());
+        bytes
+    }
+}
+
+impl SeqHashInt for u256 {
+    #[inline(always)]
+    fn to_u64_lossy(self) -> u64 {
+        self.lo as u64
+    }
+
+    #[inline(always)]
+    fn to_u128_lossy(self) -> u128 {
+        self.lo
+    }
+}
+
+impl From<u8> for u256 {
+    #[inline(always)]
+    fn from(value: u8) -> Self {
+        Self {
+            hi: 0,
+            lo: value as u128,
+        }
+    }
+}
+
+impl Shl<usize> for u256 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn shl(self, rhs: usize) -> Self {
+        debug_assert!(rhs < 256, "attempt to shift left with overflow");
+        if rhs == 0 {
+            self
+        } else if rhs < 128 {
+            Self {
+                hi: (self.hi << rhs) | (self.lo >> (128 - rhs)),
+                lo: self.lo << rhs,
+            }
+        } else {
+            Self {
+                hi: self.lo << (rhs - 128),
+                lo: 0,
+            }
+        }
+    }
+}
+
+impl Shr<usize> for u256 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn shr(self, rhs: usize) -> Self {
+        debug_assert!(rhs < 256, "attempt to shift right with overflow");
+        if rhs == 0 {
+            self
+        } else if rhs < 128 {
+            Self {
+                hi: self.hi >> rhs,
+                lo: (self.lo >> rhs) | (self.hi << (128 - rhs)),
+            }
+        } else {
+            Self {
+                hi: 0,
+                lo: self.hi >> (rhs - 128),
+            }
+        }
+    }
+}
+
+impl BitOr for u256 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitor(self, rhs: Self) -> Self {
+        Self {
+            hi: self.hi | rhs.hi,
+            lo: self.lo | rhs.lo,
+        }
+    }
+}
+
+impl BitOrAssign for u256 {
+    #[inline(always)]
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+impl BitAnd for u256 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitand(self, rhs: Self) -> Self {
+        Self {
+            hi: self.hi & rhs.hi,
+            lo: self.lo & rhs.lo,
+        }
+    }
+}
+
+impl Display for u256 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{:032x}{:032x}", self.hi, self.lo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::u256;
+
+    #[test]
+    fn u256_shifts() {
+        let value = u256::from(3);
+        assert_eq!(value << 0, value);
+        assert_eq!(value << 127, u256::from_parts(1, 1 << 127));
+        assert_eq!((value << 127) >> 127, value);
+        assert_eq!(value << 200, u256::from_parts(3 << 72, 0));
+        assert_eq!((value << 200) >> 200, value);
+        assert_eq!(u256::MAX >> 255, u256::from(1));
+        assert!(value << 128 > u256::from_parts(0, u128::MAX));
+    }
+}
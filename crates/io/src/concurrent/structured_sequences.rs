@@ -50,35 +50,82 @@ impl IdentSequenceWriter for () {
 }
 
 #[derive(Clone, Debug, Default)]
-#[cfg(feature = "support_kmer_counters")]
 pub struct SequenceAbundance {
     pub first: u64,
     pub sum: u64,
     pub last: u64,
+    /// The multiplicity of each kmer of the sequence, only filled with [`AbundanceOutputMode::PerKmer`]
+    pub kmers: Vec<u64>,
 }
 
-#[cfg(feature = "support_kmer_counters")]
-pub type SequenceAbundanceType = SequenceAbundance;
+/// The kmer abundance information written in the sequences headers of the final graph
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AbundanceOutputMode {
+    #[default]
+    None,
+    /// Total kmer count and mean kmer coverage (BCALM2 compatible KC:i: and km:f: tags)
+    Summary,
+    /// Summary tags plus the multiplicity of every kmer (ka:B:I tag)
+    PerKmer,
+}
 
-#[cfg(not(feature = "support_kmer_counters"))]
-pub type SequenceAbundanceType = ();
+impl SequenceAbundance {
+    /// Writes the tags selected by the abundance mode, each one prefixed by the separator
+    pub fn write_tags(
+        &self,
+        k: usize,
+        length: usize,
+        mode: AbundanceOutputMode,
+        separator: char,
+        stream: &mut impl Write,
+    ) {
+        if mode == AbundanceOutputMode::None {
+            return;
+        }
+
+        write!(
+            stream,
+            "{}KC:i:{}{}km:f:{:.1}",
+            separator,
+            self.sum,
+            separator,
+            self.sum as f64 / (length - k + 1) as f64
+        )
+        .unwrap();
+
+        if mode == AbundanceOutputMode::PerKmer {
+            write!(stream, "{}ka:B:I", separator).unwrap();
+            for multiplicity in &self.kmers {
+                write!(stream, ",{}", multiplicity).unwrap();
+            }
+        }
+    }
+}
+
+impl AbundanceOutputMode {
+    pub fn needs_kmers_abundances(&self) -> bool {
+        *self == AbundanceOutputMode::PerKmer
+    }
+}
 
 pub trait StructuredSequenceBackendInit: Sync + Send + Sized {
-    fn new_compressed_gzip(_path: impl AsRef<Path>, _level: u32) -> Self {
+    fn new_compressed_gzip(_path: impl AsRef<Path>, _level: u32) -> std::io::Result<Self> {
         unimplemented!()
     }
 
-    fn new_compressed_lz4(_path: impl AsRef<Path>, _level: u32) -> Self {
+    fn new_compressed_lz4(_path: impl AsRef<Path>, _level: u32) -> std::io::Result<Self> {
         unimplemented!()
     }
 
-    fn new_plain(_path: impl AsRef<Path>) -> Self {
+    fn new_plain(_path: impl AsRef<Path>) -> std::io::Result<Self> {
         unimplemented!()
     }
 }
 
 #[dynamic_dispatch]
 pub trait StructuredSequenceBackendWrapper: 'static {
+    /// Name of the output format, used to identify it in the saved builds
+    const NAME: &'static str;
     type Backend<ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenceWriter>:
          StructuredSequenceBackendInit +
          StructuredSequenceBackend<ColorInfo, LinksInfo>;
@@ -101,7 +148,8 @@ pub trait StructuredSequenceBackend<ColorInfo: IdentSequenceWriter, LinksInfo: I
         links_info: LinksInfo,
         extra_buffers: &(ColorInfo::TempBuffer, LinksInfo::TempBuffer),
 
-        #[cfg(feature = "support_kmer_counters")] abundance: SequenceAbundance,
+        abundance: SequenceAbundance,
+        abundance_mode: AbundanceOutputMode,
     );
 
     fn get_path(&self) -> PathBuf;
@@ -118,6 +166,7 @@ pub struct StructuredSequenceWriter<
 > {
     current_index: Mutex<(u64, u64)>,
     k: usize,
+    abundance_mode: AbundanceOutputMode,
     backend: Mutex<Backend>,
     index_condvar: Condvar,
     _phantom: PhantomData<(ColorInfo, LinksInfo, Backend)>,
@@ -133,19 +182,27 @@ impl<
         Self {
             current_index: Mutex::new((0, 0)),
             k,
+            abundance_mode: AbundanceOutputMode::None,
             backend: Mutex::new(backend),
             index_condvar: Condvar::new(),
             _phantom: PhantomData,
         }
     }
 
+    pub fn with_abundance_mode(mut self, abundance_mode: AbundanceOutputMode) -> Self {
+        self.abundance_mode = abundance_mode;
+        self
+    }
+
+    pub fn get_abundance_mode(&self) -> AbundanceOutputMode {
+        self.abundance_mode
+    }
+
     fn write_sequences<'a>(
         &self,
         buffer: &mut Backend::SequenceTempBuffer,
         first_index: Option<u64>,
-        sequences: impl ExactSizeIterator<
-            Item = (&'a [u8], ColorInfo, LinksInfo, SequenceAbundanceType),
-        >,
+        sequences: impl ExactSizeIterator<Item = (&'a [u8], ColorInfo, LinksInfo, SequenceAbundance)>,
         extra_buffers: &(ColorInfo::TempBuffer, LinksInfo::TempBuffer),
     ) -> u64 {
         let sequences_count = sequences.len() as u64;
@@ -164,7 +221,7 @@ impl<
 
         let mut current_index = start_sequence_index;
         // Write the sequences to a temporary buffer
-        for (sequence, color_info, links_info, _abundance) in sequences {
+        for (sequence, color_info, links_info, abundance) in sequences {
             Backend::write_sequence(
                 self.k,
                 buffer,
@@ -173,8 +230,8 @@ impl<
                 color_info,
                 links_info,
                 extra_buffers,
-                #[cfg(feature = "support_kmer_counters")]
-                _abundance,
+                abundance,
+                self.abundance_mode,
             );
             current_index += 1;
         }
@@ -205,3 +262,31 @@ impl<
         self.backend.into_inner().finalize();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AbundanceOutputMode, SequenceAbundance};
+
+    #[test]
+    fn abundance_tags() {
+        let abundance = SequenceAbundance {
+            first: 2,
+            sum: 9,
+            last: 4,
+            kmers: vec![2, 3, 4],
+        };
+
+        let write_tags = |mode| {
+            let mut buffer = vec![];
+            abundance.write_tags(3, 5, mode, ' ', &mut buffer);
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(write_tags(AbundanceOutputMode::None), "");
+        assert_eq!(write_tags(AbundanceOutputMode::Summary), " KC:i:9 km:f:3.0");
+        assert_eq!(
+            write_tags(AbundanceOutputMode::PerKmer),
+            " KC:i:9 km:f:3.0 ka:B:I,2,3,4"
+        );
+    }
+}
@@ -8,6 +8,7 @@ use crate::concurrent::temp_reads::extra_data::{
 };
 use crate::varint::{decode_varint, encode_varint, VARINT_MAX_SIZE};
 use byteorder::ReadBytesExt;
+use config::operation::MemoryTrackedBucket;
 use config::DEFAULT_PER_CPU_BUFFER_SIZE;
 use parallel_processor::buckets::bucket_writer::BucketItemSerializer;
 use parallel_processor::buckets::writers::compressed_binary_writer::{
@@ -20,13 +21,13 @@ use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 
-use super::SequenceAbundanceType;
+use super::{AbundanceOutputMode, SequenceAbundance};
 
 pub struct StructSeqBinaryWriter<
     ColorInfo: IdentSequenceWriter + SequenceExtraDataConsecutiveCompression,
     LinksInfo: IdentSequenceWriter + SequenceExtraData,
 > {
-    writer: CompressedBinaryWriter,
+    writer: MemoryTrackedBucket<CompressedBinaryWriter>,
     _phantom: PhantomData<(ColorInfo, LinksInfo)>,
 }
 
@@ -59,14 +60,19 @@ impl<
         data_format: &T,
     ) -> Self {
         Self {
-            writer: CompressedBinaryWriter::new(path.as_ref(), file_mode, 0, data_format),
+            writer: MemoryTrackedBucket::<CompressedBinaryWriter>::new(
+                path.as_ref(),
+                file_mode,
+                0,
+                data_format,
+            ),
             _phantom: Default::default(),
         }
     }
 }
 
 impl<CX: SequenceExtraDataTempBufferManagement, LX: SequenceExtraDataTempBufferManagement>
-    SequenceExtraDataTempBufferManagement for (u64, CX, LX, SequenceAbundanceType)
+    SequenceExtraDataTempBufferManagement for (u64, CX, LX, SequenceAbundance)
 {
     type TempBuffer = (CX::TempBuffer, LX::TempBuffer);
 
@@ -97,7 +103,7 @@ impl<CX: SequenceExtraDataTempBufferManagement, LX: SequenceExtraDataTempBufferM
 }
 
 impl<CX: SequenceExtraDataConsecutiveCompression, LX: SequenceExtraData>
-    SequenceExtraDataConsecutiveCompression for (u64, CX, LX, SequenceAbundanceType)
+    SequenceExtraDataConsecutiveCompression for (u64, CX, LX, SequenceAbundance)
 {
     type LastData = CX::LastData;
 
@@ -107,28 +113,25 @@ impl<CX: SequenceExtraDataConsecutiveCompression, LX: SequenceExtraData>
         last_data: Self::LastData,
     ) -> Option<Self> {
         let index = decode_varint(|| reader.read_u8().ok())?;
-        #[cfg(feature = "support_kmer_counters")]
-        let (abundance_first, abundance_sum, abundance_last) = {
-            (
-                decode_varint(|| reader.read_u8().ok())?,
-                decode_varint(|| reader.read_u8().ok())?,
-                decode_varint(|| reader.read_u8().ok())?,
-            )
-        };
+        let first = decode_varint(|| reader.read_u8().ok())?;
+        let sum = decode_varint(|| reader.read_u8().ok())?;
+        let last = decode_varint(|| reader.read_u8().ok())?;
+
+        let kmers_count = decode_varint(|| reader.read_u8().ok())? as usize;
+        let mut kmers = Vec::with_capacity(kmers_count);
+        for _ in 0..kmers_count {
+            kmers.push(decode_varint(|| reader.read_u8().ok())?);
+        }
 
         Some((
             index,
             CX::decode_extended(&mut buffer.0, reader, last_data)?,
             LX::decode_extended(&mut buffer.1, reader)?,
-            match () {
-                #[cfg(feature = "support_kmer_counters")]
-                () => SequenceAbundanceType {
-                    first: abundance_first,
-                    sum: abundance_sum,
-                    last: abundance_last,
-                },
-                #[cfg(not(feature = "support_kmer_counters"))]
-                () => (),
+            SequenceAbundance {
+                first,
+                sum,
+                last,
+                kmers,
             },
         ))
     }
@@ -140,11 +143,13 @@ impl<CX: SequenceExtraDataConsecutiveCompression, LX: SequenceExtraData>
         last_data: Self::LastData,
     ) {
         encode_varint(|b| writer.write_all(b).ok(), self.0).unwrap();
-        #[cfg(feature = "support_kmer_counters")]
-        {
-            encode_varint(|b| writer.write_all(b).ok(), self.3.first).unwrap();
-            encode_varint(|b| writer.write_all(b).ok(), self.3.sum).unwrap();
-            encode_varint(|b| writer.write_all(b).ok(), self.3.last).unwrap();
+        encode_varint(|b| writer.write_all(b).ok(), self.3.first).unwrap();
+        encode_varint(|b| writer.write_all(b).ok(), self.3.sum).unwrap();
+        encode_varint(|b| writer.write_all(b).ok(), self.3.last).unwrap();
+
+        encode_varint(|b| writer.write_all(b).ok(), self.3.kmers.len() as u64).unwrap();
+        for multiplicity in &self.3.kmers {
+            encode_varint(|b| writer.write_all(b).ok(), *multiplicity).unwrap();
         }
 
         self.1.encode_extended(&buffer.0, writer, last_data);
@@ -152,7 +157,7 @@ impl<CX: SequenceExtraDataConsecutiveCompression, LX: SequenceExtraData>
     }
 
     fn max_size(&self) -> usize {
-        VARINT_MAX_SIZE * 4 + self.1.max_size() + self.2.max_size()
+        VARINT_MAX_SIZE * (5 + self.3.kmers.len()) + self.1.max_size() + self.2.max_size()
     }
 
     fn obtain_last_data(&self, last_data: Self::LastData) -> Self::LastData {
@@ -169,7 +174,7 @@ impl<
     type SequenceTempBuffer = (
         Vec<u8>,
         CompressedReadsBucketDataSerializer<
-            (u64, ColorInfo, LinksInfo, SequenceAbundanceType),
+            (u64, ColorInfo, LinksInfo, SequenceAbundance),
             typenum::consts::U0,
             NoSecondBucket,
             NoMultiplicity,
@@ -192,22 +197,13 @@ impl<
         color_info: ColorInfo,
         links_info: LinksInfo,
         extra_buffers: &(ColorInfo::TempBuffer, LinksInfo::TempBuffer),
-        #[cfg(feature = "support_kmer_counters")] abundance: SequenceAbundanceType,
+        abundance: SequenceAbundance,
+        _abundance_mode: AbundanceOutputMode,
     ) {
         buffer.1.write_to(
             &CompressedReadsBucketData::new(sequence, 0, 0),
             &mut buffer.0,
-            &(
-                sequence_index,
-                color_info,
-                links_info,
-                match () {
-                    #[cfg(feature = "support_kmer_counters")]
-                    () => abundance,
-                    #[cfg(not(feature = "support_kmer_counters"))]
-                    () => (),
-                },
-            ),
+            &(sequence_index, color_info, links_info, abundance),
             &extra_buffers,
         );
     }
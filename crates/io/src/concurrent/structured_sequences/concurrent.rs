@@ -3,7 +3,7 @@ use crate::concurrent::structured_sequences::{
 };
 use utils::vec_slice::VecSlice;
 
-use super::SequenceAbundanceType;
+use super::SequenceAbundance;
 
 pub struct FastaWriterConcurrentBuffer<
     'a,
@@ -12,7 +12,7 @@ pub struct FastaWriterConcurrentBuffer<
     Backend: StructuredSequenceBackend<ColorInfo, LinksInfo>,
 > {
     target: &'a StructuredSequenceWriter<ColorInfo, LinksInfo, Backend>,
-    sequences: Vec<(VecSlice<u8>, ColorInfo, LinksInfo, SequenceAbundanceType)>,
+    sequences: Vec<(VecSlice<u8>, ColorInfo, LinksInfo, SequenceAbundance)>,
     seq_buf: Vec<u8>,
     extra_buffers: (ColorInfo::TempBuffer, LinksInfo::TempBuffer),
     temp_buffer: Backend::SequenceTempBuffer,
@@ -79,7 +79,7 @@ impl<
         color_extra_buffer: &ColorInfo::TempBuffer,
         links: LinksInfo,
         links_extra_buffer: &LinksInfo::TempBuffer,
-        #[cfg(feature = "support_kmer_counters")] abundance: SequenceAbundanceType,
+        abundance: SequenceAbundance,
     ) -> Option<u64> {
         let mut result = None;
         let mut different_index = false;
@@ -106,12 +106,7 @@ impl<
             VecSlice::new_extend(&mut self.seq_buf, sequence),
             color,
             links,
-            match () {
-                #[cfg(feature = "support_kmer_counters")]
-                () => abundance,
-                #[cfg(not(feature = "support_kmer_counters"))]
-                () => (),
-            },
+            abundance,
         ));
 
         if let Some(current_index) = &mut self.current_index {
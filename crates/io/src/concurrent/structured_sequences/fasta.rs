@@ -11,14 +11,16 @@ use std::path::{Path, PathBuf};
 
 use super::stream_finish::SequencesWriterWrapper;
 
-#[cfg(feature = "support_kmer_counters")]
-use super::SequenceAbundance;
-use super::{StructuredSequenceBackendInit, StructuredSequenceBackendWrapper};
+use super::{
+    AbundanceOutputMode, SequenceAbundance, StructuredSequenceBackendInit,
+    StructuredSequenceBackendWrapper,
+};
 
 pub struct FastaWriterWrapper;
 
 #[dynamic_dispatch]
 impl StructuredSequenceBackendWrapper for FastaWriterWrapper {
+    const NAME: &'static str = "fasta";
     type Backend<ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenceWriter> =
         FastaWriter<ColorInfo, LinksInfo>;
 }
@@ -42,23 +44,23 @@ unsafe impl<ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenceWriter> Sync
 impl<ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenceWriter> StructuredSequenceBackendInit
     for FastaWriter<ColorInfo, LinksInfo>
 {
-    fn new_compressed_gzip(path: impl AsRef<Path>, level: u32) -> Self {
+    fn new_compressed_gzip(path: impl AsRef<Path>, level: u32) -> std::io::Result<Self> {
         let compress_stream = GzEncoder::new(
-            BufWriter::with_capacity(DEFAULT_OUTPUT_BUFFER_SIZE, File::create(&path).unwrap()),
+            BufWriter::with_capacity(DEFAULT_OUTPUT_BUFFER_SIZE, File::create(&path)?),
             Compression::new(level),
         );
 
-        FastaWriter {
+        Ok(FastaWriter {
             writer: Box::new(SequencesWriterWrapper::new(BufWriter::with_capacity(
                 DEFAULT_OUTPUT_BUFFER_SIZE,
                 compress_stream,
             ))),
             path: path.as_ref().to_path_buf(),
             _phantom: PhantomData,
-        }
+        })
     }
 
-    fn new_compressed_lz4(path: impl AsRef<Path>, level: u32) -> Self {
+    fn new_compressed_lz4(path: impl AsRef<Path>, level: u32) -> std::io::Result<Self> {
         let compress_stream = lz4::EncoderBuilder::new()
             .level(level)
             .checksum(ContentChecksum::NoChecksum)
@@ -66,29 +68,28 @@ impl<ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenceWriter> StructuredS
             .block_size(BlockSize::Max1MB)
             .build(BufWriter::with_capacity(
                 DEFAULT_OUTPUT_BUFFER_SIZE,
-                File::create(&path).unwrap(),
-            ))
-            .unwrap();
+                File::create(&path)?,
+            ))?;
 
-        FastaWriter {
+        Ok(FastaWriter {
             writer: Box::new(SequencesWriterWrapper::new(BufWriter::with_capacity(
                 DEFAULT_OUTPUT_BUFFER_SIZE,
                 compress_stream,
             ))),
             path: path.as_ref().to_path_buf(),
             _phantom: PhantomData,
-        }
+        })
     }
 
-    fn new_plain(path: impl AsRef<Path>) -> Self {
-        FastaWriter {
+    fn new_plain(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(FastaWriter {
             writer: Box::new(SequencesWriterWrapper::new(BufWriter::with_capacity(
                 DEFAULT_OUTPUT_BUFFER_SIZE,
-                File::create(&path).unwrap(),
+                File::create(&path)?,
             ))),
             path: path.as_ref().to_path_buf(),
             _phantom: PhantomData,
-        }
+        })
     }
 }
 
@@ -102,7 +103,7 @@ impl<ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenceWriter>
     }
 
     fn write_sequence(
-        _k: usize,
+        k: usize,
         buffer: &mut Self::SequenceTempBuffer,
         sequence_index: u64,
         sequence: &[u8],
@@ -111,21 +112,11 @@ impl<ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenceWriter>
         links_info: LinksInfo,
         extra_buffers: &(ColorInfo::TempBuffer, LinksInfo::TempBuffer),
 
-        #[cfg(feature = "support_kmer_counters")] abundance: SequenceAbundance,
+        abundance: SequenceAbundance,
+        abundance_mode: AbundanceOutputMode,
     ) {
-        #[cfg(feature = "support_kmer_counters")]
-        write!(
-            buffer,
-            ">{} LN:i:{} KC:i:{} km:f:{:.1}",
-            sequence_index,
-            sequence.len(),
-            abundance.sum,
-            abundance.sum as f64 / (sequence.len() - _k + 1) as f64
-        )
-        .unwrap();
-
-        #[cfg(not(feature = "support_kmer_counters"))]
         write!(buffer, ">{} LN:i:{}", sequence_index, sequence.len(),).unwrap();
+        abundance.write_tags(k, sequence.len(), abundance_mode, ' ', buffer);
 
         color_info.write_as_ident(buffer, &extra_buffers.0);
         links_info.write_as_ident(buffer, &extra_buffers.1);
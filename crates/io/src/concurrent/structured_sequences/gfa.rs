@@ -10,14 +10,16 @@ use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 
 use super::stream_finish::SequencesWriterWrapper;
-#[cfg(feature = "support_kmer_counters")]
-use super::SequenceAbundance;
-use super::{StructuredSequenceBackendInit, StructuredSequenceBackendWrapper};
+use super::{
+    AbundanceOutputMode, SequenceAbundance, StructuredSequenceBackendInit,
+    StructuredSequenceBackendWrapper,
+};
 
 pub struct GFAWriterWrapperV1;
 
 #[dynamic_dispatch]
 impl StructuredSequenceBackendWrapper for GFAWriterWrapperV1 {
+    const NAME: &'static str = "gfa1";
     type Backend<ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenceWriter> =
         GFAWriter<ColorInfo, LinksInfo, 1>;
 }
@@ -26,6 +28,7 @@ pub struct GFAWriterWrapperV2;
 
 #[dynamic_dispatch]
 impl StructuredSequenceBackendWrapper for GFAWriterWrapperV2 {
+    const NAME: &'static str = "gfa2";
     type Backend<ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenceWriter> =
         GFAWriter<ColorInfo, LinksInfo, 2>;
 }
@@ -53,23 +56,23 @@ unsafe impl<const VERSION: u32, ColorInfo: IdentSequenceWriter, LinksInfo: Ident
 impl<const VERSION: u32, ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenceWriter>
     StructuredSequenceBackendInit for GFAWriter<ColorInfo, LinksInfo, VERSION>
 {
-    fn new_compressed_gzip(path: impl AsRef<Path>, level: u32) -> Self {
+    fn new_compressed_gzip(path: impl AsRef<Path>, level: u32) -> std::io::Result<Self> {
         let compress_stream = GzEncoder::new(
-            BufWriter::with_capacity(DEFAULT_OUTPUT_BUFFER_SIZE, File::create(&path).unwrap()),
+            BufWriter::with_capacity(DEFAULT_OUTPUT_BUFFER_SIZE, File::create(&path)?),
             Compression::new(level),
         );
 
-        GFAWriter {
+        Ok(GFAWriter {
             writer: Box::new(SequencesWriterWrapper::new(BufWriter::with_capacity(
                 DEFAULT_OUTPUT_BUFFER_SIZE,
                 compress_stream,
             ))),
             path: path.as_ref().to_path_buf(),
             _phantom: PhantomData,
-        }
+        })
     }
 
-    fn new_compressed_lz4(path: impl AsRef<Path>, level: u32) -> Self {
+    fn new_compressed_lz4(path: impl AsRef<Path>, level: u32) -> std::io::Result<Self> {
         let compress_stream = lz4::EncoderBuilder::new()
             .level(level)
             .checksum(ContentChecksum::NoChecksum)
@@ -77,29 +80,28 @@ impl<const VERSION: u32, ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenc
             .block_size(BlockSize::Max1MB)
             .build(BufWriter::with_capacity(
                 DEFAULT_OUTPUT_BUFFER_SIZE,
-                File::create(&path).unwrap(),
-            ))
-            .unwrap();
+                File::create(&path)?,
+            ))?;
 
-        GFAWriter {
+        Ok(GFAWriter {
             writer: Box::new(SequencesWriterWrapper::new(BufWriter::with_capacity(
                 DEFAULT_OUTPUT_BUFFER_SIZE,
                 compress_stream,
             ))),
             path: path.as_ref().to_path_buf(),
             _phantom: PhantomData,
-        }
+        })
     }
 
-    fn new_plain(path: impl AsRef<Path>) -> Self {
-        GFAWriter {
+    fn new_plain(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(GFAWriter {
             writer: Box::new(SequencesWriterWrapper::new(BufWriter::with_capacity(
                 DEFAULT_OUTPUT_BUFFER_SIZE,
-                File::create(&path).unwrap(),
+                File::create(&path)?,
             ))),
             path: path.as_ref().to_path_buf(),
             _phantom: PhantomData,
-        }
+        })
     }
 }
 
@@ -118,11 +120,12 @@ impl<const VERSION: u32, ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenc
         sequence_index: u64,
         sequence: &[u8],
 
-        _color_info: ColorInfo,
+        color_info: ColorInfo,
         links_info: LinksInfo,
         extra_buffers: &(ColorInfo::TempBuffer, LinksInfo::TempBuffer),
 
-        #[cfg(feature = "support_kmer_counters")] abundance: SequenceAbundance,
+        abundance: SequenceAbundance,
+        abundance_mode: AbundanceOutputMode,
     ) {
         // Sequence line
         if VERSION == 1 {
@@ -136,23 +139,22 @@ impl<const VERSION: u32, ColorInfo: IdentSequenceWriter, LinksInfo: IdentSequenc
             buffer.extend_from_slice(sequence);
         }
 
-        #[cfg(feature = "support_kmer_counters")]
-        {
-            // Continuation of the S line
-            // ... KC:i:<kmer_count>    km:f:<kmer_coverage>
-            write!(buffer, "\tKC:i:{}", abundance.sum).unwrap();
-            write!(
-                buffer,
-                "\tkm:f:{:.1}",
-                abundance.sum as f64 / (sequence.len() - k + 1) as f64
-            )
-            .unwrap();
-        }
+        // Continuation of the S line
+        // ... KC:i:<kmer_count>    km:f:<kmer_coverage>    ka:B:I,<kmer_multiplicities>
+        abundance.write_tags(k, sequence.len(), abundance_mode, '\t', buffer);
+
+        // ... CS:Z:<color runs>
+        color_info.write_as_gfa::<VERSION>(
+            k as u64,
+            sequence_index,
+            sequence.len() as u64,
+            buffer,
+            &extra_buffers.0,
+        );
 
         // End of the S line
         buffer.push(b'\n');
 
-        // color_info.write_as_ident(buffer, &extra_buffers.0);
         links_info.write_as_gfa::<VERSION>(
             k as u64,
             sequence_index,
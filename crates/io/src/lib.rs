@@ -2,7 +2,8 @@ use crate::sequences_stream::general::GeneralSequenceBlockData;
 use config::{MAX_BUCKETS_COUNT_LOG, MAX_BUCKET_SIZE, MIN_BUCKETS_COUNT_LOG};
 use parallel_processor::buckets::SingleBucket;
 use std::cmp::{max, min};
-use std::path::Path;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
 
 pub mod chunks_writer;
 pub mod compressed_read;
@@ -14,6 +15,34 @@ pub mod sequences_stream;
 pub mod structs;
 pub mod varint;
 
+/// An I/O error on a specific file
+#[derive(Debug)]
+pub struct FileIoError {
+    pub path: PathBuf,
+    pub error: std::io::Error,
+}
+
+impl FileIoError {
+    pub fn new(path: impl AsRef<Path>, error: std::io::Error) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            error,
+        }
+    }
+}
+
+impl Display for FileIoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl std::error::Error for FileIoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
 pub fn generate_bucket_names(
     root: impl AsRef<Path>,
     count: usize,
@@ -1,8 +1,12 @@
 use crate::lines_reader::LinesReader;
+use crate::FileIoError;
 use config::DEFAULT_OUTPUT_BUFFER_SIZE;
 use nightly_quirks::branch_pred::unlikely;
 use std::cmp::max;
-use std::path::Path;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
 
 const IDENT_STATE: usize = 0;
 const SEQ_STATE: usize = 1;
@@ -20,6 +24,8 @@ pub enum DnaSequencesFileType {
 pub struct DnaSequence<'a> {
     pub ident_data: &'a [u8],
     pub seq: &'a [u8],
+    /// The phred+33 encoded qualities of the bases, available only for FASTQ sequences
+    pub qual: Option<&'a [u8]>,
     pub format: DnaSequencesFileType,
 }
 
@@ -36,6 +42,26 @@ const SEQ_LETTERS_MAPPING: [u8; 256] = {
     lookup
 };
 
+/// A sequences file with an unknown or invalid format
+#[derive(Debug)]
+pub struct SequencesFormatError {
+    pub file: PathBuf,
+    pub message: String,
+}
+
+impl Display for SequencesFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid sequences file '{}': {}",
+            self.file.display(),
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for SequencesFormatError {}
+
 pub struct SequencesReader {
     lines_reader: LinesReader,
 }
@@ -53,53 +79,195 @@ impl SequencesReader {
         }
     }
 
-    pub fn process_file_extended<F: FnMut(DnaSequence)>(
-        &mut self,
-        source: impl AsRef<Path>,
-        func: F,
-        line_split_copyback: Option<usize>,
-        copy_ident: bool,
-        remove_file: bool,
-    ) {
+    /// Detects the file type from its extension, ignoring the compression extensions
+    pub fn detect_file_type(source: impl AsRef<Path>) -> Option<DnaSequencesFileType> {
         const FASTQ_EXTS: &[&str] = &["fq", "fastq"];
         const FASTA_EXTS: &[&str] = &["fa", "fasta", "fna", "ffn"];
+        const GFA_EXTS: &[&str] = &["gfa", "gfa1", "gfa2"];
 
-        let mut file_type = None;
-        let mut tmp = source.as_ref().file_name().unwrap().to_str().unwrap();
+        let mut tmp = source.as_ref().file_name()?.to_str()?;
         let mut path: &Path = tmp.as_ref();
 
         while let Some(ext) = path.extension() {
-            if FASTQ_EXTS.contains(&ext.to_str().unwrap()) {
-                file_type = Some(DnaSequencesFileType::FASTQ);
-                break;
+            let ext_str = ext.to_str()?;
+            if FASTQ_EXTS.contains(&ext_str) {
+                return Some(DnaSequencesFileType::FASTQ);
             }
-            if FASTA_EXTS.contains(&ext.to_str().unwrap()) {
-                file_type = Some(DnaSequencesFileType::FASTA);
-                break;
+            if FASTA_EXTS.contains(&ext_str) {
+                return Some(DnaSequencesFileType::FASTA);
+            }
+            if GFA_EXTS.contains(&ext_str) {
+                return Some(DnaSequencesFileType::GFA);
             }
             tmp = &tmp[0..tmp.len() - ext.len() - 1];
             path = tmp.as_ref()
         }
+        None
+    }
 
-        match file_type {
+    /// Checks that a file exists and has a supported sequences format, returning its type.
+    /// The content is checked only for uncompressed FASTA/FASTQ files, by looking at their first byte
+    pub fn check_file(source: impl AsRef<Path>) -> anyhow::Result<DnaSequencesFileType> {
+        let source = source.as_ref();
+        let file_type = Self::detect_file_type(source).ok_or_else(|| SequencesFormatError {
+            file: source.to_path_buf(),
+            message: "unknown file extension, supported extensions are .fa, .fasta, .fna, .ffn, .fq, .fastq, .gfa, .gfa1 and .gfa2, optionally compressed as .gz or .lz4".to_string(),
+        })?;
+
+        let mut file = File::open(source).map_err(|err| FileIoError::new(source, err))?;
+
+        if source
+            .extension()
+            .map(|x| x == "gz" || x == "lz4")
+            .unwrap_or(false)
+        {
+            return Ok(file_type);
+        }
+
+        let mut first_byte = [0];
+        let read_count = file
+            .read(&mut first_byte)
+            .map_err(|err| FileIoError::new(source, err))?;
+
+        let expected: &[u8] = match file_type {
+            DnaSequencesFileType::FASTA => b">;",
+            DnaSequencesFileType::FASTQ => b"@",
+            DnaSequencesFileType::GFA | DnaSequencesFileType::BINARY => return Ok(file_type),
+        };
+
+        if read_count > 0 && !expected.contains(&first_byte[0]) {
+            return Err(SequencesFormatError {
+                file: source.to_path_buf(),
+                message: format!(
+                    "expected a record starting with '{}', found '{}'",
+                    expected[0] as char,
+                    first_byte[0].escape_ascii()
+                ),
+            }
+            .into());
+        }
+
+        Ok(file_type)
+    }
+
+    /// Opens a sequences file for line by line reading, decompressing it if needed
+    pub fn open_buffered(source: impl AsRef<Path>) -> anyhow::Result<Box<dyn BufRead>> {
+        let source = source.as_ref();
+        let file = File::open(source).map_err(|err| FileIoError::new(source, err))?;
+        Ok(match source.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file))),
+            Some("lz4") => Box::new(BufReader::new(
+                lz4::Decoder::new(file).map_err(|err| FileIoError::new(source, err))?,
+            )),
+            _ => Box::new(BufReader::new(file)),
+        })
+    }
+
+    /// Reads only the first sequence of a file, returning its ident and its length.
+    /// Used to inspect a graph without processing it whole
+    pub fn read_first_sequence(
+        source: impl AsRef<Path>,
+    ) -> anyhow::Result<Option<(DnaSequencesFileType, Vec<u8>, usize)>> {
+        let source = source.as_ref();
+        let file_type = Self::check_file(source)?;
+        let mut reader = Self::open_buffered(source)?;
+
+        let mut ident = None;
+        let mut length = 0;
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+            if reader
+                .read_until(b'\n', &mut line)
+                .map_err(|err| FileIoError::new(source, err))?
+                == 0
+            {
+                break;
+            }
+            while line.last().map(|c| *c == b'\n' || *c == b'\r') == Some(true) {
+                line.pop();
+            }
+
+            match file_type {
+                DnaSequencesFileType::GFA => {
+                    if let Some((name, sequence, tags)) = Self::parse_gfa_segment(&line) {
+                        let mut segment_ident = name.to_vec();
+                        segment_ident.push(b'\t');
+                        segment_ident.extend_from_slice(tags);
+                        return Ok(Some((file_type, segment_ident, sequence.len())));
+                    }
+                }
+                DnaSequencesFileType::FASTQ => match ident {
+                    None => ident = Some(line[1.min(line.len())..].to_vec()),
+                    Some(ident) => return Ok(Some((file_type, ident, line.len()))),
+                },
+                _ => {
+                    if line.first() == Some(&b'>') {
+                        if ident.is_some() {
+                            break;
+                        }
+                        ident = Some(line[1..].to_vec());
+                    } else if ident.is_some() && line.first() != Some(&b';') {
+                        length += line.len();
+                    }
+                }
+            }
+        }
+
+        Ok(ident
+            .filter(|_| length > 0)
+            .map(|ident| (file_type, ident, length)))
+    }
+
+    /// Processes all the sequences of a file, detecting its type from the extension.
+    /// Panics if the type is unknown, the inputs should be validated with `check_file` beforehand
+    pub fn process_file_extended<F: FnMut(DnaSequence)>(
+        &mut self,
+        source: impl AsRef<Path>,
+        func: F,
+        line_split_copyback: Option<usize>,
+        copy_ident: bool,
+        remove_file: bool,
+    ) {
+        match Self::detect_file_type(&source) {
             None => panic!(
                 "Cannot recognize file type of '{}'",
                 source.as_ref().display()
             ),
-            Some(ftype) => match ftype {
-                DnaSequencesFileType::FASTA => {
-                    self.process_fasta(source, func, line_split_copyback, copy_ident, remove_file);
-                }
-                DnaSequencesFileType::FASTQ => {
-                    self.process_fastq(source, func, remove_file);
-                }
-                DnaSequencesFileType::GFA => {
-                    todo!()
-                }
-                DnaSequencesFileType::BINARY => {
-                    todo!()
-                }
-            },
+            Some(ftype) => self.process_file_with_type(
+                source,
+                ftype,
+                func,
+                line_split_copyback,
+                copy_ident,
+                remove_file,
+            ),
+        }
+    }
+
+    pub fn process_file_with_type<F: FnMut(DnaSequence)>(
+        &mut self,
+        source: impl AsRef<Path>,
+        file_type: DnaSequencesFileType,
+        func: F,
+        line_split_copyback: Option<usize>,
+        copy_ident: bool,
+        remove_file: bool,
+    ) {
+        match file_type {
+            DnaSequencesFileType::FASTA => {
+                self.process_fasta(source, func, line_split_copyback, copy_ident, remove_file);
+            }
+            DnaSequencesFileType::FASTQ => {
+                self.process_fastq(source, func, remove_file);
+            }
+            DnaSequencesFileType::GFA => {
+                self.process_gfa(source, func, copy_ident, remove_file);
+            }
+            DnaSequencesFileType::BINARY => {
+                todo!()
+            }
         }
     }
 
@@ -134,6 +302,7 @@ impl SequencesReader {
                         func(DnaSequence {
                             ident_data: &intermediate[IDENT_STATE],
                             seq: &intermediate[SEQ_STATE],
+                            qual: None,
                             format: DnaSequencesFileType::FASTA,
                         });
                     }
@@ -165,6 +334,7 @@ impl SequencesReader {
                         func(DnaSequence {
                             ident_data: &intermediate[IDENT_STATE],
                             seq: &intermediate[SEQ_STATE],
+                            qual: None,
                             format: DnaSequencesFileType::FASTQ,
                         });
                         let copy_start = intermediate[SEQ_STATE].len() - copyback;
@@ -182,7 +352,6 @@ impl SequencesReader {
         &mut self,
         source: impl AsRef<Path>,
         mut func: impl FnMut(DnaSequence),
-        // get_quality: bool,
         remove_file: bool,
     ) {
         let mut state = IDENT_STATE;
@@ -205,20 +374,14 @@ impl SequencesReader {
                         return;
                     }
 
-                    // if get_quality {
-                    //     intermediate[state].extend_from_slice(line);
-                    // }
+                    intermediate[QUAL_STATE].extend_from_slice(line);
 
                     if !partial {
                         Self::normalize_sequence(&mut intermediate[SEQ_STATE]);
                         func(DnaSequence {
                             ident_data: &intermediate[IDENT_STATE],
                             seq: &intermediate[SEQ_STATE],
-                            // qual: if get_quality {
-                            //     Some(&intermediate[QUAL_STATE])
-                            // } else {
-                            //     None
-                            // },
+                            qual: Some(&intermediate[QUAL_STATE]),
                             format: DnaSequencesFileType::FASTQ,
                         });
 
@@ -239,4 +402,156 @@ impl SequencesReader {
             remove_file,
         );
     }
+
+    /// Reads the segments (S lines) of a GFA v1 or v2 file, all the other lines are ignored.
+    /// The ident of each sequence is the segment name followed by its optional tags, separated by tabs
+    fn process_gfa(
+        &mut self,
+        source: impl AsRef<Path>,
+        mut func: impl FnMut(DnaSequence),
+        copy_ident: bool,
+        remove_file: bool,
+    ) {
+        let mut line_buffer = Vec::new();
+        let mut ident_buffer = Vec::new();
+        let mut seq_buffer = Vec::new();
+
+        self.lines_reader.process_lines(
+            source,
+            |line: &[u8], partial, _finished| {
+                line_buffer.extend_from_slice(line);
+                if partial {
+                    return;
+                }
+
+                if let Some((name, sequence, tags)) = Self::parse_gfa_segment(&line_buffer) {
+                    seq_buffer.clear();
+                    seq_buffer.extend_from_slice(sequence);
+                    Self::normalize_sequence(&mut seq_buffer);
+
+                    ident_buffer.clear();
+                    if copy_ident {
+                        ident_buffer.extend_from_slice(name);
+                        if !tags.is_empty() {
+                            ident_buffer.push(b'\t');
+                            ident_buffer.extend_from_slice(tags);
+                        }
+                    }
+
+                    func(DnaSequence {
+                        ident_data: &ident_buffer,
+                        seq: &seq_buffer,
+                        qual: None,
+                        format: DnaSequencesFileType::GFA,
+                    });
+                }
+
+                line_buffer.clear();
+            },
+            remove_file,
+        );
+    }
+
+    /// Splits a GFA segment line into its name, sequence and tags.
+    /// Returns None for other lines and for segments without sequence, marked with '*'
+    pub fn parse_gfa_segment(line: &[u8]) -> Option<(&[u8], &[u8], &[u8])> {
+        if !line.starts_with(b"S\t") {
+            return None;
+        }
+
+        let mut fields = line[2..].splitn(2, |c| *c == b'\t');
+        let name = fields.next().unwrap_or(&[]);
+        let mut remaining = fields.next().unwrap_or(&[]);
+
+        // GFA v2 segments have the length before the sequence
+        if let Some(length_end) = remaining.iter().position(|c| *c == b'\t') {
+            if length_end > 0 && remaining[..length_end].iter().all(u8::is_ascii_digit) {
+                remaining = &remaining[length_end + 1..];
+            }
+        }
+
+        let (sequence, tags) = match remaining.iter().position(|c| *c == b'\t') {
+            None => (remaining, &[][..]),
+            Some(pos) => (&remaining[..pos], &remaining[pos + 1..]),
+        };
+
+        if !sequence.is_empty() && sequence != b"*" {
+            Some((name, sequence, tags))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sequences_reader::{DnaSequencesFileType, SequencesFormatError, SequencesReader};
+    use crate::FileIoError;
+    use utils::temp_dir::TempDir;
+
+    #[test]
+    fn check_input_files() {
+        let dir = TempDir::new("ggcat-check-test");
+
+        let fasta = dir.join("reads.fa");
+        std::fs::write(&fasta, ">0 C:1:2\nACGTA\nCG\n>1\nAC\n").unwrap();
+        let fastq = dir.join("reads.fq");
+        std::fs::write(&fastq, ">0\nACGT\n").unwrap();
+        let unknown = dir.join("reads.txt");
+        std::fs::write(&unknown, ">0\nACGT\n").unwrap();
+
+        assert!(matches!(
+            SequencesReader::check_file(&fasta),
+            Ok(DnaSequencesFileType::FASTA)
+        ));
+        for invalid in [&fastq, &unknown] {
+            let err = SequencesReader::check_file(invalid).err().unwrap();
+            assert_eq!(
+                &err.downcast::<SequencesFormatError>().unwrap().file,
+                invalid
+            );
+        }
+        let err = SequencesReader::check_file(dir.join("missing.fa"))
+            .err()
+            .unwrap();
+        assert!(err.downcast_ref::<FileIoError>().is_some());
+
+        let (_, ident, length) = SequencesReader::read_first_sequence(&fasta)
+            .unwrap()
+            .unwrap();
+        assert_eq!((ident.as_slice(), length), (&b"0 C:1:2"[..], 7));
+    }
+
+    #[test]
+    fn gfa_segments() {
+        let dir = TempDir::new("ggcat-gfa-test");
+        let file = dir.join("graph.gfa");
+        std::fs::write(
+            &file,
+            "H\tVN:Z:1.0\nS\t0\tACGTN\tLN:i:5\nS\t1\t*\nL\t0\t+\t1\t-\t4M\nS\t2\t4\tacgt\n",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            SequencesReader::detect_file_type("graph.gfa.lz4"),
+            Some(DnaSequencesFileType::GFA)
+        ));
+
+        let mut segments = vec![];
+        SequencesReader::new().process_file_extended(
+            &file,
+            |seq| segments.push((seq.ident_data.to_vec(), seq.seq.to_vec())),
+            None,
+            true,
+            true,
+        );
+
+        assert_eq!(
+            segments,
+            vec![
+                (b"0\tLN:i:5".to_vec(), b"ACGTN".to_vec()),
+                (b"2".to_vec(), b"ACGT".to_vec()),
+            ]
+        );
+    }
 }
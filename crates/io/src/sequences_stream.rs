@@ -1,14 +1,25 @@
 pub mod fasta;
 pub mod general;
+pub mod gfa;
 
 use crate::sequences_reader::DnaSequence;
 use config::ColorIndexType;
 
 #[derive(Copy, Clone)]
 pub struct SequenceInfo {
+    /// The color of the sequence, or a subset of colors if it has the [`COLOR_SUBSET_FLAG`]
     pub color: Option<ColorIndexType>,
 }
 
+/// Marks the sequence colors that are subsets of colors, expanded with the [`ColorSubsets`] of their stream
+pub const COLOR_SUBSET_FLAG: ColorIndexType = 1 << (ColorIndexType::BITS - 1);
+
+/// Expands the color subsets assigned to the sequences of a stream
+pub trait ColorSubsets: Sync + Send + 'static {
+    /// Appends the colors of a subset (without the [`COLOR_SUBSET_FLAG`]) to `colors`
+    fn get_colors(&self, subset: ColorIndexType, colors: &mut Vec<ColorIndexType>);
+}
+
 pub trait GenericSequencesStream: 'static {
     type SequenceBlockData: Sync + Send + 'static;
 
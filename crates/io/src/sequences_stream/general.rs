@@ -1,6 +1,12 @@
-use crate::sequences_reader::DnaSequence;
+use crate::sequences_reader::{DnaSequence, DnaSequencesFileType, SequencesReader};
 use crate::sequences_stream::fasta::FastaFileSequencesStream;
-use crate::sequences_stream::{GenericSequencesStream, SequenceInfo};
+use crate::sequences_stream::gfa::GfaFileSequencesStream;
+use crate::sequences_stream::{
+    ColorSubsets, GenericSequencesStream, SequenceInfo, COLOR_SUBSET_FLAG,
+};
+use config::ColorIndexType;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 pub trait DynamicSequencesStream: Sync + Send + 'static {
@@ -13,32 +19,159 @@ pub trait DynamicSequencesStream: Sync + Send + 'static {
     );
 
     fn estimated_base_count(&self, block: usize) -> u64;
+
+    /// The color subsets assigned to the sequences, if any
+    fn color_subsets(&self) -> Option<Arc<dyn ColorSubsets>> {
+        None
+    }
+}
+
+/// Shifts the colors of the subsets of a wrapped stream
+struct OffsetColorSubsets {
+    inner: Arc<dyn ColorSubsets>,
+    colors_offset: ColorIndexType,
+}
+
+impl ColorSubsets for OffsetColorSubsets {
+    fn get_colors(&self, subset: ColorIndexType, colors: &mut Vec<ColorIndexType>) {
+        let start = colors.len();
+        self.inner.get_colors(subset, colors);
+        colors[start..]
+            .iter_mut()
+            .for_each(|color| *color += self.colors_offset);
+    }
+}
+
+/// Wraps a dynamic stream, shifting all its colors (also the ones of its color subsets) by a fixed offset.
+/// Sequences without an explicit color are assigned to `default_color`
+pub struct ColorsOffsetSequencesStream {
+    pub inner: Arc<dyn DynamicSequencesStream>,
+    pub colors_offset: ColorIndexType,
+    pub default_color: ColorIndexType,
+}
+
+impl DynamicSequencesStream for ColorsOffsetSequencesStream {
+    fn read_block(
+        &self,
+        block: usize,
+        copy_ident_data: bool,
+        partial_read_copyback: Option<usize>,
+        callback: &mut dyn FnMut(DnaSequence, SequenceInfo),
+    ) {
+        self.inner.read_block(
+            block,
+            copy_ident_data,
+            partial_read_copyback,
+            &mut |sequence, info| {
+                callback(
+                    sequence,
+                    SequenceInfo {
+                        color: Some(
+                            info.color
+                                .map(|c| {
+                                    if c & COLOR_SUBSET_FLAG != 0 {
+                                        c
+                                    } else {
+                                        c + self.colors_offset
+                                    }
+                                })
+                                .unwrap_or(self.default_color),
+                        ),
+                    },
+                )
+            },
+        );
+    }
+
+    fn estimated_base_count(&self, block: usize) -> u64 {
+        self.inner.estimated_base_count(block)
+    }
+
+    fn color_subsets(&self) -> Option<Arc<dyn ColorSubsets>> {
+        self.inner.color_subsets().map(|inner| {
+            Arc::new(OffsetColorSubsets {
+                inner,
+                colors_offset: self.colors_offset,
+            }) as Arc<dyn ColorSubsets>
+        })
+    }
 }
 
 pub enum GeneralSequenceBlockData {
     FASTA(<FastaFileSequencesStream as GenericSequencesStream>::SequenceBlockData),
-    GFA(),
+    GFA(<GfaFileSequencesStream as GenericSequencesStream>::SequenceBlockData),
     Dynamic((Arc<dyn DynamicSequencesStream>, usize)),
 }
 
 impl GeneralSequenceBlockData {
+    /// Creates the block for an input file, choosing the stream type from its extension
+    pub fn from_file(file: PathBuf, color: Option<ColorIndexType>) -> Self {
+        match SequencesReader::detect_file_type(&file) {
+            Some(DnaSequencesFileType::GFA) => Self::GFA((file, color)),
+            _ => Self::FASTA((file, color)),
+        }
+    }
+
     pub fn estimated_bases_count(&self) -> anyhow::Result<u64> {
         match self {
             GeneralSequenceBlockData::FASTA(block) => {
                 FastaFileSequencesStream::get_estimated_bases_count(&block.0)
             }
-            GeneralSequenceBlockData::GFA() => {
-                todo!()
+            GeneralSequenceBlockData::GFA(block) => {
+                GfaFileSequencesStream::get_estimated_bases_count(&block.0)
             }
             GeneralSequenceBlockData::Dynamic((reader, block)) => {
                 Ok(reader.estimated_base_count(*block))
             }
         }
     }
+
+    /// The color subsets assigned to the sequences of the block, if any
+    pub fn color_subsets(&self) -> Option<Arc<dyn ColorSubsets>> {
+        match self {
+            GeneralSequenceBlockData::FASTA(_) | GeneralSequenceBlockData::GFA(_) => None,
+            GeneralSequenceBlockData::Dynamic((reader, _)) => reader.color_subsets(),
+        }
+    }
+}
+
+/// The input blocks assign color subsets expanded by different sources
+#[derive(Debug)]
+pub struct MultipleColorSubsetsError;
+
+impl Display for MultipleColorSubsetsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The input sequences have color subsets from more than one source"
+        )
+    }
+}
+
+impl std::error::Error for MultipleColorSubsetsError {}
+
+/// Finds the color subsets assigned to the sequences of the blocks, they must all come from the same source
+pub fn find_color_subsets(
+    input_blocks: &[GeneralSequenceBlockData],
+) -> Result<Option<Arc<dyn ColorSubsets>>, MultipleColorSubsetsError> {
+    let mut color_subsets: Option<Arc<dyn ColorSubsets>> = None;
+    for subsets in input_blocks
+        .iter()
+        .filter_map(|block| block.color_subsets())
+    {
+        match &color_subsets {
+            Some(current) if !Arc::ptr_eq(current, &subsets) => {
+                return Err(MultipleColorSubsetsError);
+            }
+            _ => color_subsets = Some(subsets),
+        }
+    }
+    Ok(color_subsets)
 }
 
 pub struct GeneralSequencesStream {
     fasta_file_reader: Option<FastaFileSequencesStream>,
+    gfa_file_reader: Option<GfaFileSequencesStream>,
 }
 
 impl GenericSequencesStream for GeneralSequencesStream {
@@ -47,6 +180,7 @@ impl GenericSequencesStream for GeneralSequencesStream {
     fn new() -> Self {
         Self {
             fasta_file_reader: None,
+            gfa_file_reader: None,
         }
     }
 
@@ -69,8 +203,16 @@ impl GenericSequencesStream for GeneralSequencesStream {
                     callback,
                 );
             }
-            GeneralSequenceBlockData::GFA() => {
-                unimplemented!();
+            GeneralSequenceBlockData::GFA(block) => {
+                if self.gfa_file_reader.is_none() {
+                    self.gfa_file_reader = Some(GfaFileSequencesStream::new());
+                }
+                self.gfa_file_reader.as_mut().unwrap().read_block(
+                    block,
+                    copy_ident_data,
+                    partial_read_copyback,
+                    callback,
+                );
             }
             GeneralSequenceBlockData::Dynamic((reader, index)) => {
                 reader.read_block(
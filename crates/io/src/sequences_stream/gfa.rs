@@ -0,0 +1,41 @@
+use crate::sequences_reader::{DnaSequence, DnaSequencesFileType, SequencesReader};
+use crate::sequences_stream::fasta::FastaFileSequencesStream;
+use crate::sequences_stream::{GenericSequencesStream, SequenceInfo};
+use std::path::PathBuf;
+
+pub struct GfaFileSequencesStream {
+    sequences_reader: SequencesReader,
+}
+
+impl GfaFileSequencesStream {
+    pub fn get_estimated_bases_count(file: &PathBuf) -> anyhow::Result<u64> {
+        FastaFileSequencesStream::get_estimated_bases_count(file)
+    }
+}
+
+impl GenericSequencesStream for GfaFileSequencesStream {
+    type SequenceBlockData = (PathBuf, Option<u32>);
+
+    fn new() -> Self {
+        Self {
+            sequences_reader: SequencesReader::new(),
+        }
+    }
+
+    fn read_block(
+        &mut self,
+        block: &Self::SequenceBlockData,
+        copy_ident_data: bool,
+        partial_read_copyback: Option<usize>,
+        mut callback: impl FnMut(DnaSequence, SequenceInfo),
+    ) {
+        self.sequences_reader.process_file_with_type(
+            &block.0,
+            DnaSequencesFileType::GFA,
+            |x| callback(x, SequenceInfo { color: block.1 }),
+            partial_read_copyback,
+            copy_ident_data,
+            false,
+        );
+    }
+}
@@ -95,6 +95,9 @@ pub fn decode_varint(mut read_byte: impl FnMut() -> Option<u8>) -> Option<u64> {
             break;
         }
         offset += 7;
+        if offset >= u64::BITS {
+            return None;
+        }
     }
     Some(result)
 }
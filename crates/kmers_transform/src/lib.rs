@@ -3,9 +3,11 @@ mod reader;
 use crate::processor::KmersTransformProcessor;
 use crate::reader::{InputBucketDesc, KmersTransformReader};
 use crate::resplitter::KmersTransformResplitter;
+use config::operation::OperationContext;
 use config::{
-    BucketIndexType, KEEP_FILES, KMERS_TRANSFORM_READS_CHUNKS_SIZE, MAXIMUM_JIT_PROCESSED_BUCKETS,
-    MAXIMUM_SECOND_BUCKETS_COUNT, MINIMUM_LOG_DELTA_TIME, PACKETS_PRIORITY_FILES,
+    keep_file_on_disk, BucketIndexType, KMERS_TRANSFORM_READS_CHUNKS_SIZE,
+    MAXIMUM_JIT_PROCESSED_BUCKETS, MAXIMUM_SECOND_BUCKETS_COUNT, MINIMUM_LOG_DELTA_TIME,
+    PACKETS_PRIORITY_FILES,
 };
 use io::concurrent::temp_reads::extra_data::{
     SequenceExtraDataConsecutiveCompression, SequenceExtraDataTempBufferManagement,
@@ -78,7 +80,7 @@ pub struct GroupProcessStats {
 pub trait KmersTransformMapProcessor<F: KmersTransformExecutorFactory>:
     Sized + 'static + Send
 {
-    type MapStruct: PacketTrait + PoolObjectTrait<InitData = ()>;
+    type MapStruct: PacketTrait + PoolObjectTrait<InitData = Arc<F::GlobalExtraData>>;
     const MAP_SIZE: usize;
 
     fn process_group_start(
@@ -102,7 +104,7 @@ pub trait KmersTransformMapProcessor<F: KmersTransformExecutorFactory>:
 pub trait KmersTransformFinalExecutor<F: KmersTransformExecutorFactory>:
     Sized + 'static + Sync + Send
 {
-    type MapStruct: PacketTrait + PoolObjectTrait<InitData = ()>;
+    type MapStruct: PacketTrait + PoolObjectTrait<InitData = Arc<F::GlobalExtraData>>;
 
     fn process_map(
         &mut self,
@@ -142,12 +144,15 @@ pub struct KmersTransformContext<F: KmersTransformExecutorFactory> {
     read_threads_count: usize,
     max_second_buckets_count_log2: usize,
     temp_dir: PathBuf,
+    keep_input_files: bool,
 
     total_sequences: AtomicU64,
     total_kmers: AtomicU64,
     unique_kmers: AtomicU64,
 
     reader_init_lock: tokio::sync::Mutex<()>,
+    /// The operation running the transform, whose settings are used by the executors threads
+    operation: Arc<OperationContext>,
 }
 
 impl<F: KmersTransformExecutorFactory> KmersTransform<F> {
@@ -160,11 +165,10 @@ impl<F: KmersTransformExecutorFactory> KmersTransform<F> {
         threads_count: usize,
         k: usize,
         min_bucket_size: u64,
+        keep_input_files: bool,
     ) -> Self {
-        let counters = CountersAnalyzer::load_from_file(
-            buckets_counters_path,
-            !KEEP_FILES.load(Ordering::Relaxed),
-        );
+        let remove_counters = !keep_input_files && !keep_file_on_disk(&buckets_counters_path);
+        let counters = CountersAnalyzer::load_from_file(buckets_counters_path, remove_counters);
 
         let mut total_buckets_size = 0;
 
@@ -273,10 +277,12 @@ impl<F: KmersTransformExecutorFactory> KmersTransform<F> {
             read_threads_count,
             max_second_buckets_count_log2: MAXIMUM_SECOND_BUCKETS_COUNT.ilog2() as usize,
             temp_dir: temp_dir.to_path_buf(),
+            keep_input_files,
             total_sequences: AtomicU64::new(0),
             total_kmers: AtomicU64::new(0),
             unique_kmers: AtomicU64::new(0),
             reader_init_lock: tokio::sync::Mutex::new(()),
+            operation: OperationContext::current(),
         });
 
         Self {
@@ -49,13 +49,15 @@ impl<F: KmersTransformExecutorFactory> AsyncExecutor for KmersTransformProcessor
         mut receiver: ExecutorReceiver<Self>,
         memory_tracker: MemoryTracker<Self>,
     ) -> impl Future<Output = ()> + 'a {
-        async move {
+        global_context.operation.scope(async move {
             let mut map_processor =
                 F::new_map_processor(&global_context.global_extra_data, memory_tracker.clone());
             let mut final_executor = F::new_final_executor(&global_context.global_extra_data);
 
             let mut packet = Packet::new_simple(
-                <F::MapProcessorType as KmersTransformMapProcessor<F>>::MapStruct::allocate_new(&()),
+                <F::MapProcessorType as KmersTransformMapProcessor<F>>::MapStruct::allocate_new(
+                    &global_context.global_extra_data,
+                ),
             );
 
             let thread_handle = PriorityScheduler::declare_thread(PRIORITY_SCHEDULING_HIGH);
@@ -129,7 +131,7 @@ impl<F: KmersTransformExecutorFactory> AsyncExecutor for KmersTransformProcessor
                 // );
             }
             final_executor.finalize(&global_context.global_extra_data);
-        }
+        })
     }
 }
 //     const MEMORY_FIELDS_COUNT: usize = 2;
@@ -5,9 +5,10 @@ use crate::{
     KmersTransformContext, KmersTransformExecutorFactory, KmersTransformGlobalExtraData,
     KmersTransformMapProcessor,
 };
+use config::operation::MemoryTrackedBucket;
 use config::{
-    get_compression_level_info, get_memory_mode, SwapPriority, DEFAULT_OUTPUT_BUFFER_SIZE,
-    DEFAULT_PER_CPU_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT, KEEP_FILES,
+    get_compression_level_info, get_memory_mode, keep_file_on_disk, SwapPriority,
+    DEFAULT_OUTPUT_BUFFER_SIZE, DEFAULT_PER_CPU_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT,
     MAXIMUM_JIT_PROCESSED_BUCKETS, MAX_INTERMEDIATE_MAP_SIZE, MIN_BUCKET_CHUNKS_FOR_READING_THREAD,
     PACKETS_PRIORITY_DEFAULT, PACKETS_PRIORITY_REWRITTEN, PARTIAL_VECS_CHECKPOINT_SIZE,
     PRIORITY_SCHEDULING_BASE, PRIORITY_SCHEDULING_LOW, USE_SECOND_BUCKET, WORKERS_PRIORITY_BASE,
@@ -114,7 +115,11 @@ struct RewriterInitData {
 
 enum AddressMode {
     Send(ExecutorAddress),
-    Rewrite(CompressedBinaryWriter, AtomicU64, RewriterInitData),
+    Rewrite(
+        MemoryTrackedBucket<CompressedBinaryWriter>,
+        AtomicU64,
+        RewriterInitData,
+    ),
 }
 
 struct BucketsInfo {
@@ -146,8 +151,12 @@ impl<F: KmersTransformExecutorFactory> KmersTransformReader<F> {
                 AsyncBinaryReader::new(
                     path,
                     true,
-                    RemoveFileMode::Remove {
-                        remove_fs: file.rewritten || !KEEP_FILES.load(Ordering::Relaxed),
+                    if global_context.keep_input_files && !file.rewritten {
+                        RemoveFileMode::Keep
+                    } else {
+                        RemoveFileMode::Remove {
+                            remove_fs: file.rewritten || !keep_file_on_disk(path),
+                        }
                     },
                     DEFAULT_PREFETCH_AMOUNT,
                 )
@@ -269,7 +278,7 @@ impl<F: KmersTransformExecutorFactory> KmersTransformReader<F> {
                 } else {
                     static SUBSPLIT_INDEX: AtomicUsize = AtomicUsize::new(0);
 
-                    let writer = CompressedBinaryWriter::new(
+                    let writer = MemoryTrackedBucket::<CompressedBinaryWriter>::new(
                         &global_context.temp_dir.join(&format!("bucket-rewrite-",)),
                         &(
                             get_memory_mode(SwapPriority::ResultBuckets),
@@ -340,7 +349,7 @@ impl<F: KmersTransformExecutorFactory> KmersTransformReader<F> {
 
     fn flush_rewrite_bucket<MultiplicityMode: MultiplicityModeOption>(
         input_buffer: &mut Packet<ReadsBuffer<F::AssociatedExtraData>>,
-        writer: &CompressedBinaryWriter,
+        writer: &MemoryTrackedBucket<CompressedBinaryWriter>,
         seq_count: &AtomicU64,
         rewrite_buffer: &mut Vec<u8>,
     ) {
@@ -397,7 +406,7 @@ impl<F: KmersTransformExecutorFactory> KmersTransformReader<F> {
         packets_pool: Arc<PoolObject<PacketsPool<ReadsBuffer<F::AssociatedExtraData>>>>,
         thread_handle: &ThreadPriorityHandle,
     ) {
-        if bucket_info.readers.iter().all(|r| r.is_finished()) {
+        if bucket_info.readers.iter().all(|r| r.is_finished()) || config::should_interrupt_phase() {
             return;
         }
 
@@ -559,7 +568,7 @@ impl<F: KmersTransformExecutorFactory> AsyncExecutor for KmersTransformReader<F>
         mut receiver: ExecutorReceiver<Self>,
         _memory_tracker: MemoryTracker<Self>,
     ) -> impl Future<Output = ()> + 'a {
-        async move {
+        global_context.operation.scope(async move {
             let mut async_threads = Vec::new();
 
             let thread_handle = PriorityScheduler::declare_thread(PRIORITY_SCHEDULING_BASE);
@@ -614,7 +623,7 @@ impl<F: KmersTransformExecutorFactory> AsyncExecutor for KmersTransformReader<F>
                         )
                         .await;
 
-                    spawner.spawn_executor(async move {
+                    spawner.spawn_executor(global_context.operation.scope(async move {
                         let thread_handle =
                             PriorityScheduler::declare_thread(PRIORITY_SCHEDULING_LOW);
                         match buckets_info.data_format {
@@ -641,7 +650,7 @@ impl<F: KmersTransformExecutorFactory> AsyncExecutor for KmersTransformReader<F>
                                 .await;
                             }
                         }
-                    });
+                    }));
                 }
 
                 drop(reader_lock);
@@ -693,6 +702,7 @@ impl<F: KmersTransformExecutorFactory> AsyncExecutor for KmersTransformReader<F>
                     global_context
                         .processed_buckets_size
                         .fetch_add(buckets_info.total_file_size, Ordering::Relaxed);
+                    config::progress::advance_progress(1);
                 } else if is_resplitted {
                     global_context
                         .processed_extra_buckets_count
@@ -707,7 +717,7 @@ impl<F: KmersTransformExecutorFactory> AsyncExecutor for KmersTransformReader<F>
                     PACKET_WAITING_COUNTER
                 ));
             }
-        }
+        })
     }
 }
 
@@ -1,6 +1,7 @@
 use crate::reader::{InputBucketDesc, KmersTransformReader};
 use crate::reads_buffer::ReadsBuffer;
 use crate::{KmersTransformContext, KmersTransformExecutorFactory};
+use config::operation::MemoryTrackedBucket;
 use config::{
     get_compression_level_info, get_memory_mode, BucketIndexType, SwapPriority,
     DEFAULT_PER_CPU_BUFFER_SIZE, MAXIMUM_JIT_PROCESSED_BUCKETS, MAX_RESPLIT_BUCKETS_COUNT_LOG,
@@ -45,7 +46,7 @@ pub struct KmersTransformResplitter<F: KmersTransformExecutorFactory>(PhantomDat
 static BUCKET_RESPLIT_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 struct BucketsResplitInfo {
-    buckets: Arc<MultiThreadBuckets<CompressedBinaryWriter>>,
+    buckets: Arc<MultiThreadBuckets<MemoryTrackedBucket<CompressedBinaryWriter>>>,
     subsplit_buckets_count_log: usize,
     output_addresses: Vec<ExecutorAddress>,
     executors_count: usize,
@@ -256,7 +257,7 @@ impl<F: KmersTransformExecutorFactory> AsyncExecutor for KmersTransformResplitte
         mut receiver: ExecutorReceiver<Self>,
         _memory_tracker: MemoryTracker<Self>,
     ) -> impl Future<Output = ()> + 'a {
-        async move {
+        global_context.operation.scope(async move {
             let thread_handle = PriorityScheduler::declare_thread(PRIORITY_SCHEDULING_HIGH);
 
             while let Ok((address, init_data)) = track!(
@@ -270,13 +271,13 @@ impl<F: KmersTransformExecutorFactory> AsyncExecutor for KmersTransformResplitte
                 let mut spawner = address.make_spawner();
 
                 for _ in 0..resplit_info.executors_count {
-                    spawner.spawn_executor(async {
+                    spawner.spawn_executor(global_context.operation.scope(async {
                         let thread_handle =
                             PriorityScheduler::declare_thread(PRIORITY_SCHEDULING_HIGH);
 
                         Self::do_resplit(global_context, &resplit_info, &address, &thread_handle)
                             .await
-                    });
+                    }));
                 }
                 spawner.executors_await().await;
                 drop(spawner);
@@ -319,7 +320,7 @@ impl<F: KmersTransformExecutorFactory> AsyncExecutor for KmersTransformResplitte
                     );
                 }
             }
-        }
+        })
     }
 }
 
@@ -1,5 +1,6 @@
 pub mod stats;
 
+use std::cell::Cell;
 use std::fmt::{Debug, Display};
 
 use parking_lot::Mutex;
@@ -12,20 +13,40 @@ pub enum MessageLevel {
     UnrecoverableError = 3,
 }
 
-static MESSAGES_CALLBACK: Mutex<Option<fn(MessageLevel, &str)>> = Mutex::new(None);
+type MessagesCallback = fn(MessageLevel, &str);
 
-pub fn setup_logging_callback(callback: fn(MessageLevel, &str)) {
+static MESSAGES_CALLBACK: Mutex<Option<MessagesCallback>> = Mutex::new(None);
+
+thread_local! {
+    /// The messages callback of the operation running on the current thread, that replaces the global one
+    static OPERATION_MESSAGES_CALLBACK: Cell<Option<MessagesCallback>> = const { Cell::new(None) };
+}
+
+/// Sets the messages callback, if None the messages are written to stdout
+pub fn setup_logging_callback(callback: Option<fn(MessageLevel, &str)>) {
     let mut messages_callback = MESSAGES_CALLBACK.lock();
-    *messages_callback = Some(callback);
+    *messages_callback = callback;
+}
+
+/// Sets the messages callback of the operation running on the current thread, if None the global one is used
+pub fn set_operation_messages_callback(callback: Option<fn(MessageLevel, &str)>) {
+    OPERATION_MESSAGES_CALLBACK.set(callback);
 }
 
+/// Logs a message using the callback if present, otherwise writing it to stdout.
+/// Unrecoverable errors without callback are written to stderr, as the error itself is returned to the caller
 pub fn log(level: MessageLevel, message: &str) {
+    if let Some(callback) = OPERATION_MESSAGES_CALLBACK.get() {
+        callback(level, message);
+        return;
+    }
+
     let messages_callback = MESSAGES_CALLBACK.lock();
     if let Some(callback) = &*messages_callback {
         callback(level, message);
     } else {
         if let MessageLevel::UnrecoverableError = level {
-            panic!("{}", message);
+            eprintln!("{}", message);
         } else {
             println!("{}", message);
         }
@@ -6,6 +6,7 @@ use std::{
     future::Future,
     hash::Hash,
     marker::PhantomData,
+    ops::Range,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
@@ -14,11 +15,11 @@ use crate::{
     queue_data::MinimizerBucketingQueueData, MinimizerBucketMode,
     MinimizerBucketingExecutionContext, MinimizerBucketingExecutorFactory,
 };
-use colors::non_colored::NonColoredManager;
+use config::operation::MemoryTrackedBucket;
 use config::{
-    get_compression_level_info, get_memory_mode, BucketIndexType, MultiplicityCounterType,
-    SwapPriority, DEFAULT_COMPACTION_MAP_SUBBUCKET_ELEMENTS, DEFAULT_OUTPUT_BUFFER_SIZE,
-    DEFAULT_PREFETCH_AMOUNT, KEEP_FILES, MAXIMUM_SECOND_BUCKETS_COUNT,
+    get_compression_level_info, get_memory_mode, keep_files, BucketIndexType,
+    MultiplicityCounterType, SwapPriority, DEFAULT_COMPACTION_MAP_SUBBUCKET_ELEMENTS,
+    DEFAULT_OUTPUT_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT, MAXIMUM_SECOND_BUCKETS_COUNT,
     MAX_COMPACTION_MAP_SUBBUCKET_ELEMENTS, MINIMIZER_BUCKETS_CHECKPOINT_SIZE,
     PRIORITY_SCHEDULING_HIGH, WORKERS_PRIORITY_HIGH,
 };
@@ -29,7 +30,9 @@ use io::{
             CompressedReadsBucketData, CompressedReadsBucketDataSerializer, NoSecondBucket,
             ReadsCheckpointData, WithMultiplicity,
         },
-        extra_data::SequenceExtraDataTempBufferManagement,
+        extra_data::{
+            SequenceExtraDataConsecutiveCompression, SequenceExtraDataTempBufferManagement,
+        },
     },
 };
 use io::{
@@ -108,6 +111,38 @@ impl PartialEq for SuperKmerEntry {
 
 impl Eq for SuperKmerEntry {}
 
+/// A deduplicated sequence, super-kmers with different extra data (e.g. colors)
+/// are kept as separate sequences, chained from the first one found
+struct CompactedSequence {
+    flags: u8,
+    multiplicity: MultiplicityCounterType,
+    /// The encoded extra data, as a range in the extra data storage
+    extra_data: Range<usize>,
+    /// The index of the next sequence with the same super-kmer in the duplicates list
+    next_duplicate: usize,
+}
+
+impl CompactedSequence {
+    const NO_DUPLICATE: usize = usize::MAX;
+
+    fn new(
+        flags: u8,
+        multiplicity: MultiplicityCounterType,
+        encoded_extra: &[u8],
+        extra_storage: &mut Vec<u8>,
+        next_duplicate: usize,
+    ) -> Self {
+        let extra_start = extra_storage.len();
+        extra_storage.extend_from_slice(encoded_extra);
+        Self {
+            flags,
+            multiplicity,
+            extra_data: extra_start..extra_storage.len(),
+            next_duplicate,
+        }
+    }
+}
+
 impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
     for MinimizerBucketingCompactor<E>
 {
@@ -128,7 +163,7 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
         mut receiver: ExecutorReceiver<Self>,
         _memory_tracker: MemoryTracker<Self>,
     ) -> impl Future<Output = ()> + Send + 'a {
-        async move {
+        global_params.operation.scope(async move {
             let read_thread = AsyncReaderThread::new(DEFAULT_OUTPUT_BUFFER_SIZE, 4);
 
             static COMPACTED_INDEX: AtomicUsize = AtomicUsize::new(0);
@@ -136,9 +171,8 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
             const MAXIMUM_SEQUENCES: usize =
                 MAXIMUM_SECOND_BUCKETS_COUNT * MAX_COMPACTION_MAP_SUBBUCKET_ELEMENTS;
 
-            let mut super_kmers_hashmap: Vec<
-                FxHashMap<SuperKmerEntry, (u8, MultiplicityCounterType)>,
-            > = (0..MAXIMUM_SECOND_BUCKETS_COUNT)
+            let mut super_kmers_hashmap: Vec<FxHashMap<SuperKmerEntry, CompactedSequence>> = (0
+                ..MAXIMUM_SECOND_BUCKETS_COUNT)
                 .map(|_| {
                     FxHashMap::with_capacity_and_hasher(
                         DEFAULT_COMPACTION_MAP_SUBBUCKET_ELEMENTS,
@@ -234,6 +268,10 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
                 // .try_into()
                 // .unwrap();
                 let mut kmers_storage = Vec::with_capacity(DEFAULT_OUTPUT_BUFFER_SIZE);
+                let mut extra_storage = vec![];
+                let mut encoded_extra = vec![];
+                let mut duplicate_sequences: Vec<CompactedSequence> = vec![];
+                let mut subbuckets_sequences = vec![0usize; MAXIMUM_SECOND_BUCKETS_COUNT];
 
                 let mut sequences_deltas = vec![0i64; MAXIMUM_SECOND_BUCKETS_COUNT];
 
@@ -254,7 +292,7 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
                         &bucket_path,
                         true,
                         RemoveFileMode::Remove {
-                            remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                            remove_fs: !keep_files(),
                         },
                         DEFAULT_PREFETCH_AMOUNT,
                     );
@@ -275,7 +313,7 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
                             AllowedCheckpointStrategy::DecompressOnly,
                             |_passtrough| unreachable!(),
                             |checkpoint_data| { checkpoint_rewrite_bucket = checkpoint_data.map(|d| d.target_subbucket); } ,
-                            |data, _extra_buffer| {
+                            |data, extra_buffer| {
 
                                 let rewrite_bucket = checkpoint_rewrite_bucket
                                 .unwrap_or_else(|| E::RewriteBucketCompute::get_rewrite_bucket(global_params.common.k,
@@ -286,24 +324,49 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
                                 ));
                                 sequences_deltas[rewrite_bucket as usize] += 1;
 
-                                let (flags, _, _extra, read, multiplicity) = data;
+                                let (flags, _, extra, read, multiplicity) = data;
+
+                                // Super-kmers can be merged only if their extra data (colors) is the same
+                                encoded_extra.clear();
+                                extra.encode_extended(extra_buffer, &mut encoded_extra, Default::default());
 
                                 let super_kmers_hashmap = &mut super_kmers_hashmap[rewrite_bucket as usize];
 
                                 if let Some(entry) = super_kmers_hashmap.get_mut(
                                     read.get_borrowable(),
                                 ) {
-                                    // Combine the flags from the two super-kmers
-                                    entry.0 |= flags;
-                                    entry.1 += multiplicity;
+                                    let matching_sequence = if extra_storage[entry.extra_data.clone()] == encoded_extra[..] {
+                                        Some(&mut *entry)
+                                    } else {
+                                        let mut duplicate_index = entry.next_duplicate;
+                                        while duplicate_index != CompactedSequence::NO_DUPLICATE
+                                            && extra_storage[duplicate_sequences[duplicate_index].extra_data.clone()] != encoded_extra[..]
+                                        {
+                                            duplicate_index = duplicate_sequences[duplicate_index].next_duplicate;
+                                        }
+                                        duplicate_sequences.get_mut(duplicate_index)
+                                    };
+
+                                    if let Some(sequence) = matching_sequence {
+                                        // Combine the flags from the two super-kmers
+                                        sequence.flags |= flags;
+                                        sequence.multiplicity += multiplicity;
+                                    } else {
+                                        let duplicate = CompactedSequence::new(flags, multiplicity, &encoded_extra, &mut extra_storage, entry.next_duplicate);
+                                        entry.next_duplicate = duplicate_sequences.len();
+                                        duplicate_sequences.push(duplicate);
+                                        subbuckets_sequences[rewrite_bucket as usize] += 1;
+                                        total_sequences += 1;
+                                    }
                                 } else {
                                     let new_read = CompressedReadIndipendent::from_read(&read, &mut kmers_storage);
                                     assert!(!super_kmers_hashmap.contains_key(read.get_borrowable()));
                                     assert!(!super_kmers_hashmap.contains_key(&SuperKmerEntry(&kmers_storage as *const _, new_read)));
                                     super_kmers_hashmap.insert(
                                         SuperKmerEntry(&kmers_storage as *const _, new_read),
-                                        (flags, multiplicity),
+                                        CompactedSequence::new(flags, multiplicity, &encoded_extra, &mut extra_storage, CompactedSequence::NO_DUPLICATE),
                                     );
+                                    subbuckets_sequences[rewrite_bucket as usize] += 1;
                                     total_sequences += 1;
                                 }
                             },
@@ -319,7 +382,7 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
                     }
                 }
 
-                let new_bucket = CompressedBinaryWriter::new(
+                let new_bucket = MemoryTrackedBucket::<CompressedBinaryWriter>::new(
                     &new_path,
                     &(
                         get_memory_mode(SwapPriority::MinimizerBuckets),
@@ -331,7 +394,7 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
                 );
 
                 let mut serializer = CompressedReadsBucketDataSerializer::<
-                    NonColoredManager,
+                    E::ExtraData,
                     E::FLAGS_COUNT,
                     NoSecondBucket,
                     WithMultiplicity,
@@ -339,10 +402,7 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
 
                 let mut buffer = Vec::with_capacity(DEFAULT_OUTPUT_BUFFER_SIZE);
 
-                // TODO: SUPPORT COLORS?
-                let out_extra_buffer =
-                    <NonColoredManager as SequenceExtraDataTempBufferManagement>::new_temp_buffer();
-                let empty_extra = NonColoredManager::default();
+                let mut out_extra_buffer = E::ExtraData::new_temp_buffer();
 
                 for (rewrite_bucket, super_kmers_hashmap) in
                     super_kmers_hashmap.iter_mut().enumerate()
@@ -360,29 +420,49 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
                     new_bucket.set_checkpoint_data(
                         Some(&ReadsCheckpointData {
                             target_subbucket: rewrite_bucket as BucketIndexType,
-                            sequences_count: super_kmers_hashmap.len(),
+                            sequences_count: subbuckets_sequences[rewrite_bucket],
                         }),
                         None,
                     );
+                    subbuckets_sequences[rewrite_bucket] = 0;
+                    // Each checkpoint can be read independently, reset the extra data compression
+                    serializer.reset();
 
-                    for (read, (flags, multiplicity)) in super_kmers_hashmap.drain() {
+                    for (read, first_sequence) in super_kmers_hashmap.drain() {
                         let read = read.get_read();
-                        sequences_deltas[rewrite_bucket as usize] -= 1;
-
-                        serializer.write_to(
-                            &CompressedReadsBucketData::new_packed_with_multiplicity(
-                                read,
-                                flags,
-                                0,
-                                multiplicity,
-                            ),
-                            &mut buffer,
-                            &empty_extra,
-                            &out_extra_buffer,
-                        );
-                        if buffer.len() > DEFAULT_OUTPUT_BUFFER_SIZE {
-                            new_bucket.write_data(&buffer);
-                            buffer.clear();
+
+                        let mut sequence = &first_sequence;
+                        loop {
+                            sequences_deltas[rewrite_bucket as usize] -= 1;
+
+                            E::ExtraData::clear_temp_buffer(&mut out_extra_buffer);
+                            let extra = E::ExtraData::decode_from_slice_extended(
+                                &mut out_extra_buffer,
+                                &extra_storage[sequence.extra_data.clone()],
+                                Default::default(),
+                            )
+                            .unwrap();
+
+                            serializer.write_to(
+                                &CompressedReadsBucketData::new_packed_with_multiplicity(
+                                    read,
+                                    sequence.flags,
+                                    0,
+                                    sequence.multiplicity,
+                                ),
+                                &mut buffer,
+                                &extra,
+                                &out_extra_buffer,
+                            );
+                            if buffer.len() > DEFAULT_OUTPUT_BUFFER_SIZE {
+                                new_bucket.write_data(&buffer);
+                                buffer.clear();
+                            }
+
+                            if sequence.next_duplicate == CompactedSequence::NO_DUPLICATE {
+                                break;
+                            }
+                            sequence = &duplicate_sequences[sequence.next_duplicate];
                         }
                     }
 
@@ -422,6 +502,6 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
                 global_params.common.compaction_offsets[bucket_index]
                     .fetch_add(sequences_deltas.iter().sum::<i64>(), Ordering::Relaxed);
             }
-        }
+        })
     }
 }
@@ -1,6 +1,8 @@
-use config::BucketIndexType;
+use config::{get_memory_mode, BucketIndexType, SwapPriority};
+use parallel_processor::memory_fs::file::reader::FileReader;
+use parallel_processor::memory_fs::file::writer::FileWriter;
+use parallel_processor::memory_fs::{MemoryFs, RemoveFileMode};
 use serde::{Deserialize, Serialize};
-use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::path::Path;
 use std::sync::atomic::{AtomicI64, AtomicU64};
@@ -87,7 +89,10 @@ impl CountersAnalyzer {
     }
 
     pub fn load_from_file(path: impl AsRef<Path>, remove: bool) -> Self {
-        let file = BufReader::new(File::open(&path).unwrap());
+        let file = BufReader::new(
+            FileReader::open(&path, None)
+                .unwrap_or_else(|| panic!("Cannot open file {}", path.as_ref().display())),
+        );
         let rval: CountersAnalyzer = bincode::deserialize_from(file).unwrap();
 
         // rval.counters.iter_mut().enumerate().for_each(|(bn, x)| {
@@ -102,16 +107,16 @@ impl CountersAnalyzer {
         // rval.print_debug();
 
         if remove {
-            let _ = std::fs::remove_file(path);
+            let _ = MemoryFs::remove_file(path, RemoveFileMode::Remove { remove_fs: true });
         }
         rval
     }
 
     pub fn serialize_to_file(&self, path: impl AsRef<Path>) {
-        let file = BufWriter::new(
-            File::create(path.as_ref())
-                .expect(&format!("Cannot open file {}", path.as_ref().display())),
-        );
+        let file = BufWriter::new(FileWriter::create(
+            path,
+            get_memory_mode(SwapPriority::MinimizerBuckets),
+        ));
         bincode::serialize_into(file, self).unwrap();
     }
 }
@@ -11,6 +11,7 @@ use crate::reader::MinimizerBucketingFilesReader;
 use crate::sequences_splitter::SequencesSplitter;
 use colors::colors_manager::ColorsManager;
 use compactor::CompactorInitData;
+use config::operation::{MemoryTrackedBucket, OperationContext};
 use config::{
     get_compression_level_info, get_memory_mode, BucketIndexType, SwapPriority,
     DEFAULT_PER_CPU_BUFFER_SIZE, MINIMIZER_BUCKETS_CHECKPOINT_SIZE, PACKETS_PRIORITY_COMPACT,
@@ -205,7 +206,7 @@ impl<GlobalData> MinimizerBucketingCommonData<GlobalData> {
 }
 
 pub struct MinimizerBucketingExecutionContext<GlobalData> {
-    pub buckets: Arc<MultiThreadBuckets<CompressedBinaryWriter>>,
+    pub buckets: Arc<MultiThreadBuckets<MemoryTrackedBucket<CompressedBinaryWriter>>>,
     pub common: Arc<MinimizerBucketingCommonData<GlobalData>>,
     pub current_file: AtomicUsize,
     pub executor_group_address: RwLock<Option<ExecutorAddress>>,
@@ -219,6 +220,10 @@ pub struct MinimizerBucketingExecutionContext<GlobalData> {
 
     pub partial_read_copyback: Option<usize>,
     pub copy_ident: bool,
+    /// Bases with a lower phred quality are replaced with N, splitting the sequence
+    pub min_base_quality: Option<u8>,
+    /// The operation running the bucketing, whose settings are used by the executors threads
+    pub operation: Arc<OperationContext>,
 }
 
 pub struct GenericMinimizerBucketing;
@@ -263,6 +268,10 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> MinimizerBuck
         let thread_handle = PriorityScheduler::declare_thread(PRIORITY_SCHEDULING_HIGH);
 
         while let Some(input_packet) = ops.receive_packet(&thread_handle).await {
+            if config::should_interrupt_phase() {
+                continue;
+            }
+
             let mut total_bases = 0;
             let mut sequences_splitter = SequencesSplitter::new(context.common.k);
             let mut buckets_processor = E::new(&context.common);
@@ -333,6 +342,7 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> MinimizerBuck
             let total_bases_count =
                 TOT_BASES_COUNT.fetch_add(total_bases, Ordering::Relaxed) + total_bases;
             VALID_BASES_COUNT.fetch_add(sequences_splitter.valid_bases, Ordering::Relaxed);
+            config::progress::advance_progress(total_bases);
 
             const TOTAL_BASES_DIFF_LOG: u64 = 10000000000;
 
@@ -403,7 +413,7 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
     ) -> impl Future<Output = ()> + Send + 'a {
         let thread_handle = PriorityScheduler::declare_thread(PRIORITY_SCHEDULING_LOW);
 
-        async move {
+        global_params.operation.scope(async move {
             while let Ok((address, _)) = receiver
                 .obtain_address_with_priority(WORKERS_PRIORITY_BASE, &thread_handle)
                 .await
@@ -412,13 +422,13 @@ impl<E: MinimizerBucketingExecutorFactory + Sync + Send + 'static> AsyncExecutor
 
                 let mut spawner = address.make_spawner();
                 for _ in 0..max_concurrency {
-                    spawner.spawn_executor(async {
+                    spawner.spawn_executor(global_params.operation.scope(async {
                         self.execute(global_params, &address).await;
-                    });
+                    }));
                 }
                 spawner.executors_await().await;
             }
-        }
+        })
     }
 }
 
@@ -473,6 +483,7 @@ impl GenericMinimizerBucketing {
             copy_ident,
             ignored_length,
             None,
+            None,
         );
 
         (
@@ -498,12 +509,15 @@ impl GenericMinimizerBucketing {
         partial_read_copyback: Option<usize>,
         copy_ident: bool,
         ignored_length: usize,
+        min_base_quality: Option<u8>,
         maximum_disk_usage: Option<u64>,
     ) -> (Vec<MultiChunkBucket>, PathBuf) {
         let read_threads_count = max(1, threads_count / 2);
         let compute_threads_count = max(1, threads_count.saturating_sub(read_threads_count / 4));
 
-        let buckets = Arc::new(MultiThreadBuckets::<CompressedBinaryWriter>::new(
+        let buckets = Arc::new(MultiThreadBuckets::<
+            MemoryTrackedBucket<CompressedBinaryWriter>,
+        >::new(
             buckets_count,
             output_path.join("bucket"),
             maximum_disk_usage,
@@ -543,6 +557,8 @@ impl GenericMinimizerBucketing {
             partial_read_copyback,
             read_threads_count,
             copy_ident,
+            min_base_quality,
+            operation: OperationContext::current(),
         });
 
         {
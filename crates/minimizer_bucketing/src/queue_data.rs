@@ -58,6 +58,7 @@ impl<F: Clone + Sync + Send + Default + 'static> MinimizerBucketingQueueData<F>
                     DnaSequence {
                         ident_data,
                         seq,
+                        qual: None,
                         format,
                     },
                     seq_info,
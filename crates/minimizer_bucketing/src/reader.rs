@@ -1,6 +1,7 @@
 use crate::queue_data::MinimizerBucketingQueueData;
 use crate::MinimizerBucketingExecutionContext;
 use config::{PRIORITY_SCHEDULING_LOW, WORKERS_PRIORITY_BASE};
+use io::sequences_reader::DnaSequence;
 use io::sequences_stream::GenericSequencesStream;
 use nightly_quirks::branch_pred::unlikely;
 use parallel_processor::execution_manager::executor::{
@@ -15,6 +16,25 @@ use std::marker::PhantomData;
 use std::ops::DerefMut;
 use std::sync::atomic::Ordering;
 
+const PHRED_QUALITY_OFFSET: u8 = 33;
+
+/// Replaces with N all the bases with a phred quality lower than `min_quality`,
+/// so that the kmers containing them are discarded when splitting the sequence
+fn mask_low_quality_bases(seq: &[u8], qual: &[u8], min_quality: u8, output: &mut Vec<u8>) {
+    output.clear();
+    output.extend(seq.iter().zip(qual.iter()).map(|(&base, &quality)| {
+        if quality.saturating_sub(PHRED_QUALITY_OFFSET) < min_quality {
+            b'N'
+        } else {
+            base
+        }
+    }));
+    // Bases without a quality value are kept
+    if seq.len() > qual.len() {
+        output.extend_from_slice(&seq[qual.len()..]);
+    }
+}
+
 pub struct MinimizerBucketingFilesReader<
     GlobalData: Sync + Send + 'static,
     StreamInfo: Sync + Send + Clone + Default + 'static,
@@ -56,6 +76,11 @@ impl<
         let mut sequences_stream = SequencesStream::new();
 
         while let Some(mut input_packet) = ops.receive_packet(&thread_handle).await {
+            if config::should_interrupt_phase() {
+                // Skip the remaining input blocks, the operation is aborted at the end of the phase
+                continue;
+            }
+
             let mut data_packet = packets_pool.alloc_packet().await;
             let stream_info = input_packet.1.clone();
 
@@ -68,6 +93,7 @@ impl<
             context.current_file.fetch_add(1, Ordering::Relaxed);
 
             let mut max_len = 0;
+            let mut masked_seq = vec![];
 
             sequences_stream.read_block(
                 &mut input_packet.0,
@@ -82,6 +108,17 @@ impl<
 
                     max_len = max(max_len, x.ident_data.len() + x.seq.len());
 
+                    let x = match (context.min_base_quality, x.qual) {
+                        (Some(min_quality), Some(qual)) => {
+                            mask_low_quality_bases(x.seq, qual, min_quality, &mut masked_seq);
+                            DnaSequence {
+                                seq: &masked_seq,
+                                ..x
+                            }
+                        }
+                        _ => x,
+                    };
+
                     if unlikely(!data.push_sequences(x, seq_info)) {
                         assert!(
                             data.start_read_index as usize + data.sequences.len()
@@ -157,7 +194,7 @@ impl<
         mut receiver: ExecutorReceiver<Self>,
         _memory_tracker: MemoryTracker<Self>,
     ) -> impl Future<Output = ()> + Send + 'a {
-        async move {
+        global_params.operation.scope(async move {
             let thread_handle = PriorityScheduler::declare_thread(PRIORITY_SCHEDULING_LOW);
 
             while let Ok((address, _)) = receiver
@@ -168,13 +205,13 @@ impl<
 
                 let mut spawner = address.make_spawner();
                 for _ in 0..read_threads_count {
-                    spawner.spawn_executor(async {
+                    spawner.spawn_executor(global_params.operation.scope(async {
                         self.execute(global_params, &address).await;
-                    });
+                    }));
                 }
                 spawner.executors_await().await;
             }
-        }
+        })
     }
 }
 
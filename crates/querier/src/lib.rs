@@ -3,24 +3,29 @@ use crate::pipeline::colormap_reading::colormap_reading;
 use crate::pipeline::counters_sorting::counters_sorting;
 use crate::pipeline::parallel_kmers_query::parallel_kmers_counting;
 use crate::pipeline::querier_minimizer_bucketing::minimizer_bucketing;
+use crate::query_output::QueryResultsFileWriter;
+use crate::structs::queries_info::{QueriesInfo, QueriesInfoCollector};
 use ::dynamic_dispatch::dynamic_dispatch;
 use colors::colors_manager::{ColorMapReader, ColorsManager, ColorsMergeManager};
 use colors::DefaultColorsSerializer;
-use config::{INTERMEDIATE_COMPRESSION_LEVEL_FAST, INTERMEDIATE_COMPRESSION_LEVEL_SLOW};
+use config::progress::check_cancelled;
+use config::{check_memory_only_budget, keep_files, set_intermediate_compression_level};
 use hashes::default::MNHFactory;
 use hashes::HashFunctionFactory;
-use io::sequences_reader::SequencesReader;
 use io::sequences_stream::general::GeneralSequenceBlockData;
-use io::{compute_stats_from_input_blocks, generate_bucket_names};
+use io::{compute_stats_from_input_blocks, generate_bucket_names, FileIoError};
 use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use parking_lot::Mutex;
 use std::path::PathBuf;
-use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
 mod pipeline;
+mod query_output;
 mod structs;
 
+pub use query_output::{QueryOutput, QueryOutputFunction, QueryResultsFilter};
+pub use structs::query_kmers_hits::{QueryKmerHit, QueryKmersRun};
+
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
 pub enum QuerierStartingStep {
     MinimizerBucketing = 0,
@@ -40,6 +45,7 @@ pub enum ColoredQueryOutputFormat {
     #[cfg(not(feature = "devel-build"))] hashes::fw_seqhash::u32::ForwardSeqHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::fw_seqhash::u64::ForwardSeqHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::fw_seqhash::u128::ForwardSeqHashFactory,
+    #[cfg(not(feature = "devel-build"))] hashes::fw_seqhash::u256::ForwardSeqHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::fw_rkhash::u32::ForwardRabinKarpHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::fw_rkhash::u64::ForwardRabinKarpHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::fw_rkhash::u128::ForwardRabinKarpHashFactory,
@@ -47,6 +53,7 @@ pub enum ColoredQueryOutputFormat {
     #[cfg(not(feature = "devel-build"))] hashes::cn_seqhash::u32::CanonicalSeqHashFactory,
     hashes::cn_seqhash::u64::CanonicalSeqHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::cn_seqhash::u128::CanonicalSeqHashFactory,
+    #[cfg(not(feature = "devel-build"))] hashes::cn_seqhash::u256::CanonicalSeqHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::cn_rkhash::u32::CanonicalRabinKarpHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::cn_rkhash::u64::CanonicalRabinKarpHashFactory,
     #[cfg(not(feature = "devel-build"))] hashes::cn_rkhash::u128::CanonicalRabinKarpHashFactory,
@@ -59,18 +66,21 @@ pub fn run_query<MergingHash: HashFunctionFactory, QuerierColorsManager: ColorsM
     m: usize,
     step: QuerierStartingStep,
     graph_input: PathBuf,
-    query_input: PathBuf,
-    output_file_prefix: PathBuf,
+    query_inputs: Vec<GeneralSequenceBlockData>,
+    output: QueryOutput<'_>,
     temp_dir: Option<PathBuf>,
     buckets_count_log: Option<usize>,
     threads_count: usize,
     default_compression_level: Option<u32>,
-    colored_query_output_format: ColoredQueryOutputFormat,
-) -> anyhow::Result<PathBuf> {
+    emit_kmers_hits: bool,
+    results_filter: QueryResultsFilter,
+) -> anyhow::Result<Option<PathBuf>> {
     let temp_dir = temp_dir.unwrap_or(PathBuf::new());
 
     PHASES_TIMES_MONITOR.write().init();
 
+    check_cancelled()?;
+
     MNHFactory::initialize(k);
     MergingHash::initialize(k);
 
@@ -78,44 +88,53 @@ pub fn run_query<MergingHash: HashFunctionFactory, QuerierColorsManager: ColorsM
         graph_input.with_extension("colors.dat"),
     )?;
 
-    // TODO: Support GFA input
-    let file_stats = compute_stats_from_input_blocks(&[
-        GeneralSequenceBlockData::FASTA((graph_input.clone(), None)),
-        GeneralSequenceBlockData::FASTA((query_input.clone(), None)),
-    ])?;
+    let mut input_blocks = vec![GeneralSequenceBlockData::from_file(
+        graph_input.clone(),
+        None,
+    )];
+    input_blocks.extend(query_inputs);
+    let file_stats = compute_stats_from_input_blocks(&input_blocks)?;
+    let query_inputs = input_blocks.split_off(1);
+    let query_blocks_count = query_inputs.len();
 
     let buckets_count_log = buckets_count_log.unwrap_or_else(|| file_stats.best_buckets_count_log);
 
     if let Some(default_compression_level) = default_compression_level {
-        INTERMEDIATE_COMPRESSION_LEVEL_SLOW.store(default_compression_level, Ordering::Relaxed);
-        INTERMEDIATE_COMPRESSION_LEVEL_FAST.store(default_compression_level, Ordering::Relaxed);
+        set_intermediate_compression_level(default_compression_level);
     }
 
     let buckets_count = 1 << buckets_count_log;
 
-    let ((buckets, counters), queries_count) = if step <= QuerierStartingStep::MinimizerBucketing {
-        minimizer_bucketing::<QuerierColorsManager>(
+    let queries_info_file = temp_dir.join("queries-info.dat");
+    let (buckets, counters, queries_info) = if step <= QuerierStartingStep::MinimizerBucketing {
+        let queries_info_collector = Arc::new(QueriesInfoCollector::default());
+        let (buckets, counters) = minimizer_bucketing::<QuerierColorsManager>(
             graph_input.clone(),
-            query_input.clone(),
+            query_inputs,
+            queries_info_collector.clone(),
             temp_dir.as_path(),
             buckets_count,
             threads_count,
             k,
             m,
-        )
+        );
+        check_memory_only_budget()?;
+        check_cancelled()?;
+
+        let queries_info = queries_info_collector.collect_queries_info(query_blocks_count);
+        // Needed to resume the query from the next steps
+        if keep_files() {
+            queries_info
+                .save(&queries_info_file)
+                .map_err(|err| FileIoError::new(&queries_info_file, err))?;
+        }
+        (buckets, counters, queries_info)
     } else {
         (
-            (
-                generate_bucket_names(temp_dir.join("bucket"), buckets_count, None),
-                temp_dir.join("buckets-counters.dat"),
-            ),
-            {
-                let queries_count = BufReader::new(File::open(&query_input).unwrap())
-                    .lines()
-                    .count() as u64
-                    / 2;
-                queries_count
-            },
+            generate_bucket_names(temp_dir.join("bucket"), buckets_count, None),
+            temp_dir.join("buckets-counters.dat"),
+            QueriesInfo::load(&queries_info_file)
+                .map_err(|err| FileIoError::new(&queries_info_file, err))?,
         )
     };
 
@@ -128,39 +147,97 @@ pub fn run_query<MergingHash: HashFunctionFactory, QuerierColorsManager: ColorsM
             k,
             m,
             threads_count,
+            emit_kmers_hits,
+            queries_info.blocks_first_query(),
         )
     } else {
         generate_bucket_names(temp_dir.join("counters"), buckets_count, None)
     };
+    check_memory_only_budget()?;
+    check_cancelled()?;
 
     let colored_buckets_prefix = temp_dir.join("color_counters");
 
-    let query_kmers_count = {
-        let mut sequences_lengths = vec![];
-        SequencesReader::new().process_file_extended(
-            &query_input,
-            |seq| {
-                sequences_lengths.push((seq.seq.len().saturating_sub(k - 1)) as u64);
-            },
-            None,
-            false,
-            false,
-        );
-        sequences_lengths
+    let (output_file_name, output_writer) = match &output {
+        QueryOutput::File {
+            output_file_prefix,
+            colored_query_output_format,
+            emit_query_names,
+        } => {
+            let output_file_name = QueryResultsFileWriter::output_file_name(
+                output_file_prefix,
+                QuerierColorsManager::COLORS_ENABLED,
+            );
+            let writer = QueryResultsFileWriter::new(
+                &output_file_name,
+                QuerierColorsManager::COLORS_ENABLED,
+                *colored_query_output_format,
+                *emit_query_names,
+                emit_kmers_hits,
+            )?;
+            (Some(output_file_name), Some(Mutex::new(writer)))
+        }
+        QueryOutput::Callback(_) => (None, None),
     };
 
-    let colored_buckets = if step <= QuerierStartingStep::CountersSorting {
+    let write_to_file = |query_index,
+                         query_name: &[u8],
+                         matched_kmers,
+                         query_kmers,
+                         colors: Option<&_>,
+                         kmers_hits: Option<&_>| {
+        output_writer
+            .as_ref()
+            .unwrap()
+            .lock()
+            .write_result(
+                query_index,
+                query_name,
+                matched_kmers,
+                query_kmers,
+                colors,
+                kmers_hits,
+                |color| color_map.get_color_name(color, true),
+            )
+            .unwrap();
+    };
+
+    let output_function: &QueryOutputFunction = match &output {
+        QueryOutput::File { .. } => &write_to_file,
+        QueryOutput::Callback(output_function) => *output_function,
+    };
+
+    let results_function =
+        |query_index, matched_kmers, query_kmers, colors: Option<&_>, kmers_hits: Option<&_>| {
+            output_function(
+                query_index,
+                queries_info.get_name(query_index),
+                matched_kmers,
+                query_kmers,
+                colors,
+                kmers_hits,
+            )
+        };
+
+    let (colored_buckets, queries_hits) = if step <= QuerierStartingStep::CountersSorting {
         counters_sorting::<QuerierColorsManager>(
             k,
             counters_buckets,
             colored_buckets_prefix,
             color_map.colors_subsets_count(),
-            output_file_prefix.clone(),
-            &query_kmers_count,
+            queries_info.kmers_count(),
+            emit_kmers_hits,
+            &results_filter,
+            &results_function,
         )
     } else {
-        generate_bucket_names(colored_buckets_prefix, buckets_count, None)
+        (
+            generate_bucket_names(colored_buckets_prefix, buckets_count, None),
+            None,
+        )
     };
+    check_memory_only_budget()?;
+    check_cancelled()?;
 
     if QuerierColorsManager::COLORS_ENABLED {
         let colormap_file = graph_input.with_extension("colors.dat");
@@ -168,32 +245,30 @@ pub fn run_query<MergingHash: HashFunctionFactory, QuerierColorsManager: ColorsM
             colormap_file,
             colored_buckets,
             temp_dir.clone(),
-            queries_count,
+            queries_info.queries_count(),
         )?;
+        check_memory_only_budget()?;
+        check_cancelled()?;
 
         colored_query_output::<MergingHash, QuerierColorsManager>(
-            &color_map,
             remapped_query_color_buckets,
-            output_file_prefix.clone(),
             temp_dir,
-            &query_kmers_count,
-            colored_query_output_format,
+            queries_info.kmers_count(),
+            queries_hits.as_ref(),
+            &results_filter,
+            &results_function,
         )?;
+        check_memory_only_budget()?;
+        check_cancelled()?;
+    }
+
+    if let Some(output_writer) = output_writer {
+        output_writer.into_inner().finalize()?;
     }
 
     PHASES_TIMES_MONITOR
         .write()
         .print_stats("Query completed.".to_string());
 
-    let output_file_name = if output_file_prefix.extension().is_none() {
-        if QuerierColorsManager::COLORS_ENABLED {
-            output_file_prefix.with_extension("jsonl")
-        } else {
-            output_file_prefix.with_extension("csv")
-        }
-    } else {
-        output_file_prefix
-    };
-
     Ok(output_file_name)
 }
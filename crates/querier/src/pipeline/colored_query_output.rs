@@ -1,14 +1,16 @@
+use crate::query_output::{QueryResultsFilter, QueryResultsFunction};
 use crate::structs::query_colored_counters::{ColorsRange, QueryColoredCountersSerializer};
-use crate::ColoredQueryOutputFormat;
-use colors::colors_manager::ColorMapReader;
-use colors::colors_manager::{ColorsManager, ColorsMergeManager};
+use crate::structs::query_kmers_hits::QueriesKmersHits;
+use byteorder::ReadBytesExt;
+use colors::colors_manager::ColorsManager;
+use config::operation::MemoryTrackedBucket;
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::{
-    get_compression_level_info, get_memory_mode, ColorIndexType, SwapPriority,
-    DEFAULT_PREFETCH_AMOUNT, KEEP_FILES, QUERIES_COUNT_MIN_BATCH,
+    get_compression_level_info, get_memory_mode, keep_files, ColorIndexType, SwapPriority,
+    DEFAULT_PREFETCH_AMOUNT, QUERIES_COUNT_MIN_BATCH,
 };
-use flate2::Compression;
-use ggcat_logging::UnrecoverableErrorLogging;
 use hashes::HashFunctionFactory;
+use io::varint::{decode_varint, encode_varint};
 use nightly_quirks::prelude::*;
 use parallel_processor::buckets::readers::compressed_binary_reader::CompressedBinaryReader;
 use parallel_processor::buckets::readers::BucketReader;
@@ -18,44 +20,16 @@ use parallel_processor::memory_fs::RemoveFileMode;
 use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
 use parking_lot::{Condvar, Mutex};
 use rayon::prelude::*;
-use std::fs::File;
-use std::io::BufWriter;
-use std::io::Write;
-use std::ops::DerefMut;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-enum QueryOutputFileWriter {
-    Plain(File),
-    LZ4Compressed(lz4::Encoder<File>),
-    GzipCompressed(flate2::write::GzEncoder<File>),
-}
-
-impl Write for QueryOutputFileWriter {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        match self {
-            QueryOutputFileWriter::Plain(w) => w.write(buf),
-            QueryOutputFileWriter::LZ4Compressed(w) => w.write(buf),
-            QueryOutputFileWriter::GzipCompressed(w) => w.write(buf),
-        }
-    }
-
-    fn flush(&mut self) -> std::io::Result<()> {
-        match self {
-            QueryOutputFileWriter::Plain(w) => w.flush(),
-            QueryOutputFileWriter::LZ4Compressed(w) => w.flush(),
-            QueryOutputFileWriter::GzipCompressed(w) => w.flush(),
-        }
-    }
-}
-
 pub fn colored_query_output<MH: HashFunctionFactory, CX: ColorsManager>(
-    colormap: &<CX::ColorsMergeManagerType as ColorsMergeManager>::GlobalColorsTableReader,
     mut colored_query_buckets: Vec<SingleBucket>,
-    output_file: PathBuf,
     temp_dir: PathBuf,
     query_kmers_count: &[u64],
-    colored_query_output_format: ColoredQueryOutputFormat,
+    queries_hits: Option<&QueriesKmersHits>,
+    results_filter: &QueryResultsFilter,
+    output_function: &QueryResultsFunction,
 ) -> anyhow::Result<()> {
     PHASES_TIMES_MONITOR
         .write()
@@ -63,6 +37,12 @@ pub fn colored_query_output<MH: HashFunctionFactory, CX: ColorsManager>(
 
     let buckets_count = colored_query_buckets.len();
 
+    config::progress::start_progress_phase(
+        ProgressPhase::QueryOutput,
+        ProgressUnit::Buckets,
+        buckets_count as u64,
+    );
+
     let max_bucket_queries_count = (((query_kmers_count.len() + 1) as u64)
         .nq_div_ceil(QUERIES_COUNT_MIN_BATCH)
         * QUERIES_COUNT_MIN_BATCH) as usize;
@@ -73,32 +53,7 @@ pub fn colored_query_output<MH: HashFunctionFactory, CX: ColorsManager>(
     colored_query_buckets.reverse();
     let buckets_channel = Mutex::new(colored_query_buckets);
 
-    let output_file = if output_file.extension().is_none() {
-        output_file.with_extension("jsonl")
-    } else {
-        output_file
-    };
-
-    let query_output_file = File::create(&output_file)
-        .log_unrecoverable_error_with_data("Cannot create output file", output_file.display())?;
-
-    let query_output = Mutex::new((
-        BufWriter::new(
-            match output_file.extension().map(|e| e.to_str()).flatten() {
-                Some("lz4") => QueryOutputFileWriter::LZ4Compressed(
-                    lz4::EncoderBuilder::new()
-                        .level(4)
-                        .build(query_output_file)
-                        .unwrap(),
-                ),
-                Some("gz") => QueryOutputFileWriter::GzipCompressed(
-                    flate2::GzBuilder::new().write(query_output_file, Compression::default()),
-                ),
-                _ => QueryOutputFileWriter::Plain(query_output_file),
-            },
-        ),
-        0,
-    ));
+    let query_output_index = Mutex::new(0);
     let output_sync_condvar = Condvar::new();
 
     (0..rayon::current_num_threads())
@@ -112,15 +67,24 @@ pub fn colored_query_output<MH: HashFunctionFactory, CX: ColorsManager>(
             }
 
             let mut queries_colors_list_pool = vec![];
-            let mut queries_results =
-                vec![(0u32 /* epoch */, 0usize /* list index */); max_bucket_queries_count];
+            let mut queries_results = vec![
+                (
+                    0u32,   /* epoch */
+                    0usize, /* list index */
+                    0u64,   /* matched kmers */
+                );
+                max_bucket_queries_count
+            ];
             let mut temp_colors_list = vec![];
+            let mut query_colors = vec![];
 
             let mut epoch = 0;
 
             while let Some(input) = {
                 let mut lock = buckets_channel.lock();
-                let element = lock.pop();
+                // Stop taking new buckets if the operation was interrupted, the buckets are
+                // taken in order so the ones already taken can still write their results
+                let element = lock.pop().filter(|_| !config::should_interrupt_phase());
                 drop(lock);
                 element
             } {
@@ -133,7 +97,7 @@ pub fn colored_query_output<MH: HashFunctionFactory, CX: ColorsManager>(
                 CompressedBinaryReader::new(
                     &input.path,
                     RemoveFileMode::Remove {
-                        remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                        remove_fs: !keep_files(),
                     },
                     DEFAULT_PREFETCH_AMOUNT,
                 )
@@ -142,14 +106,19 @@ pub fn colored_query_output<MH: HashFunctionFactory, CX: ColorsManager>(
                     &mut (),
                     |counters, _| {
                         for query in counters.queries {
-                            let (entry_epoch, colors_map_index) = &mut queries_results
-                                [query.query_index as usize - start_query_index - 1];
+                            let (entry_epoch, colors_map_index, matched_kmers) =
+                                &mut queries_results
+                                    [query.query_index as usize - start_query_index - 1];
 
                             if *entry_epoch != epoch {
                                 *entry_epoch = epoch;
                                 *colors_map_index = usize::MAX;
+                                *matched_kmers = 0;
                             }
 
+                            // Each query appears once for every colors subset it matches
+                            *matched_kmers += query.count;
+
                             assert_eq!(counters.colors.len() % 2, 0);
                             for range in counters.colors.chunks(2) {
                                 let ColorsRange::Range(range) = ColorsRange::from_slice(range);
@@ -172,7 +141,7 @@ pub fn colored_query_output<MH: HashFunctionFactory, CX: ColorsManager>(
 
                 let bucket_index = input.index;
 
-                let compressed_stream = CompressedBinaryWriter::new(
+                let compressed_stream = MemoryTrackedBucket::<CompressedBinaryWriter>::new(
                     &temp_dir.join("query-data"),
                     &(
                         get_memory_mode(SwapPriority::ColoredQueryBuckets),
@@ -183,23 +152,27 @@ pub fn colored_query_output<MH: HashFunctionFactory, CX: ColorsManager>(
                     &(),
                 );
 
-                let mut jsonline_buffer = vec![];
-                for (query, mut query_colors_list_index) in
-                    queries_results.iter().enumerate().filter_map(|(i, r)| {
-                        if r.0 != epoch {
-                            None
-                        } else {
-                            Some((i + start_query_index, r.1))
-                        }
-                    })
-                {
-                    jsonline_buffer.clear();
-                    write!(
-                        jsonline_buffer,
-                        "{{\"query_index\":{}, \"matches\":{{",
-                        query
-                    )
-                    .unwrap();
+                // Queries in the range [first_query_index, end_query_index) are assigned to this bucket
+                let first_query_index = (bucket_index as u64 * max_bucket_queries_count as u64)
+                    .nq_div_ceil(buckets_count as u64)
+                    as usize;
+                let end_query_index =
+                    (((bucket_index + 1) as u64 * max_bucket_queries_count as u64)
+                        .nq_div_ceil(buckets_count as u64) as usize)
+                        .min(query_kmers_count.len());
+
+                let mut results_buffer = vec![];
+                for query in first_query_index..end_query_index {
+                    let (entry_epoch, mut query_colors_list_index, matched_kmers) =
+                        queries_results[query - start_query_index];
+
+                    // Queries not found in this bucket do not have any match
+                    let matched_kmers = if entry_epoch == epoch {
+                        matched_kmers
+                    } else {
+                        query_colors_list_index = usize::MAX;
+                        0
+                    };
 
                     temp_colors_list.clear();
                     while query_colors_list_index != usize::MAX {
@@ -209,37 +182,26 @@ pub fn colored_query_output<MH: HashFunctionFactory, CX: ColorsManager>(
                     }
                     temp_colors_list.sort_unstable_by_key(|r| r.0);
 
-                    for (i, qc) in temp_colors_list.nq_group_by(|a, b| a.0 == b.0).enumerate() {
-                        let color_index = qc[0].0;
-                        let color_presence = qc.iter().map(|x| x.1).sum::<u64>();
-
-                        if i != 0 {
-                            write!(jsonline_buffer, ",").unwrap();
-                        }
-
-                        match colored_query_output_format {
-                            ColoredQueryOutputFormat::JsonLinesWithNumbers => {
-                                write!(jsonline_buffer, "\"{}\"", color_index)
-                            }
-                            ColoredQueryOutputFormat::JsonLinesWithNames => {
-                                write!(
-                                    jsonline_buffer,
-                                    "\"{}\"",
-                                    colormap.get_color_name(color_index, true)
-                                )
-                            }
-                        }
-                        .unwrap();
-
-                        write!(
-                            jsonline_buffer,
-                            ": {:.2}",
-                            (color_presence as f64) / (query_kmers_count[query as usize] as f64)
-                        )
-                        .unwrap();
+                    query_colors.clear();
+                    query_colors.extend(
+                        temp_colors_list
+                            .nq_group_by(|a, b| a.0 == b.0)
+                            .map(|qc| (qc[0].0, qc.iter().map(|x| x.1).sum::<u64>())),
+                    );
+                    results_filter.filter_colors(query_kmers_count[query], &mut query_colors);
+
+                    results_buffer.clear();
+                    let mut write_varint =
+                        |value| encode_varint(|b| results_buffer.extend_from_slice(b), value);
+                    write_varint(query as u64);
+                    write_varint(matched_kmers);
+
+                    write_varint(query_colors.len() as u64);
+                    for &(color, count) in &query_colors {
+                        write_varint(color as u64);
+                        write_varint(count);
                     }
-                    writeln!(jsonline_buffer, "}}}}").unwrap();
-                    compressed_stream.write_data(&jsonline_buffer);
+                    compressed_stream.write_data(&results_buffer);
                 }
 
                 let stream_path = compressed_stream.get_path();
@@ -251,19 +213,44 @@ pub fn colored_query_output<MH: HashFunctionFactory, CX: ColorsManager>(
                     DEFAULT_PREFETCH_AMOUNT,
                 );
 
-                let mut queries_lock = query_output.lock();
+                let mut output_index_lock = query_output_index.lock();
+                while *output_index_lock != bucket_index {
+                    output_sync_condvar.wait(&mut output_index_lock);
+                }
 
-                let (queries_file, query_write_index) = {
-                    while queries_lock.1 != bucket_index {
-                        output_sync_condvar.wait(&mut queries_lock);
+                let mut results_stream = decompress_stream.get_single_stream();
+                let mut read_varint = || decode_varint(|| results_stream.read_u8().ok());
+                let mut colors_buffer = vec![];
+                let mut query_runs = vec![];
+                while let Some(query) = read_varint() {
+                    let matched_kmers = read_varint().unwrap();
+                    let colors_count = read_varint().unwrap();
+                    colors_buffer.clear();
+                    for _ in 0..colors_count {
+                        let color = read_varint().unwrap() as ColorIndexType;
+                        colors_buffer.push((color, read_varint().unwrap()));
                     }
-                    queries_lock.deref_mut()
-                };
-
-                std::io::copy(&mut decompress_stream.get_single_stream(), queries_file).unwrap();
+                    if let Some(queries_hits) = queries_hits {
+                        queries_hits.get_runs(
+                            query,
+                            query_kmers_count[query as usize],
+                            &mut query_runs,
+                        );
+                    }
+                    output_function(
+                        query,
+                        matched_kmers,
+                        query_kmers_count[query as usize],
+                        Some(&colors_buffer),
+                        queries_hits.map(|_| &query_runs[..]),
+                    );
+                }
 
-                *query_write_index += 1;
+                *output_index_lock += 1;
                 output_sync_condvar.notify_all();
+                drop(output_index_lock);
+
+                config::progress::advance_progress(1);
             }
         });
 
@@ -4,9 +4,11 @@ use crate::structs::query_colored_counters::{
 };
 use colors::storage::deserializer::ColorsDeserializer;
 use colors::storage::ColorsSerializerTrait;
+use config::operation::MemoryTrackedBucket;
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::{
-    get_compression_level_info, get_memory_mode, BucketIndexType, ColorIndexType, SwapPriority,
-    DEFAULT_PER_CPU_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT, KEEP_FILES,
+    get_compression_level_info, get_memory_mode, keep_files, BucketIndexType, ColorIndexType,
+    SwapPriority, DEFAULT_PER_CPU_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT,
     MINIMIZER_BUCKETS_CHECKPOINT_SIZE, QUERIES_COUNT_MIN_BATCH,
 };
 use nightly_quirks::prelude::*;
@@ -22,7 +24,6 @@ use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
 use parallel_processor::utils::scoped_thread_local::ScopedThreadLocal;
 use rayon::prelude::*;
 use std::path::PathBuf;
-use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 pub fn colormap_reading<CD: ColorsSerializerTrait>(
@@ -34,11 +35,18 @@ pub fn colormap_reading<CD: ColorsSerializerTrait>(
     PHASES_TIMES_MONITOR
         .write()
         .start_phase("phase: colormap reading".to_string());
+    config::progress::start_progress_phase(
+        ProgressPhase::QueryColormapReading,
+        ProgressUnit::Buckets,
+        colored_query_buckets.len() as u64,
+    );
 
     let buckets_count = colored_query_buckets.len();
     let buckets_prefix_path = temp_dir.join("query_colors");
 
-    let correct_color_buckets = Arc::new(MultiThreadBuckets::<CompressedBinaryWriter>::new(
+    let correct_color_buckets = Arc::new(MultiThreadBuckets::<
+        MemoryTrackedBucket<CompressedBinaryWriter>,
+    >::new(
         buckets_count,
         buckets_prefix_path,
         None,
@@ -62,6 +70,10 @@ pub fn colormap_reading<CD: ColorsSerializerTrait>(
     });
 
     colored_query_buckets.par_iter().for_each(|input| {
+        if config::should_interrupt_phase() {
+            return;
+        }
+
         let mut colormap_decoder = tlocal_colormap_decoder.get();
         let mut temp_colors_buffer = Vec::new();
         let mut temp_queries_buffer = Vec::new();
@@ -78,7 +90,7 @@ pub fn colormap_reading<CD: ColorsSerializerTrait>(
         CompressedBinaryReader::new(
             &input.path,
             RemoveFileMode::Remove {
-                remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                remove_fs: !keep_files(),
             },
             DEFAULT_PREFETCH_AMOUNT,
         )
@@ -173,6 +185,7 @@ pub fn colormap_reading<CD: ColorsSerializerTrait>(
             }
         }
         thread_buffer.put_back(colored_buckets_writer.finalize().0);
+        config::progress::advance_progress(1);
     });
 
     Ok(correct_color_buckets.finalize_single())
@@ -1,9 +1,13 @@
+use crate::query_output::{QueryResultsFilter, QueryResultsFunction};
+use crate::structs::query_kmers_hits::{QueriesKmersHits, QueryKmerHit};
 use byteorder::ReadBytesExt;
 use colors::colors_manager::color_types::SingleKmerColorDataType;
 use colors::colors_manager::ColorsManager;
+use config::operation::MemoryTrackedBucket;
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::{
-    get_compression_level_info, get_memory_mode, SwapPriority, DEFAULT_PER_CPU_BUFFER_SIZE,
-    DEFAULT_PREFETCH_AMOUNT, KEEP_FILES, MINIMIZER_BUCKETS_CHECKPOINT_SIZE,
+    get_compression_level_info, get_memory_mode, keep_files, SwapPriority,
+    DEFAULT_PER_CPU_BUFFER_SIZE, DEFAULT_PREFETCH_AMOUNT, MINIMIZER_BUCKETS_CHECKPOINT_SIZE,
 };
 use io::concurrent::temp_reads::extra_data::{
     SequenceExtraDataConsecutiveCompression, SequenceExtraDataOwned,
@@ -20,6 +24,7 @@ use parallel_processor::fast_smart_bucket_sort::{fast_smart_radix_sort, SortKey}
 use parallel_processor::memory_fs::RemoveFileMode;
 use parallel_processor::phase_times_monitor::PHASES_TIMES_MONITOR;
 use parallel_processor::utils::scoped_thread_local::ScopedThreadLocal;
+use parking_lot::Mutex;
 use rayon::iter::IntoParallelRefIterator;
 use rayon::iter::ParallelIterator;
 use std::io::Read;
@@ -32,6 +37,8 @@ use std::sync::Arc;
 pub struct CounterEntry<CX: SequenceExtraDataConsecutiveCompression<TempBuffer = ()>> {
     pub query_index: u64,
     pub counter: u64,
+    /// Position of the first kmer when the entry is a run of `counter` consecutive query kmers
+    pub position: Option<u64>,
     pub _phantom: PhantomData<CX>,
 }
 
@@ -70,6 +77,10 @@ impl<CX: SequenceExtraDataConsecutiveCompression<TempBuffer = ()>> BucketItemSer
     ) {
         encode_varint(|b| bucket.extend_from_slice(b), element.query_index);
         encode_varint(|b| bucket.extend_from_slice(b), element.counter);
+        encode_varint(
+            |b| bucket.extend_from_slice(b),
+            element.position.map_or(0, |p| p + 1),
+        );
         extra_data.encode(bucket, self.0);
         self.0 = extra_data.obtain_last_data(self.0);
     }
@@ -82,12 +93,14 @@ impl<CX: SequenceExtraDataConsecutiveCompression<TempBuffer = ()>> BucketItemSer
     ) -> Option<Self::ReadType<'a>> {
         let query_index = decode_varint(|| stream.read_u8().ok())?;
         let counter = decode_varint(|| stream.read_u8().ok())?;
+        let position = decode_varint(|| stream.read_u8().ok())?.checked_sub(1);
         let color = CX::decode(&mut stream, self.0)?;
         self.0 = color.obtain_last_data(self.0);
         Some((
             CounterEntry {
                 query_index,
                 counter,
+                position,
                 _phantom: PhantomData,
             },
             color,
@@ -96,7 +109,7 @@ impl<CX: SequenceExtraDataConsecutiveCompression<TempBuffer = ()>> BucketItemSer
 
     #[inline(always)]
     fn get_size(&self, _: &Self::InputElementType<'_>, data: &Self::ExtraData) -> usize {
-        VARINT_MAX_SIZE * 2 + data.max_size()
+        VARINT_MAX_SIZE * 3 + data.max_size()
     }
 }
 
@@ -105,12 +118,19 @@ pub fn counters_sorting<CX: ColorsManager>(
     file_counters_inputs: Vec<SingleBucket>,
     colored_buckets_path: PathBuf,
     colors_count: u64,
-    output_file: PathBuf,
     query_kmers_count: &[u64],
-) -> Vec<SingleBucket> {
+    emit_kmers_hits: bool,
+    results_filter: &QueryResultsFilter,
+    output_function: &QueryResultsFunction,
+) -> (Vec<SingleBucket>, Option<QueriesKmersHits>) {
     PHASES_TIMES_MONITOR
         .write()
         .start_phase("phase: counters sorting".to_string());
+    config::progress::start_progress_phase(
+        ProgressPhase::QueryCountersSorting,
+        ProgressUnit::Buckets,
+        file_counters_inputs.len() as u64,
+    );
 
     let buckets_count = file_counters_inputs.len();
 
@@ -123,7 +143,9 @@ pub fn counters_sorting<CX: ColorsManager>(
     };
 
     let color_buckets = if CX::COLORS_ENABLED {
-        Arc::new(MultiThreadBuckets::<CompressedBinaryWriter>::new(
+        Arc::new(MultiThreadBuckets::<
+            MemoryTrackedBucket<CompressedBinaryWriter>,
+        >::new(
             buckets_count,
             colored_buckets_path,
             None,
@@ -148,7 +170,14 @@ pub fn counters_sorting<CX: ColorsManager>(
 
     let buckets_count_log = buckets_count.ilog2();
 
+    let queries_hits =
+        emit_kmers_hits.then(|| Mutex::new(QueriesKmersHits::new(query_kmers_count.len())));
+
     file_counters_inputs.par_iter().for_each(|input| {
+        if config::should_interrupt_phase() {
+            return;
+        }
+
         let mut thread_buffer = thread_buffers.get();
         let mut colored_buckets_writer = BucketsThreadDispatcher::<
             _,
@@ -162,7 +191,7 @@ pub fn counters_sorting<CX: ColorsManager>(
         LockFreeBinaryReader::new(
             &input.path,
             RemoveFileMode::Remove {
-                remove_fs: !KEEP_FILES.load(Ordering::Relaxed),
+                remove_fs: !keep_files(),
             },
             DEFAULT_PREFETCH_AMOUNT,
         )
@@ -195,11 +224,36 @@ pub fn counters_sorting<CX: ColorsManager>(
 
         fast_smart_radix_sort::<_, CountersCompare, false>(&mut counters_vec[..]);
 
+        let mut bucket_hits = vec![];
+        let mut query_hits = vec![];
+
         for query_results in counters_vec.nq_group_by_mut(|a, b| a.0.query_index == b.0.query_index)
         {
             query_results.sort_unstable_by(|x, y| x.1.cmp(&y.1));
             let query_index = query_results[0].0.query_index;
 
+            if queries_hits.is_some() {
+                query_hits.clear();
+                query_hits.extend(query_results.iter().filter_map(|(entry, color)| {
+                    Some((
+                        entry.position?,
+                        entry.counter,
+                        match CX::get_color_subset(color) {
+                            Some(color_subset) => QueryKmerHit::ColorSubset(color_subset),
+                            None => QueryKmerHit::Present,
+                        },
+                    ))
+                }));
+                query_hits.sort_unstable_by_key(|h| h.0);
+                bucket_hits.push((
+                    query_index - 1,
+                    QueriesKmersHits::encode_runs(
+                        query_kmers_count[query_index as usize - 1],
+                        query_hits.iter().copied(),
+                    ),
+                ));
+            }
+
             if CX::COLORS_ENABLED {
                 for entry in query_results.nq_group_by(|a, b| a.1 == b.1) {
                     let color = entry[0].1.clone();
@@ -209,6 +263,7 @@ pub fn counters_sorting<CX: ColorsManager>(
                         &CounterEntry {
                             query_index,
                             counter: entry.iter().map(|e| e.0.counter).sum(),
+                            position: None,
                             _phantom: PhantomData,
                         },
                     );
@@ -221,45 +276,46 @@ pub fn counters_sorting<CX: ColorsManager>(
             }
         }
 
+        if let Some(queries_hits) = &queries_hits {
+            let mut queries_hits = queries_hits.lock();
+            for (query_index, runs) in bucket_hits {
+                queries_hits.set_runs(query_index, runs);
+            }
+        }
+
         thread_buffer.put_back(colored_buckets_writer.finalize().0);
+        config::progress::advance_progress(1);
     });
 
-    if !CX::COLORS_ENABLED {
-        let output_file = if output_file.extension().is_none() {
-            output_file.with_extension("csv")
-        } else {
-            output_file
-        };
-
-        let mut writer = csv::Writer::from_path(output_file).unwrap();
-        writer
-            .write_record(&[
-                "query_index",
-                "matched_kmers",
-                "query_kmers",
-                "match_percentage",
-            ])
-            .unwrap();
+    let queries_hits = queries_hits.map(|h| h.into_inner());
 
+    // Partial results are not reported if the operation was interrupted
+    if !CX::COLORS_ENABLED && !config::should_interrupt_phase() {
+        let mut query_runs = vec![];
         for (query_index, (info, counter)) in query_kmers_count
             .iter()
             .zip(final_counters.iter())
             .enumerate()
         {
-            writer
-                .write_record(&[
-                    query_index.to_string(),
-                    counter.load(Ordering::Relaxed).to_string(),
-                    info.to_string(),
-                    format!(
-                        "{:.2}",
-                        (counter.load(Ordering::Relaxed) as f64 / *info as f64)
-                    ),
-                ])
-                .unwrap();
+            let matched_kmers = counter.load(Ordering::Relaxed);
+            if !results_filter.is_match_kept(matched_kmers, *info) {
+                continue;
+            }
+
+            if let Some(queries_hits) = &queries_hits {
+                queries_hits.get_runs(query_index as u64, *info, &mut query_runs);
+            }
+
+            output_function(
+                query_index as u64,
+                matched_kmers,
+                *info,
+                None,
+                queries_hits.as_ref().map(|_| &query_runs[..]),
+            );
         }
-        vec![]
+        (vec![], queries_hits)
     } else {
-        color_buckets.finalize_single()
+        (color_buckets.finalize_single(), queries_hits)
     }
 }
@@ -1,12 +1,13 @@
 use crate::pipeline::counters_sorting::CounterEntry;
-use crate::pipeline::querier_minimizer_bucketing::{
-    QuerierMinimizerBucketingExecutorFactory, QuerierMinimizerBucketingGlobalData,
-};
+use crate::pipeline::querier_minimizer_bucketing::QuerierMinimizerBucketingExecutorFactory;
+use crate::structs::queries_info::{QueriesInfo, QueriesInfoCollector};
 use byteorder::{ReadBytesExt, WriteBytesExt};
 use colors::colors_manager::color_types::{
     MinimizerBucketingSeqColorDataType, SingleKmerColorDataType,
 };
 use colors::colors_manager::{ColorsManager, MinimizerBucketingSeqColorData};
+use config::operation::MemoryTrackedBucket;
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::{
     get_memory_mode, BucketIndexType, MultiplicityCounterType, SwapPriority,
     DEFAULT_PER_CPU_BUFFER_SIZE, MINIMUM_SUBBUCKET_KMERS_COUNT, RESPLITTING_MAX_K_M_DIFFERENCE,
@@ -50,7 +51,8 @@ use super::counters_sorting::CounterEntrySerializer;
 #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
 pub enum QueryKmersReferenceData<CX: MinimizerBucketingSeqColorData> {
     Graph(CX),
-    Query(NonZeroU64),
+    /// Query index and position of the first kmer of the sequence inside the query
+    Query(NonZeroU64, u64),
 }
 
 impl<CX: MinimizerBucketingSeqColorData> SequenceExtraDataTempBufferManagement
@@ -78,7 +80,9 @@ impl<CX: MinimizerBucketingSeqColorData> SequenceExtraDataTempBufferManagement
             QueryKmersReferenceData::Graph(color) => {
                 QueryKmersReferenceData::Graph(CX::copy_extra_from(color, &src.0, &mut dst.0))
             }
-            QueryKmersReferenceData::Query(index) => QueryKmersReferenceData::Query(index),
+            QueryKmersReferenceData::Query(index, offset) => {
+                QueryKmersReferenceData::Query(index, offset)
+            }
         }
     }
 }
@@ -102,6 +106,7 @@ impl<CX: MinimizerBucketingSeqColorData> SequenceExtraDataConsecutiveCompression
             )?)),
             _ => Some(Self::Query(
                 NonZeroU64::new(decode_varint(|| reader.read_u8().ok())? + 1).unwrap(),
+                decode_varint(|| reader.read_u8().ok())?,
             )),
         }
     }
@@ -118,9 +123,10 @@ impl<CX: MinimizerBucketingSeqColorData> SequenceExtraDataConsecutiveCompression
                 writer.write_u8(0).unwrap();
                 CX::encode_extended(cx, &buffer.0, writer, last_data);
             }
-            Self::Query(val) => {
+            Self::Query(val, offset) => {
                 writer.write_u8(1).unwrap();
                 encode_varint(|bytes| writer.write_all(bytes), val.get() - 1).unwrap();
+                encode_varint(|bytes| writer.write_all(bytes), *offset).unwrap();
             }
         }
     }
@@ -129,14 +135,14 @@ impl<CX: MinimizerBucketingSeqColorData> SequenceExtraDataConsecutiveCompression
     fn max_size(&self) -> usize {
         match self {
             Self::Graph(cx) => cx.max_size() + 1,
-            Self::Query(_) => 10 + 1,
+            Self::Query(_, _) => 10 * 2 + 1,
         }
     }
 
     fn obtain_last_data(&self, last_data: Self::LastData) -> Self::LastData {
         match self {
             Self::Graph(cx) => cx.obtain_last_data(last_data),
-            Self::Query(_) => Self::LastData::default(),
+            Self::Query(_, _) => Self::LastData::default(),
         }
     }
 }
@@ -144,8 +150,10 @@ impl<CX: MinimizerBucketingSeqColorData> SequenceExtraDataConsecutiveCompression
 struct GlobalQueryMergeData {
     k: usize,
     m: usize,
-    counters_buckets: Arc<MultiThreadBuckets<LockFreeBinaryWriter>>,
-    global_resplit_data: Arc<MinimizerBucketingCommonData<QuerierMinimizerBucketingGlobalData>>,
+    counters_buckets: Arc<MultiThreadBuckets<MemoryTrackedBucket<LockFreeBinaryWriter>>>,
+    emit_kmers_hits: bool,
+    blocks_first_query: Vec<u64>,
+    global_resplit_data: Arc<MinimizerBucketingCommonData<Arc<QueriesInfoCollector>>>,
 }
 
 impl KmersTransformGlobalExtraData for GlobalQueryMergeData {
@@ -239,13 +247,14 @@ impl RewriteBucketCompute for RewriteBucketComputeQuery {
 
 struct ParallelKmersQueryMapPacket<MH: HashFunctionFactory, CX: Sync + Send + 'static> {
     phmap: HashMap<MH::HashTypeUnextendable, CX>,
-    query_reads: Vec<(u64, MH::HashTypeUnextendable)>,
+    /// Query index, kmer position inside the query and kmer hash
+    query_reads: Vec<(u64, u64, MH::HashTypeUnextendable)>,
 }
 
 impl<MH: HashFunctionFactory, CX: Sync + Send + 'static> PoolObjectTrait
     for ParallelKmersQueryMapPacket<MH, CX>
 {
-    type InitData = ();
+    type InitData = Arc<GlobalQueryMergeData>;
 
     fn allocate_new(_init_data: &Self::InitData) -> Self {
         Self {
@@ -278,7 +287,7 @@ impl<MH: HashFunctionFactory, CX: ColorsManager>
     for ParallelKmersQueryMapProcessor<MH, CX>
 {
     type MapStruct = ParallelKmersQueryMapPacket<MH, SingleKmerColorDataType<CX>>;
-    const MAP_SIZE: usize = size_of::<MH::HashTypeUnextendable>() + 8;
+    const MAP_SIZE: usize = size_of::<MH::HashTypeUnextendable>() + 16;
 
     fn process_group_start(
         &mut self,
@@ -314,11 +323,17 @@ impl<MH: HashFunctionFactory, CX: ColorsManager>
                         map_packet.phmap.insert(hash.to_unextendable(), color);
                     }
                 }
-                QueryKmersReferenceData::Query(index) => {
-                    for hash in hashes.iter() {
-                        map_packet
-                            .query_reads
-                            .push((index.get(), hash.to_unextendable()));
+                QueryKmersReferenceData::Query(query_id, offset) => {
+                    let index = QueriesInfo::global_query_index(
+                        &global_data.blocks_first_query,
+                        query_id.get() - 1,
+                    ) + 1;
+                    for (position, hash) in hashes.iter().enumerate() {
+                        map_packet.query_reads.push((
+                            index,
+                            offset + position as u64,
+                            hash.to_unextendable(),
+                        ));
                     }
                 }
             }
@@ -340,13 +355,31 @@ impl<MH: HashFunctionFactory, CX: ColorsManager>
 
 struct ParallelKmersQueryFinalExecutor<MH: HashFunctionFactory, CX: ColorsManager> {
     counters_tmp: BucketsThreadDispatcher<
-        LockFreeBinaryWriter,
+        MemoryTrackedBucket<LockFreeBinaryWriter>,
         CounterEntrySerializer<SingleKmerColorDataType<CX>>,
     >,
     query_map: HashMap<(u64, SingleKmerColorDataType<CX>), u64>,
     _phantom: PhantomData<(MH, CX)>,
 }
 
+impl<MH: HashFunctionFactory, CX: ColorsManager> ParallelKmersQueryFinalExecutor<MH, CX> {
+    fn add_kmers_run(
+        &mut self,
+        (query_index, position, color, length): (u64, u64, SingleKmerColorDataType<CX>, u64),
+    ) {
+        self.counters_tmp.add_element(
+            (query_index % 0xFF) as BucketIndexType,
+            &color,
+            &CounterEntry {
+                query_index,
+                counter: length,
+                position: Some(position),
+                _phantom: PhantomData,
+            },
+        )
+    }
+}
+
 impl<MH: HashFunctionFactory, CX: ColorsManager>
     KmersTransformFinalExecutor<ParallelKmersQueryFactory<MH, CX>>
     for ParallelKmersQueryFinalExecutor<MH, CX>
@@ -355,12 +388,40 @@ impl<MH: HashFunctionFactory, CX: ColorsManager>
 
     fn process_map(
         &mut self,
-        _global_data: &GlobalQueryMergeData,
+        global_data: &GlobalQueryMergeData,
         map_struct: Packet<Self::MapStruct>,
     ) -> Packet<ParallelKmersQueryMapPacket<MH, SingleKmerColorDataType<CX>>> {
         let map_struct_ref = map_struct.deref();
 
-        for (query_index, kmer_hash) in &map_struct_ref.query_reads {
+        if global_data.emit_kmers_hits {
+            // Emit a positioned entry for each run of consecutive matching kmers with the same color
+            let mut current_run: Option<(u64, u64, SingleKmerColorDataType<CX>, u64)> = None;
+            for (query_index, position, kmer_hash) in &map_struct_ref.query_reads {
+                let Some(entry_color) = map_struct_ref.phmap.get(kmer_hash) else {
+                    continue;
+                };
+
+                if let Some((run_query, run_start, run_color, run_length)) = &mut current_run {
+                    if *run_query == *query_index
+                        && *run_start + *run_length == *position
+                        && run_color == entry_color
+                    {
+                        *run_length += 1;
+                        continue;
+                    }
+                }
+
+                if let Some(run) = current_run.replace((*query_index, *position, *entry_color, 1)) {
+                    self.add_kmers_run(run);
+                }
+            }
+            if let Some(run) = current_run {
+                self.add_kmers_run(run);
+            }
+            return map_struct;
+        }
+
+        for (query_index, _, kmer_hash) in &map_struct_ref.query_reads {
             if let Some(entry_color) = map_struct_ref.phmap.get(kmer_hash) {
                 *self
                     .query_map
@@ -376,6 +437,7 @@ impl<MH: HashFunctionFactory, CX: ColorsManager>
                 &CounterEntry {
                     query_index,
                     counter,
+                    position: None,
                     _phantom: PhantomData,
                 },
             )
@@ -401,12 +463,21 @@ pub fn parallel_kmers_counting<
     k: usize,
     m: usize,
     threads_count: usize,
+    emit_kmers_hits: bool,
+    blocks_first_query: &[u64],
 ) -> Vec<SingleBucket> {
     PHASES_TIMES_MONITOR
         .write()
         .start_phase("phase: kmers counting".to_string());
-
-    let counters_buckets = Arc::new(MultiThreadBuckets::<LockFreeBinaryWriter>::new(
+    config::progress::start_progress_phase(
+        ProgressPhase::QueryKmersCounting,
+        ProgressUnit::Buckets,
+        file_inputs.len() as u64,
+    );
+
+    let counters_buckets = Arc::new(MultiThreadBuckets::<
+        MemoryTrackedBucket<LockFreeBinaryWriter>,
+    >::new(
         buckets_count,
         out_directory.as_ref().join("counters"),
         None,
@@ -421,6 +492,8 @@ pub fn parallel_kmers_counting<
         k,
         m,
         counters_buckets,
+        emit_kmers_hits,
+        blocks_first_query: blocks_first_query.to_vec(),
         global_resplit_data: Arc::new(MinimizerBucketingCommonData::new(
             k,
             if k > RESPLITTING_MAX_K_M_DIFFERENCE + 1 {
@@ -431,9 +504,8 @@ pub fn parallel_kmers_counting<
             buckets_count,
             0,
             1,
-            QuerierMinimizerBucketingGlobalData {
-                queries_count: Default::default(),
-            },
+            // The resplitter only reprocesses the already bucketed queries
+            Arc::default(),
         )),
     });
 
@@ -449,6 +521,7 @@ pub fn parallel_kmers_counting<
         threads_count,
         k,
         MINIMUM_SUBBUCKET_KMERS_COUNT as u64,
+        false,
     )
     .parallel_kmers_transform();
 
@@ -1,8 +1,10 @@
 use crate::pipeline::parallel_kmers_query::QueryKmersReferenceData;
+use crate::structs::queries_info::{QueriesInfo, QueriesInfoChunkBuilder, QueriesInfoCollector};
 use byteorder::ReadBytesExt;
 use colors::colors_manager::color_types::MinimizerBucketingSeqColorDataType;
 use colors::colors_manager::{ColorsManager, MinimizerBucketingSeqColorData};
 use colors::parsers::{SequenceIdent, SingleSequenceInfo};
+use config::progress::{ProgressPhase, ProgressUnit};
 use config::BucketIndexType;
 use hashes::default::MNHFactory;
 use hashes::rolling::minqueue::RollingMinQueue;
@@ -13,7 +15,7 @@ use io::concurrent::temp_reads::extra_data::{
     HasEmptyExtraBuffer, SequenceExtraData, SequenceExtraDataTempBufferManagement,
 };
 use io::sequences_reader::{DnaSequence, DnaSequencesFileType};
-use io::sequences_stream::fasta::FastaFileSequencesStream;
+use io::sequences_stream::general::{GeneralSequenceBlockData, GeneralSequencesStream};
 use io::sequences_stream::SequenceInfo;
 use io::varint::{decode_varint, encode_varint, VARINT_MAX_SIZE};
 use minimizer_bucketing::{
@@ -27,7 +29,6 @@ use std::marker::PhantomData;
 use std::num::NonZeroU64;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use super::parallel_kmers_query::RewriteBucketComputeQuery;
@@ -38,7 +39,8 @@ pub struct KmersQueryData(pub u64);
 #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
 pub enum FileType {
     Graph,
-    Query,
+    /// The block of queries with the given index
+    Query(usize),
 }
 
 impl Default for FileType {
@@ -57,7 +59,10 @@ pub enum ReadType<CX: ColorsManager> {
     Graph {
         color: MinimizerBucketingSeqColorDataType<CX>,
     },
-    Query(NonZeroU64),
+    /// Query id and position of the first kmer of the sequence inside the query.
+    /// The id is [`QueriesInfo::block_query_id`] plus one, as the global query index
+    /// is known only after all the queries are bucketed
+    Query(NonZeroU64, u64),
 }
 
 impl<CX: ColorsManager> Default for ReadTypeBuffered<CX> {
@@ -65,7 +70,7 @@ impl<CX: ColorsManager> Default for ReadTypeBuffered<CX> {
         Self {
             colors_buffer:
                 QueryKmersReferenceData::<MinimizerBucketingSeqColorDataType<CX>>::new_temp_buffer(),
-            read_type: ReadType::Query(NonZeroU64::new(1).unwrap()),
+            read_type: ReadType::Query(NonZeroU64::new(1).unwrap(), 0),
         }
     }
 }
@@ -88,22 +93,25 @@ impl SequenceExtraData for KmersQueryData {
     }
 }
 
-pub struct QuerierMinimizerBucketingGlobalData {
-    pub queries_count: Arc<AtomicUsize>,
-}
-
 pub struct QuerierMinimizerBucketingExecutor<CX: ColorsManager> {
     minimizer_queue: RollingMinQueue,
-    global_data: Arc<MinimizerBucketingCommonData<QuerierMinimizerBucketingGlobalData>>,
+    global_data: Arc<MinimizerBucketingCommonData<Arc<QueriesInfoCollector>>>,
+    queries_info: QueriesInfoChunkBuilder,
     _phantom: PhantomData<CX>,
 }
 
+impl<CX: ColorsManager> Drop for QuerierMinimizerBucketingExecutor<CX> {
+    fn drop(&mut self) {
+        self.queries_info.flush(&self.global_data.global_data);
+    }
+}
+
 pub struct QuerierMinimizerBucketingExecutorFactory<CX: ColorsManager>(PhantomData<CX>);
 
 impl<CX: ColorsManager> MinimizerBucketingExecutorFactory
     for QuerierMinimizerBucketingExecutorFactory<CX>
 {
-    type GlobalData = QuerierMinimizerBucketingGlobalData;
+    type GlobalData = Arc<QueriesInfoCollector>;
     type ExtraData = QueryKmersReferenceData<MinimizerBucketingSeqColorDataType<CX>>;
     type PreprocessInfo = ReadTypeBuffered<CX>;
     type StreamInfo = FileType;
@@ -122,6 +130,7 @@ impl<CX: ColorsManager> MinimizerBucketingExecutorFactory
         Self::ExecutorType {
             minimizer_queue: RollingMinQueue::new(global_data.k - global_data.m + 1),
             global_data: global_data.clone(),
+            queries_info: QueriesInfoChunkBuilder::default(),
             _phantom: PhantomData,
         }
     }
@@ -177,12 +186,19 @@ impl<CX: ColorsManager> MinimizerBucketingExecutor<QuerierMinimizerBucketingExec
 
                 ReadType::Graph { color }
             }
-            FileType::Query => {
-                self.global_data
-                    .global_data
-                    .queries_count
-                    .fetch_add(1, Ordering::Relaxed);
-                ReadType::Query(NonZeroU64::new(read_index + 1).unwrap())
+            FileType::Query(block_index) => {
+                self.queries_info.add_query(
+                    *block_index,
+                    read_index,
+                    sequence.seq.len(),
+                    sequence.ident_data,
+                    self.global_data.k,
+                );
+                ReadType::Query(
+                    NonZeroU64::new(QueriesInfo::block_query_id(*block_index, read_index) + 1)
+                        .unwrap(),
+                    0,
+                )
             }
         }
     }
@@ -204,7 +220,7 @@ impl<CX: ColorsManager> MinimizerBucketingExecutor<QuerierMinimizerBucketingExec
             QueryKmersReferenceData::Graph(color) => ReadType::Graph {
                 color: color.clone(),
             },
-            QueryKmersReferenceData::Query(query) => ReadType::Query(*query),
+            QueryKmersReferenceData::Query(query, offset) => ReadType::Query(*query, *offset),
         }
     }
 
@@ -215,7 +231,7 @@ impl<CX: ColorsManager> MinimizerBucketingExecutor<QuerierMinimizerBucketingExec
         &mut self,
         preprocess_info: &<QuerierMinimizerBucketingExecutorFactory<CX> as MinimizerBucketingExecutorFactory>::PreprocessInfo,
         sequence: S,
-        _range: Range<usize>,
+        range: Range<usize>,
         used_bits: usize,
         first_bits: usize,
         second_bits: usize,
@@ -243,7 +259,10 @@ impl<CX: ColorsManager> MinimizerBucketingExecutor<QuerierMinimizerBucketingExec
                             color.get_subslice(last_index..(index + 1)),
                         ),
 
-                        ReadType::Query(val) => QueryKmersReferenceData::Query(*val),
+                        ReadType::Query(val, offset) => QueryKmersReferenceData::Query(
+                            *val,
+                            offset + (range.start + last_index) as u64,
+                        ),
                     },
                     &preprocess_info.colors_buffer,
                 );
@@ -263,7 +282,9 @@ impl<CX: ColorsManager> MinimizerBucketingExecutor<QuerierMinimizerBucketingExec
                     color.get_subslice(last_index..(sequence.seq_len() + 1 - self.global_data.k)),
                 ),
 
-                ReadType::Query(val) => QueryKmersReferenceData::Query(*val),
+                ReadType::Query(val, offset) => {
+                    QueryKmersReferenceData::Query(*val, offset + (range.start + last_index) as u64)
+                }
             },
             &preprocess_info.colors_buffer,
         );
@@ -272,42 +293,52 @@ impl<CX: ColorsManager> MinimizerBucketingExecutor<QuerierMinimizerBucketingExec
 
 pub fn minimizer_bucketing<CX: ColorsManager>(
     graph_file: PathBuf,
-    query_file: PathBuf,
+    query_blocks: Vec<GeneralSequenceBlockData>,
+    queries_info: Arc<QueriesInfoCollector>,
     output_path: &Path,
     buckets_count: usize,
     threads_count: usize,
     k: usize,
     m: usize,
-) -> ((Vec<SingleBucket>, PathBuf), u64) {
+) -> (Vec<SingleBucket>, PathBuf) {
     PHASES_TIMES_MONITOR
         .write()
         .start_phase("phase: graph + query bucketing".to_string());
 
-    let input_files = vec![
-        ((graph_file, None), FileType::Graph),
-        ((query_file, None), FileType::Query),
-    ];
-
-    let queries_count = Arc::new(AtomicUsize::new(0));
-
-    (
-        GenericMinimizerBucketing::do_bucketing_no_max_usage::<
-            QuerierMinimizerBucketingExecutorFactory<CX>,
-            FastaFileSequencesStream,
-        >(
-            input_files.into_iter(),
-            output_path,
-            buckets_count,
-            threads_count,
-            k,
-            m,
-            QuerierMinimizerBucketingGlobalData {
-                queries_count: queries_count.clone(),
-            },
-            None,
-            CX::COLORS_ENABLED,
-            0,
-        ),
-        queries_count.load(Ordering::Relaxed) as u64,
+    let mut input_blocks = vec![(
+        GeneralSequenceBlockData::from_file(graph_file, None),
+        FileType::Graph,
+    )];
+    input_blocks.extend(
+        query_blocks
+            .into_iter()
+            .enumerate()
+            .map(|(block_index, block)| (block, FileType::Query(block_index))),
+    );
+
+    config::progress::start_progress_phase(
+        ProgressPhase::MinimizerBucketing,
+        ProgressUnit::Bases,
+        input_blocks
+            .iter()
+            .map(|(block, _)| block.estimated_bases_count().unwrap_or(0))
+            .sum(),
+    );
+
+    GenericMinimizerBucketing::do_bucketing_no_max_usage::<
+        QuerierMinimizerBucketingExecutorFactory<CX>,
+        GeneralSequencesStream,
+    >(
+        input_blocks.into_iter(),
+        output_path,
+        buckets_count,
+        threads_count,
+        k,
+        m,
+        queries_info,
+        None,
+        // The query names are collected while bucketing
+        true,
+        0,
     )
 }
@@ -0,0 +1,446 @@
+use crate::structs::query_kmers_hits::{QueryKmerHit, QueryKmersRun};
+use crate::ColoredQueryOutputFormat;
+use config::ColorIndexType;
+use flate2::Compression;
+use ggcat_logging::UnrecoverableErrorLogging;
+use io::FileIoError;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Function receiving the query results, called with
+/// `(query_index, query_name, matched_kmers, query_kmers, colors, kmers_hits)`.
+/// The query index is zero based and counts the queries across all the input blocks,
+/// the query name is the sequence identifier up to the first whitespace.
+/// `colors` contains the pairs `(color, matched_kmers)` sorted by color when the query is colored,
+/// `None` otherwise.
+/// `kmers_hits` is the run-length encoded match status of each query kmer, in order,
+/// if requested when starting the query.
+/// The function is called from a single thread at a time, with increasing query indices.
+pub type QueryOutputFunction<'a> = dyn Fn(u64, &[u8], u64, u64, Option<&[(ColorIndexType, u64)]>, Option<&[QueryKmersRun]>)
+    + Send
+    + Sync
+    + 'a;
+
+/// Query results as produced by the pipeline, without the query names
+pub(crate) type QueryResultsFunction<'a> = dyn Fn(u64, u64, u64, Option<&[(ColorIndexType, u64)]>, Option<&[QueryKmersRun]>)
+    + Send
+    + Sync
+    + 'a;
+
+/// Filters applied to the query results before they are delivered
+#[derive(Copy, Clone, Debug, Default)]
+pub struct QueryResultsFilter {
+    /// Minimum fraction of the query kmers that must be matched (by each color, if colored)
+    pub min_fraction: Option<f64>,
+    /// Keeps only the colors with the highest number of matched kmers for each query
+    pub top_n: Option<usize>,
+    /// Minimum number of query kmers that must be matched (by each color, if colored)
+    pub min_kmers: Option<u64>,
+}
+
+/// Fraction of the query kmers that are matched, zero for the queries shorter than k
+fn match_fraction(matched_kmers: u64, query_kmers: u64) -> f64 {
+    if query_kmers == 0 {
+        0.0
+    } else {
+        matched_kmers as f64 / query_kmers as f64
+    }
+}
+
+impl QueryResultsFilter {
+    pub(crate) fn is_match_kept(&self, matched_kmers: u64, query_kmers: u64) -> bool {
+        self.min_kmers.is_none_or(|min| matched_kmers >= min)
+            && self.min_fraction.is_none_or(|min| {
+                query_kmers > 0 && match_fraction(matched_kmers, query_kmers) >= min
+            })
+    }
+
+    /// Filters the `(color, matched_kmers)` pairs of a query, keeping them sorted by color
+    pub(crate) fn filter_colors(&self, query_kmers: u64, colors: &mut Vec<(ColorIndexType, u64)>) {
+        colors.retain(|&(_, matched_kmers)| self.is_match_kept(matched_kmers, query_kmers));
+
+        if let Some(top_n) = self.top_n {
+            if colors.len() > top_n {
+                // Ties are broken in favor of the lower color indices
+                colors.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                colors.truncate(top_n);
+                colors.sort_unstable_by_key(|c| c.0);
+            }
+        }
+    }
+}
+
+/// Specifies where the results of a query are delivered
+pub enum QueryOutput<'a> {
+    /// Writes the results to a .csv file (uncolored) or to a .jsonl file (colored)
+    File {
+        output_file_prefix: PathBuf,
+        colored_query_output_format: ColoredQueryOutputFormat,
+        /// Adds the `query_name` column to the .csv file, the .jsonl file always contains the names
+        emit_query_names: bool,
+    },
+    /// Calls the given function for each query sequence
+    Callback(&'a QueryOutputFunction<'a>),
+}
+
+pub(crate) enum QueryOutputFileWriter {
+    Plain(File),
+    LZ4Compressed(lz4::Encoder<File>),
+    GzipCompressed(flate2::write::GzEncoder<File>),
+}
+
+impl Write for QueryOutputFileWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            QueryOutputFileWriter::Plain(w) => w.write(buf),
+            QueryOutputFileWriter::LZ4Compressed(w) => w.write(buf),
+            QueryOutputFileWriter::GzipCompressed(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            QueryOutputFileWriter::Plain(w) => w.flush(),
+            QueryOutputFileWriter::LZ4Compressed(w) => w.flush(),
+            QueryOutputFileWriter::GzipCompressed(w) => w.flush(),
+        }
+    }
+}
+
+impl QueryOutputFileWriter {
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            QueryOutputFileWriter::Plain(mut w) => w.flush(),
+            QueryOutputFileWriter::LZ4Compressed(w) => w.finish().1,
+            QueryOutputFileWriter::GzipCompressed(w) => w.finish().map(|_| ()),
+        }
+    }
+}
+
+/// Formats the kmers hits as comma separated `<length>:<hit>` runs, where the hit is `-` for
+/// missing kmers, `+` for kmers present in an uncolored graph or the color subset index
+fn write_kmers_hits(buffer: &mut Vec<u8>, runs: &[QueryKmersRun]) {
+    for (i, run) in runs.iter().enumerate() {
+        if i != 0 {
+            buffer.push(b',');
+        }
+        match run.hit {
+            QueryKmerHit::Missing => write!(buffer, "{}:-", run.length),
+            QueryKmerHit::Present => write!(buffer, "{}:+", run.length),
+            QueryKmerHit::ColorSubset(subset) => write!(buffer, "{}:{}", run.length, subset),
+        }
+        .unwrap();
+    }
+}
+
+fn write_json_escaped(buffer: &mut Vec<u8>, value: &[u8]) {
+    for &c in value {
+        match c {
+            b'"' | b'\\' => buffer.extend_from_slice(&[b'\\', c]),
+            0..=0x1f => write!(buffer, "\\u{:04x}", c).unwrap(),
+            _ => buffer.push(c),
+        }
+    }
+}
+
+/// Adapter writing the query results received through a [`QueryOutputFunction`] to a file
+pub(crate) enum QueryResultsFileWriter {
+    Csv {
+        writer: csv::Writer<File>,
+        emit_query_names: bool,
+        buffer: Vec<u8>,
+    },
+    JsonLines {
+        writer: BufWriter<QueryOutputFileWriter>,
+        format: ColoredQueryOutputFormat,
+        buffer: Vec<u8>,
+    },
+}
+
+impl QueryResultsFileWriter {
+    pub fn output_file_name(output_file_prefix: &Path, colored: bool) -> PathBuf {
+        if output_file_prefix.extension().is_none() {
+            if colored {
+                output_file_prefix.with_extension("jsonl")
+            } else {
+                output_file_prefix.with_extension("csv")
+            }
+        } else {
+            output_file_prefix.to_path_buf()
+        }
+    }
+
+    pub fn new(
+        output_file: &Path,
+        colored: bool,
+        format: ColoredQueryOutputFormat,
+        emit_query_names: bool,
+        emit_kmers_hits: bool,
+    ) -> anyhow::Result<Self> {
+        if !colored {
+            let mut writer = csv::Writer::from_writer(
+                File::create(output_file)
+                    .log_unrecoverable_error_with_data(
+                        "Cannot create output file",
+                        output_file.display(),
+                    )
+                    .map_err(|err| FileIoError::new(output_file, err))?,
+            );
+            let mut header = vec![
+                "query_index",
+                "matched_kmers",
+                "query_kmers",
+                "match_percentage",
+            ];
+            if emit_query_names {
+                header.push("query_name");
+            }
+            if emit_kmers_hits {
+                header.push("kmers_hits");
+            }
+            writer.write_record(&header)?;
+            return Ok(Self::Csv {
+                writer,
+                emit_query_names,
+                buffer: vec![],
+            });
+        }
+
+        let query_output_file = File::create(output_file)
+            .log_unrecoverable_error_with_data("Cannot create output file", output_file.display())
+            .map_err(|err| FileIoError::new(output_file, err))?;
+
+        Ok(Self::JsonLines {
+            writer: BufWriter::new(match output_file.extension().and_then(|e| e.to_str()) {
+                Some("lz4") => QueryOutputFileWriter::LZ4Compressed(
+                    lz4::EncoderBuilder::new()
+                        .level(4)
+                        .build(query_output_file)?,
+                ),
+                Some("gz") => QueryOutputFileWriter::GzipCompressed(
+                    flate2::GzBuilder::new().write(query_output_file, Compression::default()),
+                ),
+                _ => QueryOutputFileWriter::Plain(query_output_file),
+            }),
+            format,
+            buffer: vec![],
+        })
+    }
+
+    pub fn write_result<'a>(
+        &mut self,
+        query_index: u64,
+        query_name: &[u8],
+        matched_kmers: u64,
+        query_kmers: u64,
+        colors: Option<&[(ColorIndexType, u64)]>,
+        kmers_hits: Option<&[QueryKmersRun]>,
+        get_color_name: impl Fn(ColorIndexType) -> &'a str,
+    ) -> anyhow::Result<()> {
+        match self {
+            QueryResultsFileWriter::Csv {
+                writer,
+                emit_query_names,
+                buffer,
+            } => {
+                writer.write_field(query_index.to_string())?;
+                writer.write_field(matched_kmers.to_string())?;
+                writer.write_field(query_kmers.to_string())?;
+                writer.write_field(format!("{:.2}", match_fraction(matched_kmers, query_kmers)))?;
+                if *emit_query_names {
+                    writer.write_field(query_name)?;
+                }
+                if let Some(kmers_hits) = kmers_hits {
+                    buffer.clear();
+                    write_kmers_hits(buffer, kmers_hits);
+                    writer.write_field(&buffer)?;
+                }
+                writer.write_record(None::<&[u8]>)?;
+            }
+            QueryResultsFileWriter::JsonLines {
+                writer,
+                format,
+                buffer,
+            } => {
+                // Queries without any matched kmer are omitted, while the queries whose colors
+                // were all removed by the results filter are written with an empty matches list
+                if matched_kmers == 0 {
+                    return Ok(());
+                }
+                let colors = colors.unwrap_or(&[]);
+
+                buffer.clear();
+                write!(
+                    buffer,
+                    "{{\"query_index\":{}, \"query_name\":\"",
+                    query_index
+                )?;
+                write_json_escaped(buffer, query_name);
+                write!(buffer, "\", \"matches\":{{")?;
+
+                for (i, &(color_index, color_presence)) in colors.iter().enumerate() {
+                    if i != 0 {
+                        write!(buffer, ",")?;
+                    }
+
+                    match format {
+                        ColoredQueryOutputFormat::JsonLinesWithNumbers => {
+                            write!(buffer, "\"{}\"", color_index)
+                        }
+                        ColoredQueryOutputFormat::JsonLinesWithNames => {
+                            write!(buffer, "\"{}\"", get_color_name(color_index))
+                        }
+                    }?;
+
+                    write!(
+                        buffer,
+                        ": {:.2}",
+                        match_fraction(color_presence, query_kmers)
+                    )?;
+                }
+                write!(buffer, "}}")?;
+                if let Some(kmers_hits) = kmers_hits {
+                    write!(buffer, ", \"kmers_hits\":\"")?;
+                    write_kmers_hits(buffer, kmers_hits);
+                    write!(buffer, "\"")?;
+                }
+                writeln!(buffer, "}}")?;
+                writer.write_all(buffer)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finalize(self) -> anyhow::Result<()> {
+        match self {
+            QueryResultsFileWriter::Csv { mut writer, .. } => writer.flush()?,
+            QueryResultsFileWriter::JsonLines { writer, .. } => {
+                writer.into_inner().map_err(|e| e.into_error())?.finish()?
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueryResultsFileWriter, QueryResultsFilter};
+    use crate::ColoredQueryOutputFormat;
+    use utils::temp_dir::TempDir;
+
+    #[test]
+    fn results_filter_colors() {
+        let colors = vec![(0, 10), (1, 40), (2, 80), (3, 40), (4, 100)];
+
+        let filter = |filter: QueryResultsFilter| {
+            let mut colors = colors.clone();
+            filter.filter_colors(100, &mut colors);
+            colors
+        };
+
+        assert_eq!(filter(QueryResultsFilter::default()), colors);
+        assert_eq!(
+            filter(QueryResultsFilter {
+                min_fraction: Some(0.4),
+                ..Default::default()
+            }),
+            vec![(1, 40), (2, 80), (3, 40), (4, 100)]
+        );
+        assert_eq!(
+            filter(QueryResultsFilter {
+                top_n: Some(3),
+                ..Default::default()
+            }),
+            vec![(1, 40), (2, 80), (4, 100)]
+        );
+        assert_eq!(
+            filter(QueryResultsFilter {
+                min_kmers: Some(50),
+                top_n: Some(1),
+                ..Default::default()
+            }),
+            vec![(4, 100)]
+        );
+    }
+
+    #[test]
+    fn jsonl_filtered_queries() {
+        let dir = TempDir::new("ggcat-query-output-test");
+        let output_file = dir.join("results.jsonl");
+
+        let mut writer = QueryResultsFileWriter::new(
+            &output_file,
+            true,
+            ColoredQueryOutputFormat::JsonLinesWithNumbers,
+            false,
+            false,
+        )
+        .unwrap();
+        for (query_index, matched_kmers, colors) in
+            [(0, 10, &[(1, 5), (3, 10)][..]), (1, 0, &[]), (2, 4, &[])]
+        {
+            writer
+                .write_result(
+                    query_index,
+                    b"q",
+                    matched_kmers,
+                    10,
+                    Some(colors),
+                    None,
+                    |_| "",
+                )
+                .unwrap();
+        }
+        writer.finalize().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&output_file).unwrap(),
+            concat!(
+                "{\"query_index\":0, \"query_name\":\"q\", \"matches\":{\"1\": 0.50,\"3\": 1.00}}\n",
+                "{\"query_index\":2, \"query_name\":\"q\", \"matches\":{}}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn csv_short_queries() {
+        let dir = TempDir::new("ggcat-query-output-test");
+        let output_file = dir.join("results.csv");
+
+        for emit_query_names in [false, true] {
+            let mut writer = QueryResultsFileWriter::new(
+                &output_file,
+                false,
+                ColoredQueryOutputFormat::JsonLinesWithNumbers,
+                emit_query_names,
+                false,
+            )
+            .unwrap();
+            // The second query is shorter than k, so it does not have any kmer
+            for (query_index, matched_kmers, query_kmers) in [(0, 5, 10), (1, 0, 0)] {
+                writer
+                    .write_result(
+                        query_index,
+                        b"q",
+                        matched_kmers,
+                        query_kmers,
+                        None,
+                        None,
+                        |_| "",
+                    )
+                    .unwrap();
+            }
+            writer.finalize().unwrap();
+
+            assert_eq!(
+                std::fs::read_to_string(&output_file).unwrap(),
+                if emit_query_names {
+                    "query_index,matched_kmers,query_kmers,match_percentage,query_name\n0,5,10,0.50,q\n1,0,0,0.00,q\n"
+                } else {
+                    "query_index,matched_kmers,query_kmers,match_percentage\n0,5,10,0.50\n1,0,0,0.00\n"
+                }
+            );
+        }
+    }
+}
@@ -1 +1,3 @@
+pub mod queries_info;
 pub mod query_colored_counters;
+pub mod query_kmers_hits;
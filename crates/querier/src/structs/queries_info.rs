@@ -0,0 +1,250 @@
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use parking_lot::Mutex;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Bits of the bucketing query id holding the index of the query inside its block
+const BLOCK_QUERY_INDEX_BITS: u32 = 40;
+
+/// Information about the query sequences, indexed by their (zero based) global query index.
+/// Queries are numbered consecutively across all the input blocks, in the order they are given
+pub struct QueriesInfo {
+    kmers_count: Vec<u64>,
+    names_buffer: Vec<u8>,
+    names_offsets: Vec<usize>,
+    blocks_first_query: Vec<u64>,
+}
+
+/// The queries of a single sequences packet, that contains consecutive sequences of a block
+struct QueriesInfoChunk {
+    block_index: usize,
+    first_read_index: u64,
+    kmers_count: Vec<u64>,
+    names_buffer: Vec<u8>,
+    names_offsets: Vec<usize>,
+}
+
+/// Collects the queries information while the query blocks are bucketed.
+/// The blocks are read in parallel, so until the bucketing is finished each query
+/// is identified by its block and its index inside the block, see [`QueriesInfo::block_query_id`]
+#[derive(Default)]
+pub struct QueriesInfoCollector {
+    chunks: Mutex<Vec<QueriesInfoChunk>>,
+}
+
+/// Buffers the queries of a sequences packet before adding them to the collector
+#[derive(Default)]
+pub struct QueriesInfoChunkBuilder {
+    chunk: Option<QueriesInfoChunk>,
+}
+
+impl QueriesInfoChunkBuilder {
+    pub fn add_query(
+        &mut self,
+        block_index: usize,
+        read_index: u64,
+        sequence_length: usize,
+        ident: &[u8],
+        k: usize,
+    ) {
+        let chunk = self.chunk.get_or_insert_with(|| QueriesInfoChunk {
+            block_index,
+            first_read_index: read_index,
+            kmers_count: vec![],
+            names_buffer: vec![],
+            names_offsets: vec![0],
+        });
+        debug_assert_eq!(chunk.block_index, block_index);
+        debug_assert_eq!(
+            chunk.first_read_index + chunk.kmers_count.len() as u64,
+            read_index
+        );
+
+        chunk
+            .kmers_count
+            .push(sequence_length.saturating_sub(k - 1) as u64);
+        chunk
+            .names_buffer
+            .extend_from_slice(QueriesInfo::sequence_name(ident));
+        chunk.names_offsets.push(chunk.names_buffer.len());
+    }
+
+    pub fn flush(&mut self, collector: &QueriesInfoCollector) {
+        if let Some(chunk) = self.chunk.take() {
+            collector.chunks.lock().push(chunk);
+        }
+    }
+}
+
+impl QueriesInfoCollector {
+    /// Assigns the global query indices, the queries of all the blocks must have been collected
+    pub fn collect_queries_info(&self, blocks_count: usize) -> QueriesInfo {
+        let mut chunks = std::mem::take(&mut *self.chunks.lock());
+        chunks.sort_unstable_by_key(|c| (c.block_index, c.first_read_index));
+
+        let mut info = QueriesInfo {
+            kmers_count: vec![],
+            names_buffer: vec![],
+            names_offsets: vec![0],
+            blocks_first_query: Vec::with_capacity(blocks_count),
+        };
+
+        let mut chunks = chunks.into_iter().peekable();
+        for block_index in 0..blocks_count {
+            info.blocks_first_query.push(info.kmers_count.len() as u64);
+            while let Some(chunk) = chunks.next_if(|c| c.block_index == block_index) {
+                assert_eq!(
+                    info.kmers_count.len() as u64 - info.blocks_first_query[block_index],
+                    chunk.first_read_index
+                );
+                info.kmers_count.extend_from_slice(&chunk.kmers_count);
+                let names_start = info.names_buffer.len();
+                info.names_buffer.extend_from_slice(&chunk.names_buffer);
+                info.names_offsets
+                    .extend(chunk.names_offsets[1..].iter().map(|o| o + names_start));
+            }
+        }
+
+        info
+    }
+}
+
+impl QueriesInfo {
+    /// Extracts the sequence name from its header, skipping the FASTA/FASTQ marker
+    /// and everything after the first whitespace
+    fn sequence_name(ident: &[u8]) -> &[u8] {
+        let ident = match ident.first() {
+            Some(b'>' | b'@') => &ident[1..],
+            _ => ident,
+        };
+        let end = ident
+            .iter()
+            .position(|c| c.is_ascii_whitespace())
+            .unwrap_or(ident.len());
+        &ident[..end]
+    }
+
+    /// Identifies a query while it is bucketed, before its global index is known
+    pub fn block_query_id(block_index: usize, read_index: u64) -> u64 {
+        debug_assert!(read_index < (1 << BLOCK_QUERY_INDEX_BITS));
+        ((block_index as u64) << BLOCK_QUERY_INDEX_BITS) | read_index
+    }
+
+    /// Obtains the global query index from the id assigned while bucketing the query
+    pub fn global_query_index(blocks_first_query: &[u64], query_id: u64) -> u64 {
+        blocks_first_query[(query_id >> BLOCK_QUERY_INDEX_BITS) as usize]
+            + (query_id & ((1 << BLOCK_QUERY_INDEX_BITS) - 1))
+    }
+
+    pub fn queries_count(&self) -> u64 {
+        self.kmers_count.len() as u64
+    }
+
+    pub fn kmers_count(&self) -> &[u64] {
+        &self.kmers_count
+    }
+
+    /// The global index of the first query of each input block
+    pub fn blocks_first_query(&self) -> &[u64] {
+        &self.blocks_first_query
+    }
+
+    pub fn get_name(&self, query_index: u64) -> &[u8] {
+        let query_index = query_index as usize;
+        &self.names_buffer[self.names_offsets[query_index]..self.names_offsets[query_index + 1]]
+    }
+
+    /// Saves the queries information, to allow resuming the query after the bucketing
+    pub fn save(&self, file: &Path) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file)?);
+        for values in [&self.kmers_count, &self.blocks_first_query] {
+            writer.write_u64::<LE>(values.len() as u64)?;
+            for &value in values {
+                writer.write_u64::<LE>(value)?;
+            }
+        }
+        for &offset in &self.names_offsets[1..] {
+            writer.write_u64::<LE>(offset as u64)?;
+        }
+        writer.write_all(&self.names_buffer)?;
+        writer.flush()
+    }
+
+    pub fn load(file: &Path) -> std::io::Result<Self> {
+        let mut reader = BufReader::new(File::open(file)?);
+        let read_values = |reader: &mut BufReader<File>| {
+            let count = reader.read_u64::<LE>()? as usize;
+            (0..count)
+                .map(|_| reader.read_u64::<LE>())
+                .collect::<std::io::Result<Vec<_>>>()
+        };
+        let kmers_count = read_values(&mut reader)?;
+        let blocks_first_query = read_values(&mut reader)?;
+
+        let mut names_offsets = vec![0];
+        for _ in 0..kmers_count.len() {
+            names_offsets.push(reader.read_u64::<LE>()? as usize);
+        }
+        let mut names_buffer = vec![];
+        reader.read_to_end(&mut names_buffer)?;
+
+        Ok(Self {
+            kmers_count,
+            names_buffer,
+            names_offsets,
+            blocks_first_query,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueriesInfo, QueriesInfoChunkBuilder, QueriesInfoCollector};
+
+    #[test]
+    fn sequence_names() {
+        assert_eq!(
+            QueriesInfo::sequence_name(b">read1 some description"),
+            b"read1"
+        );
+        assert_eq!(QueriesInfo::sequence_name(b"@read2\tlane"), b"read2");
+        assert_eq!(QueriesInfo::sequence_name(b"read3"), b"read3");
+        assert_eq!(QueriesInfo::sequence_name(b""), b"");
+    }
+
+    #[test]
+    fn collected_chunks_order() {
+        let collector = QueriesInfoCollector::default();
+        let add_chunk = |block_index, reads: &[(u64, &[u8])]| {
+            let mut builder = QueriesInfoChunkBuilder::default();
+            for &(read_index, name) in reads {
+                builder.add_query(block_index, read_index, 30 + read_index as usize, name, 21);
+            }
+            builder.flush(&collector);
+        };
+
+        // Chunks are collected in any order, block 1 is empty
+        add_chunk(2, &[(0, b">c0")]);
+        add_chunk(0, &[(2, b">a2"), (3, b">a3 description")]);
+        add_chunk(0, &[(0, b">a0"), (1, b"@a1")]);
+
+        let info = collector.collect_queries_info(3);
+        assert_eq!(info.blocks_first_query(), &[0, 4, 4]);
+        assert_eq!(info.kmers_count(), &[10, 11, 12, 13, 10]);
+        let names: Vec<_> = (0..info.queries_count())
+            .map(|i| info.get_name(i))
+            .collect();
+        assert_eq!(names, [&b"a0"[..], b"a1", b"a2", b"a3", b"c0"]);
+
+        for (block_index, read_index, global_index) in [(0, 3, 3), (2, 0, 4)] {
+            assert_eq!(
+                QueriesInfo::global_query_index(
+                    info.blocks_first_query(),
+                    QueriesInfo::block_query_id(block_index, read_index)
+                ),
+                global_index
+            );
+        }
+    }
+}
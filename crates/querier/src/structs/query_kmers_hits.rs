@@ -0,0 +1,181 @@
+use config::ColorIndexType;
+use io::varint::{decode_varint, encode_varint};
+
+/// Match status of a query kmer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QueryKmerHit {
+    /// The kmer is not present in the graph
+    Missing,
+    /// The kmer is present in the (uncolored) graph
+    Present,
+    /// The kmer is present in the colored graph with the given color subset
+    ColorSubset(ColorIndexType),
+}
+
+impl QueryKmerHit {
+    fn to_u64(self) -> u64 {
+        match self {
+            QueryKmerHit::Missing => 0,
+            QueryKmerHit::Present => 1,
+            QueryKmerHit::ColorSubset(subset) => subset as u64 + 2,
+        }
+    }
+
+    fn from_u64(value: u64) -> Self {
+        match value {
+            0 => QueryKmerHit::Missing,
+            1 => QueryKmerHit::Present,
+            _ => QueryKmerHit::ColorSubset((value - 2) as ColorIndexType),
+        }
+    }
+}
+
+/// A run of consecutive kmers of a query sharing the same match status
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QueryKmersRun {
+    pub length: u64,
+    pub hit: QueryKmerHit,
+}
+
+/// Run-length encoded kmer hits of each query, indexed by the zero based query index
+pub struct QueriesKmersHits {
+    queries_runs: Vec<Box<[u8]>>,
+}
+
+impl QueriesKmersHits {
+    pub fn new(queries_count: usize) -> Self {
+        Self {
+            queries_runs: (0..queries_count).map(|_| Box::default()).collect(),
+        }
+    }
+
+    /// Encodes the hits of a query, given as `(position, length, hit)` sorted by position.
+    /// The positions not covered by any hit are marked as missing
+    pub fn encode_runs(
+        query_kmers: u64,
+        hits: impl Iterator<Item = (u64, u64, QueryKmerHit)>,
+    ) -> Box<[u8]> {
+        let mut encoded = vec![];
+        let mut write_run = |run: QueryKmersRun| {
+            encode_varint(|b| encoded.extend_from_slice(b), run.length);
+            encode_varint(|b| encoded.extend_from_slice(b), run.hit.to_u64());
+        };
+
+        let mut current_run = QueryKmersRun {
+            length: 0,
+            hit: QueryKmerHit::Missing,
+        };
+        let mut position = 0;
+
+        let mut add_run = |run: QueryKmersRun| {
+            if run.hit == current_run.hit {
+                current_run.length += run.length;
+            } else {
+                if current_run.length > 0 {
+                    write_run(current_run);
+                }
+                current_run = run;
+            }
+        };
+
+        for (hit_position, length, hit) in hits {
+            if hit_position > position {
+                add_run(QueryKmersRun {
+                    length: hit_position - position,
+                    hit: QueryKmerHit::Missing,
+                });
+            }
+            add_run(QueryKmersRun { length, hit });
+            position = hit_position + length;
+        }
+
+        if query_kmers > position {
+            add_run(QueryKmersRun {
+                length: query_kmers - position,
+                hit: QueryKmerHit::Missing,
+            });
+        }
+
+        if current_run.length > 0 {
+            write_run(current_run);
+        }
+
+        encoded.into_boxed_slice()
+    }
+
+    pub fn set_runs(&mut self, query_index: u64, runs: Box<[u8]>) {
+        self.queries_runs[query_index as usize] = runs;
+    }
+
+    /// Decodes the runs of a query into `output`, queries without any hit are entirely missing
+    pub fn get_runs(&self, query_index: u64, query_kmers: u64, output: &mut Vec<QueryKmersRun>) {
+        output.clear();
+
+        let mut encoded = &self.queries_runs[query_index as usize][..];
+        if encoded.is_empty() {
+            if query_kmers > 0 {
+                output.push(QueryKmersRun {
+                    length: query_kmers,
+                    hit: QueryKmerHit::Missing,
+                });
+            }
+            return;
+        }
+
+        let mut read_varint = || {
+            decode_varint(|| {
+                let (byte, rest) = encoded.split_first()?;
+                encoded = rest;
+                Some(*byte)
+            })
+        };
+
+        while let Some(length) = read_varint() {
+            output.push(QueryKmersRun {
+                length,
+                hit: QueryKmerHit::from_u64(read_varint().unwrap()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueriesKmersHits, QueryKmerHit, QueryKmersRun};
+
+    #[test]
+    fn kmers_hits_runs() {
+        let mut hits = QueriesKmersHits::new(2);
+        hits.set_runs(
+            0,
+            QueriesKmersHits::encode_runs(
+                20,
+                [
+                    (2, 3, QueryKmerHit::ColorSubset(4)),
+                    (5, 2, QueryKmerHit::ColorSubset(4)),
+                    (7, 1, QueryKmerHit::ColorSubset(0)),
+                    (10, 4, QueryKmerHit::ColorSubset(0)),
+                ]
+                .into_iter(),
+            ),
+        );
+
+        let mut runs = vec![];
+        hits.get_runs(0, 20, &mut runs);
+        let run = |length, hit| QueryKmersRun { length, hit };
+        assert_eq!(
+            runs,
+            vec![
+                run(2, QueryKmerHit::Missing),
+                run(5, QueryKmerHit::ColorSubset(4)),
+                run(1, QueryKmerHit::ColorSubset(0)),
+                run(2, QueryKmerHit::Missing),
+                run(4, QueryKmerHit::ColorSubset(0)),
+                run(6, QueryKmerHit::Missing),
+            ]
+        );
+
+        hits.get_runs(1, 7, &mut runs);
+        assert_eq!(runs, vec![run(7, QueryKmerHit::Missing)]);
+    }
+}
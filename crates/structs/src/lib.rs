@@ -1,3 +1,2 @@
 pub mod map_entry;
-#[cfg(feature = "support_kmer_counters")]
 pub mod unitigs_counters;
@@ -82,3 +82,43 @@ impl<CHI> MapEntry<CHI> {
             >> ((self.get_flags() == (READ_FLAG_INCL_BEGIN | READ_FLAG_INCL_END)) as u8)
     }
 }
+
+/// Where the temporary color index of a kmer is stored while merging the colors
+pub trait TempColorIndexStorage: Default + Send + Sync + 'static {
+    /// The kmer multiplicity is still available after the color index is assigned
+    const KEEPS_MULTIPLICITY: bool;
+
+    fn get_color_index(entry: &MapEntry<Self>) -> usize;
+    fn set_color_index(entry: &mut MapEntry<Self>, index: usize);
+}
+
+/// The color index replaces the kmer counter, that is not needed anymore once the colors are assigned
+impl TempColorIndexStorage for () {
+    const KEEPS_MULTIPLICITY: bool = false;
+
+    #[inline(always)]
+    fn get_color_index(entry: &MapEntry<Self>) -> usize {
+        entry.get_counter()
+    }
+
+    #[inline(always)]
+    fn set_color_index(entry: &mut MapEntry<Self>, index: usize) {
+        entry.set_counter_after_check(index);
+    }
+}
+
+/// The color index is kept apart from the counter, so that the kmers multiplicities
+/// are still available when extending the unitigs
+impl TempColorIndexStorage for usize {
+    const KEEPS_MULTIPLICITY: bool = true;
+
+    #[inline(always)]
+    fn get_color_index(entry: &MapEntry<Self>) -> usize {
+        entry.color_index
+    }
+
+    #[inline(always)]
+    fn set_color_index(entry: &mut MapEntry<Self>, index: usize) {
+        entry.color_index = index;
+    }
+}
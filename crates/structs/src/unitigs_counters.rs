@@ -1,25 +1,44 @@
+use crate::map_entry::TempColorIndexStorage;
 use byteorder::ReadBytesExt;
 use io::{
+    concurrent::structured_sequences::SequenceAbundance,
     concurrent::temp_reads::extra_data::{HasEmptyExtraBuffer, SequenceExtraData},
     varint::{decode_varint, encode_varint, VARINT_MAX_SIZE},
 };
 use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
 use std::io::{Read, Write};
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct UnitigsCounters {
     pub first: u64,
     pub sum: u64,
     pub last: u64,
+    /// The multiplicity of each kmer, left empty if per-kmer abundances are not requested
+    pub kmers: Vec<u64>,
 }
 
+static EMPTY_UNITIGS_COUNTERS: UnitigsCounters = UnitigsCounters::new();
+
 impl UnitigsCounters {
     #[inline]
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
             first: 0,
             sum: 0,
             last: 0,
+            kmers: Vec::new(),
+        }
+    }
+}
+
+impl From<UnitigsCounters> for SequenceAbundance {
+    fn from(counters: UnitigsCounters) -> Self {
+        Self {
+            first: counters.first,
+            sum: counters.sum,
+            last: counters.last,
+            kmers: counters.kmers,
         }
     }
 }
@@ -31,17 +50,102 @@ impl SequenceExtraData for UnitigsCounters {
         let first = decode_varint(|| reader.read_u8().ok())?;
         let sum = decode_varint(|| reader.read_u8().ok())?;
         let last = decode_varint(|| reader.read_u8().ok())?;
-        Some(Self { first, sum, last })
+
+        let kmers_count = decode_varint(|| reader.read_u8().ok())? as usize;
+        let mut kmers = Vec::with_capacity(kmers_count);
+        for _ in 0..kmers_count {
+            kmers.push(decode_varint(|| reader.read_u8().ok())?);
+        }
+
+        Some(Self {
+            first,
+            sum,
+            last,
+            kmers,
+        })
     }
 
     fn encode_extended(&self, _: &Self::TempBuffer, writer: &mut impl Write) {
         encode_varint(|b| writer.write(b).ok(), self.first).unwrap();
         encode_varint(|b| writer.write(b).ok(), self.sum).unwrap();
         encode_varint(|b| writer.write(b).ok(), self.last).unwrap();
+
+        encode_varint(|b| writer.write(b).ok(), self.kmers.len() as u64).unwrap();
+        for multiplicity in &self.kmers {
+            encode_varint(|b| writer.write(b).ok(), *multiplicity).unwrap();
+        }
     }
 
     #[inline(always)]
     fn max_size(&self) -> usize {
-        3 * VARINT_MAX_SIZE
+        (4 + self.kmers.len()) * VARINT_MAX_SIZE
+    }
+}
+
+/// The kmers multiplicities stored with the unitigs, `()` if they are not collected
+pub trait UnitigsCountersStorage:
+    SequenceExtraData<TempBuffer = ()> + Clone + Debug + Sync + Send + 'static
+{
+    /// The multiplicities must still be available when extending the unitigs after the colors are assigned
+    type TempColorIndex: TempColorIndexStorage;
+
+    /// Starts the counters of a unitig from its first kmer
+    fn new_unitig(multiplicity: u64) -> Self;
+
+    /// The collected counters, all zero if they are not collected
+    fn get_counters(&self) -> &UnitigsCounters;
+    fn get_counters_mut(&mut self) -> Option<&mut UnitigsCounters>;
+
+    fn into_abundance(self) -> SequenceAbundance;
+}
+
+impl UnitigsCountersStorage for () {
+    type TempColorIndex = ();
+
+    #[inline(always)]
+    fn new_unitig(_multiplicity: u64) -> Self {}
+
+    #[inline(always)]
+    fn get_counters(&self) -> &UnitigsCounters {
+        &EMPTY_UNITIGS_COUNTERS
+    }
+
+    #[inline(always)]
+    fn get_counters_mut(&mut self) -> Option<&mut UnitigsCounters> {
+        None
+    }
+
+    #[inline(always)]
+    fn into_abundance(self) -> SequenceAbundance {
+        SequenceAbundance::default()
+    }
+}
+
+impl UnitigsCountersStorage for UnitigsCounters {
+    type TempColorIndex = usize;
+
+    #[inline(always)]
+    fn new_unitig(multiplicity: u64) -> Self {
+        Self {
+            first: multiplicity,
+            sum: multiplicity,
+            last: multiplicity,
+            kmers: Vec::new(),
+        }
+    }
+
+    #[inline(always)]
+    fn get_counters(&self) -> &UnitigsCounters {
+        self
+    }
+
+    #[inline(always)]
+    fn get_counters_mut(&mut self) -> Option<&mut UnitigsCounters> {
+        Some(self)
+    }
+
+    #[inline(always)]
+    fn into_abundance(self) -> SequenceAbundance {
+        self.into()
     }
 }
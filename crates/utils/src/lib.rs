@@ -3,6 +3,7 @@ pub mod debug_functions;
 pub mod fast_rand_bool;
 pub mod owned_drop;
 pub mod resource_counter;
+pub mod temp_dir;
 pub mod vec_slice;
 
 use std::cmp::max;
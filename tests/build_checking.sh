@@ -11,7 +11,7 @@ cargo check
 cargo check --release
 
 
-export TESTABLE_FEATURES="mem-analysis no-stats process-stats tracing devel-build kmer-counters"
+export TESTABLE_FEATURES="mem-analysis no-stats process-stats tracing devel-build"
 for feature in ${TESTABLE_FEATURES}; do
     echo "Checking with feature $feature"
     cargo check --tests --features "$feature"